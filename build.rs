@@ -0,0 +1,34 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Hash of the rust sources (including the generated ones), used by the jit cache to reject modules
+// from a different build
+fn main() {
+    println!("cargo:rerun-if-changed=src/rust");
+
+    let mut files = Vec::new();
+    collect_files(Path::new("src/rust"), &mut files);
+    files.sort();
+
+    let mut hash: u64 = 0xCBF29CE484222325;
+    for file in &files {
+        let contents = fs::read(file).unwrap();
+        for &b in file.to_string_lossy().as_bytes().iter().chain(contents.iter()) {
+            hash = (hash ^ b as u64).wrapping_mul(0x100000001B3);
+        }
+    }
+
+    println!("cargo:rustc-env=V86_BUILD_HASH={}", hash as u32 ^ (hash >> 32) as u32);
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        }
+        else if path.extension() == Some("rs".as_ref()) {
+            files.push(path);
+        }
+    }
+}
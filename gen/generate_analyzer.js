@@ -213,6 +213,31 @@ function gen_instruction_body_after_prefix(encodings, size)
 }
 
 function gen_instruction_body_after_fixed_g(encoding, size)
+{
+    if(!encoding.prefix && !encoding.lock)
+    {
+        // a lock prefix on an instruction that can't be locked raises #ud before anything else
+        return [{
+            type: "if-else",
+            if_blocks: [{
+                condition: "cpu.prefixes & ::prefix::PREFIX_LOCK != 0",
+                body: [
+                    "analysis.ty = ::analysis::AnalysisType::BlockBoundary;",
+                    "analysis.no_next_instruction = true;",
+                ],
+            }],
+            else_block: {
+                body: gen_instruction_body_after_lock_check(encoding, size),
+            },
+        }];
+    }
+    else
+    {
+        return gen_instruction_body_after_lock_check(encoding, size);
+    }
+}
+
+function gen_instruction_body_after_lock_check(encoding, size)
 {
     const imm_read = gen_read_imm_call(encoding, size);
     const instruction_postfix = [];
@@ -270,6 +295,18 @@ function gen_instruction_body_after_fixed_g(encoding, size)
             );
         }
 
+        if(encoding.lock)
+        {
+            // only the memory form can be locked
+            reg_postfix.push({
+                type: "if-else",
+                if_blocks: [{
+                    condition: "cpu.prefixes & ::prefix::PREFIX_LOCK != 0",
+                    body: ["analysis.ty = ::analysis::AnalysisType::BlockBoundary;"],
+                }],
+            });
+        }
+
         if(encoding.ignore_mod)
         {
             assert(!imm_read, "Unexpected instruction (ignore mod with immediate value)");
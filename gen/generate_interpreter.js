@@ -232,6 +232,28 @@ function gen_instruction_body_after_fixed_g(encoding, size)
         (!encoding.custom && encoding.e) ?
         ["after_block_boundary();"] : [];
 
+    if(encoding.sse_fp)
+    {
+        // unmasked exceptions are raised after the instruction has returned
+        instruction_postfix.unshift("::cpu::sse_instr::sse_exception_test();");
+    }
+
+    // a lock prefix on an instruction that can't be locked raises #ud before anything else
+    const lock_ud = {
+        type: "if-else",
+        if_blocks: [
+            {
+                condition: "*prefixes & ::prefix::PREFIX_LOCK != 0",
+                body: ["trigger_ud();", "return;"],
+            }
+        ],
+    };
+
+    if(!encoding.prefix && !encoding.lock)
+    {
+        instruction_prefix.push(lock_ud);
+    }
+
     if(encoding.task_switch_test || encoding.sse)
     {
         instruction_prefix.push(
@@ -284,6 +306,37 @@ function gen_instruction_body_after_fixed_g(encoding, size)
 
             const reg_args = ["modrm_byte & 7"];
 
+            const mem_prefix = [];
+            const reg_prefix = [];
+
+            if(encoding.lock)
+            {
+                // only the memory form can be locked
+                reg_prefix.push(lock_ud);
+            }
+
+            if(encoding.is_fpu)
+            {
+                // waiting fpu instructions report pending exceptions first
+                const fpu_exception_test = {
+                    type: "if-else",
+                    if_blocks: [
+                        {
+                            condition: "!::cpu::fpu::fpu_exception_test()",
+                            body: ["return;"],
+                        }
+                    ],
+                };
+                if(!encoding.fpu_nowait_mem)
+                {
+                    mem_prefix.push(fpu_exception_test);
+                }
+                if(!encoding.fpu_nowait_reg)
+                {
+                    reg_prefix.push(fpu_exception_test);
+                }
+            }
+
             if(encoding.fixed_g === undefined)
             {
                 mem_args.push("modrm_byte >> 3 & 7");
@@ -304,12 +357,16 @@ function gen_instruction_body_after_fixed_g(encoding, size)
                         {
                             condition: "modrm_byte < 0xC0",
                             body: [].concat(
+                                mem_prefix,
                                 gen_call(`${instruction_name}_mem`, mem_args)
                             ),
                         }
                     ],
                     else_block: {
-                        body: [gen_call(`${instruction_name}_reg`, reg_args)],
+                        body: [].concat(
+                            reg_prefix,
+                            gen_call(`${instruction_name}_reg`, reg_args)
+                        ),
                     },
                 },
                 instruction_postfix
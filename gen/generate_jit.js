@@ -213,6 +213,56 @@ function gen_instruction_body_after_prefix(encodings, size)
 }
 
 function gen_instruction_body_after_fixed_g(encoding, size)
+{
+    if(!encoding.prefix && !encoding.lock)
+    {
+        // a lock prefix on an instruction that can't be locked raises #ud before anything else
+        return [{
+            type: "if-else",
+            if_blocks: [{
+                condition: "ctx.cpu.prefixes & ::prefix::PREFIX_LOCK != 0",
+                body: gen_lock_ud(),
+            }],
+            else_block: {
+                body: gen_instruction_body_after_lock_check(encoding, size),
+            },
+        }];
+    }
+    else
+    {
+        return gen_instruction_body_after_lock_check(encoding, size);
+    }
+}
+
+function gen_lock_ud()
+{
+    return [].concat(
+        gen_call(`::codegen::gen_trigger_ud`, ["ctx"]),
+        "*instr_flags |= ::jit::JIT_INSTR_BLOCK_BOUNDARY_FLAG;"
+    );
+}
+
+function gen_reg_lock_check(encoding, body)
+{
+    if(!encoding.lock)
+    {
+        return body;
+    }
+
+    // only the memory form can be locked
+    return [{
+        type: "if-else",
+        if_blocks: [{
+            condition: "ctx.cpu.prefixes & ::prefix::PREFIX_LOCK != 0",
+            body: gen_lock_ud(),
+        }],
+        else_block: {
+            body,
+        },
+    }];
+}
+
+function gen_instruction_body_after_lock_check(encoding, size)
 {
     const instruction_postfix = [];
 
@@ -255,11 +305,31 @@ function gen_instruction_body_after_fixed_g(encoding, size)
         }
     }
 
+    if(encoding.sse_fp)
+    {
+        instruction_postfix.push(gen_call("::codegen::gen_sse_exception_test", ["ctx"]));
+    }
+
     if(encoding.e)
     {
+        const reg_prefix = [];
+        const mem_prefix = [];
         const reg_postfix = [];
         const mem_postfix = [];
 
+        if(encoding.is_fpu)
+        {
+            // waiting fpu instructions report pending exceptions first
+            if(!encoding.fpu_nowait_mem)
+            {
+                mem_prefix.push(gen_call("::codegen::gen_fpu_exception_test", ["ctx"]));
+            }
+            if(!encoding.fpu_nowait_reg)
+            {
+                reg_prefix.push(gen_call("::codegen::gen_fpu_exception_test", ["ctx"]));
+            }
+        }
+
         if(encoding.mem_ud)
         {
             mem_postfix.push(
@@ -314,6 +384,7 @@ function gen_instruction_body_after_fixed_g(encoding, size)
                         condition: "modrm_byte < 0xC0",
                         body: [].concat(
                             "let addr = ::modrm::decode(ctx.cpu, modrm_byte);",
+                            mem_prefix,
                             imm_read_bindings,
                             gen_call(`::jit_instructions::${instruction_name}_mem_jit`, mem_args),
                             mem_postfix
@@ -321,9 +392,12 @@ function gen_instruction_body_after_fixed_g(encoding, size)
                     }],
                     else_block: {
                         body: [].concat(
+                            reg_prefix,
                             imm_read_bindings,
-                            gen_call(`::jit_instructions::${instruction_name}_reg_jit`, reg_args),
-                            reg_postfix
+                            gen_reg_lock_check(encoding, [].concat(
+                                gen_call(`::jit_instructions::${instruction_name}_reg_jit`, reg_args),
+                                reg_postfix
+                            ))
                         ),
                     },
                 },
@@ -355,6 +429,7 @@ function gen_instruction_body_after_fixed_g(encoding, size)
                         condition: "modrm_byte < 0xC0",
                         body: [].concat(
                             "let addr = ::modrm::decode(ctx.cpu, modrm_byte);",
+                            mem_prefix,
                             gen_call(`::codegen::gen_modrm_resolve`, ["ctx", "addr"]),
                             imm_read_bindings,
                             gen_call(`::codegen::gen_modrm_fn${mem_args.length - 2}`, mem_args),
@@ -363,9 +438,12 @@ function gen_instruction_body_after_fixed_g(encoding, size)
                     }],
                     else_block: {
                         body: [].concat(
+                            reg_prefix,
                             imm_read_bindings,
-                            gen_call(`::codegen::gen_fn${reg_args.length - 2}_const`, reg_args),
-                            reg_postfix
+                            gen_reg_lock_check(encoding, [].concat(
+                                gen_call(`::codegen::gen_fn${reg_args.length - 2}_const`, reg_args),
+                                reg_postfix
+                            ))
                         ),
                     },
                 },
@@ -56,6 +56,8 @@ const TESTS_ASSUME_INTEL = false;
 // prefix: is a prefix instruction
 // imm8, imm8s, imm16, imm1632, immaddr, extra_imm8, extra_imm16: one or two immediate bytes follows the instruction
 // custom: will callback jit to generate custom code
+// lock: the memory form accepts a lock prefix, all other instructions raise #ud when locked
+// sse_fp: may raise a simd floating point exception, which is delivered after the instruction
 // block_boundary: may change eip in a way not handled by the jit
 // no_next_instruction: jit will stop analysing after instruction (e.g., unconditional jump, ret)
 const encodings = [
@@ -106,8 +108,8 @@ const encodings = [
 
     { opcode: 0x84, custom: 1, e: 1 },
     { opcode: 0x85, custom: 1, e: 1, os: 1 },
-    { opcode: 0x86, custom: 1, e: 1 },
-    { opcode: 0x87, custom: 1, os: 1, e: 1 },
+    { opcode: 0x86, custom: 1, e: 1, lock: 1 },
+    { opcode: 0x87, custom: 1, os: 1, e: 1, lock: 1 },
     { opcode: 0x88, custom: 1, e: 1 },
     { opcode: 0x89, custom: 1, os: 1, e: 1 },
     { opcode: 0x8A, custom: 1, e: 1 },
@@ -221,8 +223,8 @@ const encodings = [
     { opcode: 0xD9, e: 1, fixed_g: 3, custom: 1, is_fpu: 1, task_switch_test: 1, os: 1 },
     { opcode: 0xD9, e: 1, fixed_g: 4, custom: 1, is_fpu: 1, task_switch_test: 1, os: 1, skip_mem: 1 }, // fldenv (mem)
     { opcode: 0xD9, e: 1, fixed_g: 5, custom: 1, is_fpu: 1, task_switch_test: 1, os: 1 },
-    { opcode: 0xD9, e: 1, fixed_g: 6, custom: 1, is_fpu: 1, task_switch_test: 1, os: 1, skip: 1 }, // fstenv (mem), fprem (reg)
-    { opcode: 0xD9, e: 1, fixed_g: 7, custom: 1, is_fpu: 1, task_switch_test: 1, os: 1, skip_reg: 1 }, // fprem, fyl2xp1 (precision issues)
+    { opcode: 0xD9, e: 1, fixed_g: 6, custom: 1, is_fpu: 1, task_switch_test: 1, os: 1, skip: 1, fpu_nowait_mem: 1 }, // fstenv (mem), fprem (reg)
+    { opcode: 0xD9, e: 1, fixed_g: 7, custom: 1, is_fpu: 1, task_switch_test: 1, os: 1, skip_reg: 1, fpu_nowait_mem: 1 }, // fstcw (mem), fprem, fyl2xp1 (precision issues)
 
     { opcode: 0xDA, e: 1, fixed_g: 0, custom: 1, is_fpu: 1, task_switch_test: 1 },
     { opcode: 0xDA, e: 1, fixed_g: 1, custom: 1, is_fpu: 1, task_switch_test: 1 },
@@ -237,7 +239,7 @@ const encodings = [
     { opcode: 0xDB, e: 1, fixed_g: 1, custom: 1, is_fpu: 1, task_switch_test: 1 }, // fisttp (sse3)
     { opcode: 0xDB, e: 1, fixed_g: 2, custom: 1, is_fpu: 1, task_switch_test: 1 },
     { opcode: 0xDB, e: 1, fixed_g: 3, custom: 1, is_fpu: 1, task_switch_test: 1 },
-    { opcode: 0xDB, e: 1, fixed_g: 4, custom: 0, is_fpu: 1, task_switch_test: 1 },
+    { opcode: 0xDB, e: 1, fixed_g: 4, custom: 0, is_fpu: 1, task_switch_test: 1, fpu_nowait_reg: 1, block_boundary: 1, no_block_boundary_in_interpreted: 1 }, // fninit, fnclex (reg): block_boundary since they may change the fpu_f64 state flag
     { opcode: 0xDB, e: 1, fixed_g: 5, custom: 1, is_fpu: 1, task_switch_test: 1 },
     { opcode: 0xDB, e: 1, fixed_g: 6, custom: 1, is_fpu: 1, task_switch_test: 1 },
     { opcode: 0xDB, e: 1, fixed_g: 7, custom: 0, is_fpu: 1, task_switch_test: 1 },
@@ -255,10 +257,10 @@ const encodings = [
     { opcode: 0xDD, e: 1, fixed_g: 1, custom: 1, is_fpu: 1, task_switch_test: 1, os: 1 }, // fisttp (sse3)
     { opcode: 0xDD, e: 1, fixed_g: 2, custom: 1, is_fpu: 1, task_switch_test: 1, os: 1 },
     { opcode: 0xDD, e: 1, fixed_g: 3, custom: 1, is_fpu: 1, task_switch_test: 1, os: 1 },
-    { opcode: 0xDD, e: 1, fixed_g: 4, custom: 0, is_fpu: 1, task_switch_test: 1, os: 1, skip_mem: 1 }, // frstor
+    { opcode: 0xDD, e: 1, fixed_g: 4, custom: 0, is_fpu: 1, task_switch_test: 1, os: 1, skip_mem: 1, block_boundary: 1, no_block_boundary_in_interpreted: 1 }, // frstor: block_boundary since it may change the fpu_f64 state flag
     { opcode: 0xDD, e: 1, fixed_g: 5, custom: 1, is_fpu: 1, task_switch_test: 1, os: 1 },
-    { opcode: 0xDD, e: 1, fixed_g: 6, custom: 0, is_fpu: 1, task_switch_test: 1, os: 1, skip_mem: 1 }, // fsave
-    { opcode: 0xDD, e: 1, fixed_g: 7, custom: 0, is_fpu: 1, task_switch_test: 1, os: 1, skip_mem: 1 }, // fstsw (denormal flag)
+    { opcode: 0xDD, e: 1, fixed_g: 6, custom: 0, is_fpu: 1, task_switch_test: 1, os: 1, skip_mem: 1, fpu_nowait_mem: 1, block_boundary: 1, no_block_boundary_in_interpreted: 1 }, // fsave: block_boundary since it reinitialises the control word
+    { opcode: 0xDD, e: 1, fixed_g: 7, custom: 0, is_fpu: 1, task_switch_test: 1, os: 1, skip_mem: 1, fpu_nowait_mem: 1 }, // fstsw (denormal flag)
 
     { opcode: 0xDE, e: 1, fixed_g: 0, custom: 1, is_fpu: 1, task_switch_test: 1 },
     { opcode: 0xDE, e: 1, fixed_g: 1, custom: 1, is_fpu: 1, task_switch_test: 1 },
@@ -273,7 +275,7 @@ const encodings = [
     { opcode: 0xDF, e: 1, fixed_g: 1, custom: 1, is_fpu: 1, task_switch_test: 1 }, // fisttp (sse3)
     { opcode: 0xDF, e: 1, fixed_g: 2, custom: 1, is_fpu: 1, task_switch_test: 1 },
     { opcode: 0xDF, e: 1, fixed_g: 3, custom: 1, is_fpu: 1, task_switch_test: 1 },
-    { opcode: 0xDF, e: 1, fixed_g: 4, custom: 1, is_fpu: 1, task_switch_test: 1, skip: 1 }, // unimplemented: Binary Coded Decimals / fsts (denormal flag)
+    { opcode: 0xDF, e: 1, fixed_g: 4, custom: 1, is_fpu: 1, task_switch_test: 1, skip: 1, fpu_nowait_reg: 1 }, // unimplemented: Binary Coded Decimals / fsts (denormal flag)
     { opcode: 0xDF, e: 1, fixed_g: 5, custom: 1, is_fpu: 1, task_switch_test: 1 },
     { opcode: 0xDF, e: 1, fixed_g: 6, custom: 1, is_fpu: 1, task_switch_test: 1 },
     { opcode: 0xDF, e: 1, fixed_g: 7, custom: 1, is_fpu: 1, task_switch_test: 1 },
@@ -301,7 +303,7 @@ const encodings = [
     { opcode: 0xEF, block_boundary: 1, os: 1, skip: 1 },
 
     { opcode: 0xF0, prefix: 1 },
-    { opcode: 0xF1, skip: 1 },
+    { opcode: 0xF1, block_boundary: 1, skip: 1 }, // int1 (icebp)
     { opcode: 0xF2, prefix: 1 },
     { opcode: 0xF3, prefix: 1 },
     { opcode: 0xF4, block_boundary: 1, no_next_instruction: 1, skip: 1 }, // hlt
@@ -309,8 +311,8 @@ const encodings = [
 
     { opcode: 0xF6, e: 1, fixed_g: 0, imm8: 1, custom: 1 },
     { opcode: 0xF6, e: 1, fixed_g: 1, imm8: 1, custom: 1 },
-    { opcode: 0xF6, e: 1, fixed_g: 2, custom: 1 },
-    { opcode: 0xF6, e: 1, fixed_g: 3, custom: 1 },
+    { opcode: 0xF6, e: 1, fixed_g: 2, custom: 1, lock: 1 },
+    { opcode: 0xF6, e: 1, fixed_g: 3, custom: 1, lock: 1 },
     { opcode: 0xF6, e: 1, fixed_g: 4, mask_flags: TESTS_ASSUME_INTEL ? af | zf : sf | zf | af | pf },
     { opcode: 0xF6, e: 1, fixed_g: 5, mask_flags: TESTS_ASSUME_INTEL ? af | zf : sf | zf | af | pf },
     // div/idiv: Not a block boundary, but doesn't use control flow exceptions
@@ -319,8 +321,8 @@ const encodings = [
 
     { opcode: 0xF7, os: 1, e: 1, fixed_g: 0, imm1632: 1, custom: 1 },
     { opcode: 0xF7, os: 1, e: 1, fixed_g: 1, imm1632: 1, custom: 1 },
-    { opcode: 0xF7, os: 1, e: 1, fixed_g: 2, custom: 1 },
-    { opcode: 0xF7, os: 1, e: 1, fixed_g: 3, custom: 1 },
+    { opcode: 0xF7, os: 1, e: 1, fixed_g: 2, custom: 1, lock: 1 },
+    { opcode: 0xF7, os: 1, e: 1, fixed_g: 3, custom: 1, lock: 1 },
     { opcode: 0xF7, os: 1, e: 1, fixed_g: 4, mask_flags: TESTS_ASSUME_INTEL ? af | zf : sf | zf | af | pf, custom: 1 },
     { opcode: 0xF7, os: 1, e: 1, fixed_g: 5, mask_flags: TESTS_ASSUME_INTEL ? af | zf : sf | zf | af | pf, custom: 1 },
     { opcode: 0xF7, os: 1, e: 1, fixed_g: 6, mask_flags: TESTS_ASSUME_INTEL ? 0 : sf | zf | af | pf, custom: 1 },
@@ -334,10 +336,10 @@ const encodings = [
     { opcode: 0xFC, custom: 1 },
     { opcode: 0xFD, custom: 1 },
 
-    { opcode: 0xFE, e: 1, fixed_g: 0, custom: 1 },
-    { opcode: 0xFE, e: 1, fixed_g: 1, custom: 1 },
-    { opcode: 0xFF, os: 1, e: 1, fixed_g: 0, custom: 1 },
-    { opcode: 0xFF, os: 1, e: 1, fixed_g: 1, custom: 1 },
+    { opcode: 0xFE, e: 1, fixed_g: 0, custom: 1, lock: 1 },
+    { opcode: 0xFE, e: 1, fixed_g: 1, custom: 1, lock: 1 },
+    { opcode: 0xFF, os: 1, e: 1, fixed_g: 0, custom: 1, lock: 1 },
+    { opcode: 0xFF, os: 1, e: 1, fixed_g: 1, custom: 1, lock: 1 },
     { opcode: 0xFF, os: 1, e: 1, fixed_g: 2, custom: 1, block_boundary: 1, absolute_jump: 1, skip: 1 },
     { opcode: 0xFF, os: 1, e: 1, fixed_g: 3, block_boundary: 1, skip: 1 },
     { opcode: 0xFF, os: 1, e: 1, fixed_g: 4, custom: 1, block_boundary: 1, absolute_jump: 1, no_next_instruction: 1, skip: 1 },
@@ -352,7 +354,7 @@ const encodings = [
     { opcode: 0x0F00, fixed_g: 5, e: 1, skip: 1, block_boundary: 1, os: 1 },
 
     { opcode: 0x0F01, fixed_g: 0, e: 1, skip: 1, block_boundary: 1, os: 1 }, // sgdt, ...
-    { opcode: 0x0F01, fixed_g: 1, e: 1, skip: 1, block_boundary: 1, os: 1 },
+    { opcode: 0x0F01, fixed_g: 1, e: 1, skip: 1, block_boundary: 1, os: 1, custom: 1 }, // sidt, monitor, mwait
     { opcode: 0x0F01, fixed_g: 2, e: 1, skip: 1, block_boundary: 1, os: 1 },
     { opcode: 0x0F01, fixed_g: 3, e: 1, skip: 1, block_boundary: 1, os: 1 },
     { opcode: 0x0F01, fixed_g: 4, e: 1, skip: 1, block_boundary: 1, os: 1 },
@@ -407,7 +409,7 @@ const encodings = [
     { opcode: 0x0F37, skip: 1, block_boundary: 1 }, // getsec
 
     // ssse3+
-    { opcode: 0x0F38, skip: 1, block_boundary: 1 },
+    { opcode: 0x0F38, prefix: 1, skip: 1, block_boundary: 1 }, // three-byte opcodes, decoded by instr_0F38_analyze and instr_0F38_jit like the 0F prefix
     { opcode: 0x0F39, skip: 1, block_boundary: 1 },
     { opcode: 0x0F3A, skip: 1, block_boundary: 1 },
     { opcode: 0x0F3B, skip: 1, block_boundary: 1 },
@@ -425,14 +427,14 @@ const encodings = [
     { opcode: 0x0FA9, os: 1, block_boundary: 1, skip: 1 }, // pop gs
 
     { opcode: 0x0FA3, os: 1, e: 1, custom: 1, skip_mem: 1 }, // bt (can also index memory, but not supported by test right now)
-    { opcode: 0x0FAB, os: 1, e: 1, custom: 1, skip_mem: 1 },
-    { opcode: 0x0FB3, os: 1, e: 1, custom: 1, skip_mem: 1 },
-    { opcode: 0x0FBB, os: 1, e: 1, custom: 1, skip_mem: 1 },
+    { opcode: 0x0FAB, os: 1, e: 1, custom: 1, skip_mem: 1, lock: 1 },
+    { opcode: 0x0FB3, os: 1, e: 1, custom: 1, skip_mem: 1, lock: 1 },
+    { opcode: 0x0FBB, os: 1, e: 1, custom: 1, skip_mem: 1, lock: 1 },
 
     { opcode: 0x0FBA, os: 1, e: 1, fixed_g: 4, imm8: 1, custom: 1 }, // bt
-    { opcode: 0x0FBA, os: 1, e: 1, fixed_g: 5, imm8: 1, custom: 1 },
-    { opcode: 0x0FBA, os: 1, e: 1, fixed_g: 6, imm8: 1, custom: 1 },
-    { opcode: 0x0FBA, os: 1, e: 1, fixed_g: 7, imm8: 1, custom: 1 },
+    { opcode: 0x0FBA, os: 1, e: 1, fixed_g: 5, imm8: 1, custom: 1, lock: 1 },
+    { opcode: 0x0FBA, os: 1, e: 1, fixed_g: 6, imm8: 1, custom: 1, lock: 1 },
+    { opcode: 0x0FBA, os: 1, e: 1, fixed_g: 7, imm8: 1, custom: 1, lock: 1 },
 
     { opcode: 0x0FBC, os: 1, e: 1, mask_flags: of | sf | af | pf | cf, custom: 1 }, // bsf
     { opcode: 0x0FBD, os: 1, e: 1, mask_flags: of | sf | af | pf | cf, custom: 1 },
@@ -460,9 +462,9 @@ const encodings = [
 
     { opcode: 0x0FAF, os: 1, e: 1, mask_flags: TESTS_ASSUME_INTEL ? af | zf : sf | zf | af | pf, custom: 1 }, // imul
 
-    { opcode: 0x0FB0, e: 1 }, // cmxchg
-    { opcode: 0x0FB1, os: 1, e: 1, custom: 1 },
-    { opcode: 0x0FC7, e: 1, fixed_g: 1, os: 1, reg_ud: 1, custom: 1 }, // cmpxchg8b (memory)
+    { opcode: 0x0FB0, e: 1, lock: 1 }, // cmxchg
+    { opcode: 0x0FB1, os: 1, e: 1, custom: 1, lock: 1 },
+    { opcode: 0x0FC7, e: 1, fixed_g: 1, os: 1, reg_ud: 1, custom: 1, lock: 1 }, // cmpxchg8b (memory)
     { opcode: 0x0FC7, e: 1, fixed_g: 6, os: 1, mem_ud: 1, skip: 1 }, // rdrand
 
     { opcode: 0x0FB2, block_boundary: 1, os: 1, e: 1, skip: 1 }, // lss
@@ -480,8 +482,8 @@ const encodings = [
     { opcode: 0x0FBE, os: 1, e: 1, custom: 1 }, // movsx
     { opcode: 0x0FBF, os: 1, e: 1, custom: 1 },
 
-    { opcode: 0x0FC0, e: 1 }, // xadd
-    { opcode: 0x0FC1, os: 1, e: 1, custom: 1 },
+    { opcode: 0x0FC0, e: 1, lock: 1 }, // xadd
+    { opcode: 0x0FC1, os: 1, e: 1, custom: 1, lock: 1 },
 
     { opcode: 0x0FC8, custom: 1 }, // bswap
     { opcode: 0x0FC9, custom: 1 },
@@ -523,33 +525,33 @@ const encodings = [
     { sse: 1, opcode: 0x660F28, e: 1, custom: 1 },
     { sse: 1, opcode: 0x0F29, e: 1, custom: 1 },
     { sse: 1, opcode: 0x660F29, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x0F2A, e: 1, custom: 1 },
+    { sse: 1, opcode: 0x0F2A, e: 1, custom: 1, sse_fp: 1 },
     { sse: 1, opcode: 0x660F2A, e: 1, custom: 1 },
     { sse: 1, opcode: 0xF20F2A, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30F2A, e: 1, custom: 1 },
+    { sse: 1, opcode: 0xF30F2A, e: 1, custom: 1, sse_fp: 1 },
     { sse: 1, opcode: 0x0F2B, reg_ud: 1, e: 1, custom: 1 },
     { sse: 1, opcode: 0x660F2B, reg_ud: 1, e: 1, custom: 1 },
 
-    { sse: 1, opcode: 0x0F2C, e: 1 },
-    { sse: 1, opcode: 0x660F2C, e: 1 },
-    { sse: 1, opcode: 0xF20F2C, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30F2C, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x0F2D, e: 1 },
-    { sse: 1, opcode: 0x660F2D, e: 1 },
-    { sse: 1, opcode: 0xF20F2D, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30F2D, e: 1, custom: 1 },
+    { sse: 1, opcode: 0x0F2C, e: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F2C, e: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20F2C, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF30F2C, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x0F2D, e: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F2D, e: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20F2D, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF30F2D, e: 1, custom: 1, sse_fp: 1 },
 
-    { sse: 1, opcode: 0x0F2E, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x660F2E, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x0F2F, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x660F2F, e: 1, custom: 1 },
+    { sse: 1, opcode: 0x0F2E, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F2E, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x0F2F, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F2F, e: 1, custom: 1, sse_fp: 1 },
 
     { sse: 1, opcode: 0x0F50, mem_ud: 1, e: 1 },
     { sse: 1, opcode: 0x660F50, mem_ud: 1, e: 1 },
-    { sse: 1, opcode: 0x0F51, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x660F51, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF20F51, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30F51, e: 1, custom: 1 },
+    { sse: 1, opcode: 0x0F51, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F51, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20F51, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF30F51, e: 1, custom: 1, sse_fp: 1 },
 
     // approximation of 1/sqrt(x). Skipped because our approximation doesn't match intel's
     { sse: 1, opcode: 0x0F52, e: 1, skip: 1, custom: 1 },
@@ -568,41 +570,41 @@ const encodings = [
     { sse: 1, opcode: 0x0F57, e: 1, custom: 1 },
     { sse: 1, opcode: 0x660F57, e: 1, custom: 1 },
 
-    { sse: 1, opcode: 0x0F58, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x660F58, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF20F58, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30F58, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x0F59, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x660F59, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF20F59, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30F59, e: 1, custom: 1 },
-
-    { sse: 1, opcode: 0x0F5A, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x660F5A, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF20F5A, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30F5A, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x0F5B, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x660F5B, e: 1, custom: 1 },
+    { sse: 1, opcode: 0x0F58, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F58, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20F58, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF30F58, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x0F59, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F59, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20F59, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF30F59, e: 1, custom: 1, sse_fp: 1 },
+
+    { sse: 1, opcode: 0x0F5A, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F5A, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20F5A, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF30F5A, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x0F5B, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F5B, e: 1, custom: 1, sse_fp: 1 },
     // no F2 variant
-    { sse: 1, opcode: 0xF30F5B, e: 1, custom: 1 },
-
-    { sse: 1, opcode: 0x0F5C, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x660F5C, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF20F5C, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30F5C, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x0F5D, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x660F5D, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF20F5D, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30F5D, e: 1, custom: 1 },
-
-    { sse: 1, opcode: 0x0F5E, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x660F5E, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF20F5E, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30F5E, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x0F5F, e: 1, custom: 1 },
-    { sse: 1, opcode: 0x660F5F, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF20F5F, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30F5F, e: 1, custom: 1 },
+    { sse: 1, opcode: 0xF30F5B, e: 1, custom: 1, sse_fp: 1 },
+
+    { sse: 1, opcode: 0x0F5C, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F5C, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20F5C, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF30F5C, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x0F5D, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F5D, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20F5D, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF30F5D, e: 1, custom: 1, sse_fp: 1 },
+
+    { sse: 1, opcode: 0x0F5E, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F5E, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20F5E, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF30F5E, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x0F5F, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660F5F, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20F5F, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF30F5F, e: 1, custom: 1, sse_fp: 1 },
 
     { sse: 1, opcode: 0x660F60, e: 1, custom: 1 },
     { sse: 1, opcode: 0x0F60, e: 1, custom: 1 },
@@ -680,10 +682,10 @@ const encodings = [
     { opcode: 0x0F7A, skip: 1, block_boundary: 1 }, // ud
     { opcode: 0x0F7B, skip: 1, block_boundary: 1 }, // ud
 
-    { sse: 1, opcode: 0x660F7C, e: 1, custom: 1 }, // sse3
-    { sse: 1, opcode: 0xF20F7C, e: 1, custom: 1 }, // sse3
-    { sse: 1, opcode: 0x660F7D, e: 1, custom: 1 }, // sse3
-    { sse: 1, opcode: 0xF20F7D, e: 1, custom: 1 }, // sse3
+    { sse: 1, opcode: 0x660F7C, e: 1, custom: 1, sse_fp: 1 }, // sse3
+    { sse: 1, opcode: 0xF20F7C, e: 1, custom: 1, sse_fp: 1 }, // sse3
+    { sse: 1, opcode: 0x660F7D, e: 1, custom: 1, sse_fp: 1 }, // sse3
+    { sse: 1, opcode: 0xF20F7D, e: 1, custom: 1, sse_fp: 1 }, // sse3
 
     { opcode: 0x0F7C, skip: 1, block_boundary: 1 }, // ud
     { opcode: 0x0F7D, skip: 1, block_boundary: 1 }, // ud
@@ -695,10 +697,10 @@ const encodings = [
     { sse: 1, opcode: 0x660F7F, e: 1, custom: 1 },
     { sse: 1, opcode: 0xF30F7F, e: 1, custom: 1 },
 
-    { sse: 1, opcode: 0x0FC2, e: 1, imm8: 1, custom: 1 },
-    { sse: 1, opcode: 0x660FC2, e: 1, imm8: 1, custom: 1 },
-    { sse: 1, opcode: 0xF20FC2, e: 1, imm8: 1, custom: 1 },
-    { sse: 1, opcode: 0xF30FC2, e: 1, imm8: 1, custom: 1 },
+    { sse: 1, opcode: 0x0FC2, e: 1, imm8: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0x660FC2, e: 1, imm8: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20FC2, e: 1, imm8: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF30FC2, e: 1, imm8: 1, custom: 1, sse_fp: 1 },
 
     { opcode: 0x0FC3, e: 1, custom: 1, reg_ud: 1 }, // movnti: Uses normal registers, hence not marked as sse
 
@@ -761,8 +763,8 @@ const encodings = [
     { sse: 1, opcode: 0x0FE5, e: 1, custom: 1 },
     { sse: 1, opcode: 0x660FE5, e: 1, custom: 1 },
 
-    { sse: 1, opcode: 0x660FE6, e: 1, custom: 1 },
-    { sse: 1, opcode: 0xF20FE6, e: 1, custom: 1 },
+    { sse: 1, opcode: 0x660FE6, e: 1, custom: 1, sse_fp: 1 },
+    { sse: 1, opcode: 0xF20FE6, e: 1, custom: 1, sse_fp: 1 },
     { sse: 1, opcode: 0xF30FE6, e: 1, custom: 1 },
     { sse: 1, opcode: 0x0FE6, e: 1, block_boundary: 1 }, // ud
     { sse: 1, opcode: 0x0FE7, e: 1, reg_ud: 1 },
@@ -825,8 +827,9 @@ const encodings = [
 for(let i = 0; i < 8; i++)
 {
     encodings.push.apply(encodings, [
-        { opcode: 0x00 | i << 3, custom: 1, e: 1 },
-        { opcode: 0x01 | i << 3, custom: 1, os: 1, e: 1 },
+        // cmp (i = 7) only reads its destination
+        { opcode: 0x00 | i << 3, custom: 1, e: 1, lock: i !== 7 },
+        { opcode: 0x01 | i << 3, custom: 1, os: 1, e: 1, lock: i !== 7 },
         { opcode: 0x02 | i << 3, custom: 1, e: 1 },
         { opcode: 0x03 | i << 3, custom: 1, os: 1, e: 1 },
         { opcode: 0x04 | i << 3, custom: 1, imm8: 1 },
@@ -841,10 +844,10 @@ for(let i = 0; i < 8; i++)
         { opcode: 0x70 | i, block_boundary: 1, no_block_boundary_in_interpreted: 1, jump_offset_imm: 1, conditional_jump: 1, os: 1, imm8s: 1, custom: 1, skip: 1 },
         { opcode: 0x78 | i, block_boundary: 1, no_block_boundary_in_interpreted: 1, jump_offset_imm: 1, conditional_jump: 1, os: 1, imm8s: 1, custom: 1, skip: 1 },
 
-        { opcode: 0x80, e: 1, fixed_g: i, imm8: 1, custom: 1 },
-        { opcode: 0x81, os: 1, e: 1, fixed_g: i, imm1632: 1, custom: 1 },
-        { opcode: 0x82, e: 1, fixed_g: i, imm8: 1, custom: 1 },
-        { opcode: 0x83, os: 1, e: 1, fixed_g: i, imm8s: 1, custom: 1 },
+        { opcode: 0x80, e: 1, fixed_g: i, imm8: 1, custom: 1, lock: i !== 7 },
+        { opcode: 0x81, os: 1, e: 1, fixed_g: i, imm1632: 1, custom: 1, lock: i !== 7 },
+        { opcode: 0x82, e: 1, fixed_g: i, imm8: 1, custom: 1, lock: i !== 7 },
+        { opcode: 0x83, os: 1, e: 1, fixed_g: i, imm8s: 1, custom: 1, lock: i !== 7 },
 
         { opcode: 0xB0 | i, custom: 1, imm8: 1 },
         { opcode: 0xB8 | i, custom: 1, os: 1, imm1632: 1 },
@@ -30,24 +30,33 @@ var DELIVERY_MODES = [
     "Reserved (3)",
     "NMI (4)",
     "INIT (5)",
-    "Reserved (6)",
+    "Startup (6)",
     "ExtINT (7)",
 ];
 
 /** @const */
 var DESTINATION_MODES = ["physical", "logical"];
 
+/** @const */
+var APIC_DELIVERY_STARTUP = 6;
+
+/** @const */
+var APIC_ICR_LEVEL_ASSERT = 1 << 14;
+
 
 /**
+ * One local apic per cpu, see smp.rs
+ *
  * @constructor
  * @param {CPU} cpu
+ * @param {number} apic_id
  */
-function APIC(cpu)
+function APIC(cpu, apic_id)
 {
     /** @type {CPU} */
     this.cpu = cpu;
 
-    this.apic_id = 0;
+    this.apic_id = apic_id;
 
     this.timer_divider = 0;
     this.timer_divider_shift = 1;
@@ -77,13 +86,19 @@ function APIC(cpu)
     this.error = 0;
     this.read_error = 0;
 
+    if(apic_id !== 0)
+    {
+        return;
+    }
+
+    // Shared by all local apics: Accesses go to the apic of the cpu that is currently running
     cpu.io.mmap_register(APIC_ADDRESS, 0x100000,
         (addr) =>
         {
             dbg_log("Unsupported read8 from apic: " + h(addr >>> 0), LOG_APIC);
             var off = addr & 3;
             addr &= ~3;
-            return this.read32(addr) >> (off * 8) & 0xFF;
+            return cpu.get_current_apic().read32(addr) >> (off * 8) & 0xFF;
         },
         (addr, value) =>
         {
@@ -91,8 +106,8 @@ function APIC(cpu)
             dbg_trace();
             dbg_assert(false);
         },
-        (addr) => this.read32(addr),
-        (addr, value) => this.write32(addr, value)
+        (addr) => cpu.get_current_apic().read32(addr),
+        (addr, value) => cpu.get_current_apic().write32(addr, value)
     );
 }
 
@@ -104,7 +119,7 @@ APIC.prototype.read32 = function(addr)
     {
         case 0x20:
             dbg_log("APIC read id", LOG_APIC);
-            return this.apic_id;
+            return this.apic_id << 24;
 
         case 0x30:
             // version
@@ -286,6 +301,14 @@ APIC.prototype.write32 = function(addr, value)
             value &= ~(1 << 12);
             this.icr0 = value;
 
+            if(delivery_mode === IOAPIC_DELIVERY_INIT && !(value & APIC_ICR_LEVEL_ASSERT))
+            {
+                // INIT level de-assert, only used for synchronising arbitration ids
+                break;
+            }
+
+            var apics = this.cpu.devices.apics;
+
             if(destination_shorthand === 0)
             {
                 // no shorthand
@@ -294,16 +317,17 @@ APIC.prototype.write32 = function(addr, value)
             else if(destination_shorthand === 1)
             {
                 // self
-                this.deliver(vector, IOAPIC_DELIVERY_FIXED, is_level);
+                this.send_ipi(vector, delivery_mode, is_level);
             }
             else if(destination_shorthand === 2)
             {
                 // all including self
-                this.deliver(vector, delivery_mode, is_level);
+                apics.forEach(apic => apic.send_ipi(vector, delivery_mode, is_level));
             }
             else if(destination_shorthand === 3)
             {
                 // all but self
+                apics.forEach(apic => apic !== this && apic.send_ipi(vector, delivery_mode, is_level));
             }
             else
             {
@@ -419,19 +443,73 @@ APIC.prototype.timer = function(now)
 
 APIC.prototype.route = function(vector, mode, is_level, destination, destination_mode)
 {
-    // TODO
-    this.deliver(vector, mode, is_level);
+    var apics = this.cpu.devices.apics;
+
+    if(apics.length === 1)
+    {
+        this.send_ipi(vector, mode, is_level);
+        return;
+    }
+
+    var targets = apics.filter(apic => apic.matches_destination(destination, destination_mode));
+
+    if(targets.length === 0)
+    {
+        dbg_log("No apic for destination " + h(destination, 2) + " (" + DESTINATION_MODES[destination_mode] + ")", LOG_APIC);
+        return;
+    }
+
+    if(mode === IOAPIC_DELIVERY_LOWEST_PRIORITY)
+    {
+        // TODO: Take the tpr into account
+        targets.length = 1;
+    }
+
+    targets.forEach(apic => apic.send_ipi(vector, mode, is_level));
 };
 
-APIC.prototype.deliver = function(vector, mode, is_level)
+APIC.prototype.matches_destination = function(destination, destination_mode)
 {
-    APIC_LOG_VERBOSE && dbg_log("Deliver " + h(vector, 2) + " mode=" + mode + " level=" + is_level, LOG_APIC);
+    if(destination_mode === 0)
+    {
+        // physical
+        return destination === 0xFF || destination === this.apic_id;
+    }
+
+    var logical_id = this.local_destination >>> 24;
 
+    if((this.destination_format >>> 28) === 0xF)
+    {
+        // flat model
+        return (logical_id & destination) !== 0;
+    }
+    else
+    {
+        // cluster model
+        return (destination >> 4 === 0xF || destination >> 4 === logical_id >> 4) &&
+            (logical_id & destination & 0xF) !== 0;
+    }
+};
+
+APIC.prototype.send_ipi = function(vector, mode, is_level)
+{
     if(mode === IOAPIC_DELIVERY_INIT)
     {
-        // TODO
-        return;
+        this.cpu.smp_send_init(this.apic_id);
+    }
+    else if(mode === APIC_DELIVERY_STARTUP)
+    {
+        this.cpu.smp_send_startup(this.apic_id, vector);
     }
+    else
+    {
+        this.deliver(vector, mode, is_level);
+    }
+};
+
+APIC.prototype.deliver = function(vector, mode, is_level)
+{
+    APIC_LOG_VERBOSE && dbg_log("Deliver " + h(vector, 2) + " mode=" + mode + " level=" + is_level, LOG_APIC);
 
     if(mode === IOAPIC_DELIVERY_NMI)
     {
@@ -503,7 +581,11 @@ APIC.prototype.check_vector = function()
         return;
     }
 
-    this.cpu.handle_irqs();
+    if(this.apic_id === this.cpu.smp_current_cpu())
+    {
+        this.cpu.handle_irqs();
+    }
+    // otherwise handled when the cpu is switched in
 };
 
 APIC.prototype.acknowledge_irq = function()
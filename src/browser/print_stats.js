@@ -13,9 +13,12 @@ const print_stats = {
 
         const stat_names = [
             "COMPILE",
+            "COMPILE_BASELINE",
+            "COMPILE_PROMOTE_MODULE",
             "COMPILE_SKIPPED_NO_NEW_ENTRY_POINTS",
             "COMPILE_WRONG_ADDRESS_SPACE",
             "COMPILE_CUT_OFF_AT_END_OF_PAGE",
+            "COMPILE_CONTINUED_INTO_NEXT_PAGE",
             "COMPILE_WITH_LOOP_SAFETY",
             "COMPILE_PAGE",
             "COMPILE_PAGE/COMPILE",
@@ -26,6 +29,9 @@ const print_stats = {
             "COMPILE_DISPATCHER",
             "COMPILE_ENTRY_POINT",
             "COMPILE_WASM_TOTAL_BYTES",
+            "COMPILE_FAILED",
+            "JIT_CACHE_HIT",
+            "JIT_CACHE_MISS",
             "COMPILE_WASM_TOTAL_BYTES/COMPILE_PAGE",
             "RUN_INTERPRETED",
             "RUN_INTERPRETED_NEW_PAGE",
@@ -38,6 +44,7 @@ const print_stats = {
             "RUN_INTERPRETED_DIFFERENT_STATE_IS32",
             "RUN_INTERPRETED_DIFFERENT_STATE_SS32",
             "RUN_INTERPRETED_MISSED_COMPILED_ENTRY_RUN_INTERPRETED",
+            "RUN_INTERPRETED_MAPPED_MEMORY",
             "RUN_INTERPRETED_STEPS",
             "RUN_FROM_CACHE",
             "RUN_FROM_CACHE_STEPS",
@@ -46,6 +53,10 @@ const print_stats = {
             "DIRECT_EXIT",
             "INDIRECT_JUMP",
             "INDIRECT_JUMP_NO_ENTRY",
+            "INDIRECT_JUMP_INLINE_CACHE_HIT",
+            "INDIRECT_JUMP_INLINE_CACHE_MISS",
+            "RETURN_STACK_HIT",
+            "RETURN_STACK_MISS",
             "NORMAL_PAGE_CHANGE",
             "NORMAL_FALLTHRU",
             "NORMAL_FALLTHRU_WITH_TARGET_BLOCK",
@@ -87,19 +98,24 @@ const print_stats = {
             "SAFE_READ_WRITE_SLOW_IN_MAPPED_RANGE",
             "SAFE_READ_WRITE_SLOW_READ_ONLY",
             "SAFE_READ_WRITE_SLOW_HAS_CODE",
+            "REP_STRING_FAST",
+            "REP_STRING_SLOW",
             "PAGE_FAULT",
             "TLB_MISS",
             "MAIN_LOOP",
             "MAIN_LOOP_IDLE",
             "DO_MANY_CYCLES",
             "CYCLE_INTERNAL",
-            "INVALIDATE_ALL_MODULES_NO_FREE_WASM_INDICES",
+            "INVALIDATE_MODULES_NO_FREE_WASM_INDICES",
+            "INVALIDATE_MODULE_EVICTED",
             "INVALIDATE_MODULE_WRITTEN_WHILE_COMPILED",
             "INVALIDATE_MODULE_UNUSED_AFTER_OVERWRITE",
             "INVALIDATE_MODULE_DIRTY_PAGE",
             "INVALIDATE_PAGE_HAD_CODE",
             "INVALIDATE_PAGE_HAD_ENTRY_POINTS",
             "DIRTY_PAGE_DID_NOT_HAVE_CODE",
+            "DIRTY_PAGE_DID_NOT_WRITE_CODE",
+            "DIRTY_PAGE_CODE_UNCHANGED",
             "RUN_FROM_CACHE_EXIT_SAME_PAGE",
             "RUN_FROM_CACHE_EXIT_NEAR_END_OF_PAGE",
             "RUN_FROM_CACHE_EXIT_DIFFERENT_PAGE",
@@ -107,6 +123,7 @@ const print_stats = {
             "FULL_CLEAR_TLB",
             "TLB_FULL",
             "TLB_GLOBAL_FULL",
+            "SMP_CPU_SWITCH",
             "MODRM_SIMPLE_REG",
             "MODRM_SIMPLE_REG_WITH_OFFSET",
             "MODRM_SIMPLE_CONST_OFFSET",
@@ -117,6 +134,7 @@ const print_stats = {
             "SEG_OFFSET_NOT_OPTIMISED_FS",
             "SEG_OFFSET_NOT_OPTIMISED_GS",
             "SEG_OFFSET_NOT_OPTIMISED_NOT_FLAT",
+            "LOCKED_OPERATION",
         ];
 
         let j = 0;
@@ -157,6 +175,8 @@ const print_stats = {
         text += "MAX_PAGES=" + cpu.wm.exports["get_jit_config"](1) + "\n";
         text += "JIT_USE_LOOP_SAFETY=" + Boolean(cpu.wm.exports["get_jit_config"](2)) + "\n";
         text += "MAX_EXTRA_BASIC_BLOCKS=" + cpu.wm.exports["get_jit_config"](3) + "\n";
+        text += "JIT_CACHE_RECORDING=" + Boolean(cpu.wm.exports["get_jit_config"](4)) + "\n";
+        text += "JIT_USE_SIMD=" + Boolean(cpu.wm.exports["get_jit_config"](5)) + "\n";
 
         return text;
     },
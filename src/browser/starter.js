@@ -8,8 +8,11 @@
  * Options can have the following properties (all optional, default in parenthesis):
  *
  * - `memory_size number` (16 * 1024 * 1024) - The memory size in bytes, should
- *   be a power of 2.
+ *   be a power of 2. At most 3.5 GiB minus 128 KiB, the part above 3 GiB is
+ *   visible to the guest above 4 GiB.
  * - `vga_memory_size number` (8 * 1024 * 1024) - VGA memory size in bytes.
+ * - `cpu_count number` (1) - The number of cpus, requires `acpi`. The cpus
+ *   are time-sliced on one thread.
  *
  * - `autostart boolean` (false) - If emulation should be started when emulator
  *   is ready.
@@ -36,6 +39,14 @@
  * - `initial_state Object` (Normal boot) - An initial state to load, see
  *   [`restore_state`](#restore_statearraybuffer-state) and below.
  *
+ * - `jit_cache Object` (No cache) - Compiled code exported by
+ *   [`export_jit_cache`](#export_jit_cache-arraybuffer) from a previous run
+ *   of the same version of v86 with the same memory size, see below.
+ * - `record_jit_cache boolean` (false) - Keep a copy of compiled code, so that
+ *   it can be exported using `export_jit_cache`.
+ * - `disable_jit_simd boolean` (false) - Don't use wasm simd instructions for
+ *   sse code, even if the browser supports them.
+ *
  * - `filesystem Object` (No 9p filesystem) - A 9p filesystem, see
  *   [filesystem.md](filesystem.md).
  *
@@ -114,7 +125,7 @@ function V86(options)
         "abort": function() { dbg_assert(false); },
         "microtick": v86.microtick,
         "get_rand_int": function() { return v86util.get_rand_int(); },
-        "apic_acknowledge_irq": function() { return cpu.devices.apic.acknowledge_irq(); },
+        "apic_acknowledge_irq": function() { return cpu.get_current_apic().acknowledge_irq(); },
 
         "io_port_read8": function(addr) { return cpu.io.port_read8(addr); },
         "io_port_read16": function(addr) { return cpu.io.port_read16(addr); },
@@ -263,7 +274,10 @@ V86.prototype.continue_init = async function(emulator, options)
         options.hda ? BOOT_ORDER_HD_FIRST : BOOT_ORDER_CD_FIRST;
 
     settings.acpi = options.acpi;
+    settings.cpu_count = options.cpu_count;
     settings.disable_jit = options.disable_jit;
+    settings.disable_jit_simd = options.disable_jit_simd;
+    settings.record_jit_cache = options.record_jit_cache;
     settings.load_devices = true;
     settings.log_level = options.log_level;
     settings.memory_size = options.memory_size || 64 * 1024 * 1024;
@@ -368,6 +382,9 @@ V86.prototype.continue_init = async function(emulator, options)
             case "initial_state":
                 settings.initial_state = buffer.buffer;
                 break;
+            case "jit_cache":
+                settings.jit_cache = buffer.buffer;
+                break;
             case "fs9p_json":
                 settings.fs9p_json = buffer;
                 break;
@@ -396,7 +413,7 @@ V86.prototype.continue_init = async function(emulator, options)
 
         if(name === "bios" || name === "vga_bios" ||
             name === "initial_state" || name === "multiboot" ||
-            name === "bzimage" || name === "initrd")
+            name === "bzimage" || name === "initrd" || name === "jit_cache")
         {
             // Ignore async for these because they must be available before boot.
             // This should make result.buffer available after the object is loaded
@@ -433,6 +450,7 @@ V86.prototype.continue_init = async function(emulator, options)
     add_file("fda", options.fda);
     add_file("fdb", options.fdb);
     add_file("initial_state", options.initial_state);
+    add_file("jit_cache", options.jit_cache);
     add_file("multiboot", options.multiboot);
     add_file("bzimage", options.bzimage);
     add_file("initrd", options.initrd);
@@ -864,6 +882,22 @@ V86.prototype.save_state = async function()
     return this.v86.save_state();
 };
 
+/**
+ * Export the code that has been compiled so far, to be passed as the
+ * `jit_cache` option to future instances. Requires the `record_jit_cache`
+ * option. Code is only used if the memory it has been compiled from has the
+ * same contents, for example when booting the same image again. Contents are
+ * compared using a non-cryptographic hash, so only import caches exported
+ * from trusted guests.
+ *
+ * @return {ArrayBuffer}
+ * @export
+ */
+V86.prototype.export_jit_cache = function()
+{
+    return this.v86.cpu.export_jit_cache();
+};
+
 /**
  * @return {number}
  * @ignore
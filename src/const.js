@@ -119,6 +119,27 @@ var
     /** @const */
     MMAP_MAX = 0x100000000;
 
+var
+    /**
+     * Memory beyond this address is moved above 4 GiB (HIGH_MEMORY_ADDRESS),
+     * see map_physical_address in memory.rs
+     *
+     * @const
+     */
+    HIGH_MEMORY_REMAP_START = 0xC0000000,
+    /** @const */
+    HIGH_MEMORY_ADDRESS = 0x100000000,
+    /**
+     * Memory must end below the vga lfb (0xE0000000), with one block
+     * left for unmapped physical addresses
+     *
+     * @const
+     */
+    MAX_MEMORY_SIZE = 0xE0000000 - MMAP_BLOCK_SIZE;
+
+/** @const */
+var SMP_MAX_CPUS = 16;
+
 /** @const */
 var CR0_PG = 1 << 31;
 /** @const */
@@ -230,6 +230,11 @@ CPU.prototype.wasm_patch = function()
 
     this.set_jit_config = get_import("set_jit_config");
 
+    this.jit_cache_export = get_import("jit_cache_export");
+    this.jit_cache_import = get_import("jit_cache_import");
+    this.jit_cache_get_buffer = get_import("jit_cache_get_buffer");
+    this.jit_cache_allocate_buffer = get_import("jit_cache_allocate_buffer");
+
     this.read8 = get_import("read8");
     this.read16 = get_import("read16");
     this.read32s = get_import("read32s");
@@ -250,6 +255,8 @@ CPU.prototype.wasm_patch = function()
 
     this.clear_tlb = get_import("clear_tlb");
     this.full_clear_tlb = get_import("full_clear_tlb");
+    this.get_a20 = get_import("get_a20");
+    this.set_a20 = get_import("set_a20");
     this.update_state_flags = get_import("update_state_flags");
 
     this.set_tsc = get_import("set_tsc");
@@ -257,6 +264,17 @@ CPU.prototype.wasm_patch = function()
 
     this.set_cpuid_level = get_import("set_cpuid_level");
 
+    this.smp_set_cpu_count = get_import("smp_set_cpu_count");
+    this.smp_get_cpu_count = get_import("smp_get_cpu_count");
+    this.smp_current_cpu = get_import("smp_current_cpu");
+    this.smp_reset = get_import("smp_reset");
+    this.smp_send_init = get_import("smp_send_init");
+    this.smp_send_startup = get_import("smp_send_startup");
+    this.smp_get_cpu_state = get_import("smp_get_cpu_state");
+    this.smp_get_cpu_state_size = get_import("smp_get_cpu_state_size");
+    this.smp_is_running = get_import("smp_is_running");
+    this.smp_set_running = get_import("smp_set_running");
+
     this.pic_set_irq = get_import("pic_set_irq");
     this.pic_clear_irq = get_import("pic_clear_irq");
 
@@ -268,6 +286,7 @@ CPU.prototype.wasm_patch = function()
     this.jit_clear_cache = get_import("jit_clear_cache_js");
     this.jit_dirty_cache = get_import("jit_dirty_cache");
     this.codegen_finalize_finished = get_import("codegen_finalize_finished");
+    this.codegen_finalize_failed = get_import("codegen_finalize_failed");
 
     this.allocate_memory = get_import("allocate_memory");
     this.zero_memory = get_import("zero_memory");
@@ -300,9 +319,15 @@ CPU.prototype.wasm_patch = function()
     this.port4D1_read = get_import("port4D1_read");
     this.port4D0_write = get_import("port4D0_write");
     this.port4D1_write = get_import("port4D1_write");
+
+    this.portF0_write = get_import("portF0_write");
 };
 
-CPU.prototype.jit_force_generate = function(addr)
+/**
+ * @param {number} addr
+ * @param {boolean=} baseline compile using the baseline tier instead of the optimising tier
+ */
+CPU.prototype.jit_force_generate = function(addr, baseline)
 {
     if(!this.jit_force_generate_unsafe)
     {
@@ -310,7 +335,32 @@ CPU.prototype.jit_force_generate = function(addr)
         return;
     }
 
-    this.jit_force_generate_unsafe(addr);
+    this.jit_force_generate_unsafe(addr, !!baseline);
+};
+
+/**
+ * @return {ArrayBuffer}
+ */
+CPU.prototype.export_jit_cache = function()
+{
+    const len = this.jit_cache_export();
+    const ptr = this.jit_cache_get_buffer() >>> 0;
+    return new Uint8Array(this.wasm_memory.buffer, ptr, len).slice().buffer;
+};
+
+/**
+ * @param {ArrayBuffer} buffer
+ */
+CPU.prototype.import_jit_cache = function(buffer)
+{
+    const data = new Uint8Array(buffer);
+    const ptr = this.jit_cache_allocate_buffer(data.length) >>> 0;
+    new Uint8Array(this.wasm_memory.buffer, ptr, data.length).set(data);
+
+    if(!this.jit_cache_import())
+    {
+        console.warn("Warning: The jit cache is from a different version of v86 or memory size and has been ignored");
+    }
 };
 
 CPU.prototype.jit_clear_func = function(index)
@@ -418,9 +468,54 @@ CPU.prototype.get_state = function()
     state[81] = this.devices.uart3;
     state[82] = this.devices.virtio_console;
 
+    state[83] = this.get_a20();
+
+    if(this.smp_get_cpu_count() > 1)
+    {
+        state[84] = this.devices.apics.slice(1);
+        state[85] = this.get_state_smp();
+    }
+
     return state;
 };
 
+CPU.prototype.get_state_smp = function()
+{
+    // main_loop returns with the bootstrap processor switched in, the others are saved here
+    dbg_assert(this.smp_current_cpu() === 0);
+
+    const size = this.smp_get_cpu_state_size();
+    const cpus = [];
+
+    for(let i = 1; i < this.smp_get_cpu_count(); i++)
+    {
+        const cpu_state = new Uint8Array(this.wasm_memory.buffer, this.smp_get_cpu_state(i), size);
+        cpus.push([this.smp_is_running(i), cpu_state.slice()]);
+    }
+
+    return cpus;
+};
+
+CPU.prototype.set_state_smp = function(apics, cpus)
+{
+    if(cpus.length !== this.smp_get_cpu_count() - 1 || apics.length !== cpus.length)
+    {
+        console.warn("Note: Cpu count mismatch. we=" + this.smp_get_cpu_count() + " state=" + (cpus.length + 1));
+        return;
+    }
+
+    const size = this.smp_get_cpu_state_size();
+
+    for(let i = 1; i < this.smp_get_cpu_count(); i++)
+    {
+        const [running, cpu_state] = cpus[i - 1];
+        dbg_assert(cpu_state.length === size);
+        new Uint8Array(this.wasm_memory.buffer, this.smp_get_cpu_state(i), size).set(cpu_state);
+        this.smp_set_running(i, running);
+        this.devices.apics[i].set_state(apics[i - 1]);
+    }
+};
+
 CPU.prototype.get_state_pic = function()
 {
     const pic_size = 13;
@@ -535,6 +630,14 @@ CPU.prototype.set_state = function(state)
 
     this.fw_value = state[62];
 
+    // a20 is enabled in old state images
+    this.set_a20(state[83] === undefined || state[83]);
+
+    if(state[85])
+    {
+        this.set_state_smp(state[84], state[85]);
+    }
+
     this.devices.ioapic && this.devices.ioapic.set_state(state[63]);
 
     this.tss_size_32[0] = state[64];
@@ -604,13 +707,13 @@ CPU.prototype.pack_memory = function()
 {
     dbg_assert((this.mem8.length & 0xFFF) === 0);
 
-    const page_count = this.mem8.length >> 12;
+    const page_count = this.mem8.length >>> 12;
     const nonzero_pages = [];
 
     for(let page = 0; page < page_count; page++)
     {
-        const offset = page << 12;
-        const view = this.mem32s.subarray(offset >> 2, offset + 0x1000 >> 2);
+        const offset = page * 4096;
+        const view = this.mem32s.subarray(offset >>> 2, offset + 0x1000 >>> 2);
         let is_zero = true;
 
         for(let i = 0; i < view.length; i++)
@@ -629,15 +732,15 @@ CPU.prototype.pack_memory = function()
     }
 
     const bitmap = new v86util.Bitmap(page_count);
-    const packed_memory = new Uint8Array(nonzero_pages.length << 12);
+    const packed_memory = new Uint8Array(nonzero_pages.length * 4096);
 
     for(let [i, page] of nonzero_pages.entries())
     {
         bitmap.set(page, 1);
 
-        const offset = page << 12;
+        const offset = page * 4096;
         const page_contents = this.mem8.subarray(offset, offset + 0x1000);
-        packed_memory.set(page_contents, i << 12);
+        packed_memory.set(page_contents, i * 4096);
     }
 
     return { bitmap, packed_memory };
@@ -647,16 +750,16 @@ CPU.prototype.unpack_memory = function(bitmap, packed_memory)
 {
     this.zero_memory(this.memory_size[0]);
 
-    const page_count = this.memory_size[0] >> 12;
+    const page_count = this.memory_size[0] >>> 12;
     let packed_page = 0;
 
     for(let page = 0; page < page_count; page++)
     {
         if(bitmap.get(page))
         {
-            let offset = packed_page << 12;
+            let offset = packed_page * 4096;
             let view = packed_memory.subarray(offset, offset + 0x1000);
-            this.mem8.set(view, page << 12);
+            this.mem8.set(view, page * 4096);
             packed_page++;
         }
     }
@@ -665,6 +768,7 @@ CPU.prototype.unpack_memory = function(bitmap, packed_memory)
 CPU.prototype.reboot_internal = function()
 {
     this.reset_cpu();
+    this.smp_reset();
 
     this.fw_value = [];
 
@@ -692,13 +796,15 @@ CPU.prototype.create_memory = function(size)
     {
         size = 1024 * 1024;
     }
-    else if((size | 0) < 0)
+    else if(size > MAX_MEMORY_SIZE)
     {
-        size = Math.pow(2, 31) - MMAP_BLOCK_SIZE;
+        // mem8 and the physical addresses used by the mmap handlers are 32-bit
+        throw new Error("memory_size is too large: " + size + " bytes, the maximum is " +
+            MAX_MEMORY_SIZE + " bytes");
     }
 
-    size = ((size - 1) | (MMAP_BLOCK_SIZE - 1)) + 1 | 0;
-    dbg_assert((size | 0) > 0);
+    size = ((size - 1) | (MMAP_BLOCK_SIZE - 1)) + 1 >>> 0;
+    dbg_assert(size > 0);
     dbg_assert((size & MMAP_BLOCK_SIZE - 1) === 0);
 
     console.assert(this.memory_size[0] === 0, "Expected uninitialised memory");
@@ -707,8 +813,13 @@ CPU.prototype.create_memory = function(size)
 
     const memory_offset = this.allocate_memory(size);
 
+    if(memory_offset === 0)
+    {
+        throw new Error("Could not allocate " + (size >>> 20) + "M of memory");
+    }
+
     this.mem8 = v86util.view(Uint8Array, this.wasm_memory, memory_offset, size);
-    this.mem32s = v86util.view(Uint32Array, this.wasm_memory, memory_offset, size >> 2);
+    this.mem32s = v86util.view(Uint32Array, this.wasm_memory, memory_offset, size >>> 2);
 };
 
 CPU.prototype.init = function(settings, device_bus)
@@ -727,12 +838,50 @@ CPU.prototype.init = function(settings, device_bus)
         this.set_jit_config(0, 1);
     }
 
+    if(settings.record_jit_cache)
+    {
+        this.set_jit_config(4, 1);
+    }
+
+    // Module returning i8x16.popcnt(i8x16.splat(0)), only valid if the host supports wasm simd
+    const simd_test_module = new Uint8Array([
+        0, 97, 115, 109, 1, 0, 0, 0, 1, 5, 1, 96, 0, 1, 123, 3, 2, 1, 0, 10, 10, 1, 8, 0, 65, 0,
+        253, 15, 253, 98, 11,
+    ]);
+    if(!settings.disable_jit_simd && WebAssembly.validate(simd_test_module))
+    {
+        this.set_jit_config(5, 1);
+    }
+
+    // Module calling memory.fill, only valid if the host supports wasm bulk memory operations
+    const bulk_memory_test_module = new Uint8Array([
+        0, 97, 115, 109, 1, 0, 0, 0, 1, 4, 1, 96, 0, 0, 3, 2, 1, 0, 5, 3, 1, 0, 0, 10, 13, 1, 11,
+        0, 65, 0, 65, 0, 65, 0, 252, 11, 0, 11,
+    ]);
+    if(WebAssembly.validate(bulk_memory_test_module))
+    {
+        this.set_jit_config(6, 1);
+    }
+
+    if(settings.jit_cache)
+    {
+        this.import_jit_cache(settings.jit_cache);
+    }
+
     settings.cpuid_level && this.set_cpuid_level(settings.cpuid_level);
 
     this.acpi_enabled[0] = +settings.acpi;
 
     this.reset_cpu();
 
+    let cpu_count = settings.cpu_count || 1;
+    if(cpu_count > 1 && !settings.acpi)
+    {
+        console.warn("Multiple cpus require acpi, using one cpu");
+        cpu_count = 1;
+    }
+    this.smp_set_cpu_count(Math.min(cpu_count, SMP_MAX_CPUS));
+
     var io = new IO(this);
     this.io = io;
 
@@ -762,12 +911,14 @@ CPU.prototype.init = function(settings, device_bus)
 
     io.register_read(0x92, this, function()
     {
-        return a20_byte;
+        // bit 1 reflects the a20 gate, which can also be changed through the keyboard controller
+        return a20_byte & ~2 | (this.get_a20() ? 2 : 0);
     });
 
     io.register_write(0x92, this, function(out_byte)
     {
         a20_byte = out_byte;
+        this.set_a20((out_byte & 2) === 2);
     });
 
     io.register_read(0x511, this, function()
@@ -818,19 +969,22 @@ CPU.prototype.init = function(settings, device_bus)
         }
         else if(value === FW_CFG_RAM_SIZE)
         {
-            this.fw_value = i32(this.memory_size[0]);
+            // 64-bit value
+            this.fw_value = new Uint8Array(8);
+            this.fw_value.set(i32(this.memory_size[0]));
         }
         else if(value === FW_CFG_NB_CPUS)
         {
-            this.fw_value = i32(1);
+            this.fw_value = i32(this.smp_get_cpu_count());
         }
         else if(value === FW_CFG_MAX_CPUS)
         {
-            this.fw_value = i32(1);
+            this.fw_value = i32(this.smp_get_cpu_count());
         }
         else if(value === FW_CFG_NUMA)
         {
-            this.fw_value = new Uint8Array(16);
+            // number of nodes (0), followed by the node of each cpu
+            this.fw_value = new Uint8Array(8 + 8 * this.smp_get_cpu_count());
         }
         else if(value === FW_CFG_FILE_DIR)
         {
@@ -898,6 +1052,9 @@ CPU.prototype.init = function(settings, device_bus)
     io.register_write(0x4D0, this, this.port4D0_write);
     io.register_write(0x4D1, this, this.port4D1_write);
 
+    // clears FERR# (irq 13) after an fpu exception with cr0.ne=0
+    io.register_write(0xF0, this, this.portF0_write);
+
     this.devices = {};
 
     // TODO: Make this more configurable
@@ -908,7 +1065,13 @@ CPU.prototype.init = function(settings, device_bus)
         if(this.acpi_enabled[0])
         {
             this.devices.ioapic = new IOAPIC(this);
-            this.devices.apic = new APIC(this);
+            this.devices.apics = [];
+            for(let i = 0; i < this.smp_get_cpu_count(); i++)
+            {
+                this.devices.apics.push(new APIC(this, i));
+            }
+            // local apic of the bootstrap processor
+            this.devices.apic = this.devices.apics[0];
             this.devices.acpi = new ACPI(this);
         }
 
@@ -1101,7 +1264,11 @@ CPU.prototype.load_multiboot_option_rom = function(buffer, initrd, cmdline)
                 let was_memory = false;
                 for(let addr = 0; addr < MMAP_MAX; addr += MMAP_BLOCK_SIZE)
                 {
-                    if(was_memory && cpu.memory_map_read8[addr >>> MMAP_BLOCK_BITS] !== undefined)
+                    // memory above HIGH_MEMORY_REMAP_START is visible at HIGH_MEMORY_ADDRESS instead
+                    const is_memory = addr < HIGH_MEMORY_REMAP_START &&
+                        cpu.memory_map_read8[addr >>> MMAP_BLOCK_BITS] === undefined;
+
+                    if(was_memory && !is_memory)
                     {
                         cpu.write32(multiboot_data, 20); // size
                         cpu.write32(multiboot_data + 4, start); //addr (64-bit)
@@ -1113,13 +1280,25 @@ CPU.prototype.load_multiboot_option_rom = function(buffer, initrd, cmdline)
                         multiboot_mmap_count += 24;
                         was_memory = false;
                     }
-                    else if(!was_memory && cpu.memory_map_read8[addr >>> MMAP_BLOCK_BITS] === undefined)
+                    else if(!was_memory && is_memory)
                     {
                         start = addr;
                         was_memory = true;
                     }
                 }
                 dbg_assert (!was_memory, "top of 4GB shouldn't have memory");
+
+                if(cpu.memory_size[0] > HIGH_MEMORY_REMAP_START)
+                {
+                    cpu.write32(multiboot_data, 20); // size
+                    cpu.write32(multiboot_data + 4, 0); //addr (64-bit)
+                    cpu.write32(multiboot_data + 8, HIGH_MEMORY_ADDRESS / 0x100000000);
+                    cpu.write32(multiboot_data + 12, cpu.memory_size[0] - HIGH_MEMORY_REMAP_START); // len (64-bit)
+                    cpu.write32(multiboot_data + 16, 0);
+                    cpu.write32(multiboot_data + 20, 1); // type (MULTIBOOT_MEMORY_AVAILABLE)
+                    multiboot_data += 24;
+                    multiboot_mmap_count += 24;
+                }
                 cpu.write32(multiboot_info_addr + 44, multiboot_mmap_count);
             }
 
@@ -1184,7 +1363,7 @@ CPU.prototype.load_multiboot_option_rom = function(buffer, initrd, cmdline)
 
                         dbg_assert(program.filesz <= program.memsz);
 
-                        if(program.paddr + program.memsz < cpu.memory_size[0])
+                        if(program.paddr + program.memsz < Math.min(cpu.memory_size[0], HIGH_MEMORY_REMAP_START))
                         {
                             if(program.filesz) // offset might be outside of buffer if filesz is 0
                             {
@@ -1374,7 +1553,7 @@ CPU.prototype.fill_cmos = function(rtc, settings)
     var memory_above_1m = 0; // in k
     if(this.memory_size[0] >= 1024 * 1024)
     {
-        memory_above_1m = (this.memory_size[0] - 1024 * 1024) >> 10;
+        memory_above_1m = (this.memory_size[0] - 1024 * 1024) >>> 10;
         memory_above_1m = Math.min(memory_above_1m, 0xFFFF);
     }
 
@@ -1383,23 +1562,26 @@ CPU.prototype.fill_cmos = function(rtc, settings)
     rtc.cmos_write(CMOS_MEM_EXTMEM_LOW, memory_above_1m & 0xFF);
     rtc.cmos_write(CMOS_MEM_EXTMEM_HIGH, memory_above_1m >> 8 & 0xFF);
 
+    var memory_below_4g = Math.min(this.memory_size[0], HIGH_MEMORY_REMAP_START);
+
     var memory_above_16m = 0; // in 64k blocks
-    if(this.memory_size[0] >= 16 * 1024 * 1024)
+    if(memory_below_4g >= 16 * 1024 * 1024)
     {
-        memory_above_16m = (this.memory_size[0] - 16 * 1024 * 1024) >> 16;
+        memory_above_16m = (memory_below_4g - 16 * 1024 * 1024) >>> 16;
         memory_above_16m = Math.min(memory_above_16m, 0xFFFF);
     }
     rtc.cmos_write(CMOS_MEM_EXTMEM2_LOW, memory_above_16m & 0xFF);
     rtc.cmos_write(CMOS_MEM_EXTMEM2_HIGH, memory_above_16m >> 8 & 0xFF);
 
-    // memory above 4G (not supported by this emulator)
-    rtc.cmos_write(CMOS_MEM_HIGHMEM_LOW, 0);
-    rtc.cmos_write(CMOS_MEM_HIGHMEM_MID, 0);
-    rtc.cmos_write(CMOS_MEM_HIGHMEM_HIGH, 0);
+    // memory above 4G, remapped from above HIGH_MEMORY_REMAP_START (in 64k blocks)
+    var memory_above_4g = (this.memory_size[0] - memory_below_4g) >>> 16;
+    rtc.cmos_write(CMOS_MEM_HIGHMEM_LOW, memory_above_4g & 0xFF);
+    rtc.cmos_write(CMOS_MEM_HIGHMEM_MID, memory_above_4g >> 8 & 0xFF);
+    rtc.cmos_write(CMOS_MEM_HIGHMEM_HIGH, memory_above_4g >> 16 & 0xFF);
 
     rtc.cmos_write(CMOS_EQUIPMENT_INFO, 0x2F);
 
-    rtc.cmos_write(CMOS_BIOS_SMP_COUNT, 0);
+    rtc.cmos_write(CMOS_BIOS_SMP_COUNT, this.smp_get_cpu_count() - 1);
 
     // Used by bochs BIOS to skip the boot menu delay.
     if (settings.fastboot) rtc.cmos_write(0x3f, 0x01);
@@ -1545,6 +1727,9 @@ CPU.prototype.codegen_finalize = function(wasm_table_index, start, state_flags,
         {
             this.test_hook_did_finalize_wasm(code);
         }
+    }, error => {
+        dbg_log("Instantiating generated module failed: " + error, LOG_CPU);
+        this.codegen_finalize_failed(wasm_table_index, start);
     });
 
     if(DEBUG)
@@ -1636,12 +1821,20 @@ CPU.prototype.run_hardware_timers = function(acpi_enabled, now)
     if(acpi_enabled)
     {
         acpi_time = this.devices.acpi.timer(now);
-        apic_time = this.devices.apic.timer(now);
+        for(const apic of this.devices.apics)
+        {
+            apic_time = Math.min(apic_time, apic.timer(now));
+        }
     }
 
     return Math.min(pit_time, rtc_time, acpi_time, apic_time);
 };
 
+CPU.prototype.get_current_apic = function()
+{
+    return this.devices.apics[this.smp_current_cpu()];
+};
+
 CPU.prototype.device_raise_irq = function(i)
 {
     dbg_assert(arguments.length === 1);
@@ -22,7 +22,7 @@ function IO(cpu)
 
     var memory_size = cpu.memory_size[0];
 
-    for(var i = 0; (i << MMAP_BLOCK_BITS) < memory_size; i++)
+    for(var i = 0; i < (memory_size >>> MMAP_BLOCK_BITS); i++)
     {
         // avoid sparse arrays
         cpu.memory_map_read8[i] = cpu.memory_map_write8[i] = undefined;
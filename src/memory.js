@@ -69,6 +69,23 @@ CPU.prototype.mmap_write128 = function(addr, value0, value1, value2, value3)
     write_func32(addr + 12, value3);
 };
 
+/**
+ * Translate a guest physical address, which may be above 4 GiB, into the address used by
+ * read_blob, write_blob and the read and write functions (see map_physical_address in memory.rs)
+ * @param {number} addr
+ * @return {number}
+ */
+CPU.prototype.map_physical_address = function(addr)
+{
+    if(addr >= HIGH_MEMORY_ADDRESS)
+    {
+        const offset = addr - HIGH_MEMORY_ADDRESS + HIGH_MEMORY_REMAP_START;
+        dbg_assert(offset < this.memory_size[0], "Physical address outside of memory: " + h(addr));
+        return offset;
+    }
+    return addr >>> 0;
+};
+
 /**
  * @param {Array.<number>|Uint8Array} blob
  * @param {number} offset
@@ -681,8 +681,8 @@ PS2.prototype.port60_write = function(write_byte)
     {
         this.read_controller_output_port = false;
         this.controller_output_port = write_byte;
-        // If we ever want to implement A20 masking, here is where
-        // we should turn the masking off if the second bit is on
+        // the second bit controls the a20 gate
+        this.cpu.set_a20((write_byte & 2) === 2);
     }
     else
     {
@@ -763,6 +763,16 @@ PS2.prototype.port64_write = function(write_byte)
     case 0xD4:
         this.next_is_mouse_command = true;
         break;
+    case 0xDD:
+        // Disable A20
+        dbg_log("Disable A20", LOG_PS2);
+        this.cpu.set_a20(false);
+        break;
+    case 0xDF:
+        // Enable A20
+        dbg_log("Enable A20", LOG_PS2);
+        this.cpu.set_a20(true);
+        break;
     case 0xA7:
         // Disable second port
         dbg_log("Disable second port", LOG_PS2);
@@ -1,7 +1,7 @@
 #![allow(non_snake_case)]
 
 use cpu_context::CpuContext;
-use prefix::{PREFIX_66, PREFIX_67, PREFIX_F2, PREFIX_F3};
+use prefix::{PREFIX_66, PREFIX_67, PREFIX_F2, PREFIX_F3, PREFIX_LOCK};
 use regs::{CS, DS, ES, FS, GS, SS};
 
 #[derive(PartialEq, Eq)]
@@ -57,6 +57,18 @@ pub fn instr16_0F_analyze(cpu: &mut CpuContext, analysis: &mut Analysis) {
 pub fn instr32_0F_analyze(cpu: &mut CpuContext, analysis: &mut Analysis) {
     ::gen::analyzer0f::analyzer(cpu.read_imm8() as u32 | 0x100, cpu, analysis)
 }
+pub fn instr_0F38_analyze(cpu: &mut CpuContext, analysis: &mut Analysis) {
+    // three-byte opcodes, only invpcid is implemented, everything else raises #ud
+    let opcode = cpu.read_imm8();
+    if opcode == 0x82 && cpu.prefixes & PREFIX_66 != 0 && cpu.prefixes & PREFIX_LOCK == 0 {
+        let modrm_byte = cpu.read_imm8();
+        if modrm_byte < 0xC0 {
+            modrm_analyze(cpu, modrm_byte);
+            return;
+        }
+    }
+    analysis.no_next_instruction = true;
+}
 pub fn instr_26_analyze(cpu: &mut CpuContext, analysis: &mut Analysis) {
     analyze_step_handle_segment_prefix(ES, cpu, analysis)
 }
@@ -84,7 +96,7 @@ pub fn instr_67_analyze(cpu: &mut CpuContext, analysis: &mut Analysis) {
     analyze_step_handle_prefix(cpu, analysis)
 }
 pub fn instr_F0_analyze(cpu: &mut CpuContext, analysis: &mut Analysis) {
-    // lock: Ignored
+    cpu.prefixes |= PREFIX_LOCK;
     analyze_step_handle_prefix(cpu, analysis)
 }
 pub fn instr_F2_analyze(cpu: &mut CpuContext, analysis: &mut Analysis) {
@@ -97,3 +109,133 @@ pub fn instr_F3_analyze(cpu: &mut CpuContext, analysis: &mut Analysis) {
 }
 
 pub fn modrm_analyze(ctx: &mut CpuContext, modrm_byte: u8) { ::modrm::skip(ctx, modrm_byte); }
+
+/// How an instruction interacts with the arithmetic flags, used by the jit to find flags that are
+/// overwritten before they are observed
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum FlagsEffect {
+    /// Overwrites all arithmetic flags without reading them, can't fault
+    Define,
+    /// Neither reads nor writes the flags, can't fault
+    Preserve,
+    /// May read the flags or fault (which makes the flags visible to the exception handler), the
+    /// default for everything not handled below
+    Use,
+}
+
+pub fn flags_effect(mut cpu: CpuContext) -> FlagsEffect {
+    let mut opcode = cpu.read_imm8();
+    while let 0x26 | 0x2E | 0x36 | 0x3E | 0x64 | 0x65 | 0x66 | 0x67 = opcode {
+        opcode = cpu.read_imm8();
+    }
+
+    fn modrm(cpu: &mut CpuContext) -> (bool, u8) {
+        let modrm_byte = cpu.read_imm8();
+        (modrm_byte >> 6 == 3, modrm_byte >> 3 & 7)
+    }
+
+    match opcode {
+        // add, or, and, sub, xor, cmp (but not adc and sbb, which read the carry flag)
+        0x00..=0x3F if opcode & 7 < 6 && opcode >> 3 != 2 && opcode >> 3 != 3 => {
+            if opcode & 7 >= 4 || modrm(&mut cpu).0 {
+                FlagsEffect::Define
+            }
+            else {
+                FlagsEffect::Use
+            }
+        },
+        0x80 | 0x81 | 0x83 => match modrm(&mut cpu) {
+            (true, g) if g != 2 && g != 3 => FlagsEffect::Define,
+            _ => FlagsEffect::Use,
+        },
+        0x84 | 0x85 if modrm(&mut cpu).0 => FlagsEffect::Define,
+        0xA8 | 0xA9 => FlagsEffect::Define,
+        0xF6 | 0xF7 if modrm(&mut cpu) == (true, 0) => FlagsEffect::Define,
+
+        0x86..=0x8B if modrm(&mut cpu).0 => FlagsEffect::Preserve,
+        // lea, the register form raises #ud
+        0x8D if !modrm(&mut cpu).0 => FlagsEffect::Preserve,
+        0x90..=0x97 | 0xB0..=0xBF | 0xE9 | 0xEB => FlagsEffect::Preserve,
+        0x0F => match cpu.read_imm8() {
+            0xB6 | 0xB7 | 0xBE | 0xBF if modrm(&mut cpu).0 => FlagsEffect::Preserve,
+            _ => FlagsEffect::Use,
+        },
+        _ => FlagsEffect::Use,
+    }
+}
+
+/// A conservative mask of the xmm registers an instruction may read or write, used by the jit to
+/// decide which xmm registers to keep in locals
+pub fn xmm_registers_used(mut cpu: CpuContext) -> u8 {
+    let mut opcode = cpu.read_imm8();
+    let mut has_sse_prefix = false;
+    while let 0x26 | 0x2E | 0x36 | 0x3E | 0x64 | 0x65 | 0x66 | 0x67 | 0xF0 | 0xF2 | 0xF3 = opcode {
+        has_sse_prefix |= opcode == 0x66 || opcode == 0xF2 || opcode == 0xF3;
+        opcode = cpu.read_imm8();
+    }
+
+    if opcode != 0x0F {
+        return 0;
+    }
+
+    let mut mask = 0;
+    let opcode = cpu.read_imm8();
+    let is_sse = match opcode {
+        0x10..=0x17 | 0x28..=0x2F | 0x50..=0x5F | 0xC2 | 0xC6 => true,
+        0x60..=0x7F | 0xC4 | 0xC5 | 0xD0..=0xFF => has_sse_prefix,
+        0x38 | 0x3A => {
+            cpu.read_imm8();
+            // pblendvb and friends use xmm0 implicitly
+            mask |= 1;
+            true
+        },
+        _ => false,
+    };
+
+    if !is_sse {
+        return 0;
+    }
+
+    let modrm_byte = cpu.read_imm8();
+    mask |= 1 << (modrm_byte >> 3 & 7);
+    if modrm_byte >> 6 == 3 {
+        mask |= 1 << (modrm_byte & 7);
+    }
+    mask
+}
+
+/// Whether the jit can run an instruction on x87 registers kept in f64 locals (see
+/// codegen::gen_fpu_get_sti_f64). All other instructions expect the x87 registers in memory
+pub fn is_fpu_f64_instruction(mut cpu: CpuContext) -> bool {
+    let mut opcode = cpu.read_imm8();
+    while let 0x26 | 0x2E | 0x36 | 0x3E | 0x64 | 0x65 | 0x66 | 0x67 = opcode {
+        opcode = cpu.read_imm8();
+    }
+
+    if !(0xD8..=0xDE).contains(&opcode) {
+        return false;
+    }
+
+    let modrm_byte = cpu.read_imm8();
+    let is_reg = modrm_byte >> 6 == 3;
+    let r = modrm_byte & 7;
+    match (opcode, modrm_byte >> 3 & 7, is_reg) {
+        // fcom, fcomp
+        (0xD8 | 0xDC, 2 | 3, _) => false,
+        // fadd, fmul, fsub, fsubr, fdiv, fdivr
+        (0xD8 | 0xDC, _, _) => true,
+        // fld, fxch, fst m32, fstp m32
+        (0xD9, 0, _) | (0xD9, 1, true) | (0xD9, 2 | 3, false) => true,
+        // fchs, fabs
+        (0xD9, 4, true) => r == 0 || r == 1,
+        // fld1, fldz
+        (0xD9, 5, true) => r == 0 || r == 6,
+        // fsqrt
+        (0xD9, 7, true) => r == 2,
+        // fld m64, fst, fstp
+        (0xDD, 0, false) | (0xDD, 2 | 3, _) => true,
+        // faddp, fmulp, fsubp, fsubrp, fdivp, fdivrp
+        (0xDE, 0 | 1 | 4..=7, true) => true,
+        _ => false,
+    }
+}
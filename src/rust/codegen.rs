@@ -7,9 +7,10 @@ use cpu::memory;
 use jit::{Instruction, InstructionOperand, InstructionOperandDest, JitContext};
 use modrm;
 use modrm::ModrmByte;
+use page::Page;
 use profiler;
 use regs;
-use wasmgen::wasm_builder::{WasmBuilder, WasmLocal, WasmLocalI64};
+use wasmgen::wasm_builder::{Label, WasmBuilder, WasmLocal, WasmLocalI64};
 
 pub fn gen_add_cs_offset(ctx: &mut JitContext) {
     if !ctx.cpu.has_flat_segmentation() {
@@ -31,9 +32,17 @@ pub fn gen_set_eip_to_after_current_instruction(ctx: &mut JitContext) {
     ctx.builder.and_i32();
     ctx.builder.const_i32(ctx.cpu.eip as i32 & 0xFFF);
     ctx.builder.or_i32();
+    gen_add_page_crossed_by_current_instruction(ctx);
     ctx.builder.store_aligned_i32(0);
 }
 
+fn gen_add_page_crossed_by_current_instruction(ctx: &mut JitContext) {
+    if Page::page_of(ctx.cpu.eip) != Page::page_of(ctx.start_of_current_instruction) {
+        ctx.builder.const_i32(0x1000);
+        ctx.builder.add_i32();
+    }
+}
+
 pub fn gen_set_previous_eip_offset_from_eip_with_low_bits(
     builder: &mut WasmBuilder,
     low_bits: i32,
@@ -116,6 +125,37 @@ pub fn gen_page_switch_check(
     }
 }
 
+pub fn gen_next_page_check(ctx: &mut JitContext, next_page_addr: u32) {
+    // Before an instruction that continues into the next page, check that the next virtual page
+    // still maps to the physical page the instruction was decoded from. If not, exit and let the
+    // interpreter run it. A page fault happens at this instruction, like in the interpreter
+
+    gen_set_eip_low_bits(ctx.builder, ctx.start_of_current_instruction as i32 & 0xFFF);
+    gen_get_eip(ctx.builder);
+    ctx.builder.const_i32(!0xFFF);
+    ctx.builder.and_i32();
+    ctx.builder.const_i32(0x1000);
+    ctx.builder.add_i32();
+    let address_local = ctx.builder.set_new_local();
+    gen_get_phys_eip_plus_mem(ctx, &address_local);
+    ctx.builder.free_local(address_local);
+
+    ctx.builder
+        .const_i32(next_page_addr as i32 + unsafe { memory::mem8 } as i32);
+    ctx.builder.ne_i32();
+
+    if cfg!(debug_assertions) {
+        ctx.builder.if_void();
+        gen_profiler_stat_increment(ctx.builder, profiler::stat::FAILED_PAGE_CHANGE);
+        gen_debug_track_jit_exit(ctx.builder, ctx.start_of_current_instruction);
+        ctx.builder.br(ctx.exit_label);
+        ctx.builder.block_end();
+    }
+    else {
+        ctx.builder.br_if(ctx.exit_label);
+    }
+}
+
 pub fn gen_update_instruction_counter(ctx: &mut JitContext) {
     ctx.builder
         .const_i32(global_pointers::instruction_counter as i32);
@@ -308,19 +348,130 @@ pub fn decr_exc_asize(ctx: &mut JitContext) {
     }
 }
 
+/// Push one quadword (0 for the low, 1 for the high half) of an xmm register, from its local if
+/// the module keeps the register in locals
+fn gen_get_reg_xmm_quad(ctx: &mut JitContext, r: u32, i: u32) {
+    match &ctx.xmm_register_locals[r as usize] {
+        Some((low, high)) => ctx.builder.get_local_i64(if i == 0 { low } else { high }),
+        None => ctx
+            .builder
+            .load_fixed_i64(global_pointers::get_reg_xmm_offset(r) + 8 * i),
+    }
+}
+fn gen_set_reg_xmm_quad(ctx: &mut JitContext, r: u32, i: u32) {
+    match &ctx.xmm_register_locals[r as usize] {
+        Some((low, high)) => ctx.builder.set_local_i64(if i == 0 { low } else { high }),
+        None => {
+            let value_local = ctx.builder.set_new_local_i64();
+            ctx.builder
+                .const_i32((global_pointers::get_reg_xmm_offset(r) + 8 * i) as i32);
+            ctx.builder.get_local_i64(&value_local);
+            ctx.builder.store_aligned_i64(0);
+            ctx.builder.free_local_i64(value_local);
+        },
+    }
+}
+
+pub fn gen_get_reg_xmm_low(ctx: &mut JitContext, r: u32) { gen_get_reg_xmm_quad(ctx, r, 0) }
+pub fn gen_get_reg_xmm_high(ctx: &mut JitContext, r: u32) { gen_get_reg_xmm_quad(ctx, r, 1) }
+pub fn gen_set_reg_xmm_low(ctx: &mut JitContext, r: u32) { gen_set_reg_xmm_quad(ctx, r, 0) }
+pub fn gen_set_reg_xmm_high(ctx: &mut JitContext, r: u32) { gen_set_reg_xmm_quad(ctx, r, 1) }
+
+pub fn gen_get_reg_xmm_dword(ctx: &mut JitContext, r: u32, i: u32) {
+    dbg_assert!(i < 4);
+    gen_get_reg_xmm_quad(ctx, r, i >> 1);
+    if i & 1 == 1 {
+        ctx.builder.const_i64(32);
+        ctx.builder.shr_u_i64();
+    }
+    ctx.builder.wrap_i64_to_i32();
+}
+/// Pop an i32 and write it to one dword of an xmm register, leaving the other dwords unchanged
+pub fn gen_set_reg_xmm_dword(ctx: &mut JitContext, r: u32, i: u32) {
+    dbg_assert!(i < 4);
+    let shift = if i & 1 == 1 { 32 } else { 0 };
+    ctx.builder.extend_unsigned_i32_to_i64();
+    if shift != 0 {
+        ctx.builder.const_i64(shift);
+        ctx.builder.shl_i64();
+    }
+    gen_get_reg_xmm_quad(ctx, r, i >> 1);
+    ctx.builder.const_i64(!(0xFFFF_FFFFu64 << shift) as i64);
+    ctx.builder.and_i64();
+    ctx.builder.or_i64();
+    gen_set_reg_xmm_quad(ctx, r, i >> 1);
+}
+
+/// Push an xmm register as a v128, see jit::jit_use_simd
+pub fn gen_get_reg_xmm_v128(ctx: &mut JitContext, r: u32) {
+    match &ctx.xmm_register_locals[r as usize] {
+        Some((low, high)) => {
+            ctx.builder.get_local_i64(low);
+            ctx.builder.splat_i64x2();
+            ctx.builder.get_local_i64(high);
+            ctx.builder.replace_lane_i64x2(1);
+        },
+        None => {
+            ctx.builder
+                .const_i32(global_pointers::get_reg_xmm_offset(r) as i32);
+            ctx.builder.load_aligned_v128(0);
+        },
+    }
+}
+pub fn gen_set_reg_xmm_v128(ctx: &mut JitContext, r: u32) {
+    let value_local = ctx.builder.set_new_local_v128();
+    match &ctx.xmm_register_locals[r as usize] {
+        Some((low, high)) => {
+            ctx.builder.get_local_v128(&value_local);
+            ctx.builder.extract_lane_i64x2(0);
+            ctx.builder.set_local_i64(low);
+            ctx.builder.get_local_v128(&value_local);
+            ctx.builder.extract_lane_i64x2(1);
+            ctx.builder.set_local_i64(high);
+        },
+        None => {
+            ctx.builder
+                .const_i32(global_pointers::get_reg_xmm_offset(r) as i32);
+            ctx.builder.get_local_v128(&value_local);
+            ctx.builder.store_aligned_v128(0);
+        },
+    }
+    ctx.builder.free_local_v128(value_local);
+}
+
+/// Write back an xmm register kept in locals, before code that accesses reg_xmm directly
+pub fn gen_move_reg_xmm_from_local_to_memory(ctx: &mut JitContext, r: u32) {
+    if let Some((low, high)) = &ctx.xmm_register_locals[r as usize] {
+        ctx.builder
+            .const_i32(global_pointers::get_reg_xmm_offset(r) as i32);
+        ctx.builder.get_local_i64(low);
+        ctx.builder.store_aligned_i64(0);
+        ctx.builder
+            .const_i32(global_pointers::get_reg_xmm_offset(r) as i32 + 8);
+        ctx.builder.get_local_i64(high);
+        ctx.builder.store_aligned_i64(0);
+    }
+}
+pub fn gen_move_reg_xmm_from_memory_to_local(ctx: &mut JitContext, r: u32) {
+    if let Some((low, high)) = &ctx.xmm_register_locals[r as usize] {
+        ctx.builder
+            .load_fixed_i64(global_pointers::get_reg_xmm_offset(r));
+        ctx.builder.set_local_i64(low);
+        ctx.builder
+            .load_fixed_i64(global_pointers::get_reg_xmm_offset(r) + 8);
+        ctx.builder.set_local_i64(high);
+    }
+}
+
 pub fn gen_read_reg_xmm128_into_scratch(ctx: &mut JitContext, r: u32) {
     ctx.builder
         .const_i32(global_pointers::sse_scratch_register as i32);
-    let dest = global_pointers::get_reg_xmm_offset(r);
-    ctx.builder.const_i32(dest as i32);
-    ctx.builder.load_aligned_i64(0);
+    gen_get_reg_xmm_low(ctx, r);
     ctx.builder.store_aligned_i64(0);
 
     ctx.builder
         .const_i32(global_pointers::sse_scratch_register as i32 + 8);
-    let dest = global_pointers::get_reg_xmm_offset(r) + 8;
-    ctx.builder.const_i32(dest as i32);
-    ctx.builder.load_aligned_i64(0);
+    gen_get_reg_xmm_high(ctx, r);
     ctx.builder.store_aligned_i64(0);
 }
 
@@ -501,6 +652,15 @@ pub fn gen_modrm_resolve_safe_read128(
         gen_safe_read128(ctx, addr, where_to_write)
     });
 }
+pub fn gen_modrm_resolve_safe_read128_aligned(
+    ctx: &mut JitContext,
+    modrm_byte: ModrmByte,
+    where_to_write: u32,
+) {
+    gen_modrm_resolve_with_local(ctx, modrm_byte, &|ctx, addr| {
+        gen_safe_read128_aligned(ctx, addr, where_to_write)
+    });
+}
 
 pub fn gen_safe_read8(ctx: &mut JitContext, address_local: &WasmLocal) {
     gen_safe_read(ctx, BitSize::BYTE, address_local, None);
@@ -517,6 +677,24 @@ pub fn gen_safe_read64(ctx: &mut JitContext, address_local: &WasmLocal) {
 pub fn gen_safe_read128(ctx: &mut JitContext, address_local: &WasmLocal, where_to_write: u32) {
     gen_safe_read(ctx, BitSize::DQWORD, &address_local, Some(where_to_write));
 }
+pub fn gen_safe_read128_aligned(
+    ctx: &mut JitContext,
+    address_local: &WasmLocal,
+    where_to_write: u32,
+) {
+    gen_trigger_gp_if_unaligned(ctx, address_local, BitSize::DQWORD);
+    gen_safe_read128(ctx, address_local, where_to_write);
+}
+
+pub fn gen_trigger_gp_if_unaligned(ctx: &mut JitContext, address_local: &WasmLocal, bits: BitSize) {
+    // generates: if(address & (bytes - 1)) { trigger_gp_jit(0); goto exit_with_fault; }
+    ctx.builder.get_local(address_local);
+    ctx.builder.const_i32(bits.bytes() as i32 - 1);
+    ctx.builder.and_i32();
+    ctx.builder.if_void();
+    gen_trigger_gp(ctx, 0);
+    ctx.builder.block_end();
+}
 
 // only used internally for gen_safe_write
 enum GenSafeWriteValue<'a> {
@@ -601,6 +779,16 @@ pub fn gen_safe_write128(
     )
 }
 
+pub fn gen_safe_write128_aligned(
+    ctx: &mut JitContext,
+    address_local: &WasmLocal,
+    value_local_low: &WasmLocalI64,
+    value_local_high: &WasmLocalI64,
+) {
+    gen_trigger_gp_if_unaligned(ctx, address_local, BitSize::DQWORD);
+    gen_safe_write128(ctx, address_local, value_local_low, value_local_high);
+}
+
 fn gen_safe_read(
     ctx: &mut JitContext,
     bits: BitSize,
@@ -692,7 +880,7 @@ fn gen_safe_read(
         ctx.builder.and_i32();
     }
 
-    ctx.builder.br_if(ctx.exit_with_fault_label);
+    gen_br_if_exit_with_fault(ctx);
 
     ctx.builder.block_end();
 
@@ -749,7 +937,7 @@ pub fn gen_get_phys_eip_plus_mem(ctx: &mut JitContext, address_local: &WasmLocal
     // We cannot the same while processing an AbsoluteEip flow control change so there we need to fix the value
     // by subscracting memory::mem. Overall, since AbsoluteEip is encountered less often than memory accesses so
     // this ends up improving perf.
-    // Does not (need to) handle mapped memory
+    // Mapped memory takes the slow path and never matches a compiled page
     // XXX: Currently does not use ctx.start_of_current_instruction, but rather assumes that eip is
     //      already correct (pointing at the current instruction)
 
@@ -802,7 +990,7 @@ pub fn gen_get_phys_eip_plus_mem(ctx: &mut JitContext, address_local: &WasmLocal
         ctx.builder.and_i32();
     }
 
-    ctx.builder.br_if(ctx.exit_with_fault_label);
+    gen_br_if_exit_with_fault(ctx);
 
     ctx.builder.block_end();
 
@@ -913,7 +1101,7 @@ fn gen_safe_write(
         ctx.builder.and_i32();
     }
 
-    ctx.builder.br_if(ctx.exit_with_fault_label);
+    gen_br_if_exit_with_fault(ctx);
 
     ctx.builder.block_end();
 
@@ -960,6 +1148,62 @@ fn gen_safe_write(
     ctx.builder.free_local(entry_local);
 }
 
+/// Translate a range of `length_local` bytes starting at the virtual address in `address_local`
+/// for use with memory.copy or memory.fill, replacing it with the address in wasm memory. Branches
+/// to `slow_path` if the range crosses a page boundary, the page is not in the tlb or not
+/// accessible, or it is memory-mapped or contains code
+pub fn gen_translate_range_or_branch(
+    ctx: &mut JitContext,
+    address_local: &WasmLocal,
+    length_local: &WasmLocal,
+    for_writing: bool,
+    slow_path: Label,
+) {
+    //   if (addr & 0xFFF) + length > 0x1000: goto slow
+    //   entry <- tlb_data[addr >> 12 << 2]
+    //   if entry & MASK != TLB_VALID: goto slow
+    //   addr <- (entry & ~0xFFF) ^ addr
+
+    ctx.builder.get_local(address_local);
+    ctx.builder.const_i32(0xFFF);
+    ctx.builder.and_i32();
+    ctx.builder.get_local(length_local);
+    ctx.builder.add_i32();
+    ctx.builder.const_i32(0x1000);
+    ctx.builder.gtu_i32();
+    ctx.builder.br_if(slow_path);
+
+    ctx.builder.get_local(address_local);
+    ctx.builder.const_i32(12);
+    ctx.builder.shr_u_i32();
+    ctx.builder.const_i32(2);
+    ctx.builder.shl_i32();
+
+    ctx.builder
+        .load_aligned_i32(unsafe { &tlb_data[0] as *const i32 as u32 });
+    let entry_local = ctx.builder.tee_new_local();
+
+    ctx.builder.const_i32(
+        0xFFF
+            & !TLB_GLOBAL
+            & !(if for_writing { 0 } else { TLB_READONLY })
+            & !(if ctx.cpu.cpl3() { 0 } else { TLB_NO_USER }),
+    );
+    ctx.builder.and_i32();
+    ctx.builder.const_i32(TLB_VALID);
+    ctx.builder.ne_i32();
+    ctx.builder.br_if(slow_path);
+
+    ctx.builder.get_local(&entry_local);
+    ctx.builder.const_i32(!0xFFF);
+    ctx.builder.and_i32();
+    ctx.builder.get_local(address_local);
+    ctx.builder.xor_i32();
+    ctx.builder.set_local(address_local);
+
+    ctx.builder.free_local(entry_local);
+}
+
 pub fn gen_safe_read_write(
     ctx: &mut JitContext,
     bits: BitSize,
@@ -1052,7 +1296,7 @@ pub fn gen_safe_read_write(
         ctx.builder.and_i32();
     }
 
-    ctx.builder.br_if(ctx.exit_with_fault_label);
+    gen_br_if_exit_with_fault(ctx);
 
     ctx.builder.block_end();
 
@@ -1107,8 +1351,7 @@ pub fn gen_safe_read_write(
             GenSafeReadWriteValue::I64(l) => ctx.builder.get_local_i64(l),
         }
 
-        ctx.builder
-            .const_i32(ctx.start_of_current_instruction as i32);
+        ctx.builder.const_i32(ctx.start_of_current_instruction as i32);
 
         match bits {
             BitSize::BYTE => {
@@ -1406,6 +1649,7 @@ pub fn gen_task_switch_test(ctx: &mut JitContext) {
 
     ctx.builder.if_void();
     {
+        gen_fpu_spill_f64(ctx);
         gen_debug_track_jit_exit(ctx.builder, ctx.start_of_current_instruction);
         gen_fn1_const(
             ctx.builder,
@@ -1417,6 +1661,47 @@ pub fn gen_task_switch_test(ctx: &mut JitContext) {
     ctx.builder.block_end();
 }
 
+pub fn gen_fpu_exception_test(ctx: &mut JitContext) {
+    // generate if(fpu_status_word & ~fpu_control_word & FPU_EX_ALL) {
+    //     if(fpu_exception_test_jit()) goto exit_with_fault;
+    // }
+    ctx.builder.load_fixed_u16(global_pointers::fpu_status_word as u32);
+    ctx.builder.load_fixed_u16(global_pointers::fpu_control_word as u32);
+    ctx.builder.const_i32(-1);
+    ctx.builder.xor_i32();
+    ctx.builder.and_i32();
+    ctx.builder.const_i32(::cpu::fpu::FPU_EX_ALL as i32);
+    ctx.builder.and_i32();
+
+    ctx.builder.if_void();
+    {
+        ctx.builder.const_i32(ctx.start_of_current_instruction as i32);
+        ctx.builder.call_fn1_ret("fpu_exception_test_jit");
+        ctx.builder.if_void();
+        {
+            gen_fpu_spill_f64(ctx);
+            gen_debug_track_jit_exit(ctx.builder, ctx.start_of_current_instruction);
+            ctx.builder.br(ctx.exit_with_fault_label);
+        }
+        ctx.builder.block_end();
+    }
+    ctx.builder.block_end();
+}
+
+pub fn gen_sse_exception_test(ctx: &mut JitContext) {
+    // generate if(sse_exception_pending) { trigger_xm_jit(); goto exit_with_fault; }
+    ctx.builder
+        .load_fixed_u8(std::ptr::addr_of!(::cpu::sse_instr::sse_exception_pending) as u32);
+
+    ctx.builder.if_void();
+    {
+        gen_debug_track_jit_exit(ctx.builder, ctx.start_of_current_instruction);
+        gen_fn1_const(ctx.builder, "trigger_xm_jit", ctx.start_of_current_instruction);
+        ctx.builder.br(ctx.exit_with_fault_label);
+    }
+    ctx.builder.block_end();
+}
+
 pub fn gen_task_switch_test_mmx(ctx: &mut JitContext) {
     // generate if(cr[0] & (CR0_EM | CR0_TS)) { task_switch_test_mmx_jit(); goto exit_with_fault; }
     let cr0_offset = global_pointers::get_creg_offset(0);
@@ -1589,6 +1874,7 @@ pub fn gen_get_real_eip(ctx: &mut JitContext) {
     ctx.builder.and_i32();
     ctx.builder.const_i32(ctx.cpu.eip as i32 & 0xFFF);
     ctx.builder.or_i32();
+    gen_add_page_crossed_by_current_instruction(ctx);
     if !ctx.cpu.has_flat_segmentation() {
         ctx.builder
             .load_fixed_i32(global_pointers::get_seg_offset(regs::CS));
@@ -2445,6 +2731,102 @@ pub fn gen_fpu_get_sti(ctx: &mut JitContext, i: u32) {
         .load_fixed_u16(global_pointers::sse_scratch_register as u32 + 8);
 }
 
+/// Push st(i) as an f64. It is read into a local the first time, and taken from the local
+/// afterwards. Only used if the module was compiled for CachedStateFlags::fpu_f64, where f64 and
+/// x87 arithmetic agree except for the exponent range and exception flags
+pub fn gen_fpu_get_sti_f64(ctx: &mut JitContext, i: u32) {
+    dbg_assert!(ctx.fpu_f64);
+    match &ctx.fpu_stack_locals[i as usize] {
+        Some((local, _)) => ctx.builder.get_local_f64(local),
+        None => {
+            ctx.builder.const_i32(i as i32);
+            ctx.builder.call_fn1_f64_ret("fpu_get_sti_f64_jit");
+            let local = ctx.builder.tee_new_local_f64();
+            ctx.fpu_stack_locals[i as usize] = Some((local, false));
+        },
+    }
+}
+/// Pop an f64 and write it to st(i). The tag word is not changed
+pub fn gen_fpu_set_sti_f64(ctx: &mut JitContext, i: u32) {
+    dbg_assert!(ctx.fpu_f64);
+    match &mut ctx.fpu_stack_locals[i as usize] {
+        Some((local, dirty)) => {
+            ctx.builder.set_local_f64(local);
+            *dirty = true;
+        },
+        None => {
+            let local = ctx.builder.set_new_local_f64();
+            ctx.fpu_stack_locals[i as usize] = Some((local, true));
+        },
+    }
+}
+pub fn gen_fpu_push_f64(ctx: &mut JitContext) {
+    dbg_assert!(ctx.fpu_f64);
+    let value = ctx.builder.set_new_local_f64();
+
+    ctx.fpu_stack_locals.rotate_right(1);
+    if let Some((local, _)) = ctx.fpu_stack_locals[0].take() {
+        ctx.builder.free_local_f64(local);
+    }
+
+    ctx.builder.get_local_f64(&value);
+    // the indefinite nan
+    ctx.builder.const_f64(f64::from_bits(0xFFF8_0000_0000_0000));
+    ctx.builder.call_fn0_ret("fpu_push_f64_jit");
+    ctx.builder.select();
+    ctx.builder.set_local_f64(&value);
+    ctx.fpu_stack_locals[0] = Some((value, true));
+}
+pub fn gen_fpu_pop_f64(ctx: &mut JitContext) {
+    dbg_assert!(ctx.fpu_f64);
+    if let Some((local, dirty)) = ctx.fpu_stack_locals[0].take() {
+        if dirty {
+            // the register keeps its value after being marked as empty, like fpu_pop
+            ctx.builder.const_i32(0);
+            ctx.builder.get_local_f64(&local);
+            ctx.builder.reinterpret_f64_as_i64();
+            ctx.builder.call_fn2_i32_i64("fpu_set_sti_f64_jit");
+        }
+        ctx.builder.free_local_f64(local);
+    }
+    gen_fn0_const(ctx.builder, "fpu_pop");
+    ctx.fpu_stack_locals.rotate_left(1);
+}
+/// Write back the x87 registers that have been changed in locals, for example before leaving the
+/// module. They stay in their locals
+pub fn gen_fpu_spill_f64(ctx: &mut JitContext) {
+    for i in 0..8 {
+        if let Some((local, true)) = &ctx.fpu_stack_locals[i] {
+            ctx.builder.const_i32(i as i32);
+            ctx.builder.get_local_f64(local);
+            ctx.builder.reinterpret_f64_as_i64();
+            ctx.builder.call_fn2_i32_i64("fpu_set_sti_f64_jit");
+        }
+    }
+}
+/// Write back the x87 registers kept in locals and free the locals, before an instruction that
+/// accesses the x87 registers in memory and at the end of basic blocks
+pub fn gen_fpu_flush_f64(ctx: &mut JitContext) {
+    gen_fpu_spill_f64(ctx);
+    for i in 0..8 {
+        if let Some((local, _)) = ctx.fpu_stack_locals[i].take() {
+            ctx.builder.free_local_f64(local);
+        }
+    }
+}
+
+fn gen_br_if_exit_with_fault(ctx: &mut JitContext) {
+    if ctx.fpu_stack_locals.iter().any(|l| matches!(l, Some((_, true)))) {
+        ctx.builder.if_void();
+        gen_fpu_spill_f64(ctx);
+        ctx.builder.br(ctx.exit_with_fault_label);
+        ctx.builder.block_end();
+    }
+    else {
+        ctx.builder.br_if(ctx.exit_with_fault_label);
+    }
+}
+
 pub fn gen_fpu_load_m32(ctx: &mut JitContext, modrm_byte: ModrmByte) {
     ctx.builder
         .const_i32(global_pointers::sse_scratch_register as i32);
@@ -2456,6 +2838,12 @@ pub fn gen_fpu_load_m32(ctx: &mut JitContext, modrm_byte: ModrmByte) {
         .load_fixed_u16(global_pointers::sse_scratch_register as u32 + 8);
 }
 
+pub fn gen_fpu_load_m32_f64(ctx: &mut JitContext, modrm_byte: ModrmByte) {
+    gen_modrm_resolve_safe_read32(ctx, modrm_byte);
+    ctx.builder.reinterpret_i32_as_f32();
+    ctx.builder.promote_f32_to_f64();
+}
+
 pub fn gen_fpu_load_m64(ctx: &mut JitContext, modrm_byte: ModrmByte) {
     ctx.builder
         .const_i32(global_pointers::sse_scratch_register as i32);
@@ -2467,6 +2855,24 @@ pub fn gen_fpu_load_m64(ctx: &mut JitContext, modrm_byte: ModrmByte) {
         .load_fixed_u16(global_pointers::sse_scratch_register as u32 + 8);
 }
 
+pub fn gen_fpu_load_m64_f64(ctx: &mut JitContext, modrm_byte: ModrmByte) {
+    gen_modrm_resolve_safe_read64(ctx, modrm_byte);
+    ctx.builder.reinterpret_i64_as_f64();
+}
+
+/// After an instruction that writes the fpu control word: Leave the module if the following code
+/// has been compiled for a different mode of x87 arithmetic, see CachedStateFlags::fpu_f64
+pub fn gen_exit_if_state_flags_changed(ctx: &mut JitContext) {
+    ctx.builder.load_fixed_u8(global_pointers::state_flags as u32);
+    ctx.builder.const_i32(ctx.cpu.state_flags.to_u32() as i32);
+    ctx.builder.ne_i32();
+    ctx.builder.if_void();
+    gen_set_eip_to_after_current_instruction(ctx);
+    gen_debug_track_jit_exit(ctx.builder, ctx.start_of_current_instruction);
+    ctx.builder.br(ctx.exit_label);
+    ctx.builder.block_end();
+}
+
 pub fn gen_fpu_load_i16(ctx: &mut JitContext, modrm_byte: ModrmByte) {
     ctx.builder
         .const_i32(global_pointers::sse_scratch_register as i32);
@@ -2625,6 +3031,10 @@ pub fn gen_move_registers_from_locals_to_memory(ctx: &mut JitContext) {
         ctx.builder.get_local(&ctx.register_locals[i]);
         ctx.builder.store_aligned_i32(0);
     }
+    for r in 0..8 {
+        gen_move_reg_xmm_from_local_to_memory(ctx, r);
+    }
+    gen_fpu_spill_f64(ctx);
 }
 pub fn gen_move_registers_from_memory_to_locals(ctx: &mut JitContext) {
     if cfg!(feature = "profiler") {
@@ -2638,6 +3048,9 @@ pub fn gen_move_registers_from_memory_to_locals(ctx: &mut JitContext) {
         ctx.builder.load_aligned_i32(0);
         ctx.builder.set_local(&ctx.register_locals[i]);
     }
+    for r in 0..8 {
+        gen_move_reg_xmm_from_memory_to_local(ctx, r);
+    }
 }
 
 pub fn gen_profiler_stat_increment(builder: &mut WasmBuilder, stat: profiler::stat) {
@@ -1,12 +1,15 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::iter;
 
+use analysis::FlagsEffect;
 use jit::{BasicBlock, BasicBlockType, MAX_EXTRA_BASIC_BLOCKS};
+use page::Page;
 use profiler;
 
 const ENTRY_NODE_ID: u32 = 0xffff_ffff;
 
-type Graph = HashMap<u32, HashSet<u32>>;
+// Ordered, so that the structure of the generated code doesn't depend on hash map iteration order
+type Graph = BTreeMap<u32, BTreeSet<u32>>;
 
 /// Reverse the direction of all edges in the graph
 fn rev_graph_edges(nodes: &Graph) -> Graph {
@@ -15,7 +18,7 @@ fn rev_graph_edges(nodes: &Graph) -> Graph {
         for to in tos {
             rev_nodes
                 .entry(*to)
-                .or_insert_with(|| HashSet::new())
+                .or_insert_with(|| BTreeSet::new())
                 .insert(*from);
         }
     }
@@ -24,10 +27,10 @@ fn rev_graph_edges(nodes: &Graph) -> Graph {
 
 pub fn make_graph(basic_blocks: &Vec<BasicBlock>) -> Graph {
     let mut nodes = Graph::new();
-    let mut entry_edges = HashSet::new();
+    let mut entry_edges = BTreeSet::new();
 
     for b in basic_blocks.iter() {
-        let mut edges = HashSet::new();
+        let mut edges = BTreeSet::new();
 
         match &b.ty {
             &BasicBlockType::ConditionalJump {
@@ -339,6 +342,28 @@ pub fn loopify(nodes: &Graph) -> Vec<WasmStructure> {
         .collect();
 }
 
+/// Structure for graphs without backward edges, as generated by the baseline tier: The entry
+/// dispatcher followed by all basic blocks in order of their address. Much cheaper than loopify
+pub fn linearize(nodes: &Graph) -> Vec<WasmStructure> {
+    let mut addrs: Vec<u32> = nodes
+        .keys()
+        .copied()
+        .filter(|&addr| addr != ENTRY_NODE_ID)
+        .collect();
+    addrs.sort_unstable();
+
+    if cfg!(debug_assertions) {
+        for addr in &addrs {
+            dbg_assert!(nodes.get(addr).unwrap().iter().all(|dest| dest > addr));
+        }
+    }
+
+    let entries = nodes.get(&ENTRY_NODE_ID).unwrap().iter().copied().collect();
+    let mut result = vec![WasmStructure::Dispatcher(entries)];
+    result.extend(addrs.into_iter().map(WasmStructure::BasicBlock));
+    result
+}
+
 pub fn blockify(blocks: &mut Vec<WasmStructure>, edges: &Graph) {
     let mut cached_branches: Vec<HashSet<u32>> = Vec::new();
     for i in 0..blocks.len() {
@@ -418,3 +443,100 @@ pub fn blockify(blocks: &mut Vec<WasmStructure>, edges: &Graph) {
         i = source + 2;
     }
 }
+
+/// Compute for each instruction whether the flags it produces are overwritten before they can be
+/// observed, so that the generated code can skip updating the lazy flags. Flags are observed by
+/// instructions that read them or may fault, and whenever the generated code may leave the module:
+/// At exits, page switches, loop heads (which check the instruction counter) and after sti
+pub fn flags_liveness(basic_blocks: &mut [BasicBlock], structure: &Vec<WasmStructure>) {
+    fn collect_loop_heads(nodes: &Vec<WasmStructure>, result: &mut HashSet<u32>) {
+        for node in nodes {
+            match node {
+                WasmStructure::BasicBlock(_) | WasmStructure::Dispatcher(_) => {},
+                WasmStructure::Loop(children) => {
+                    result.extend(children[0].head());
+                    collect_loop_heads(children, result);
+                },
+                WasmStructure::Block(children) => collect_loop_heads(children, result),
+            }
+        }
+    }
+    let mut loop_heads = HashSet::new();
+    collect_loop_heads(structure, &mut loop_heads);
+
+    let index_for_addr: HashMap<u32, usize> = basic_blocks
+        .iter()
+        .enumerate()
+        .map(|(i, b)| (b.addr, i))
+        .collect();
+
+    // successors within this module, or None if the flags may be observed after the block
+    let successors: Vec<Option<Vec<usize>>> = basic_blocks
+        .iter()
+        .map(|b| {
+            let targets = match b.ty {
+                _ if b.has_sti => return None,
+                BasicBlockType::Normal {
+                    next_block_addr: Some(next_block_addr),
+                    ..
+                } => vec![next_block_addr],
+                BasicBlockType::ConditionalJump {
+                    next_block_addr: Some(next_block_addr),
+                    next_block_branch_taken_addr: Some(next_block_branch_taken_addr),
+                    ..
+                } => vec![next_block_addr, next_block_branch_taken_addr],
+                _ => return None,
+            };
+            targets
+                .into_iter()
+                .map(|addr| {
+                    if Page::page_of(addr) != Page::page_of(b.addr) || loop_heads.contains(&addr) {
+                        None
+                    }
+                    else {
+                        index_for_addr.get(&addr).copied()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    let live_at_end = |live_in: &Vec<bool>, successors: &Option<Vec<usize>>| match successors {
+        None => true,
+        Some(successors) => successors.iter().any(|&s| live_in[s]),
+    };
+
+    let mut live_in = vec![false; basic_blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (i, b) in basic_blocks.iter().enumerate().rev() {
+            let mut live = live_at_end(&live_in, &successors[i]);
+            for &effect in b.flags_effects.iter().rev() {
+                match effect {
+                    FlagsEffect::Use => live = true,
+                    FlagsEffect::Define => live = false,
+                    FlagsEffect::Preserve => {},
+                }
+            }
+            if live && !live_in[i] {
+                live_in[i] = true;
+                changed = true;
+            }
+        }
+    }
+
+    for i in 0..basic_blocks.len() {
+        let mut live = live_at_end(&live_in, &successors[i]);
+        let b = &mut basic_blocks[i];
+        b.flags_unused = vec![false; b.flags_effects.len()];
+        for (j, &effect) in b.flags_effects.iter().enumerate().rev() {
+            b.flags_unused[j] = !live;
+            match effect {
+                FlagsEffect::Use => live = true,
+                FlagsEffect::Define => live = false,
+                FlagsEffect::Preserve => {},
+            }
+        }
+    }
+}
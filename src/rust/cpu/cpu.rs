@@ -21,7 +21,6 @@ use config;
 use cpu::fpu::fpu_set_tag_word;
 use cpu::global_pointers::*;
 use cpu::memory;
-use cpu::memory::mem8;
 use cpu::memory::{in_mapped_range, read128, read16, read32s, read64s, read8, write8};
 use cpu::misc_instr::{
     adjust_stack_reg, get_stack_pointer, getaf, getcf, getof, getpf, getsf, getzf, pop16, pop32s,
@@ -29,6 +28,7 @@ use cpu::misc_instr::{
 };
 use cpu::modrm::{resolve_modrm16, resolve_modrm32};
 use cpu::pic;
+use cpu::smp;
 use jit;
 use jit::is_near_end_of_page;
 use page::Page;
@@ -154,6 +154,8 @@ pub const PAGE_TABLE_ACCESSED_MASK: i32 = 1 << 5;
 pub const PAGE_TABLE_DIRTY_MASK: i32 = 1 << 6;
 pub const PAGE_TABLE_PSE_MASK: i32 = 1 << 7;
 pub const PAGE_TABLE_GLOBAL_MASK: i32 = 1 << 8;
+// Physical address bits of a pae paging structure entry (36-bit physical addresses)
+pub const PAE_ADDRESS_MASK: u64 = 0xF_FFFF_F000;
 pub const MMAP_BLOCK_BITS: i32 = 17;
 pub const MMAP_BLOCK_SIZE: i32 = 1 << MMAP_BLOCK_BITS;
 pub const CR0_PE: i32 = 1;
@@ -161,6 +163,7 @@ pub const CR0_MP: i32 = 1 << 1;
 pub const CR0_EM: i32 = 1 << 2;
 pub const CR0_TS: i32 = 1 << 3;
 pub const CR0_ET: i32 = 1 << 4;
+pub const CR0_NE: i32 = 1 << 5;
 pub const CR0_WP: i32 = 1 << 16;
 pub const CR0_AM: i32 = 1 << 18;
 pub const CR0_NW: i32 = 1 << 29;
@@ -175,6 +178,7 @@ pub const CR4_PAE: i32 = 1 << 5;
 pub const CR4_PGE: i32 = 1 << 7;
 pub const CR4_OSFXSR: i32 = 1 << 9;
 pub const CR4_OSXMMEXCPT: i32 = 1 << 10;
+pub const CR4_UMIP: i32 = 1 << 11;
 pub const CR4_SMEP: i32 = 1 << 20;
 
 pub const TSR_BACKLINK: i32 = 0x00;
@@ -220,7 +224,7 @@ pub const IA32_MISC_ENABLE: i32 = 0x1A0;
 pub const IA32_PAT: i32 = 0x277;
 pub const IA32_RTIT_CTL: i32 = 0x570;
 pub const MSR_PKG_C2_RESIDENCY: i32 = 0x60D;
-pub const IA32_KERNEL_GS_BASE: i32 = 0xC0000101u32 as i32;
+pub const IA32_GS_BASE: i32 = 0xC0000101u32 as i32;
 pub const MSR_AMD64_LS_CFG: i32 = 0xC0011020u32 as i32;
 pub const MSR_AMD64_DE_CFG: i32 = 0xC0011029u32 as i32;
 
@@ -234,6 +238,14 @@ pub const MXCSR_MASK: i32 = 0xffff;
 pub const MXCSR_FZ: i32 = 1 << 15;
 pub const MXCSR_DAZ: i32 = 1 << 6;
 pub const MXCSR_RC_SHIFT: i32 = 13;
+// exception flags, the corresponding mask bits are at MXCSR_EXCEPTION_MASK_SHIFT
+pub const MXCSR_IE: i32 = 1;
+pub const MXCSR_DE: i32 = 1 << 1;
+pub const MXCSR_ZE: i32 = 1 << 2;
+pub const MXCSR_OE: i32 = 1 << 3;
+pub const MXCSR_UE: i32 = 1 << 4;
+pub const MXCSR_PE: i32 = 1 << 5;
+pub const MXCSR_EXCEPTION_MASK_SHIFT: i32 = 7;
 
 pub const VALID_TLB_ENTRY_MAX: i32 = 10000;
 pub const TLB_VALID: i32 = 1 << 0;
@@ -278,6 +290,17 @@ pub static mut rdtsc_imprecision_offset: u64 = 0;
 pub static mut rdtsc_last_value: u64 = 0;
 pub static mut tsc_offset: u64 = 0;
 
+// Size of the line armed by monitor, must match cpuid leaf 5
+pub const MONITOR_LINE_SIZE: u32 = 0x40;
+// Physical address of the line armed by monitor, cleared by a write to its page
+pub static mut monitor_address: Option<u32> = None;
+pub static mut in_mwait: bool = false;
+
+// State of the a20 gate, controlled through port 0x92 and the keyboard controller. When disabled,
+// bit 20 of all physical addresses generated by the cpu is forced to zero
+pub static mut a20_enabled: bool = true;
+pub const A20_BIT: u32 = 1 << 20;
+
 pub struct Code {
     pub wasm_table_index: jit::WasmTableIndex,
     pub state_flags: CachedStateFlags,
@@ -389,6 +412,11 @@ impl SegmentDescriptor {
             raw: self.raw | 2 << 40,
         }
     }
+    pub fn clear_busy(&self) -> SegmentDescriptor {
+        SegmentDescriptor {
+            raw: self.raw & !(2 << 40),
+        }
+    }
 }
 
 pub struct InterruptDescriptor {
@@ -450,12 +478,40 @@ pub unsafe fn iret32() { iret(false); }
 
 pub unsafe fn iret(is_16: bool) {
     if vm86_mode() && getiopl() < 3 {
+        if is_16 && *cr.offset(4) & CR4_VME != 0 {
+            // vm86 mode extensions: if is loaded into vif
+            let new_ip = return_on_pagefault!(safe_read16(get_stack_pointer(0)));
+            let new_cs = return_on_pagefault!(safe_read16(get_stack_pointer(2)));
+            let new_flags = return_on_pagefault!(safe_read16(get_stack_pointer(4)));
+
+            if !vme_update_flags16(new_flags) {
+                dbg_log!("#gp iret vme, vip or tf");
+                trigger_gp(0);
+                return;
+            }
+
+            switch_cs_real_mode(new_cs);
+            *instruction_pointer = get_seg_cs() + new_ip;
+            adjust_stack_reg(3 * 2);
+            update_state_flags();
+            return;
+        }
+
         // vm86 mode, iopl != 3
         dbg_log!("#gp iret vm86 mode, iopl != 3");
         trigger_gp(0);
         return;
     }
 
+    if *protected_mode && !vm86_mode() && *flags & FLAG_NT != 0 {
+        // return to the task in the back link of the current task
+        let back_link = return_on_pagefault!(safe_read16(
+            *segment_offsets.offset(TR as isize) + TSR_BACKLINK
+        ));
+        do_task_switch(back_link, TaskSwitch::Iret, None);
+        return;
+    }
+
     let (new_eip, new_cs, mut new_flags) = if is_16 {
         (
             return_on_pagefault!(safe_read16(get_stack_pointer(0))),
@@ -500,13 +556,6 @@ pub unsafe fn iret(is_16: bool) {
 
     dbg_assert!(!vm86_mode());
 
-    if *flags & FLAG_NT != 0 {
-        if DEBUG {
-            panic!("NT");
-        }
-        trigger_gp(0);
-        return;
-    }
 
     if new_flags & FLAG_VM != 0 {
         if *cpl == 0 {
@@ -728,16 +777,25 @@ pub unsafe fn iret(is_16: bool) {
     handle_irqs();
 }
 
+/// The kind of event delivered through call_interrupt_vector, which decides the privilege checks
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum EventType {
+    /// Exceptions and external interrupts
+    Hardware,
+    /// int n, int3 and into: Checked against the gate's dpl, and iopl-sensitive in vm86 mode
+    SoftwareInterrupt,
+    /// int1 (icebp): Raises #db after the instruction, without the checks of a software interrupt
+    PrivilegedSoftwareException,
+}
+
 pub unsafe fn call_interrupt_vector(
     interrupt_nr: i32,
-    is_software_int: bool,
+    event_type: EventType,
     error_code: Option<i32>,
 ) {
-    if *protected_mode {
-        if vm86_mode() && *cr.offset(4) & CR4_VME != 0 {
-            panic!("Unimplemented: VME");
-        }
+    let is_software_int = event_type == EventType::SoftwareInterrupt;
 
+    if *protected_mode {
         if vm86_mode() && is_software_int && getiopl() < 3 {
             dbg_log!("call_interrupt_vector #GP. vm86 && software int && iopl < 3");
             dbg_trace();
@@ -809,9 +867,9 @@ pub unsafe fn call_interrupt_vector(
                 dpl
             );
             dbg_trace();
-            dbg_assert!(descriptor.is_32(), "TODO: Check this (likely #GP)");
+            dbg_assert!(!descriptor.is_32(), "TODO: Check this (likely #GP)");
             dbg_assert!(offset == 0, "TODO: Check this (likely #GP)");
-            do_task_switch(selector, error_code);
+            do_task_switch(selector, TaskSwitch::CallOrInterrupt, error_code);
             return;
         }
 
@@ -1052,6 +1110,60 @@ pub unsafe fn call_interrupt_vector(
     }
 }
 
+/// With cr4.vme, int n in vm86 mode is redirected to the interrupt vector table of the vm86 task
+/// if its bit in the interrupt redirection bitmap is clear. The bitmap occupies the 32 bytes of the
+/// tss below the i/o permission bitmap. Returns false if the interrupt goes through the idt instead
+pub unsafe fn vme_redirect_interrupt(interrupt_nr: i32) -> OrPageFault<bool> {
+    dbg_assert!(vm86_mode() && *cr.offset(4) & CR4_VME != 0);
+
+    let tsr_size = *segment_limits.offset(TR as isize);
+    let tsr_offset = *segment_offsets.offset(TR as isize);
+
+    if !*tss_size_32 || tsr_size < 0x67 {
+        dbg_log!("#gp vme int, no 32-bit tss");
+        trigger_gp(0);
+        return Err(());
+    }
+
+    let iomap_base = read16(translate_address_system_read(tsr_offset + 0x64 + 2)?);
+    let bitmap_offset = iomap_base - 32 + (interrupt_nr >> 3);
+
+    if bitmap_offset < 0 || bitmap_offset as u32 > tsr_size {
+        dbg_log!("#gp vme int, redirection bitmap outside of tss");
+        trigger_gp(0);
+        return Err(());
+    }
+
+    let redirection_bits = read8(translate_address_system_read(tsr_offset + bitmap_offset)?);
+
+    if redirection_bits >> (interrupt_nr & 7) & 1 != 0 {
+        return Ok(false);
+    }
+
+    // like an interrupt in real mode, using the virtual interrupt flag if iopl < 3
+    let index = interrupt_nr << 2;
+    let new_ip = safe_read16(index)?;
+    let new_cs = safe_read16(index + 2)?;
+
+    writable_or_pagefault(get_stack_pointer(-6), 6)?;
+
+    // no exceptions below
+
+    let iopl_3 = getiopl() == 3;
+
+    push16(if iopl_3 { get_eflags() } else { get_eflags_vme() }).unwrap();
+    push16(*sreg.offset(CS as isize) as i32).unwrap();
+    push16(get_real_eip()).unwrap();
+
+    *flags &= !FLAG_AC & !FLAG_TRAP & if iopl_3 { !FLAG_INTERRUPT } else { !FLAG_VIF };
+
+    switch_cs_real_mode(new_cs);
+    *instruction_pointer = get_seg_cs() + new_ip;
+    update_state_flags();
+
+    Ok(true)
+}
+
 pub unsafe fn far_jump(eip: i32, selector: i32, is_call: bool, is_osize_32: bool) {
     dbg_assert!(selector < 0x10000 && selector >= 0);
 
@@ -1094,10 +1206,36 @@ pub unsafe fn far_jump(eip: i32, selector: i32, is_call: bool, is_osize_32: bool
     };
 
     if info.is_system() {
-        dbg_assert!(is_call, "TODO: Jump");
-
         dbg_log!("system type cs: {:x}", selector);
 
+        let task_switch = if is_call { TaskSwitch::CallOrInterrupt } else { TaskSwitch::Jump };
+
+        if info.system_type() == 5 || info.system_type() == 1 || info.system_type() == 9 {
+            // task gate or available tss
+            if info.dpl() < *cpl || info.dpl() < cs_selector.rpl() {
+                dbg_log!("#gp task dpl < cpl or dpl < rpl: {:x}", selector);
+                trigger_gp(selector & !3);
+                return;
+            }
+
+            if !info.is_present() {
+                dbg_log!("#NP for loading not-present task sel={:x}", selector);
+                trigger_np(selector & !3);
+                return;
+            }
+
+            let tss_selector = if info.system_type() == 5 {
+                (info.raw >> 16 & 0xFFFF) as i32
+            }
+            else {
+                selector
+            };
+            do_task_switch(tss_selector, task_switch, None);
+            return;
+        }
+
+        dbg_assert!(is_call, "TODO: Jump");
+
         if info.system_type() == 0xC || info.system_type() == 4 {
             // call gate
             let is_16 = info.system_type() == 4;
@@ -1524,7 +1662,14 @@ pub unsafe fn far_return(eip: i32, selector: i32, stack_adjust: i32, is_osize_32
     update_state_flags();
 }
 
-pub unsafe fn do_task_switch(selector: i32, error_code: Option<i32>) {
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum TaskSwitch {
+    Jump,
+    CallOrInterrupt,
+    Iret,
+}
+
+pub unsafe fn do_task_switch(selector: i32, task_switch: TaskSwitch, error_code: Option<i32>) {
     dbg_log!("do_task_switch sel={:x}", selector);
 
     dbg_assert!(*tss_size_32, "TODO: 16-bit TSS in task switch");
@@ -1543,8 +1688,12 @@ pub unsafe fn do_task_switch(selector: i32, error_code: Option<i32>) {
     let tss_is_16 = descriptor.system_type() <= 3;
     let tss_is_busy = (descriptor.system_type() & 2) == 2;
 
-    if (descriptor.system_type() & 2) == 2 {
-        // is busy
+    if task_switch == TaskSwitch::Iret {
+        if !tss_is_busy {
+            panic!("#TS handler");
+        }
+    }
+    else if tss_is_busy {
         panic!("#GP handler");
     }
 
@@ -1561,7 +1710,7 @@ pub unsafe fn do_task_switch(selector: i32, error_code: Option<i32>) {
 
     let mut old_eflags = get_eflags();
 
-    if tss_is_busy {
+    if task_switch == TaskSwitch::Iret {
         old_eflags &= !FLAG_NT;
     }
 
@@ -1592,20 +1741,27 @@ pub unsafe fn do_task_switch(selector: i32, error_code: Option<i32>) {
 
     //safe_write32(tsr_offset + TSR_LDT, *sreg.offset(reg_ldtr));
 
-    if true
-    /* is jump or call or int */
-    {
-        safe_write64(descriptor_address, descriptor.set_busy().raw).unwrap();
+    if task_switch != TaskSwitch::CallOrInterrupt {
+        // the old task isn't busy anymore, unless it is nested
+        let old_selector = SegmentSelector::of_u16(*sreg.offset(TR as isize));
+        match lookup_segment_selector(old_selector).expect("TODO: handle pagefault") {
+            Ok((old_descriptor, old_descriptor_address)) => {
+                safe_write64(old_descriptor_address, old_descriptor.clear_busy().raw).unwrap();
+            },
+            Err(_) => {
+                panic!("#TS handler");
+            },
+        }
     }
 
+    safe_write64(descriptor_address, descriptor.set_busy().raw).unwrap();
+
     //let new_tsr_size = descriptor.effective_limit;
     let new_tsr_offset = descriptor.base();
 
     dbg_assert!(!tss_is_16, "unimplemented");
 
-    if true
-    /* is call or int */
-    {
+    if task_switch == TaskSwitch::CallOrInterrupt {
         safe_write16(
             new_tsr_offset + TSR_BACKLINK,
             *sreg.offset(TR as isize) as i32,
@@ -1615,82 +1771,76 @@ pub unsafe fn do_task_switch(selector: i32, error_code: Option<i32>) {
 
     let new_cr3 = safe_read32s(new_tsr_offset + TSR_CR3).unwrap();
 
-    *flags &= !FLAG_VM;
-
     let new_eip = safe_read32s(new_tsr_offset + TSR_EIP).unwrap();
     let new_cs = safe_read16(new_tsr_offset + TSR_CS).unwrap();
-    let new_cs_selector = SegmentSelector::of_u16(new_cs as u16);
-    let new_cs_descriptor =
-        match lookup_segment_selector(new_cs_selector).expect("TODO: handle pagefault") {
-            Ok((desc, _)) => desc,
-            Err(SelectorNullOrInvalid::IsNull) => {
-                dbg_log!("null cs");
-                panic!("#TS handler");
-            },
-            Err(SelectorNullOrInvalid::OutsideOfTableLimit) => {
-                dbg_log!("invalid cs: {:x}", new_cs);
-                panic!("#TS handler");
-            },
-        };
-
-    if new_cs_descriptor.is_system() {
-        panic!("#TS handler");
-    }
-
-    if !new_cs_descriptor.is_executable() {
-        panic!("#TS handler");
-    }
+    let mut new_eflags = safe_read32s(new_tsr_offset + TSR_EFLAGS).unwrap();
 
-    if new_cs_descriptor.is_dc() && new_cs_descriptor.dpl() > new_cs_selector.rpl() {
-        dbg_log!("cs conforming and dpl > rpl: {:x}", selector.raw);
-        panic!("#TS handler");
+    if task_switch == TaskSwitch::CallOrInterrupt {
+        new_eflags |= FLAG_NT;
     }
 
-    if !new_cs_descriptor.is_dc() && new_cs_descriptor.dpl() != new_cs_selector.rpl() {
-        dbg_log!("cs non-conforming and dpl != rpl: {:x}", selector.raw);
-        panic!("#TS handler");
-    }
+    // All flags are loaded from the tss, independent of the privilege level
+    *flags = new_eflags & FLAGS_MASK | FLAGS_DEFAULT;
+    *flags_changed = 0;
 
-    if !new_cs_descriptor.is_present() {
-        dbg_log!("#NP for loading not-present in cs sel={:x}", selector.raw);
-        panic!("#TS handler");
+    if new_eflags & FLAG_VM != 0 {
+        // virtual 8086 mode task, the segment registers are loaded like in real mode
+        switch_cs_real_mode(new_cs);
+        *cpl = 3;
+        cpl_changed();
     }
+    else {
+        let new_cs_selector = SegmentSelector::of_u16(new_cs as u16);
+        let new_cs_descriptor =
+            match lookup_segment_selector(new_cs_selector).expect("TODO: handle pagefault") {
+                Ok((desc, _)) => desc,
+                Err(SelectorNullOrInvalid::IsNull) => {
+                    dbg_log!("null cs");
+                    panic!("#TS handler");
+                },
+                Err(SelectorNullOrInvalid::OutsideOfTableLimit) => {
+                    dbg_log!("invalid cs: {:x}", new_cs);
+                    panic!("#TS handler");
+                },
+            };
 
-    *segment_is_null.offset(CS as isize) = false;
-    *segment_limits.offset(CS as isize) = new_cs_descriptor.effective_limit();
-    *segment_offsets.offset(CS as isize) = new_cs_descriptor.base();
-    *sreg.offset(CS as isize) = new_cs as u16;
-
-    *cpl = new_cs_descriptor.dpl();
-    cpl_changed();
+        if new_cs_descriptor.is_system() {
+            panic!("#TS handler");
+        }
 
-    dbg_assert!((*sreg.offset(CS as isize) & 3) as u8 == *cpl);
+        if !new_cs_descriptor.is_executable() {
+            panic!("#TS handler");
+        }
 
-    dbg_assert!(
-        new_eip as u32 <= new_cs_descriptor.effective_limit(),
-        "todo: #gp"
-    );
-    update_cs_size(new_cs_descriptor.is_32());
+        if new_cs_descriptor.is_dc() && new_cs_descriptor.dpl() > new_cs_selector.rpl() {
+            dbg_log!("cs conforming and dpl > rpl: {:x}", selector.raw);
+            panic!("#TS handler");
+        }
 
-    let mut new_eflags = safe_read32s(new_tsr_offset + TSR_EFLAGS).unwrap();
+        if !new_cs_descriptor.is_dc() && new_cs_descriptor.dpl() != new_cs_selector.rpl() {
+            dbg_log!("cs non-conforming and dpl != rpl: {:x}", selector.raw);
+            panic!("#TS handler");
+        }
 
-    if true
-    /* is call or int */
-    {
-        safe_write32(tsr_offset + TSR_BACKLINK, selector.raw as i32).unwrap();
-        new_eflags |= FLAG_NT;
-    }
+        if !new_cs_descriptor.is_present() {
+            dbg_log!("#NP for loading not-present in cs sel={:x}", selector.raw);
+            panic!("#TS handler");
+        }
 
-    if new_eflags & FLAG_VM != 0 {
-        panic!("task switch to VM mode");
-    }
+        *segment_is_null.offset(CS as isize) = false;
+        *segment_limits.offset(CS as isize) = new_cs_descriptor.effective_limit();
+        *segment_offsets.offset(CS as isize) = new_cs_descriptor.base();
+        *sreg.offset(CS as isize) = new_cs as u16;
 
-    update_eflags(new_eflags);
+        // for conforming code segments, the cpl is the rpl of the selector (not the dpl)
+        *cpl = new_cs_selector.rpl();
+        cpl_changed();
 
-    if true
-    /* call or int */
-    {
-        *flags |= FLAG_NT;
+        dbg_assert!(
+            new_eip as u32 <= new_cs_descriptor.effective_limit(),
+            "todo: #gp"
+        );
+        update_cs_size(new_cs_descriptor.is_32());
     }
 
     let new_ldt = safe_read16(new_tsr_offset + TSR_LDT).unwrap();
@@ -1758,6 +1908,32 @@ pub unsafe fn get_eflags() -> i32 {
         | (getof() as i32) << 11;
 }
 
+/// The flags seen by pushf, int n and iret in vm86 mode with cr4.vme and iopl < 3: if is replaced
+/// by vif and iopl reads as 3
+#[no_mangle]
+pub unsafe fn get_eflags_vme() -> i32 {
+    get_eflags() & 0xFFFF & !FLAG_INTERRUPT
+        | if *flags & FLAG_VIF != 0 { FLAG_INTERRUPT } else { 0 }
+        | FLAG_IOPL
+}
+
+/// The counterpart of get_eflags_vme for popf and iret: if is loaded into vif, iopl and if are
+/// left unchanged. Returns false without updating the flags if #gp must be raised instead
+#[no_mangle]
+pub unsafe fn vme_update_flags16(new_flags: i32) -> bool {
+    if new_flags & FLAG_TRAP != 0 || new_flags & FLAG_INTERRUPT != 0 && *flags & FLAG_VIP != 0 {
+        return false;
+    }
+
+    let unchanged = !0xFFFF | FLAG_IOPL | FLAG_INTERRUPT;
+    let new_vif = if new_flags & FLAG_INTERRUPT != 0 { FLAG_VIF } else { 0 };
+
+    *flags = (*flags & unchanged | new_flags & !unchanged) & !FLAG_VIF | new_vif;
+    *flags = *flags & FLAGS_MASK | FLAGS_DEFAULT;
+    *flags_changed = 0;
+    true
+}
+
 pub unsafe fn readable_or_pagefault(addr: i32, size: i32) -> OrPageFault<()> {
     dbg_assert!(size < 0x1000);
     dbg_assert!(size > 0);
@@ -1883,7 +2059,7 @@ pub unsafe fn do_page_walk(
 
     if cr0 & CR0_PG == 0 {
         // paging disabled
-        high = addr as u32 & 0xFFFFF000;
+        high = (addr as u32 & 0xFFFFF000) as u64;
         global = false
     }
     else {
@@ -1891,7 +2067,8 @@ pub unsafe fn do_page_walk(
 
         let pae = cr4 & CR4_PAE != 0;
 
-        let (page_dir_addr, page_dir_entry) = if pae {
+        // page_dir_entry_high holds bits 32 to 35 of the physical address in pae mode
+        let (page_dir_addr, page_dir_entry, page_dir_entry_high) = if pae {
             let pdpt_entry = *reg_pdpte.offset(((addr as u32) >> 30) as isize);
             if pdpt_entry as i32 & PAGE_TABLE_PRESENT_MASK == 0 {
                 if side_effects {
@@ -1900,24 +2077,31 @@ pub unsafe fn do_page_walk(
                 return Err(());
             }
 
-            let page_dir_addr =
-                (pdpt_entry as u32 & 0xFFFFF000) + ((((addr as u32) >> 21) & 0x1FF) << 3);
+            let page_dir_addr = memory::map_physical_address(
+                (pdpt_entry & PAE_ADDRESS_MASK) + ((((addr as u32) >> 21) & 0x1FF) << 3) as u64,
+            );
             let page_dir_entry = read64s(page_dir_addr);
             dbg_assert!(
-                page_dir_entry as u64 & 0x7FFF_FFFF_0000_0000 == 0,
-                "Unsupported: Page directory entry larger than 32 bits"
+                page_dir_entry as u64 & 0x7FFF_FFF0_0000_0000 == 0,
+                "Unsupported: Reserved bits in page directory entry"
             );
             dbg_assert!(
                 page_dir_entry & 0x8000_0000_0000_0000u64 as i64 == 0,
                 "Unsupported: NX bit"
             );
 
-            (page_dir_addr, page_dir_entry as i32)
+            (
+                page_dir_addr,
+                page_dir_entry as i32,
+                (page_dir_entry as u64 >> 32) as u32,
+            )
         }
         else {
-            let page_dir_addr = *cr.offset(3) as u32 + (((addr as u32) >> 22) << 2);
+            let page_dir_addr = memory::map_physical_address(
+                ((*cr.offset(3) & !0xFFF) as u32 + (((addr as u32) >> 22) << 2)) as u64,
+            );
             let page_dir_entry = read32s(page_dir_addr);
-            (page_dir_addr, page_dir_entry)
+            (page_dir_addr, page_dir_entry, 0)
         };
 
         if page_dir_entry & PAGE_TABLE_PRESENT_MASK == 0 {
@@ -1959,34 +2143,46 @@ pub unsafe fn do_page_walk(
             }
 
             high = if pae {
-                page_dir_entry as u32 & 0xFFE00000 | (addr & 0x1FF000) as u32
+                (page_dir_entry_high as u64) << 32
+                    | (page_dir_entry as u32 & 0xFFE00000 | (addr & 0x1FF000) as u32) as u64
             }
             else {
-                page_dir_entry as u32 & 0xFFC00000 | (addr & 0x3FF000) as u32
+                // pse-36: bits 13 to 16 of the entry are bits 32 to 35 of the physical address
+                ((page_dir_entry as u32 >> 13 & 0xF) as u64) << 32
+                    | (page_dir_entry as u32 & 0xFFC00000 | (addr & 0x3FF000) as u32) as u64
             };
             global = page_dir_entry & PAGE_TABLE_GLOBAL_MASK == PAGE_TABLE_GLOBAL_MASK
         }
         else {
-            let (page_table_addr, page_table_entry) = if pae {
-                let page_table_addr =
-                    (page_dir_entry as u32 & 0xFFFFF000) + (((addr as u32 >> 12) & 0x1FF) << 3);
+            let (page_table_addr, page_table_entry, page_table_entry_high) = if pae {
+                let page_table_addr = memory::map_physical_address(
+                    (page_dir_entry_high as u64) << 32
+                        | ((page_dir_entry as u32 & 0xFFFFF000)
+                            + (((addr as u32 >> 12) & 0x1FF) << 3)) as u64,
+                );
                 let page_table_entry = read64s(page_table_addr);
                 dbg_assert!(
-                    page_table_entry as u64 & 0x7FFF_FFFF_0000_0000 == 0,
-                    "Unsupported: Page table entry larger than 32 bits"
+                    page_table_entry as u64 & 0x7FFF_FFF0_0000_0000 == 0,
+                    "Unsupported: Reserved bits in page table entry"
                 );
                 dbg_assert!(
                     page_table_entry & 0x8000_0000_0000_0000u64 as i64 == 0,
                     "Unsupported: NX bit"
                 );
 
-                (page_table_addr, page_table_entry as i32)
+                (
+                    page_table_addr,
+                    page_table_entry as i32,
+                    (page_table_entry as u64 >> 32) as u32,
+                )
             }
             else {
-                let page_table_addr =
-                    (page_dir_entry as u32 & 0xFFFFF000) + (((addr as u32 >> 12) & 0x3FF) << 2);
+                let page_table_addr = memory::map_physical_address(
+                    ((page_dir_entry as u32 & 0xFFFFF000) + (((addr as u32 >> 12) & 0x3FF) << 2))
+                        as u64,
+                );
                 let page_table_entry = read32s(page_table_addr);
-                (page_table_addr, page_table_entry)
+                (page_table_addr, page_table_entry, 0)
             };
 
             if page_table_entry & PAGE_TABLE_PRESENT_MASK == 0 {
@@ -2025,7 +2221,8 @@ pub unsafe fn do_page_walk(
                 write8(page_table_addr, new_page_table_entry);
             }
 
-            high = page_table_entry as u32 & 0xFFFFF000;
+            high = (page_table_entry_high as u64) << 32
+                | (page_table_entry as u32 & 0xFFFFF000) as u64;
             global = page_table_entry & PAGE_TABLE_GLOBAL_MASK == PAGE_TABLE_GLOBAL_MASK
         }
     }
@@ -2058,8 +2255,12 @@ pub unsafe fn do_page_walk(
         dbg_assert!(found);
     }
 
+    let high = if a20_enabled { high } else { high & !(A20_BIT as u64) };
+    let high = memory::map_physical_address(high);
+
     let is_in_mapped_range = in_mapped_range(high);
-    let has_code = !is_in_mapped_range && jit::jit_page_has_code(Page::page_of(high));
+    let has_code = !is_in_mapped_range
+        && (jit::jit_page_has_code(Page::page_of(high)) || is_monitored_page(Page::page_of(high)));
     let info_bits = TLB_VALID
         | if for_writing { 0 } else { TLB_READONLY }
         | if allow_user { 0 } else { TLB_NO_USER }
@@ -2135,6 +2336,27 @@ pub unsafe fn clear_tlb() {
     };
 }
 
+/// Reinsert a tlb entry that has been saved away by an smp cpu switch
+pub unsafe fn restore_tlb_entry(page: i32, mut entry: i32) {
+    if tlb_data[page as usize] != 0 || valid_tlb_entries_count == VALID_TLB_ENTRY_MAX {
+        // an existing (global) entry takes precedence
+        return;
+    }
+
+    // Code may have been compiled into or removed from the physical page while the entry was
+    // saved
+    let high = (entry as u32 & !0xFFF ^ (page as u32) << 12) - memory::mem8 as u32;
+    let physical_page = Page::page_of(high);
+    let has_code = entry & TLB_IN_MAPPED_RANGE == 0
+        && (jit::jit_page_has_code(physical_page) || is_monitored_page(physical_page));
+    entry = if has_code { entry | TLB_HAS_CODE } else { entry & !TLB_HAS_CODE };
+
+    tlb_data[page as usize] = entry;
+    valid_tlb_entries[valid_tlb_entries_count as usize] = page;
+    valid_tlb_entries_count += 1;
+    jit::update_tlb_code(Page::page_of((page as u32) << 12), physical_page);
+}
+
 #[no_mangle]
 pub unsafe fn trigger_de_jit(start_eip: i32) {
     dbg_log!("#de in jit mode");
@@ -2156,6 +2378,21 @@ pub unsafe fn trigger_nm_jit(start_eip: i32) {
     jit_fault = Some((CPU_EXCEPTION_NM, None))
 }
 
+#[no_mangle]
+pub unsafe fn trigger_mf_jit(start_eip: i32) {
+    dbg_log!("#mf in jit mode");
+    *instruction_pointer = *instruction_pointer & !0xFFF | start_eip & 0xFFF;
+    jit_fault = Some((CPU_EXCEPTION_MF, None))
+}
+
+#[no_mangle]
+pub unsafe fn trigger_xm_jit(start_eip: i32) {
+    dbg_log!("#xm in jit mode");
+    ::cpu::sse_instr::sse_exception_pending = false;
+    *instruction_pointer = *instruction_pointer & !0xFFF | start_eip & 0xFFF;
+    jit_fault = Some((simd_fp_exception_vector(), None))
+}
+
 #[no_mangle]
 pub unsafe fn trigger_gp_jit(code: i32, start_eip: i32) {
     dbg_log!("#gp in jit mode");
@@ -2171,7 +2408,7 @@ pub unsafe fn trigger_fault_end_jit() {
             return;
         }
     }
-    call_interrupt_vector(code, false, error_code);
+    call_interrupt_vector(code, EventType::Hardware, error_code);
 }
 
 /// Pagefault handling with the jit works as follows:
@@ -2211,7 +2448,7 @@ pub unsafe fn trigger_pagefault(addr: i32, present: bool, write: bool, user: boo
     }
     else {
         *instruction_pointer = *previous_ip;
-        call_interrupt_vector(CPU_EXCEPTION_PF, false, Some(error_code));
+        call_interrupt_vector(CPU_EXCEPTION_PF, EventType::Hardware, Some(error_code));
     }
 }
 
@@ -2281,6 +2518,7 @@ pub fn check_tlb_invariants() {
         // If some code has been created in a page, the corresponding tlb entries must be marked
         dbg_assert!(!has_code || entry_has_code);
     }
+
 }
 
 pub const DISABLE_EIP_TRANSLATION_OPTIMISATION: bool = false;
@@ -2291,8 +2529,8 @@ pub unsafe fn read_imm8() -> OrPageFault<i32> {
         *eip_phys = (translate_address_read(eip)? ^ eip as u32) as i32;
         *last_virt_eip = eip & !0xFFF
     }
-    dbg_assert!(!in_mapped_range((*eip_phys ^ eip) as u32));
-    let data8 = *mem8.offset((*eip_phys ^ eip) as isize) as i32;
+    // Goes through mmap_read8 if executing from memory-mapped io (option roms, flash)
+    let data8 = read8((*eip_phys ^ eip) as u32);
     *instruction_pointer = eip + 1;
     return Ok(data8);
 }
@@ -2643,12 +2881,13 @@ pub unsafe fn set_cr3(mut cr3: i32) {
 pub unsafe fn load_pdpte(cr3: i32) {
     dbg_assert!(cr3 & 0b1111 == 0);
     for i in 0..4 {
-        let mut pdpt_entry = read64s(cr3 as u32 + 8 * i as u32) as u64;
+        let mut pdpt_entry =
+            read64s(memory::map_physical_address((cr3 as u32 + 8 * i as u32) as u64)) as u64;
         pdpt_entry &= !0b1110_0000_0000;
         dbg_assert!(pdpt_entry & 0b11000 == 0, "TODO");
         dbg_assert!(
-            pdpt_entry as u64 & 0xFFFF_FFFF_0000_0000 == 0,
-            "Unsupported: PDPT entry larger than 32 bits"
+            pdpt_entry as u64 & 0xFFFF_FFF0_0000_0000 == 0,
+            "Unsupported: Reserved bits in PDPT entry"
         );
         if pdpt_entry as i32 & PAGE_TABLE_PRESENT_MASK != 0 {
             dbg_assert!(
@@ -2828,7 +3067,7 @@ pub unsafe fn cycle_internal() {
     }
 
     if let Some((wasm_table_index, initial_state)) = jit_entry {
-        if jit::CHECK_JIT_STATE_INVARIANTS {
+        if jit::check_jit_state_invariants_enabled() {
             match get_phys_eip() {
                 Err(()) => dbg_assert!(false),
                 Ok(phys_eip) => {
@@ -2839,6 +3078,8 @@ pub unsafe fn cycle_internal() {
             }
         }
         profiler::stat_increment(RUN_FROM_CACHE);
+        let promote = jit::record_module_use(wasm_table_index);
+        let initial_cs_offset = get_seg_cs() as u32;
         let initial_instruction_counter = *instruction_counter;
         #[cfg(debug_assertions)]
         {
@@ -2878,6 +3119,19 @@ pub unsafe fn cycle_internal() {
             ::opstats::record_opstat_jit_exit(last_jump_opcode as u32);
         }
 
+        if promote {
+            // after running the module, since compiling may evict it
+            if let Ok(phys_eip) = translate_address_read_no_side_effects(initial_eip) {
+                jit::jit_promote_module(
+                    wasm_table_index,
+                    initial_eip,
+                    phys_eip,
+                    initial_cs_offset,
+                    initial_state_flags,
+                );
+            }
+        }
+
         if is_near_end_of_page(*instruction_pointer as u32) {
             profiler::stat_increment(RUN_FROM_CACHE_EXIT_NEAR_END_OF_PAGE);
         }
@@ -2942,13 +3196,11 @@ pub unsafe fn get_phys_eip() -> OrPageFault<u32> {
         *last_virt_eip = eip & !0xFFF
     }
     let phys_addr = (*eip_phys ^ eip) as u32;
-    dbg_assert!(!in_mapped_range(phys_addr));
     return Ok(phys_addr);
 }
 
 unsafe fn jit_run_interpreted(mut phys_addr: u32) {
     profiler::stat_increment(RUN_INTERPRETED);
-    dbg_assert!(!in_mapped_range(phys_addr));
 
     jit_block_boundary = false;
     let mut i = 0;
@@ -2963,7 +3215,7 @@ unsafe fn jit_run_interpreted(mut phys_addr: u32) {
 
         i += 1;
         let start_eip = *instruction_pointer;
-        let opcode = *mem8.offset(phys_addr as isize) as i32;
+        let opcode = read8(phys_addr);
         *instruction_pointer += 1;
         dbg_assert!(*prefixes == 0);
         run_instruction(opcode | (*is_32 as i32) << 8);
@@ -2983,6 +3235,12 @@ unsafe fn jit_run_interpreted(mut phys_addr: u32) {
         phys_addr = return_on_pagefault!(get_phys_eip()) as u32;
     }
 
+    if jit_block_boundary {
+        if let Ok(target) = translate_address_read_no_side_effects(*instruction_pointer) {
+            jit::record_indirect_jump(phys_addr, target);
+        }
+    }
+
     if cfg!(debug_assertions) {
         debug_last_jump = LastJump::Interpreted { phys_addr };
     }
@@ -2997,7 +3255,8 @@ pub fn update_state_flags() {
             (*is_32 as u32) << 0
                 | (*stack_size_32 as u32) << 1
                 | ((*cpl == 3) as u32) << 2
-                | (has_flat_segmentation() as u32) << 3,
+                | (has_flat_segmentation() as u32) << 3
+                | ((*fpu_control_word & 0xF3F == 0x23F) as u32) << 4,
         )
     }
 }
@@ -3028,6 +3287,10 @@ pub unsafe fn main_loop() -> f64 {
 
     let start = microtick();
 
+    if smp::smp_get_cpu_count() > 1 {
+        return smp::main_loop(start);
+    }
+
     if *in_hlt {
         if *flags & FLAG_INTERRUPT != 0 {
             let t = run_hardware_timers(*acpi_enabled, start);
@@ -3080,7 +3343,7 @@ pub unsafe fn trigger_de() {
             return;
         }
     }
-    call_interrupt_vector(CPU_EXCEPTION_DE, false, None);
+    call_interrupt_vector(CPU_EXCEPTION_DE, EventType::Hardware, None);
 }
 
 #[inline(never)]
@@ -3093,7 +3356,7 @@ pub unsafe fn trigger_ud() {
             return;
         }
     }
-    call_interrupt_vector(CPU_EXCEPTION_UD, false, None);
+    call_interrupt_vector(CPU_EXCEPTION_UD, EventType::Hardware, None);
 }
 
 #[inline(never)]
@@ -3106,7 +3369,36 @@ pub unsafe fn trigger_nm() {
             return;
         }
     }
-    call_interrupt_vector(CPU_EXCEPTION_NM, false, None);
+    call_interrupt_vector(CPU_EXCEPTION_NM, EventType::Hardware, None);
+}
+
+pub unsafe fn trigger_mf() {
+    dbg_log!("#mf eip={:x}", *previous_ip);
+    *instruction_pointer = *previous_ip;
+    if DEBUG {
+        if cpu_exception_hook(CPU_EXCEPTION_MF) {
+            return;
+        }
+    }
+    call_interrupt_vector(CPU_EXCEPTION_MF, EventType::Hardware, None);
+}
+
+/// Unmasked simd floating point exceptions raise #XM, or #UD if the os hasn't enabled them
+pub unsafe fn simd_fp_exception_vector() -> i32 {
+    if *cr.offset(4) & CR4_OSXMMEXCPT != 0 { CPU_EXCEPTION_XM } else { CPU_EXCEPTION_UD }
+}
+
+pub unsafe fn trigger_xm() {
+    dbg_log!("#xm eip={:x}", *previous_ip);
+    ::cpu::sse_instr::sse_exception_pending = false;
+    *instruction_pointer = *previous_ip;
+    let vector = simd_fp_exception_vector();
+    if DEBUG {
+        if cpu_exception_hook(vector) {
+            return;
+        }
+    }
+    call_interrupt_vector(vector, EventType::Hardware, None);
 }
 
 #[inline(never)]
@@ -3118,7 +3410,7 @@ pub unsafe fn trigger_gp(code: i32) {
             return;
         }
     }
-    call_interrupt_vector(CPU_EXCEPTION_GP, false, Some(code));
+    call_interrupt_vector(CPU_EXCEPTION_GP, EventType::Hardware, Some(code));
 }
 
 #[cold]
@@ -3227,6 +3519,17 @@ pub unsafe fn safe_read128s(addr: i32) -> OrPageFault<reg128> {
     }
 }
 
+/// Like safe_read128s, but raises #GP(0) for addresses that are not 16-byte aligned, as required
+/// by movaps and most other sse instructions with a 128-bit memory operand
+pub unsafe fn safe_read128s_aligned(addr: i32) -> OrPageFault<reg128> {
+    if addr & 0xF != 0 {
+        dbg_log!("#gp unaligned 128-bit read from {:x}", addr);
+        trigger_gp(0);
+        return Err(());
+    }
+    safe_read128s(addr)
+}
+
 #[no_mangle]
 #[cfg(feature = "profiler")]
 pub fn report_safe_read_jit_slow(address: u32, entry: i32) {
@@ -3418,7 +3721,8 @@ pub unsafe fn get_phys_eip_slow_jit(addr: i32) -> i32 {
     match translate_address_read_jit(addr) {
         Err(()) => 1,
         Ok(addr_low) => {
-            dbg_assert!(!in_mapped_range(addr_low as u32)); // same assumption as in read_imm8
+            // Mapped memory is never compiled, so the page switch check fails and exits to the
+            // interpreter
             ((addr_low as i32 + memory::mem8 as i32) ^ addr) & !0xFFF
         },
     }
@@ -3524,7 +3828,14 @@ pub unsafe fn safe_write_slow_jit(
     }
     else {
         if !can_skip_dirty_page {
-            jit::jit_dirty_page(jit::get_jit_state(), Page::page_of(addr_low));
+            let value = reg128 {
+                u64: [value_low, value_high],
+            };
+            jit::jit_dirty_write(
+                jit::get_jit_state(),
+                addr_low,
+                &value.u8[..bitsize as usize / 8],
+            );
         }
         ((addr_low as i32 + memory::mem8 as i32) ^ addr) & !0xFFF
     }
@@ -3558,7 +3869,7 @@ pub unsafe fn safe_write8(addr: i32, value: i32) -> OrPageFault<()> {
     }
     else {
         if !can_skip_dirty_page {
-            jit::jit_dirty_page(jit::get_jit_state(), Page::page_of(phys_addr));
+            jit::jit_dirty_write(jit::get_jit_state(), phys_addr, &[value as u8]);
         }
         else {
             dbg_assert!(!jit::jit_page_has_code(Page::page_of(phys_addr as u32)));
@@ -3578,7 +3889,7 @@ pub unsafe fn safe_write16(addr: i32, value: i32) -> OrPageFault<()> {
     }
     else {
         if !can_skip_dirty_page {
-            jit::jit_dirty_page(jit::get_jit_state(), Page::page_of(phys_addr));
+            jit::jit_dirty_write(jit::get_jit_state(), phys_addr, &(value as u16).to_le_bytes());
         }
         else {
             dbg_assert!(!jit::jit_page_has_code(Page::page_of(phys_addr as u32)));
@@ -3602,7 +3913,7 @@ pub unsafe fn safe_write32(addr: i32, value: i32) -> OrPageFault<()> {
     }
     else {
         if !can_skip_dirty_page {
-            jit::jit_dirty_page(jit::get_jit_state(), Page::page_of(phys_addr));
+            jit::jit_dirty_write(jit::get_jit_state(), phys_addr, &value.to_le_bytes());
         }
         else {
             dbg_assert!(!jit::jit_page_has_code(Page::page_of(phys_addr as u32)));
@@ -3625,7 +3936,7 @@ pub unsafe fn safe_write64(addr: i32, value: u64) -> OrPageFault<()> {
         }
         else {
             if !can_skip_dirty_page {
-                jit::jit_dirty_page(jit::get_jit_state(), Page::page_of(phys_addr));
+                jit::jit_dirty_write(jit::get_jit_state(), phys_addr, &value.to_le_bytes());
             }
             else {
                 dbg_assert!(!jit::jit_page_has_code(Page::page_of(phys_addr as u32)));
@@ -3636,6 +3947,15 @@ pub unsafe fn safe_write64(addr: i32, value: u64) -> OrPageFault<()> {
     Ok(())
 }
 
+pub unsafe fn safe_write128_aligned(addr: i32, value: reg128) -> OrPageFault<()> {
+    if addr & 0xF != 0 {
+        dbg_log!("#gp unaligned 128-bit write to {:x}", addr);
+        trigger_gp(0);
+        return Err(());
+    }
+    safe_write128(addr, value)
+}
+
 pub unsafe fn safe_write128(addr: i32, value: reg128) -> OrPageFault<()> {
     if addr & 0xFFF > 0x1000 - 16 {
         writable_or_pagefault(addr, 16)?;
@@ -3649,7 +3969,7 @@ pub unsafe fn safe_write128(addr: i32, value: reg128) -> OrPageFault<()> {
         }
         else {
             if !can_skip_dirty_page {
-                jit::jit_dirty_page(jit::get_jit_state(), Page::page_of(phys_addr));
+                jit::jit_dirty_write(jit::get_jit_state(), phys_addr, &value.u8);
             }
             else {
                 dbg_assert!(!jit::jit_page_has_code(Page::page_of(phys_addr as u32)));
@@ -3671,7 +3991,7 @@ pub unsafe fn safe_read_write8(addr: i32, instruction: &dyn Fn(i32) -> i32) {
     }
     else {
         if !can_skip_dirty_page {
-            ::jit::jit_dirty_page(::jit::get_jit_state(), Page::page_of(phys_addr));
+            ::jit::jit_dirty_write(::jit::get_jit_state(), phys_addr, &[value as u8]);
         }
         else {
             dbg_assert!(!::jit::jit_page_has_code(Page::page_of(phys_addr as u32)));
@@ -3697,7 +4017,11 @@ pub unsafe fn safe_read_write16(addr: i32, instruction: &dyn Fn(i32) -> i32) {
         }
         else {
             if !can_skip_dirty_page {
-                ::jit::jit_dirty_page(::jit::get_jit_state(), Page::page_of(phys_addr));
+                ::jit::jit_dirty_write(
+                    ::jit::get_jit_state(),
+                    phys_addr,
+                    &(value as u16).to_le_bytes(),
+                );
             }
             else {
                 dbg_assert!(!::jit::jit_page_has_code(Page::page_of(phys_addr as u32)));
@@ -3725,7 +4049,7 @@ pub unsafe fn safe_read_write32(addr: i32, instruction: &dyn Fn(i32) -> i32) {
         }
         else {
             if !can_skip_dirty_page {
-                ::jit::jit_dirty_page(::jit::get_jit_state(), Page::page_of(phys_addr));
+                ::jit::jit_dirty_write(::jit::get_jit_state(), phys_addr, &value.to_le_bytes());
             }
             else {
                 dbg_assert!(!::jit::jit_page_has_code(Page::page_of(phys_addr as u32)));
@@ -3823,30 +4147,6 @@ pub unsafe fn task_switch_test() -> bool {
 
 pub unsafe fn set_mxcsr(new_mxcsr: i32) {
     dbg_assert!(new_mxcsr & !MXCSR_MASK == 0); // checked by caller
-
-    if *mxcsr & MXCSR_DAZ == 0 && new_mxcsr & MXCSR_DAZ != 0 {
-        dbg_log!("Warning: Unimplemented MXCSR bit: Denormals Are Zero");
-    }
-    if *mxcsr & MXCSR_FZ == 0 && new_mxcsr & MXCSR_FZ != 0 {
-        dbg_log!("Warning: Unimplemented MXCSR bit: Flush To Zero");
-    }
-
-    let rounding_mode = new_mxcsr >> MXCSR_RC_SHIFT & 3;
-    if *mxcsr >> MXCSR_RC_SHIFT & 3 == 0 && rounding_mode != 0 {
-        dbg_log!(
-            "Warning: Unimplemented MXCSR rounding mode: {}",
-            rounding_mode
-        );
-    }
-
-    let exception_mask = new_mxcsr >> 7 & 0b111111;
-    if *mxcsr >> 7 & 0b111111 != exception_mask && exception_mask != 0b111111 {
-        dbg_log!(
-            "Warning: Unimplemented MXCSR exception mask: 0b{:b}",
-            exception_mask
-        );
-    }
-
     *mxcsr = new_mxcsr;
 }
 
@@ -4053,6 +4353,21 @@ pub fn clear_tlb_code(page: i32) {
     }
 }
 
+#[no_mangle]
+pub unsafe fn get_a20() -> bool { a20_enabled }
+
+/// The mask is applied when tlb entries are created, so all of them are dropped when it changes.
+/// Compiled code is keyed by physical page and stays valid, only the tlb_code mapping from
+/// virtual pages to it (also cleared here) depends on the mask
+#[no_mangle]
+pub unsafe fn set_a20(enabled: bool) {
+    if a20_enabled != enabled {
+        dbg_log!("a20 {}", if enabled { "enabled" } else { "disabled" });
+        a20_enabled = enabled;
+        full_clear_tlb();
+    }
+}
+
 pub unsafe fn invlpg(addr: i32) {
     let page = (addr as u32 >> 12) as i32;
     // Note: Doesn't remove this page from valid_tlb_entries: This isn't
@@ -4064,6 +4379,133 @@ pub unsafe fn invlpg(addr: i32) {
     *last_virt_eip = -1;
 }
 
+pub const INVPCID_INDIVIDUAL_ADDRESS: i32 = 0;
+pub const INVPCID_SINGLE_CONTEXT: i32 = 1;
+pub const INVPCID_ALL_CONTEXTS_INCLUDING_GLOBAL: i32 = 2;
+pub const INVPCID_ALL_CONTEXTS: i32 = 3;
+
+/// Without cr4.pcide (which can't be set outside of long mode), all tlb entries belong to pcid 0
+pub unsafe fn invpcid(ty: i32, descriptor_addr: i32) {
+    if *protected_mode && 0 != *cpl {
+        trigger_gp(0);
+        return;
+    }
+    if ty as u32 > INVPCID_ALL_CONTEXTS as u32 {
+        dbg_log!("invpcid: invalid type {}", ty);
+        trigger_gp(0);
+        return;
+    }
+    let descriptor = return_on_pagefault!(safe_read128s(descriptor_addr));
+    let addr = descriptor.u32[2] as i32;
+
+    if (ty == INVPCID_INDIVIDUAL_ADDRESS || ty == INVPCID_SINGLE_CONTEXT) && descriptor.u64[0] != 0
+    {
+        dbg_log!("invpcid: invalid descriptor {:x}", descriptor.u64[0]);
+        trigger_gp(0);
+        return;
+    }
+
+    match ty {
+        INVPCID_INDIVIDUAL_ADDRESS => {
+            if tlb_data[(addr as u32 >> 12) as usize] & TLB_GLOBAL == 0 {
+                invlpg(addr);
+            }
+        },
+        INVPCID_SINGLE_CONTEXT | INVPCID_ALL_CONTEXTS => clear_tlb(),
+        INVPCID_ALL_CONTEXTS_INCLUDING_GLOBAL => full_clear_tlb(),
+        _ => dbg_assert!(false),
+    }
+}
+
+#[no_mangle]
+pub unsafe fn monitor(addr: i32) {
+    if 0 != *cpl {
+        trigger_ud();
+        return;
+    }
+    let phys_addr = return_on_pagefault!(translate_address_read(addr));
+
+    if let Some(old_address) = monitor_address {
+        let old_page = Page::page_of(old_address);
+        monitor_address = None;
+        if !jit::jit_page_has_code(old_page) && !is_monitored_page(old_page) {
+            tlb_set_has_code(old_page, false);
+        }
+    }
+
+    if in_mapped_range(phys_addr) {
+        // writes to mapped memory aren't tracked, mwait will return immediately
+        return;
+    }
+
+    monitor_address = Some(phys_addr & !(MONITOR_LINE_SIZE - 1));
+
+    // Writes to pages with code are reported to the jit, which calls monitor_write_hook
+    tlb_set_has_code(Page::page_of(phys_addr), true);
+}
+
+#[no_mangle]
+pub unsafe fn mwait() {
+    if 0 != *cpl {
+        trigger_ud();
+        return;
+    }
+    if read_reg32(ECX) & !1 != 0 {
+        // only the "interrupts as break event" extension is supported
+        trigger_gp(0);
+        return;
+    }
+
+    if let None = monitor_address {
+        // not armed, or the monitored line has been written since monitor
+        return;
+    }
+
+    if *flags & FLAG_INTERRUPT == 0 {
+        // Masked interrupts can only be detected by acknowledging them, so treat this as a
+        // spurious wake-up (which is allowed by the specification) rather than hanging
+        return;
+    }
+
+    in_mwait = true;
+    *in_hlt = true;
+
+    // same as hlt: run timers and handle an interrupt that is due right away
+    run_hardware_timers(*acpi_enabled, microtick());
+    handle_irqs();
+}
+
+fn is_monitored_by_current_cpu(page: Page) -> bool {
+    match unsafe { monitor_address } {
+        Some(addr) => Page::page_of(addr) == page,
+        None => false,
+    }
+}
+
+/// Whether any cpu has armed monitor on the physical page
+pub fn is_monitored_page(page: Page) -> bool {
+    is_monitored_by_current_cpu(page) || smp::is_monitored_by_other_cpu(page)
+}
+
+/// Called by the jit for every write to a page marked with TLB_HAS_CODE. Disarms the
+/// monitors on the page and wakes up the cpus waiting in mwait, including the ones that are
+/// switched out. This is tracked per page, writes to other lines in the same page cause spurious
+/// wake-ups.
+pub fn monitor_write_hook(page: Page) -> bool {
+    let was_monitored = smp::monitor_write_hook_other_cpus(page);
+    if !is_monitored_by_current_cpu(page) {
+        return was_monitored;
+    }
+    unsafe {
+        monitor_address = None;
+        if in_mwait {
+            in_mwait = false;
+            *in_hlt = false;
+        }
+    }
+    true
+}
+
 #[no_mangle]
 pub unsafe fn update_eflags(new_flags: i32) {
     let mut dont_update: i32 = FLAG_RF | FLAG_VM | FLAG_VIP | FLAG_VIF;
@@ -4140,7 +4582,7 @@ pub unsafe fn trigger_np(code: i32) {
             return;
         }
     }
-    call_interrupt_vector(CPU_EXCEPTION_NP, false, Some(code));
+    call_interrupt_vector(CPU_EXCEPTION_NP, EventType::Hardware, Some(code));
 }
 
 #[inline(never)]
@@ -4152,7 +4594,7 @@ pub unsafe fn trigger_ss(code: i32) {
             return;
         }
     }
-    call_interrupt_vector(CPU_EXCEPTION_SS, false, Some(code));
+    call_interrupt_vector(CPU_EXCEPTION_SS, EventType::Hardware, Some(code));
 }
 
 #[no_mangle]
@@ -4161,7 +4603,9 @@ pub unsafe fn store_current_tsc() { *current_tsc = read_tsc(); }
 #[no_mangle]
 pub unsafe fn handle_irqs() {
     if *flags & FLAG_INTERRUPT != 0 {
-        if let Some(irq) = pic::pic_acknowledge_irq() {
+        // the pic is only connected to the bootstrap processor
+        let pic_irq = if smp::is_bootstrap_processor() { pic::pic_acknowledge_irq() } else { None };
+        if let Some(irq) = pic_irq {
             pic_call_irq(irq)
         }
         else if *acpi_enabled {
@@ -4176,7 +4620,8 @@ pub unsafe fn handle_irqs() {
 unsafe fn pic_call_irq(interrupt_nr: u8) {
     *previous_ip = *instruction_pointer; // XXX: What if called after instruction (port IO)
     *in_hlt = false;
-    call_interrupt_vector(interrupt_nr as i32, false, None);
+    in_mwait = false;
+    call_interrupt_vector(interrupt_nr as i32, EventType::Hardware, None);
 }
 
 #[no_mangle]
@@ -4228,9 +4673,12 @@ pub unsafe fn reset_cpu() {
     *fpu_opcode = 0;
     *fpu_dp = 0;
     *fpu_dp_selector = 0;
+    ::cpu::fpu::fpu_ferr_irq_raised = false;
 
     *mxcsr = 0x1F80;
+    ::cpu::sse_instr::sse_exception_pending = false;
 
+    a20_enabled = true;
     full_clear_tlb();
 
     *protected_mode = false;
@@ -4260,6 +4708,8 @@ pub unsafe fn reset_cpu() {
     *instruction_counter = 0;
     *previous_ip = 0;
     *in_hlt = false;
+    in_mwait = false;
+    monitor_address = None;
 
     *sysenter_cs = 0;
     *sysenter_esp = 0;
@@ -1,5 +1,8 @@
+#![allow(non_upper_case_globals)]
+
 use cpu::cpu::*;
 use cpu::global_pointers::*;
+use cpu::pic;
 use paging::OrPageFault;
 use softfloat::{Precision, RoundingMode, F80};
 use std::f64;
@@ -11,15 +14,19 @@ const FPU_C3: u16 = 0x4000;
 const FPU_RESULT_FLAGS: u16 = FPU_C0 | FPU_C1 | FPU_C2 | FPU_C3;
 
 const FPU_EX_I: u16 = 1 << 0; // invalid operation
-#[allow(dead_code)]
 const FPU_EX_D: u16 = 1 << 1; // denormal operand
 const FPU_EX_Z: u16 = 1 << 2; // zero divide
-#[allow(dead_code)]
 const FPU_EX_O: u16 = 1 << 3; // overflow
 const FPU_EX_U: u16 = 1 << 4; // underflow
-#[allow(dead_code)]
 const FPU_EX_P: u16 = 1 << 5; // precision
 const FPU_EX_SF: u16 = 1 << 6;
+pub const FPU_EX_ALL: u16 = FPU_EX_I | FPU_EX_D | FPU_EX_Z | FPU_EX_O | FPU_EX_U | FPU_EX_P;
+
+const FPU_ES: u16 = 1 << 7; // error summary
+const FPU_B: u16 = 1 << 15; // busy, same as FPU_ES
+
+// FERR# has been signalled on irq 13 and not yet acknowledged through port 0xF0
+pub static mut fpu_ferr_irq_raised: bool = false;
 
 pub fn fpu_write_st(index: i32, value: F80) {
     dbg_assert!(index >= 0 && index < 8);
@@ -54,6 +61,66 @@ pub unsafe fn fpu_underflow_fault() {
     *fpu_status_word |= FPU_EX_U;
 }
 
+pub unsafe fn fpu_check_denormal(x: F80) {
+    if x.is_denormal() {
+        *fpu_status_word |= FPU_EX_D;
+    }
+}
+
+/// Whether an exception flag is set whose mask bit is clear
+pub unsafe fn fpu_exception_pending() -> bool {
+    *fpu_status_word & !*fpu_control_word & FPU_EX_ALL != 0
+}
+
+/// Called before waiting x87 instructions (all except fninit, fnclex, fnstcw, fnstsw, fnstenv and
+/// fnsave) and fwait. Unmasked exceptions are reported here, not by the instruction that caused
+/// them. Returns false if #MF was raised
+pub unsafe fn fpu_exception_test() -> bool {
+    if !fpu_exception_pending() {
+        return true;
+    }
+    if *cr & CR0_NE != 0 {
+        trigger_mf();
+        false
+    }
+    else {
+        fpu_raise_ferr();
+        true
+    }
+}
+
+#[no_mangle]
+pub unsafe fn fpu_exception_test_jit(start_eip: i32) -> bool {
+    dbg_assert!(fpu_exception_pending());
+    if *cr & CR0_NE != 0 {
+        trigger_mf_jit(start_eip);
+        true
+    }
+    else {
+        fpu_raise_ferr();
+        false
+    }
+}
+
+unsafe fn fpu_raise_ferr() {
+    // With cr0.ne=0, FERR# is routed to irq 13 by the chipset. The instruction continues as if
+    // IGNNE# was asserted, the handler acknowledges the irq by writing to port 0xF0
+    if !fpu_ferr_irq_raised {
+        fpu_ferr_irq_raised = true;
+        pic::pic_set_irq(13);
+    }
+}
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub unsafe fn portF0_write(_v: u8) {
+    // clear FERR#
+    if fpu_ferr_irq_raised {
+        fpu_ferr_irq_raised = false;
+        pic::pic_clear_irq(13);
+    }
+}
+
 pub unsafe fn fpu_sti_empty(mut i: i32) -> bool {
     dbg_assert!(i >= 0 && i < 8);
     i = i + *fpu_stack_ptr as i32 & 7;
@@ -84,6 +151,33 @@ pub unsafe fn fpu_get_sti_f64(mut i: i32) -> f64 {
     std::mem::transmute((*fpu_st.offset(i as isize)).to_f64())
 }
 
+// Used by the jit for x87 registers that it keeps in f64 locals, see CachedStateFlags::fpu_f64.
+// Exception flags other than stack faults are not updated, as all exceptions are masked
+#[no_mangle]
+pub fn fpu_get_sti_f64_jit(i: i32) -> f64 { f64::from_bits(unsafe { fpu_get_sti(i) }.to_f64()) }
+#[no_mangle]
+pub fn fpu_set_sti_f64_jit(i: i32, v: u64) {
+    unsafe { fpu_write_st((*fpu_stack_ptr as i32 + i) & 7, F80::of_f64(v)) }
+}
+/// The tag and stack pointer part of fpu_push. Returns false on stack overflow, in which case the
+/// pushed value is the indefinite nan
+#[no_mangle]
+pub fn fpu_push_f64_jit() -> bool {
+    unsafe {
+        *fpu_stack_ptr = (*fpu_stack_ptr - 1) & 7;
+        if 0 != *fpu_stack_empty >> *fpu_stack_ptr & 1 {
+            *fpu_status_word &= !FPU_C1;
+            *fpu_stack_empty &= !(1 << *fpu_stack_ptr);
+            true
+        }
+        else {
+            *fpu_status_word |= FPU_C1;
+            fpu_stack_fault();
+            false
+        }
+    }
+}
+
 #[no_mangle]
 pub unsafe fn f32_to_f80_jit(dst: *mut F80, v: i32) {
     *dst = f32_to_f80(v)
@@ -141,13 +235,21 @@ pub unsafe fn fpu_load_i64(addr: i32) -> OrPageFault<F80> {
 
 pub unsafe fn fpu_load_m32(addr: i32) -> OrPageFault<F80> {
     F80::clear_exception_flags();
-    let v = F80::of_f32(safe_read32s(addr)?);
+    let bits = safe_read32s(addr)?;
+    if bits & 0x7F80_0000 == 0 && bits & 0x7F_FFFF != 0 {
+        *fpu_status_word |= FPU_EX_D;
+    }
+    let v = F80::of_f32(bits);
     *fpu_status_word |= F80::get_exception_flags() as u16;
     Ok(v)
 }
 pub unsafe fn fpu_load_m64(addr: i32) -> OrPageFault<F80> {
     F80::clear_exception_flags();
-    let v = F80::of_f64(safe_read64s(addr)?);
+    let bits = safe_read64s(addr)?;
+    if bits & 0x7FF0_0000_0000_0000 == 0 && bits & 0xF_FFFF_FFFF_FFFF != 0 {
+        *fpu_status_word |= FPU_EX_D;
+    }
+    let v = F80::of_f64(bits);
     *fpu_status_word |= F80::get_exception_flags() as u16;
     Ok(v)
 }
@@ -164,12 +266,20 @@ pub unsafe fn fpu_load_m80(addr: i32) -> OrPageFault<F80> {
 #[no_mangle]
 pub unsafe fn fpu_load_status_word() -> u16 {
     dbg_assert!(*fpu_stack_ptr < 8);
-    return *fpu_status_word & !(7 << 11) | (*fpu_stack_ptr as u16) << 11;
+    let status_word = *fpu_status_word & !(7 << 11 | FPU_ES | FPU_B) | (*fpu_stack_ptr as u16) << 11;
+    if fpu_exception_pending() {
+        status_word | FPU_ES | FPU_B
+    }
+    else {
+        status_word
+    }
 }
 #[no_mangle]
 pub unsafe fn fpu_fadd(target_index: i32, val: F80) {
     F80::clear_exception_flags();
     let st0 = fpu_get_st0();
+    fpu_check_denormal(st0);
+    fpu_check_denormal(val);
     fpu_write_st(*fpu_stack_ptr as i32 + target_index & 7, st0 + val);
     *fpu_status_word |= F80::get_exception_flags() as u16;
 }
@@ -193,6 +303,8 @@ pub unsafe fn fpu_fcmovcc(condition: bool, r: i32) {
 pub unsafe fn fpu_fcom(y: F80) {
     F80::clear_exception_flags();
     let x = fpu_get_st0();
+    fpu_check_denormal(x);
+    fpu_check_denormal(y);
     *fpu_status_word &= !FPU_RESULT_FLAGS;
     match x.partial_cmp(&y) {
         Some(std::cmp::Ordering::Greater) => {},
@@ -208,6 +320,8 @@ pub unsafe fn fpu_fcomi(r: i32) {
     F80::clear_exception_flags();
     let x = fpu_get_st0();
     let y = fpu_get_sti(r);
+    fpu_check_denormal(x);
+    fpu_check_denormal(y);
     *flags_changed = 0;
     *flags &= !FLAGS_ALL;
     match x.partial_cmp(&y) {
@@ -242,6 +356,8 @@ pub unsafe fn fpu_fcomp(val: F80) {
 pub unsafe fn fpu_fdiv(target_index: i32, val: F80) {
     F80::clear_exception_flags();
     let st0 = fpu_get_st0();
+    fpu_check_denormal(st0);
+    fpu_check_denormal(val);
     fpu_write_st(*fpu_stack_ptr as i32 + target_index & 7, st0 / val);
     *fpu_status_word |= F80::get_exception_flags() as u16;
 }
@@ -249,6 +365,8 @@ pub unsafe fn fpu_fdiv(target_index: i32, val: F80) {
 pub unsafe fn fpu_fdivr(target_index: i32, val: F80) {
     F80::clear_exception_flags();
     let st0 = fpu_get_st0();
+    fpu_check_denormal(st0);
+    fpu_check_denormal(val);
     fpu_write_st(*fpu_stack_ptr as i32 + target_index & 7, val / st0);
     *fpu_status_word |= F80::get_exception_flags() as u16;
 }
@@ -310,6 +428,8 @@ pub unsafe fn set_control_word(cw: u16) {
             Precision::P80
         },
     });
+
+    update_state_flags();
 }
 
 pub unsafe fn fpu_invalid_arithmetic() { *fpu_status_word |= FPU_EX_I; }
@@ -418,10 +538,37 @@ pub unsafe fn fpu_fldcw(addr: i32) {
     set_control_word(word);
 }
 
+/// The 16-bit environment stores 20-bit linear instruction and operand pointers in real and vm86
+/// mode, and offsets with selectors in protected mode
+unsafe fn fpu_env16_real_mode() -> bool { !*protected_mode || vm86_mode() }
+
 #[no_mangle]
-pub unsafe fn fpu_fldenv16(_addr: i32) {
-    dbg_log!("fldenv16");
-    fpu_unimpl();
+pub unsafe fn fpu_fldenv16(addr: i32) {
+    if let Err(()) = readable_or_pagefault(addr, 14) {
+        *page_fault = true;
+        return;
+    }
+    *page_fault = false;
+    set_control_word(safe_read16(addr).unwrap() as u16);
+    fpu_set_status_word(safe_read16(addr + 2).unwrap() as u16);
+    fpu_set_tag_word(safe_read16(addr + 4).unwrap());
+    let ip = safe_read16(addr + 6).unwrap();
+    let ip_high = safe_read16(addr + 8).unwrap();
+    let dp = safe_read16(addr + 10).unwrap();
+    let dp_high = safe_read16(addr + 12).unwrap();
+    if fpu_env16_real_mode() {
+        *fpu_ip = ip | (ip_high & 0xF000) << 4;
+        *fpu_ip_selector = 0;
+        *fpu_opcode = ip_high & 0x7FF;
+        *fpu_dp = dp | (dp_high & 0xF000) << 4;
+        *fpu_dp_selector = 0;
+    }
+    else {
+        *fpu_ip = ip;
+        *fpu_ip_selector = ip_high;
+        *fpu_dp = dp;
+        *fpu_dp_selector = dp_high;
+    }
 }
 #[no_mangle]
 pub unsafe fn fpu_fldenv32(addr: i32) {
@@ -472,8 +619,12 @@ pub unsafe fn fpu_fldm80(addr: i32) {
 
 #[no_mangle]
 pub unsafe fn fpu_fmul(target_index: i32, val: F80) {
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
+    fpu_check_denormal(st0);
+    fpu_check_denormal(val);
     fpu_write_st(*fpu_stack_ptr as i32 + target_index & 7, st0 * val);
+    *fpu_status_word |= F80::get_exception_flags() as u16;
 }
 pub unsafe fn fpu_fnstsw_mem(addr: i32) {
     return_on_pagefault!(safe_write16(addr, fpu_load_status_word().into()));
@@ -529,9 +680,15 @@ pub unsafe fn fpu_fprem(ieee: bool) {
     }
 }
 
-pub unsafe fn fpu_frstor16(_addr: i32) {
-    dbg_log!("frstor16");
-    fpu_unimpl();
+pub unsafe fn fpu_frstor16(mut addr: i32) {
+    return_on_pagefault!(readable_or_pagefault(addr, 14 + 8 * 10));
+    fpu_fldenv16(addr);
+    addr += 14;
+    for i in 0..8 {
+        let reg_index = *fpu_stack_ptr as i32 + i & 7;
+        *fpu_st.offset(reg_index as isize) = fpu_load_m80(addr).unwrap();
+        addr += 10;
+    }
 }
 pub unsafe fn fpu_frstor32(mut addr: i32) {
     return_on_pagefault!(readable_or_pagefault(addr, 28 + 8 * 10));
@@ -544,9 +701,16 @@ pub unsafe fn fpu_frstor32(mut addr: i32) {
     }
 }
 
-pub unsafe fn fpu_fsave16(_addr: i32) {
-    dbg_log!("fsave16");
-    fpu_unimpl();
+pub unsafe fn fpu_fsave16(mut addr: i32) {
+    return_on_pagefault!(writable_or_pagefault(addr, 94));
+    fpu_fstenv16(addr);
+    addr += 14;
+    for i in 0..8 {
+        let reg_index = i + *fpu_stack_ptr as i32 & 7;
+        fpu_store_m80(addr, *fpu_st.offset(reg_index as isize));
+        addr += 10;
+    }
+    fpu_finit();
 }
 pub unsafe fn fpu_fsave32(mut addr: i32) {
     return_on_pagefault!(writable_or_pagefault(addr, 108));
@@ -567,9 +731,31 @@ pub unsafe fn fpu_store_m80(addr: i32, f: F80) {
 }
 
 #[no_mangle]
-pub unsafe fn fpu_fstenv16(_addr: i32) {
-    dbg_log!("fstenv16");
-    fpu_unimpl();
+pub unsafe fn fpu_fstenv16(addr: i32) {
+    match writable_or_pagefault(addr, 14) {
+        Ok(()) => *page_fault = false,
+        Err(()) => {
+            *page_fault = true;
+            return;
+        },
+    }
+    safe_write16(addr + 0, *fpu_control_word as i32).unwrap();
+    safe_write16(addr + 2, fpu_load_status_word() as i32).unwrap();
+    safe_write16(addr + 4, fpu_load_tag_word()).unwrap();
+    if fpu_env16_real_mode() {
+        safe_write16(addr + 6, *fpu_ip & 0xFFFF).unwrap();
+        safe_write16(addr + 8, *fpu_ip >> 4 & 0xF000 | *fpu_opcode & 0x7FF).unwrap();
+        safe_write16(addr + 10, *fpu_dp & 0xFFFF).unwrap();
+        safe_write16(addr + 12, *fpu_dp >> 4 & 0xF000).unwrap();
+    }
+    else {
+        safe_write16(addr + 6, *fpu_ip & 0xFFFF).unwrap();
+        safe_write16(addr + 8, *fpu_ip_selector).unwrap();
+        safe_write16(addr + 10, *fpu_dp & 0xFFFF).unwrap();
+        safe_write16(addr + 12, *fpu_dp_selector).unwrap();
+    }
+    // fstenv masks all exceptions after saving, so that exception handlers don't fault again
+    set_control_word(*fpu_control_word | FPU_EX_ALL);
 }
 
 #[no_mangle]
@@ -590,6 +776,8 @@ pub unsafe fn fpu_fstenv32(addr: i32) {
     safe_write16(addr + 18, *fpu_opcode).unwrap();
     safe_write32(addr + 20, *fpu_dp).unwrap();
     safe_write32(addr + 24, high_bits | *fpu_dp_selector).unwrap();
+    // fstenv masks all exceptions after saving, so that exception handlers don't fault again
+    set_control_word(*fpu_control_word | FPU_EX_ALL);
 }
 #[no_mangle]
 pub unsafe fn fpu_load_tag_word() -> i32 {
@@ -679,13 +867,21 @@ pub unsafe fn fpu_fbstp(addr: i32) {
 
 #[no_mangle]
 pub unsafe fn fpu_fsub(target_index: i32, val: F80) {
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
-    fpu_write_st(*fpu_stack_ptr as i32 + target_index & 7, st0 - val)
+    fpu_check_denormal(st0);
+    fpu_check_denormal(val);
+    fpu_write_st(*fpu_stack_ptr as i32 + target_index & 7, st0 - val);
+    *fpu_status_word |= F80::get_exception_flags() as u16;
 }
 #[no_mangle]
 pub unsafe fn fpu_fsubr(target_index: i32, val: F80) {
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
-    fpu_write_st(*fpu_stack_ptr as i32 + target_index & 7, val - st0)
+    fpu_check_denormal(st0);
+    fpu_check_denormal(val);
+    fpu_write_st(*fpu_stack_ptr as i32 + target_index & 7, val - st0);
+    *fpu_status_word |= F80::get_exception_flags() as u16;
 }
 
 pub unsafe fn fpu_ftst() {
@@ -708,6 +904,8 @@ pub unsafe fn fpu_fucom(r: i32) {
     F80::clear_exception_flags();
     let x = fpu_get_st0();
     let y = fpu_get_sti(r);
+    fpu_check_denormal(x);
+    fpu_check_denormal(y);
     *fpu_status_word &= !FPU_RESULT_FLAGS;
     match x.partial_cmp_quiet(&y) {
         Some(std::cmp::Ordering::Greater) => {},
@@ -723,6 +921,8 @@ pub unsafe fn fpu_fucomi(r: i32) {
     F80::clear_exception_flags();
     let x = fpu_get_st0();
     let y = fpu_get_sti(r);
+    fpu_check_denormal(x);
+    fpu_check_denormal(y);
     *flags_changed = 0;
     *flags &= !FLAGS_ALL;
     match x.partial_cmp_quiet(&y) {
@@ -783,6 +983,7 @@ pub unsafe fn fpu_fxch(i: i32) {
     fpu_write_st(*fpu_stack_ptr as i32, sti);
 }
 pub unsafe fn fpu_fyl2x() {
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
     if st0 < F80::ZERO {
         fpu_invalid_arithmetic();
@@ -792,8 +993,9 @@ pub unsafe fn fpu_fyl2x() {
     }
     fpu_write_st(
         *fpu_stack_ptr as i32 + 1 & 7,
-        fpu_get_sti(1) * st0.ln() / F80::LN_2,
+        st0.mul_log2(fpu_get_sti(1)),
     );
+    *fpu_status_word |= F80::get_exception_flags() as u16;
     fpu_pop();
 }
 
@@ -815,7 +1017,9 @@ pub unsafe fn fpu_fxtract() {
 }
 
 pub unsafe fn fwait() {
-    // NOP unless FPU instructions run in parallel with CPU instructions
+    // Other than waiting for the FPU (which is not needed, as FPU instructions don't run in
+    // parallel with CPU instructions), only reports pending exceptions
+    fpu_exception_test();
 }
 
 pub unsafe fn fpu_fchs() {
@@ -829,58 +1033,85 @@ pub unsafe fn fpu_fabs() {
 }
 
 pub unsafe fn fpu_f2xm1() {
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
-    let r = st0.two_pow() - F80::ONE;
-    fpu_write_st(*fpu_stack_ptr as i32, r)
+    fpu_check_denormal(st0);
+    fpu_write_st(*fpu_stack_ptr as i32, st0.two_pow_minus_one());
+    *fpu_status_word |= F80::get_exception_flags() as u16;
+}
+
+/// fsin, fcos, fsincos and fptan only accept |x| < 2**63. Larger operands are left unchanged and
+/// C2 is set, so that software can reduce them with fprem1 and try again
+unsafe fn fpu_trig_operand_out_of_range(x: F80) -> bool {
+    *fpu_status_word &= !(FPU_C1 | FPU_C2);
+    fpu_check_denormal(x);
+    if x.is_nan() {
+        return false;
+    }
+    if !x.is_finite() {
+        fpu_invalid_arithmetic();
+        return false;
+    }
+    if x.exponent() >= 63 {
+        *fpu_status_word |= FPU_C2;
+        return true;
+    }
+    false
 }
 
 pub unsafe fn fpu_fptan() {
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
-    //if -pow(2.0, 63.0) < st0 && st0 < pow(2.0, 63.0) {
+    if fpu_trig_operand_out_of_range(st0) {
+        return;
+    }
     fpu_write_st(*fpu_stack_ptr as i32, st0.tan());
     // no bug: push constant 1
     fpu_push(F80::ONE);
-    *fpu_status_word &= !FPU_C2;
-    //}
-    //else {
-    //    *fpu_status_word |= FPU_C2;
-    //}
+    *fpu_status_word |= F80::get_exception_flags() as u16;
 }
 
 pub unsafe fn fpu_fpatan() {
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
     let st1 = fpu_get_sti(1);
+    fpu_check_denormal(st0);
+    fpu_check_denormal(st1);
     fpu_write_st(*fpu_stack_ptr as i32 + 1 & 7, st1.atan2(st0));
+    *fpu_status_word |= F80::get_exception_flags() as u16;
     fpu_pop();
 }
 
 pub unsafe fn fpu_fyl2xp1() {
     // fyl2xp1: y * log2(x+1) and pop
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
     let st1 = fpu_get_sti(1);
-    let y = st1 * (st0 + F80::ONE).ln() / F80::LN_2;
+    fpu_check_denormal(st0);
+    fpu_check_denormal(st1);
+    let y = st0.mul_log2_1p(st1);
     fpu_write_st(*fpu_stack_ptr as i32 + 1 & 7, y);
+    *fpu_status_word |= F80::get_exception_flags() as u16;
     fpu_pop();
 }
 
 pub unsafe fn fpu_fsqrt() {
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
-    //if st0 < 0.0 {
-    //    fpu_invalid_arithmetic();
-    //}
-    fpu_write_st(*fpu_stack_ptr as i32, st0.sqrt())
+    fpu_check_denormal(st0);
+    fpu_write_st(*fpu_stack_ptr as i32, st0.sqrt());
+    *fpu_status_word |= F80::get_exception_flags() as u16;
 }
 
 pub unsafe fn fpu_fsincos() {
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
-    //if pow(-2.0, 63.0) < st0 && st0 < pow(2.0, 63.0) {
+    if fpu_trig_operand_out_of_range(st0) {
+        return;
+    }
     fpu_write_st(*fpu_stack_ptr as i32, st0.sin());
     fpu_push(st0.cos());
-    *fpu_status_word &= !FPU_C2;
-    //}
-    //else {
-    //    *fpu_status_word |= FPU_C2;
-    //}
+    *fpu_status_word |= F80::get_exception_flags() as u16;
 }
 
 pub unsafe fn fpu_frndint() {
@@ -895,25 +1126,23 @@ pub unsafe fn fpu_fscale() {
 }
 
 pub unsafe fn fpu_fsin() {
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
-    //if pow(-2.0, 63.0) < st0 && st0 < pow(2.0, 63.0) {
+    if fpu_trig_operand_out_of_range(st0) {
+        return;
+    }
     fpu_write_st(*fpu_stack_ptr as i32, st0.sin());
-    *fpu_status_word &= !FPU_C2;
-    //}
-    //else {
-    //    *fpu_status_word |= FPU_C2;
-    //}
+    *fpu_status_word |= F80::get_exception_flags() as u16;
 }
 
 pub unsafe fn fpu_fcos() {
+    F80::clear_exception_flags();
     let st0 = fpu_get_st0();
-    //if pow(-2.0, 63.0) < st0 && st0 < pow(2.0, 63.0) {
+    if fpu_trig_operand_out_of_range(st0) {
+        return;
+    }
     fpu_write_st(*fpu_stack_ptr as i32, st0.cos());
-    *fpu_status_word &= !FPU_C2;
-    //}
-    //else {
-    //    *fpu_status_word |= FPU_C2;
-    //}
+    *fpu_status_word |= F80::get_exception_flags() as u16;
 }
 
 pub unsafe fn fpu_fdecstp() {
@@ -8,6 +8,7 @@ use cpu::misc_instr::*;
 use cpu::misc_instr::{pop16, pop32s, push16, push32};
 use cpu::string::*;
 use prefix;
+use profiler;
 use softfloat::F80;
 
 pub unsafe fn instr_00_mem(addr: i32, r: i32) { safe_read_write8(addr, &|x| add8(x, read_reg8(r))) }
@@ -1029,6 +1030,10 @@ pub unsafe fn instr16_9C() {
     // pushf
     if instr_pushf_popf_check() {
         dbg_assert!(*protected_mode);
+        if *cr.offset(4) & CR4_VME != 0 {
+            return_on_pagefault!(push16(get_eflags_vme()));
+            return;
+        }
         dbg_log!("pushf #gp");
         trigger_gp(0);
     }
@@ -1053,6 +1058,13 @@ pub unsafe fn instr32_9C() {
 pub unsafe fn instr16_9D() {
     // popf
     if instr_pushf_popf_check() {
+        if *cr.offset(4) & CR4_VME != 0 {
+            let new_flags = return_on_pagefault!(safe_read16(get_stack_pointer(0)));
+            if vme_update_flags16(new_flags) {
+                adjust_stack_reg(2);
+                return;
+            }
+        }
         dbg_log!("popf #gp");
         trigger_gp(0);
         return;
@@ -1451,12 +1463,17 @@ pub unsafe fn instr_CC() {
     // INT3
     // TODO: inhibit iopl checks
     dbg_log!("INT3");
-    call_interrupt_vector(3, true, None);
+    call_interrupt_vector(3, EventType::SoftwareInterrupt, None);
 }
 #[no_mangle]
 pub unsafe fn instr_CD(imm8: i32) {
     // INT
-    call_interrupt_vector(imm8, true, None);
+    if vm86_mode() && *cr.offset(4) & CR4_VME != 0 {
+        if return_on_pagefault!(vme_redirect_interrupt(imm8)) {
+            return;
+        }
+    }
+    call_interrupt_vector(imm8, EventType::SoftwareInterrupt, None);
 }
 #[no_mangle]
 pub unsafe fn instr_CE() {
@@ -1464,7 +1481,7 @@ pub unsafe fn instr_CE() {
     dbg_log!("INTO");
     if getof() {
         // TODO: inhibit iopl checks
-        call_interrupt_vector(CPU_EXCEPTION_OF, true, None);
+        call_interrupt_vector(CPU_EXCEPTION_OF, EventType::SoftwareInterrupt, None);
     };
 }
 #[no_mangle]
@@ -2147,20 +2164,21 @@ pub unsafe fn instr32_EF() {
 
 pub unsafe fn instr_F0() {
     // lock
-    if false {
-        dbg_log!("lock");
-    }
-    // TODO
-    // This triggers UD when used with
-    // some instructions that don't write to memory
+    // Instructions that can't be locked check for the prefix and raise #ud. All memory accesses
+    // of a single instruction are already atomic, as only one cpu is emulated
+    profiler::stat_increment(profiler::stat::LOCKED_OPERATION);
+    *prefixes |= prefix::PREFIX_LOCK;
     run_prefix_instruction();
+    *prefixes = 0;
 }
 
 #[no_mangle]
 pub unsafe fn instr_F1() {
     // INT1
     // https://code.google.com/p/corkami/wiki/x86oddities#IceBP
-    dbg_assert!(false);
+    // Unlike int 1, this ignores the gate's dpl and iopl, and doesn't set any status bits in dr6
+    dbg_log!("INT1");
+    call_interrupt_vector(CPU_EXCEPTION_DB, EventType::PrivilegedSoftwareException, None);
 }
 
 pub unsafe fn instr_F2() {
@@ -2312,8 +2330,7 @@ pub unsafe fn instr_FA_without_fault() -> bool {
         *flags &= !FLAG_INTERRUPT;
         return true;
     }
-    else if false
-        && getiopl() < 3
+    else if getiopl() < 3
         && if 0 != *flags & FLAG_VM {
             0 != *cr.offset(4) & CR4_VME
         }
@@ -2344,8 +2361,7 @@ pub unsafe fn instr_FB_without_fault() -> bool {
         *flags |= FLAG_INTERRUPT;
         return true;
     }
-    else if false
-        && getiopl() < 3
+    else if getiopl() < 3
         && *flags & FLAG_VIP == 0
         && if 0 != *flags & FLAG_VM {
             0 != *cr.offset(4) & CR4_VME
@@ -33,6 +33,17 @@ use cpu::misc_instr::{
 use cpu::misc_instr::{lar, lsl, verr, verw};
 use cpu::misc_instr::{lss16, lss32};
 use cpu::sse_instr::*;
+use prefix;
+
+/// sgdt, sidt, sldt, smsw and str raise #gp outside of ring 0 when cr4.umip is set
+unsafe fn umip_check() -> bool {
+    if 0 != *cpl && 0 != *cr.offset(4) & CR4_UMIP {
+        dbg_log!("#gp umip");
+        trigger_gp(0);
+        return false;
+    }
+    true
+}
 
 #[no_mangle]
 pub unsafe fn instr16_0F00_0_mem(addr: i32) {
@@ -41,6 +52,9 @@ pub unsafe fn instr16_0F00_0_mem(addr: i32) {
         trigger_ud();
         return;
     }
+    if !umip_check() {
+        return;
+    }
     return_on_pagefault!(safe_write16(addr, *sreg.offset(LDTR as isize) as i32));
 }
 #[no_mangle]
@@ -51,6 +65,9 @@ pub unsafe fn instr16_0F00_0_reg(r: i32) {
         trigger_ud();
         return;
     }
+    if !umip_check() {
+        return;
+    }
     write_reg16(r, *sreg.offset(LDTR as isize) as i32);
 }
 #[no_mangle]
@@ -59,6 +76,9 @@ pub unsafe fn instr32_0F00_0_reg(r: i32) {
         trigger_ud();
         return;
     }
+    if !umip_check() {
+        return;
+    }
     write_reg32(r, *sreg.offset(LDTR as isize) as i32);
 }
 
@@ -69,6 +89,9 @@ pub unsafe fn instr16_0F00_1_mem(addr: i32) {
         trigger_ud();
         return;
     }
+    if !umip_check() {
+        return;
+    }
     return_on_pagefault!(safe_write16(addr, *sreg.offset(TR as isize) as i32));
 }
 #[no_mangle]
@@ -79,6 +102,9 @@ pub unsafe fn instr16_0F00_1_reg(r: i32) {
         trigger_ud();
         return;
     }
+    if !umip_check() {
+        return;
+    }
     write_reg16(r, *sreg.offset(TR as isize) as i32);
 }
 #[no_mangle]
@@ -87,6 +113,9 @@ pub unsafe fn instr32_0F00_1_reg(r: i32) {
         trigger_ud();
         return;
     }
+    if !umip_check() {
+        return;
+    }
     write_reg32(r, *sreg.offset(TR as isize) as i32);
 }
 
@@ -201,6 +230,9 @@ pub unsafe fn instr16_0F01_0_reg(_r: i32) { trigger_ud(); }
 pub unsafe fn instr32_0F01_0_reg(_r: i32) { trigger_ud(); }
 
 unsafe fn sgdt(addr: i32, mask: i32) {
+    if !umip_check() {
+        return;
+    }
     return_on_pagefault!(writable_or_pagefault(addr, 6));
     safe_write16(addr, *gdtr_size).unwrap();
     safe_write32(addr + 2, *gdtr_offset & mask).unwrap();
@@ -211,11 +243,24 @@ pub unsafe fn instr16_0F01_0_mem(addr: i32) { sgdt(addr, 0xFFFFFF) }
 pub unsafe fn instr32_0F01_0_mem(addr: i32) { sgdt(addr, -1) }
 
 #[no_mangle]
-pub unsafe fn instr16_0F01_1_reg(_r: i32) { trigger_ud(); }
+pub unsafe fn instr16_0F01_1_reg(r: i32) {
+    match r {
+        0 => {
+            // monitor (the jit resolves the address itself, see instr16_0F01_1_reg_jit)
+            let addr = if is_asize_32() { read_reg32(EAX) } else { read_reg16(AX) };
+            monitor(return_on_pagefault!(get_seg_prefix_ds(addr)));
+        },
+        1 => mwait(),
+        _ => trigger_ud(),
+    }
+}
 #[no_mangle]
-pub unsafe fn instr32_0F01_1_reg(_r: i32) { trigger_ud(); }
+pub unsafe fn instr32_0F01_1_reg(r: i32) { instr16_0F01_1_reg(r) }
 
 unsafe fn sidt(addr: i32, mask: i32) {
+    if !umip_check() {
+        return;
+    }
     return_on_pagefault!(writable_or_pagefault(addr, 6));
     safe_write16(addr, *idtr_size).unwrap();
     safe_write32(addr + 2, *idtr_offset & mask).unwrap();
@@ -268,18 +313,27 @@ pub unsafe fn instr32_0F01_3_mem(addr: i32) { lidt(addr, -1); }
 #[no_mangle]
 pub unsafe fn instr16_0F01_4_reg(r: i32) {
     // smsw
+    if !umip_check() {
+        return;
+    }
     write_reg16(r, *cr);
 }
 #[no_mangle]
-pub unsafe fn instr32_0F01_4_reg(r: i32) { write_reg32(r, *cr); }
+pub unsafe fn instr32_0F01_4_reg(r: i32) {
+    if !umip_check() {
+        return;
+    }
+    write_reg32(r, *cr);
+}
 #[no_mangle]
 pub unsafe fn instr16_0F01_4_mem(addr: i32) {
+    if !umip_check() {
+        return;
+    }
     return_on_pagefault!(safe_write16(addr, *cr));
 }
 #[no_mangle]
-pub unsafe fn instr32_0F01_4_mem(addr: i32) {
-    return_on_pagefault!(safe_write16(addr, *cr));
-}
+pub unsafe fn instr32_0F01_4_mem(addr: i32) { instr16_0F01_4_mem(addr) }
 
 #[no_mangle]
 pub unsafe fn lmsw(mut new_cr0: i32) {
@@ -579,7 +633,7 @@ pub unsafe fn instr_F30F12(source: reg128, r: i32) {
 }
 pub unsafe fn instr_F30F12_reg(r1: i32, r2: i32) { instr_F30F12(read_xmm128s(r1), r2); }
 pub unsafe fn instr_F30F12_mem(addr: i32, r: i32) {
-    instr_F30F12(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_F30F12(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 pub unsafe fn instr_0F13_mem(addr: i32, r: i32) {
     // movlps m64, xmm
@@ -595,7 +649,6 @@ pub unsafe fn instr_660F13_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_0F14(source: u64, r: i32) {
     // unpcklps xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm64s(r);
     write_xmm128(
         r,
@@ -607,12 +660,12 @@ pub unsafe fn instr_0F14(source: u64, r: i32) {
 }
 pub unsafe fn instr_0F14_reg(r1: i32, r2: i32) { instr_0F14(read_xmm64s(r1), r2); }
 pub unsafe fn instr_0F14_mem(addr: i32, r: i32) {
-    instr_0F14(return_on_pagefault!(safe_read64s(addr)), r);
+    // only the low quadword is used, but the operand is still an aligned m128
+    instr_0F14(return_on_pagefault!(safe_read128s_aligned(addr)).u64[0], r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F14(source: u64, r: i32) {
     // unpcklpd xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm64s(r);
     write_xmm128(
         r,
@@ -624,12 +677,12 @@ pub unsafe fn instr_660F14(source: u64, r: i32) {
 }
 pub unsafe fn instr_660F14_reg(r1: i32, r2: i32) { instr_660F14(read_xmm64s(r1), r2); }
 pub unsafe fn instr_660F14_mem(addr: i32, r: i32) {
-    instr_660F14(return_on_pagefault!(safe_read64s(addr)), r);
+    // only the low quadword is used, but the operand is still an aligned m128
+    instr_660F14(return_on_pagefault!(safe_read128s_aligned(addr)).u64[0], r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F15(source: reg128, r: i32) {
     // unpckhps xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     write_xmm128(
         r,
@@ -641,12 +694,11 @@ pub unsafe fn instr_0F15(source: reg128, r: i32) {
 }
 pub unsafe fn instr_0F15_reg(r1: i32, r2: i32) { instr_0F15(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F15_mem(addr: i32, r: i32) {
-    instr_0F15(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F15(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F15(source: reg128, r: i32) {
     // unpckhpd xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     write_xmm128(
         r,
@@ -658,7 +710,7 @@ pub unsafe fn instr_660F15(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F15_reg(r1: i32, r2: i32) { instr_660F15(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F15_mem(addr: i32, r: i32) {
-    instr_660F15(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F15(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 
 #[no_mangle]
@@ -688,7 +740,7 @@ pub unsafe fn instr_F30F16(source: reg128, r: i32) {
 }
 pub unsafe fn instr_F30F16_reg(r1: i32, r2: i32) { instr_F30F16(read_xmm128s(r1), r2); }
 pub unsafe fn instr_F30F16_mem(addr: i32, r: i32) {
-    instr_F30F16(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_F30F16(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 pub unsafe fn instr_0F17_mem(addr: i32, r: i32) {
     // movhps m64, xmm
@@ -803,8 +855,9 @@ pub unsafe fn instr_0F22(r: i32, creg: i32) {
         3 => set_cr3(data),
         4 => {
             dbg_log!("cr4 <- {:x}", data);
+            // pcide (bit 17) can only be set in long mode
             if 0 != data as u32
-                & ((1 << 11 | 1 << 12 | 1 << 15 | 1 << 16 | 1 << 19) as u32 | 0xFFC00000)
+                & ((1 << 12 | 1 << 15 | 1 << 16 | 1 << 17 | 1 << 19) as u32 | 0xFFC00000)
             {
                 dbg_log!("trigger_gp: Invalid cr4 bit");
                 trigger_gp(0);
@@ -866,28 +919,25 @@ pub unsafe fn instr_0F27() { undefined_instruction(); }
 
 pub unsafe fn instr_0F28(source: reg128, r: i32) {
     // movaps xmm, xmm/m128
-    // XXX: Aligned read or #gp
     mov_rm_r128(source, r);
 }
 pub unsafe fn instr_0F28_reg(r1: i32, r2: i32) { instr_0F28(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F28_mem(addr: i32, r: i32) {
-    instr_0F28(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F28(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 pub unsafe fn instr_660F28(source: reg128, r: i32) {
     // movapd xmm, xmm/m128
-    // XXX: Aligned read or #gp
     // Note: Same as movdqa (660F6F)
     mov_rm_r128(source, r);
 }
 pub unsafe fn instr_660F28_reg(r1: i32, r2: i32) { instr_660F28(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F28_mem(addr: i32, r: i32) {
-    instr_660F28(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F28(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 pub unsafe fn instr_0F29_mem(addr: i32, r: i32) {
     // movaps m128, xmm
     let data = read_xmm128s(r);
-    // XXX: Aligned write or #gp
-    return_on_pagefault!(safe_write128(addr, data));
+    return_on_pagefault!(safe_write128_aligned(addr, data));
 }
 pub unsafe fn instr_0F29_reg(r1: i32, r2: i32) {
     // movaps xmm, xmm
@@ -896,8 +946,7 @@ pub unsafe fn instr_0F29_reg(r1: i32, r2: i32) {
 pub unsafe fn instr_660F29_mem(addr: i32, r: i32) {
     // movapd m128, xmm
     let data = read_xmm128s(r);
-    // XXX: Aligned write or #gp
-    return_on_pagefault!(safe_write128(addr, data));
+    return_on_pagefault!(safe_write128_aligned(addr, data));
 }
 pub unsafe fn instr_660F29_reg(r1: i32, r2: i32) {
     // movapd xmm, xmm
@@ -907,10 +956,15 @@ pub unsafe fn instr_660F29_reg(r1: i32, r2: i32) {
 #[no_mangle]
 pub unsafe fn instr_0F2A(source: u64, r: i32) {
     // cvtpi2ps xmm, mm/m64
-    // Note: Casts here can fail
-    // XXX: Should round according to round control
     let source: [i32; 2] = std::mem::transmute(source);
-    let result = [source[0] as f32, source[1] as f32];
+    sse_fp_begin();
+    let result = [
+        sse_convert_i32_to_f32(source[0]),
+        sse_convert_i32_to_f32(source[1]),
+    ];
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm64(r, std::mem::transmute(result));
     transition_fpu_to_mmx();
 }
@@ -946,9 +1000,11 @@ pub unsafe fn instr_F20F2A_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_F30F2A(source: i32, r: i32) {
     // cvtsi2ss xmm, r/m32
-    // Note: This cast can fail
-    // XXX: Should round according to round control
-    let result = source as f32;
+    sse_fp_begin();
+    let result = sse_convert_i32_to_f32(source);
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_f32(r, result);
 }
 pub unsafe fn instr_F30F2A_reg(r1: i32, r2: i32) { instr_F30F2A(read_reg32(r1), r2); }
@@ -959,25 +1015,25 @@ pub unsafe fn instr_F30F2A_mem(addr: i32, r: i32) {
 pub unsafe fn instr_0F2B_reg(_r1: i32, _r2: i32) { trigger_ud(); }
 pub unsafe fn instr_0F2B_mem(addr: i32, r: i32) {
     // movntps m128, xmm
-    // XXX: Aligned write or #gp
-    mov_r_m128(addr, r);
+    mov_r_m128_aligned(addr, r);
 }
 pub unsafe fn instr_660F2B_reg(_r1: i32, _r2: i32) { trigger_ud(); }
 pub unsafe fn instr_660F2B_mem(addr: i32, r: i32) {
     // movntpd m128, xmm
-    // XXX: Aligned write or #gp
-    mov_r_m128(addr, r);
+    mov_r_m128_aligned(addr, r);
 }
 
 pub unsafe fn instr_0F2C(source: u64, r: i32) {
     // cvttps2pi mm, xmm/m64
     let low = f32::from_bits(source as u32);
     let high = f32::from_bits((source >> 32) as u32);
-    write_mmx_reg64(
-        r,
-        sse_convert_with_truncation_f32_to_i32(low) as u32 as u64
-            | (sse_convert_with_truncation_f32_to_i32(high) as u32 as u64) << 32,
-    );
+    sse_fp_begin();
+    let result = sse_convert_with_truncation_f32_to_i32(low) as u32 as u64
+        | (sse_convert_with_truncation_f32_to_i32(high) as u32 as u64) << 32;
+    if !sse_fp_end() {
+        return;
+    }
+    write_mmx_reg64(r, result);
     transition_fpu_to_mmx();
 }
 #[no_mangle]
@@ -989,24 +1045,29 @@ pub unsafe fn instr_0F2C_reg(r1: i32, r2: i32) { instr_0F2C(read_xmm64s(r1), r2)
 
 pub unsafe fn instr_660F2C(source: reg128, r: i32) {
     // cvttpd2pi mm, xmm/m128
-    write_mmx_reg64(
-        r,
-        sse_convert_with_truncation_f64_to_i32(source.f64[0]) as u32 as u64
-            | (sse_convert_with_truncation_f64_to_i32(source.f64[1]) as u32 as u64) << 32,
-    );
+    sse_fp_begin();
+    let result = sse_convert_with_truncation_f64_to_i32(source.f64[0]) as u32 as u64
+        | (sse_convert_with_truncation_f64_to_i32(source.f64[1]) as u32 as u64) << 32;
+    if !sse_fp_end() {
+        return;
+    }
+    write_mmx_reg64(r, result);
     transition_fpu_to_mmx();
 }
 #[no_mangle]
 pub unsafe fn instr_660F2C_mem(addr: i32, r: i32) {
-    instr_660F2C(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F2C(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F2C_reg(r1: i32, r2: i32) { instr_660F2C(read_xmm128s(r1), r2); }
 
 pub unsafe fn instr_F20F2C(source: u64, r: i32) {
     // cvttsd2si r32, xmm/m64
-    let source = f64::from_bits(source);
-    write_reg32(r, sse_convert_with_truncation_f64_to_i32(source));
+    let result = cvttsd2si(f64::from_bits(source));
+    if sse_exception_pending {
+        return;
+    }
+    write_reg32(r, result);
 }
 #[no_mangle]
 pub unsafe fn instr_F20F2C_reg(r1: i32, r2: i32) { instr_F20F2C(read_xmm64s(r1), r2); }
@@ -1017,7 +1078,11 @@ pub unsafe fn instr_F20F2C_mem(addr: i32, r: i32) {
 
 pub unsafe fn instr_F30F2C(source: f32, r: i32) {
     // cvttss2si
-    write_reg32(r, sse_convert_with_truncation_f32_to_i32(source));
+    let result = cvttss2si(source);
+    if sse_exception_pending {
+        return;
+    }
+    write_reg32(r, result);
 }
 #[no_mangle]
 pub unsafe fn instr_F30F2C_mem(addr: i32, r: i32) {
@@ -1029,10 +1094,14 @@ pub unsafe fn instr_F30F2C_reg(r1: i32, r2: i32) { instr_F30F2C(read_xmm_f32(r1)
 pub unsafe fn instr_0F2D(source: u64, r: i32) {
     // cvtps2pi mm, xmm/m64
     let source: [f32; 2] = std::mem::transmute(source);
+    sse_fp_begin();
     let result = [
         sse_convert_f32_to_i32(source[0]),
         sse_convert_f32_to_i32(source[1]),
     ];
+    if !sse_fp_end() {
+        return;
+    }
     write_mmx_reg64(r, std::mem::transmute(result));
     transition_fpu_to_mmx();
 }
@@ -1045,10 +1114,14 @@ pub unsafe fn instr_0F2D_mem(addr: i32, r: i32) {
 
 pub unsafe fn instr_660F2D(source: reg128, r: i32) {
     // cvtpd2pi mm, xmm/m128
+    sse_fp_begin();
     let result = [
         sse_convert_f64_to_i32(source.f64[0]),
         sse_convert_f64_to_i32(source.f64[1]),
     ];
+    if !sse_fp_end() {
+        return;
+    }
     write_mmx_reg64(r, std::mem::transmute(result));
     transition_fpu_to_mmx();
 }
@@ -1056,11 +1129,15 @@ pub unsafe fn instr_660F2D(source: reg128, r: i32) {
 pub unsafe fn instr_660F2D_reg(r1: i32, r2: i32) { instr_660F2D(read_xmm128s(r1), r2); }
 #[no_mangle]
 pub unsafe fn instr_660F2D_mem(addr: i32, r: i32) {
-    instr_660F2D(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F2D(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 pub unsafe fn instr_F20F2D(source: u64, r: i32) {
     // cvtsd2si r32, xmm/m64
-    write_reg32(r, sse_convert_f64_to_i32(f64::from_bits(source)));
+    let result = cvtsd2si(f64::from_bits(source));
+    if sse_exception_pending {
+        return;
+    }
+    write_reg32(r, result);
 }
 pub unsafe fn instr_F20F2D_reg(r1: i32, r2: i32) { instr_F20F2D(read_xmm64s(r1), r2); }
 pub unsafe fn instr_F20F2D_mem(addr: i32, r: i32) {
@@ -1068,7 +1145,11 @@ pub unsafe fn instr_F20F2D_mem(addr: i32, r: i32) {
 }
 pub unsafe fn instr_F30F2D(source: f32, r: i32) {
     // cvtss2si r32, xmm1/m32
-    write_reg32(r, sse_convert_f32_to_i32(source));
+    let result = cvtss2si(source);
+    if sse_exception_pending {
+        return;
+    }
+    write_reg32(r, result);
 }
 pub unsafe fn instr_F30F2D_reg(r1: i32, r2: i32) { instr_F30F2D(read_xmm_f32(r1), r2); }
 pub unsafe fn instr_F30F2D_mem(addr: i32, r: i32) {
@@ -1079,21 +1160,13 @@ pub unsafe fn instr_F30F2D_mem(addr: i32, r: i32) {
 pub unsafe fn instr_0F2E(source: f32, r: i32) {
     // ucomiss xmm1, xmm2/m32
     let destination = read_xmm_f32(r);
-    *flags_changed = 0;
-    *flags &= !FLAGS_ALL;
-    if destination == source {
-        *flags |= FLAG_ZERO
-    }
-    else if destination < source {
-        *flags |= FLAG_CARRY
-    }
-    else if destination > source {
-        // all flags cleared
-    }
-    else {
-        // TODO: Signal on SNaN
-        *flags |= FLAG_ZERO | FLAG_PARITY | FLAG_CARRY
+    sse_fp_begin();
+    let result = sse_compare_f32(destination, source, false);
+    if !sse_fp_end() {
+        return;
     }
+    *flags_changed = 0;
+    *flags = *flags & !FLAGS_ALL | result;
 }
 pub unsafe fn instr_0F2E_reg(r1: i32, r2: i32) { instr_0F2E(read_xmm_f32(r1), r2) }
 pub unsafe fn instr_0F2E_mem(addr: i32, r: i32) {
@@ -1105,21 +1178,13 @@ pub unsafe fn instr_660F2E(source: u64, r: i32) {
     // ucomisd xmm1, xmm2/m64
     let destination = f64::from_bits(read_xmm64s(r));
     let source = f64::from_bits(source);
-    *flags_changed = 0;
-    *flags &= !FLAGS_ALL;
-    if destination == source {
-        *flags |= FLAG_ZERO
-    }
-    else if destination < source {
-        *flags |= FLAG_CARRY
-    }
-    else if destination > source {
-        // all flags cleared
-    }
-    else {
-        // TODO: Signal on SNaN
-        *flags |= FLAG_ZERO | FLAG_PARITY | FLAG_CARRY
+    sse_fp_begin();
+    let result = sse_compare_f64(destination, source, false);
+    if !sse_fp_end() {
+        return;
     }
+    *flags_changed = 0;
+    *flags = *flags & !FLAGS_ALL | result;
 }
 pub unsafe fn instr_660F2E_reg(r1: i32, r: i32) { instr_660F2E(read_xmm64s(r1), r); }
 pub unsafe fn instr_660F2E_mem(addr: i32, r: i32) {
@@ -1130,21 +1195,13 @@ pub unsafe fn instr_660F2E_mem(addr: i32, r: i32) {
 pub unsafe fn instr_0F2F(source: f32, r: i32) {
     // comiss xmm1, xmm2/m32
     let destination = read_xmm_f32(r);
-    *flags_changed = 0;
-    *flags &= !FLAGS_ALL;
-    if destination == source {
-        *flags |= FLAG_ZERO
-    }
-    else if destination < source {
-        *flags |= FLAG_CARRY
-    }
-    else if destination > source {
-        // all flags cleared
-    }
-    else {
-        // TODO: Signal on SNaN or QNaN
-        *flags |= FLAG_ZERO | FLAG_PARITY | FLAG_CARRY
+    sse_fp_begin();
+    let result = sse_compare_f32(destination, source, true);
+    if !sse_fp_end() {
+        return;
     }
+    *flags_changed = 0;
+    *flags = *flags & !FLAGS_ALL | result;
 }
 pub unsafe fn instr_0F2F_reg(r1: i32, r2: i32) { instr_0F2F(read_xmm_f32(r1), r2) }
 pub unsafe fn instr_0F2F_mem(addr: i32, r: i32) {
@@ -1156,21 +1213,13 @@ pub unsafe fn instr_660F2F(source: u64, r: i32) {
     // comisd xmm1, xmm2/m64
     let destination = f64::from_bits(read_xmm64s(r));
     let source = f64::from_bits(source);
-    *flags_changed = 0;
-    *flags &= !FLAGS_ALL;
-    if destination == source {
-        *flags |= FLAG_ZERO
-    }
-    else if destination < source {
-        *flags |= FLAG_CARRY
-    }
-    else if destination > source {
-        // all flags cleared
-    }
-    else {
-        // TODO: Signal on SNaN or QNaN
-        *flags |= FLAG_ZERO | FLAG_PARITY | FLAG_CARRY
+    sse_fp_begin();
+    let result = sse_compare_f64(destination, source, true);
+    if !sse_fp_end() {
+        return;
     }
+    *flags_changed = 0;
+    *flags = *flags & !FLAGS_ALL | result;
 }
 pub unsafe fn instr_660F2F_reg(r1: i32, r: i32) { instr_660F2F(read_xmm64s(r1), r); }
 pub unsafe fn instr_660F2F_mem(addr: i32, r: i32) {
@@ -1221,9 +1270,10 @@ pub unsafe fn instr_0F30() {
             // Enable Misc. Processor Features
         },
         IA32_MCG_CAP => {}, // netbsd
-        IA32_KERNEL_GS_BASE => {
-            // Only used in 64 bit mode (by SWAPGS), but set by kvm-unit-test
+        IA32_GS_BASE => {
+            // Only available in 64 bit mode, but used by kvm-unit-test to set up the per-cpu area
             dbg_log!("GS Base written");
+            *segment_offsets.offset(GS as isize) = low;
         },
         IA32_PAT => {},
         IA32_SPEC_CTRL => {},      // linux 5.19
@@ -1272,6 +1322,7 @@ pub unsafe fn instr_0F32() {
         IA32_SYSENTER_CS => low = *sysenter_cs,
         IA32_SYSENTER_EIP => low = *sysenter_eip,
         IA32_SYSENTER_ESP => low = *sysenter_esp,
+        IA32_GS_BASE => low = *segment_offsets.offset(GS as isize),
         IA32_TIME_STAMP_COUNTER => {
             let tsc = read_tsc();
             low = tsc as i32;
@@ -1286,6 +1337,9 @@ pub unsafe fn instr_0F32() {
                 if *apic_enabled {
                     low |= IA32_APIC_BASE_EN
                 }
+                if ::cpu::smp::is_bootstrap_processor() {
+                    low |= IA32_APIC_BASE_BSP
+                }
             }
         },
         IA32_BIOS_SIGN_ID => {},
@@ -1387,7 +1441,31 @@ pub unsafe fn instr_0F37() {
     undefined_instruction();
 }
 #[no_mangle]
-pub unsafe fn instr_0F38() { unimplemented_sse(); }
+pub unsafe fn instr_0F38() {
+    // three-byte opcodes, only invpcid is implemented
+    if *prefixes & prefix::PREFIX_LOCK != 0 {
+        trigger_ud();
+        return;
+    }
+    let opcode = return_on_pagefault!(read_imm8());
+    if opcode == 0x82 && *prefixes & prefix::PREFIX_66 != 0 {
+        let modrm_byte = return_on_pagefault!(read_imm8());
+        if modrm_byte >= 0xC0 {
+            trigger_ud();
+            return;
+        }
+        let addr = return_on_pagefault!(modrm_resolve(modrm_byte));
+        instr_660F3882_mem(addr, modrm_byte >> 3 & 7);
+    }
+    else {
+        unimplemented_sse();
+    }
+}
+#[no_mangle]
+pub unsafe fn instr_660F3882_mem(addr: i32, r: i32) {
+    // invpcid r32, m128
+    invpcid(read_reg32(r), addr);
+}
 #[no_mangle]
 pub unsafe fn instr_0F39() { unimplemented_sse(); }
 #[no_mangle]
@@ -1557,39 +1635,49 @@ pub unsafe fn instr_660F50_mem(_addr: i32, _r1: i32) { trigger_ud(); }
 #[no_mangle]
 pub unsafe fn instr_0F51(source: reg128, r: i32) {
     // sqrtps xmm, xmm/mem128
-    // XXX: Should round according to round control
+    sse_fp_begin();
     let result = reg128 {
         f32: [
-            source.f32[0].sqrt(),
-            source.f32[1].sqrt(),
-            source.f32[2].sqrt(),
-            source.f32[3].sqrt(),
+            sse_sqrt_f32(source.f32[0]),
+            sse_sqrt_f32(source.f32[1]),
+            sse_sqrt_f32(source.f32[2]),
+            sse_sqrt_f32(source.f32[3]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_0F51_reg(r1: i32, r2: i32) { instr_0F51(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F51_mem(addr: i32, r: i32) {
-    instr_0F51(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F51(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F51(source: reg128, r: i32) {
     // sqrtpd xmm, xmm/mem128
-    // XXX: Should round according to round control
+    sse_fp_begin();
     let result = reg128 {
-        f64: [source.f64[0].sqrt(), source.f64[1].sqrt()],
+        f64: [sse_sqrt_f64(source.f64[0]), sse_sqrt_f64(source.f64[1])],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660F51_reg(r1: i32, r2: i32) { instr_660F51(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F51_mem(addr: i32, r: i32) {
-    instr_660F51(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F51(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F20F51(source: u64, r: i32) {
     // sqrtsd xmm, xmm/mem64
-    // XXX: Should round according to round control
-    write_xmm_f64(r, f64::from_bits(source).sqrt());
+    sse_fp_begin();
+    let result = sse_sqrt_f64(f64::from_bits(source));
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_f64(r, result);
 }
 pub unsafe fn instr_F20F51_reg(r1: i32, r2: i32) { instr_F20F51(read_xmm64s(r1), r2); }
 pub unsafe fn instr_F20F51_mem(addr: i32, r: i32) {
@@ -1598,8 +1686,12 @@ pub unsafe fn instr_F20F51_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_F30F51(source: f32, r: i32) {
     // sqrtss xmm, xmm/mem32
-    // XXX: Should round according to round control
-    write_xmm_f32(r, source.sqrt());
+    sse_fp_begin();
+    let result = sse_sqrt_f32(source);
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_f32(r, result);
 }
 pub unsafe fn instr_F30F51_reg(r1: i32, r2: i32) { instr_F30F51(read_xmm_f32(r1), r2); }
 pub unsafe fn instr_F30F51_mem(addr: i32, r: i32) {
@@ -1621,7 +1713,7 @@ pub unsafe fn instr_0F52(source: reg128, r: i32) {
 }
 pub unsafe fn instr_0F52_reg(r1: i32, r2: i32) { instr_0F52(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F52_mem(addr: i32, r: i32) {
-    instr_0F52(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F52(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F30F52(source: f32, r: i32) {
@@ -1648,7 +1740,7 @@ pub unsafe fn instr_0F53(source: reg128, r: i32) {
 }
 pub unsafe fn instr_0F53_reg(r1: i32, r2: i32) { instr_0F53(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F53_mem(addr: i32, r: i32) {
-    instr_0F53(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F53(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F30F53(source: f32, r: i32) {
@@ -1663,123 +1755,128 @@ pub unsafe fn instr_F30F53_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_0F54(source: reg128, r: i32) {
     // andps xmm, xmm/mem128
-    // XXX: Aligned access or #gp
     pand_r128(source, r);
 }
 pub unsafe fn instr_0F54_reg(r1: i32, r2: i32) { instr_0F54(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F54_mem(addr: i32, r: i32) {
-    instr_0F54(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F54(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F54(source: reg128, r: i32) {
     // andpd xmm, xmm/mem128
-    // XXX: Aligned access or #gp
     pand_r128(source, r);
 }
 pub unsafe fn instr_660F54_reg(r1: i32, r2: i32) { instr_660F54(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F54_mem(addr: i32, r: i32) {
-    instr_660F54(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F54(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F55(source: reg128, r: i32) {
     // andnps xmm, xmm/mem128
-    // XXX: Aligned access or #gp
     pandn_r128(source, r);
 }
 pub unsafe fn instr_0F55_reg(r1: i32, r2: i32) { instr_0F55(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F55_mem(addr: i32, r: i32) {
-    instr_0F55(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F55(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F55(source: reg128, r: i32) {
     // andnpd xmm, xmm/mem128
-    // XXX: Aligned access or #gp
     pandn_r128(source, r);
 }
 pub unsafe fn instr_660F55_reg(r1: i32, r2: i32) { instr_660F55(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F55_mem(addr: i32, r: i32) {
-    instr_660F55(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F55(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F56(source: reg128, r: i32) {
     // orps xmm, xmm/mem128
-    // XXX: Aligned access or #gp
     por_r128(source, r);
 }
 pub unsafe fn instr_0F56_reg(r1: i32, r2: i32) { instr_0F56(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F56_mem(addr: i32, r: i32) {
-    instr_0F56(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F56(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F56(source: reg128, r: i32) {
     // orpd xmm, xmm/mem128
-    // XXX: Aligned access or #gp
     por_r128(source, r);
 }
 pub unsafe fn instr_660F56_reg(r1: i32, r2: i32) { instr_660F56(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F56_mem(addr: i32, r: i32) {
-    instr_660F56(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F56(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F57(source: reg128, r: i32) {
     // xorps xmm, xmm/mem128
-    // XXX: Aligned access or #gp
     pxor_r128(source, r);
 }
 pub unsafe fn instr_0F57_reg(r1: i32, r2: i32) { instr_0F57(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F57_mem(addr: i32, r: i32) {
-    instr_0F57(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F57(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F57(source: reg128, r: i32) {
     // xorpd xmm, xmm/mem128
-    // XXX: Aligned access or #gp
     pxor_r128(source, r);
 }
 pub unsafe fn instr_660F57_reg(r1: i32, r2: i32) { instr_660F57(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F57_mem(addr: i32, r: i32) {
-    instr_660F57(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F57(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 
 #[no_mangle]
 pub unsafe fn instr_0F58(source: reg128, r: i32) {
     // addps xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f32: [
-            source.f32[0] + destination.f32[0],
-            source.f32[1] + destination.f32[1],
-            source.f32[2] + destination.f32[2],
-            source.f32[3] + destination.f32[3],
+            sse_add_f32(destination.f32[0], source.f32[0]),
+            sse_add_f32(destination.f32[1], source.f32[1]),
+            sse_add_f32(destination.f32[2], source.f32[2]),
+            sse_add_f32(destination.f32[3], source.f32[3]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_0F58_reg(r1: i32, r2: i32) { instr_0F58(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F58_mem(addr: i32, r: i32) {
-    instr_0F58(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F58(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F58(source: reg128, r: i32) {
     // addpd xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f64: [
-            source.f64[0] + destination.f64[0],
-            source.f64[1] + destination.f64[1],
+            sse_add_f64(destination.f64[0], source.f64[0]),
+            sse_add_f64(destination.f64[1], source.f64[1]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660F58_reg(r1: i32, r2: i32) { instr_660F58(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F58_mem(addr: i32, r: i32) {
-    instr_660F58(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F58(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F20F58(source: u64, r: i32) {
     // addsd xmm, xmm/mem64
     let destination = read_xmm64s(r);
-    write_xmm_f64(r, f64::from_bits(source) + f64::from_bits(destination));
+    sse_fp_begin();
+    let result = sse_add_f64(f64::from_bits(destination), f64::from_bits(source));
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_f64(r, result);
 }
 pub unsafe fn instr_F20F58_reg(r1: i32, r2: i32) { instr_F20F58(read_xmm64s(r1), r2); }
 pub unsafe fn instr_F20F58_mem(addr: i32, r: i32) {
@@ -1789,7 +1886,11 @@ pub unsafe fn instr_F20F58_mem(addr: i32, r: i32) {
 pub unsafe fn instr_F30F58(source: f32, r: i32) {
     // addss xmm, xmm/mem32
     let destination = read_xmm_f32(r);
-    let result = source + destination;
+    sse_fp_begin();
+    let result = sse_add_f32(destination, source);
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_f32(r, result);
 }
 pub unsafe fn instr_F30F58_reg(r1: i32, r2: i32) { instr_F30F58(read_xmm_f32(r1), r2); }
@@ -1801,41 +1902,54 @@ pub unsafe fn instr_F30F58_mem(addr: i32, r: i32) {
 pub unsafe fn instr_0F59(source: reg128, r: i32) {
     // mulps xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f32: [
-            source.f32[0] * destination.f32[0],
-            source.f32[1] * destination.f32[1],
-            source.f32[2] * destination.f32[2],
-            source.f32[3] * destination.f32[3],
+            sse_mul_f32(destination.f32[0], source.f32[0]),
+            sse_mul_f32(destination.f32[1], source.f32[1]),
+            sse_mul_f32(destination.f32[2], source.f32[2]),
+            sse_mul_f32(destination.f32[3], source.f32[3]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_0F59_reg(r1: i32, r2: i32) { instr_0F59(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F59_mem(addr: i32, r: i32) {
-    instr_0F59(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F59(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F59(source: reg128, r: i32) {
     // mulpd xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f64: [
-            source.f64[0] * destination.f64[0],
-            source.f64[1] * destination.f64[1],
+            sse_mul_f64(destination.f64[0], source.f64[0]),
+            sse_mul_f64(destination.f64[1], source.f64[1]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660F59_reg(r1: i32, r2: i32) { instr_660F59(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F59_mem(addr: i32, r: i32) {
-    instr_660F59(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F59(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F20F59(source: u64, r: i32) {
     // mulsd xmm, xmm/mem64
     let destination = read_xmm64s(r);
-    write_xmm_f64(r, f64::from_bits(source) * f64::from_bits(destination));
+    sse_fp_begin();
+    let result = sse_mul_f64(f64::from_bits(destination), f64::from_bits(source));
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_f64(r, result);
 }
 pub unsafe fn instr_F20F59_reg(r1: i32, r2: i32) { instr_F20F59(read_xmm64s(r1), r2); }
 pub unsafe fn instr_F20F59_mem(addr: i32, r: i32) {
@@ -1845,7 +1959,11 @@ pub unsafe fn instr_F20F59_mem(addr: i32, r: i32) {
 pub unsafe fn instr_F30F59(source: f32, r: i32) {
     // mulss xmm, xmm/mem32
     let destination = read_xmm_f32(r);
-    let result = source * destination;
+    sse_fp_begin();
+    let result = sse_mul_f32(destination, source);
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_f32(r, result);
 }
 pub unsafe fn instr_F30F59_reg(r1: i32, r2: i32) { instr_F30F59(read_xmm_f32(r1), r2); }
@@ -1857,9 +1975,16 @@ pub unsafe fn instr_F30F59_mem(addr: i32, r: i32) {
 pub unsafe fn instr_0F5A(source: u64, r: i32) {
     // cvtps2pd xmm1, xmm2/m64
     let source: [f32; 2] = std::mem::transmute(source);
+    sse_fp_begin();
     let result = reg128 {
-        f64: [source[0] as f64, source[1] as f64],
+        f64: [
+            sse_convert_f32_to_f64(source[0]),
+            sse_convert_f32_to_f64(source[1]),
+        ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_0F5A_reg(r1: i32, r2: i32) { instr_0F5A(read_xmm64s(r1), r2); }
@@ -1869,21 +1994,33 @@ pub unsafe fn instr_0F5A_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F5A(source: reg128, r: i32) {
     // cvtpd2ps xmm1, xmm2/m128
+    sse_fp_begin();
     let result = reg128 {
-        // XXX: These conversions are lossy and should round according to the round control
-        f32: [source.f64[0] as f32, source.f64[1] as f32, 0., 0.],
+        f32: [
+            sse_convert_f64_to_f32(source.f64[0]),
+            sse_convert_f64_to_f32(source.f64[1]),
+            0.,
+            0.,
+        ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660F5A_reg(r1: i32, r2: i32) { instr_660F5A(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F5A_mem(addr: i32, r: i32) {
-    instr_660F5A(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F5A(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F20F5A(source: u64, r: i32) {
     // cvtsd2ss xmm1, xmm2/m64
-    // XXX: This conversions is lossy and should round according to the round control
-    write_xmm_f32(r, f64::from_bits(source) as f32);
+    sse_fp_begin();
+    let result = sse_convert_f64_to_f32(f64::from_bits(source));
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_f32(r, result);
 }
 pub unsafe fn instr_F20F5A_reg(r1: i32, r2: i32) { instr_F20F5A(read_xmm64s(r1), r2); }
 pub unsafe fn instr_F20F5A_mem(addr: i32, r: i32) {
@@ -1892,7 +2029,12 @@ pub unsafe fn instr_F20F5A_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_F30F5A(source: f32, r: i32) {
     // cvtss2sd xmm1, xmm2/m32
-    write_xmm_f64(r, source as f64);
+    sse_fp_begin();
+    let result = sse_convert_f32_to_f64(source);
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_f64(r, result);
 }
 pub unsafe fn instr_F30F5A_reg(r1: i32, r2: i32) { instr_F30F5A(read_xmm_f32(r1), r2); }
 pub unsafe fn instr_F30F5A_mem(addr: i32, r: i32) {
@@ -1902,43 +2044,49 @@ pub unsafe fn instr_F30F5A_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_0F5B(source: reg128, r: i32) {
     // cvtdq2ps xmm1, xmm2/m128
-    // XXX: Should round according to round control
+    sse_fp_begin();
     let result = reg128 {
         f32: [
-            // XXX: Precision exception
-            source.i32[0] as f32,
-            source.i32[1] as f32,
-            source.i32[2] as f32,
-            source.i32[3] as f32,
+            sse_convert_i32_to_f32(source.i32[0]),
+            sse_convert_i32_to_f32(source.i32[1]),
+            sse_convert_i32_to_f32(source.i32[2]),
+            sse_convert_i32_to_f32(source.i32[3]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_0F5B_reg(r1: i32, r2: i32) { instr_0F5B(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F5B_mem(addr: i32, r: i32) {
-    instr_0F5B(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F5B(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F5B(source: reg128, r: i32) {
     // cvtps2dq xmm1, xmm2/m128
+    sse_fp_begin();
     let result = reg128 {
         i32: [
-            // XXX: Precision exception
             sse_convert_f32_to_i32(source.f32[0]),
             sse_convert_f32_to_i32(source.f32[1]),
             sse_convert_f32_to_i32(source.f32[2]),
             sse_convert_f32_to_i32(source.f32[3]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660F5B_reg(r1: i32, r2: i32) { instr_660F5B(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F5B_mem(addr: i32, r: i32) {
-    instr_660F5B(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F5B(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F30F5B(source: reg128, r: i32) {
     // cvttps2dq xmm1, xmm2/m128
+    sse_fp_begin();
     let result = reg128 {
         i32: [
             sse_convert_with_truncation_f32_to_i32(source.f32[0]),
@@ -1947,52 +2095,68 @@ pub unsafe fn instr_F30F5B(source: reg128, r: i32) {
             sse_convert_with_truncation_f32_to_i32(source.f32[3]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_F30F5B_reg(r1: i32, r2: i32) { instr_F30F5B(read_xmm128s(r1), r2); }
 pub unsafe fn instr_F30F5B_mem(addr: i32, r: i32) {
-    instr_F30F5B(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_F30F5B(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 
 #[no_mangle]
 pub unsafe fn instr_0F5C(source: reg128, r: i32) {
     // subps xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f32: [
-            destination.f32[0] - source.f32[0],
-            destination.f32[1] - source.f32[1],
-            destination.f32[2] - source.f32[2],
-            destination.f32[3] - source.f32[3],
+            sse_sub_f32(destination.f32[0], source.f32[0]),
+            sse_sub_f32(destination.f32[1], source.f32[1]),
+            sse_sub_f32(destination.f32[2], source.f32[2]),
+            sse_sub_f32(destination.f32[3], source.f32[3]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_0F5C_reg(r1: i32, r2: i32) { instr_0F5C(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F5C_mem(addr: i32, r: i32) {
-    instr_0F5C(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F5C(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F5C(source: reg128, r: i32) {
     // subpd xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f64: [
-            destination.f64[0] - source.f64[0],
-            destination.f64[1] - source.f64[1],
+            sse_sub_f64(destination.f64[0], source.f64[0]),
+            sse_sub_f64(destination.f64[1], source.f64[1]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660F5C_reg(r1: i32, r2: i32) { instr_660F5C(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F5C_mem(addr: i32, r: i32) {
-    instr_660F5C(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F5C(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F20F5C(source: u64, r: i32) {
     // subsd xmm, xmm/mem64
     let destination = read_xmm64s(r);
-    write_xmm_f64(r, f64::from_bits(destination) - f64::from_bits(source));
+    sse_fp_begin();
+    let result = sse_sub_f64(f64::from_bits(destination), f64::from_bits(source));
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_f64(r, result);
 }
 pub unsafe fn instr_F20F5C_reg(r1: i32, r2: i32) { instr_F20F5C(read_xmm64s(r1), r2); }
 pub unsafe fn instr_F20F5C_mem(addr: i32, r: i32) {
@@ -2002,7 +2166,11 @@ pub unsafe fn instr_F20F5C_mem(addr: i32, r: i32) {
 pub unsafe fn instr_F30F5C(source: f32, r: i32) {
     // subss xmm, xmm/mem32
     let destination = read_xmm_f32(r);
-    let result = destination - source;
+    sse_fp_begin();
+    let result = sse_sub_f32(destination, source);
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_f32(r, result);
 }
 pub unsafe fn instr_F30F5C_reg(r1: i32, r2: i32) { instr_F30F5C(read_xmm_f32(r1), r2); }
@@ -2013,44 +2181,54 @@ pub unsafe fn instr_F30F5C_mem(addr: i32, r: i32) {
 pub unsafe fn instr_0F5D(source: reg128, r: i32) {
     // minps xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f32: [
-            sse_min(destination.f32[0] as f64, source.f32[0] as f64) as f32,
-            sse_min(destination.f32[1] as f64, source.f32[1] as f64) as f32,
-            sse_min(destination.f32[2] as f64, source.f32[2] as f64) as f32,
-            sse_min(destination.f32[3] as f64, source.f32[3] as f64) as f32,
+            sse_min_f32(destination.f32[0], source.f32[0]),
+            sse_min_f32(destination.f32[1], source.f32[1]),
+            sse_min_f32(destination.f32[2], source.f32[2]),
+            sse_min_f32(destination.f32[3], source.f32[3]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_0F5D_reg(r1: i32, r2: i32) { instr_0F5D(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F5D_mem(addr: i32, r: i32) {
-    instr_0F5D(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F5D(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F5D(source: reg128, r: i32) {
     // minpd xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f64: [
-            sse_min(destination.f64[0], source.f64[0]),
-            sse_min(destination.f64[1], source.f64[1]),
+            sse_min_f64(destination.f64[0], source.f64[0]),
+            sse_min_f64(destination.f64[1], source.f64[1]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660F5D_reg(r1: i32, r2: i32) { instr_660F5D(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F5D_mem(addr: i32, r: i32) {
-    instr_660F5D(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F5D(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F20F5D(source: u64, r: i32) {
     // minsd xmm, xmm/mem64
     let destination = read_xmm64s(r);
-    write_xmm_f64(
-        r,
-        sse_min(f64::from_bits(destination), f64::from_bits(source)),
-    );
+    sse_fp_begin();
+    let result = sse_min_f64(f64::from_bits(destination), f64::from_bits(source));
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_f64(r, result);
 }
 pub unsafe fn instr_F20F5D_reg(r1: i32, r2: i32) { instr_F20F5D(read_xmm64s(r1), r2); }
 pub unsafe fn instr_F20F5D_mem(addr: i32, r: i32) {
@@ -2060,7 +2238,11 @@ pub unsafe fn instr_F20F5D_mem(addr: i32, r: i32) {
 pub unsafe fn instr_F30F5D(source: f32, r: i32) {
     // minss xmm, xmm/mem32
     let destination = read_xmm_f32(r);
-    let result = sse_min(destination as f64, source as f64) as f32;
+    sse_fp_begin();
+    let result = sse_min_f32(destination, source);
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_f32(r, result);
 }
 pub unsafe fn instr_F30F5D_reg(r1: i32, r2: i32) { instr_F30F5D(read_xmm_f32(r1), r2); }
@@ -2071,41 +2253,54 @@ pub unsafe fn instr_F30F5D_mem(addr: i32, r: i32) {
 pub unsafe fn instr_0F5E(source: reg128, r: i32) {
     // divps xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f32: [
-            destination.f32[0] / source.f32[0],
-            destination.f32[1] / source.f32[1],
-            destination.f32[2] / source.f32[2],
-            destination.f32[3] / source.f32[3],
+            sse_div_f32(destination.f32[0], source.f32[0]),
+            sse_div_f32(destination.f32[1], source.f32[1]),
+            sse_div_f32(destination.f32[2], source.f32[2]),
+            sse_div_f32(destination.f32[3], source.f32[3]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_0F5E_reg(r1: i32, r2: i32) { instr_0F5E(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F5E_mem(addr: i32, r: i32) {
-    instr_0F5E(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F5E(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F5E(source: reg128, r: i32) {
     // divpd xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f64: [
-            destination.f64[0] / source.f64[0],
-            destination.f64[1] / source.f64[1],
+            sse_div_f64(destination.f64[0], source.f64[0]),
+            sse_div_f64(destination.f64[1], source.f64[1]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660F5E_reg(r1: i32, r2: i32) { instr_660F5E(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F5E_mem(addr: i32, r: i32) {
-    instr_660F5E(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F5E(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F20F5E(source: u64, r: i32) {
     // divsd xmm, xmm/mem64
     let destination = read_xmm64s(r);
-    write_xmm_f64(r, f64::from_bits(destination) / f64::from_bits(source));
+    sse_fp_begin();
+    let result = sse_div_f64(f64::from_bits(destination), f64::from_bits(source));
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_f64(r, result);
 }
 pub unsafe fn instr_F20F5E_reg(r1: i32, r2: i32) { instr_F20F5E(read_xmm64s(r1), r2); }
 pub unsafe fn instr_F20F5E_mem(addr: i32, r: i32) {
@@ -2115,7 +2310,11 @@ pub unsafe fn instr_F20F5E_mem(addr: i32, r: i32) {
 pub unsafe fn instr_F30F5E(source: f32, r: i32) {
     // divss xmm, xmm/mem32
     let destination = read_xmm_f32(r);
-    let result = destination / source;
+    sse_fp_begin();
+    let result = sse_div_f32(destination, source);
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_f32(r, result);
 }
 pub unsafe fn instr_F30F5E_reg(r1: i32, r2: i32) { instr_F30F5E(read_xmm_f32(r1), r2); }
@@ -2126,44 +2325,54 @@ pub unsafe fn instr_F30F5E_mem(addr: i32, r: i32) {
 pub unsafe fn instr_0F5F(source: reg128, r: i32) {
     // maxps xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f32: [
-            sse_max(destination.f32[0] as f64, source.f32[0] as f64) as f32,
-            sse_max(destination.f32[1] as f64, source.f32[1] as f64) as f32,
-            sse_max(destination.f32[2] as f64, source.f32[2] as f64) as f32,
-            sse_max(destination.f32[3] as f64, source.f32[3] as f64) as f32,
+            sse_max_f32(destination.f32[0], source.f32[0]),
+            sse_max_f32(destination.f32[1], source.f32[1]),
+            sse_max_f32(destination.f32[2], source.f32[2]),
+            sse_max_f32(destination.f32[3], source.f32[3]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_0F5F_reg(r1: i32, r2: i32) { instr_0F5F(read_xmm128s(r1), r2); }
 pub unsafe fn instr_0F5F_mem(addr: i32, r: i32) {
-    instr_0F5F(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_0F5F(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_660F5F(source: reg128, r: i32) {
     // maxpd xmm, xmm/mem128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         f64: [
-            sse_max(destination.f64[0], source.f64[0]),
-            sse_max(destination.f64[1], source.f64[1]),
+            sse_max_f64(destination.f64[0], source.f64[0]),
+            sse_max_f64(destination.f64[1], source.f64[1]),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660F5F_reg(r1: i32, r2: i32) { instr_660F5F(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F5F_mem(addr: i32, r: i32) {
-    instr_660F5F(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F5F(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F20F5F(source: u64, r: i32) {
     // maxsd xmm, xmm/mem64
     let destination = read_xmm64s(r);
-    write_xmm_f64(
-        r,
-        sse_max(f64::from_bits(destination), f64::from_bits(source)),
-    );
+    sse_fp_begin();
+    let result = sse_max_f64(f64::from_bits(destination), f64::from_bits(source));
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_f64(r, result);
 }
 pub unsafe fn instr_F20F5F_reg(r1: i32, r2: i32) { instr_F20F5F(read_xmm64s(r1), r2); }
 pub unsafe fn instr_F20F5F_mem(addr: i32, r: i32) {
@@ -2173,7 +2382,11 @@ pub unsafe fn instr_F20F5F_mem(addr: i32, r: i32) {
 pub unsafe fn instr_F30F5F(source: f32, r: i32) {
     // maxss xmm, xmm/mem32
     let destination = read_xmm_f32(r);
-    let result = sse_max(destination as f64, source as f64) as f32;
+    sse_fp_begin();
+    let result = sse_max_f32(destination, source);
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_f32(r, result);
 }
 pub unsafe fn instr_F30F5F_reg(r1: i32, r2: i32) { instr_F30F5F(read_xmm_f32(r1), r2); }
@@ -2201,7 +2414,6 @@ pub unsafe fn instr_0F60_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F60(source: reg128, r: i32) {
     // punpcklbw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination: [u8; 8] = std::mem::transmute(read_xmm64s(r));
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -2212,7 +2424,7 @@ pub unsafe fn instr_660F60(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F60_reg(r1: i32, r2: i32) { instr_660F60(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F60_mem(addr: i32, r: i32) {
-    instr_660F60(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F60(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F61(source: i32, r: i32) {
@@ -2234,7 +2446,6 @@ pub unsafe fn instr_0F61_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F61(source: reg128, r: i32) {
     // punpcklwd xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination: [u16; 4] = std::mem::transmute(read_xmm64s(r));
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..4 {
@@ -2245,7 +2456,7 @@ pub unsafe fn instr_660F61(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F61_reg(r1: i32, r2: i32) { instr_660F61(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F61_mem(addr: i32, r: i32) {
-    instr_660F61(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F61(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F62(source: i32, r: i32) {
@@ -2263,7 +2474,6 @@ pub unsafe fn instr_0F62_mem(addr: i32, r: i32) {
 }
 pub unsafe fn instr_660F62(source: reg128, r: i32) {
     // punpckldq xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     write_xmm128(
         r,
@@ -2275,7 +2485,7 @@ pub unsafe fn instr_660F62(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F62_reg(r1: i32, r2: i32) { instr_660F62(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F62_mem(addr: i32, r: i32) {
-    instr_660F62(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F62(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F63(source: u64, r: i32) {
@@ -2297,7 +2507,6 @@ pub unsafe fn instr_0F63_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F63(source: reg128, r: i32) {
     // packsswb xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -2308,7 +2517,7 @@ pub unsafe fn instr_660F63(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F63_reg(r1: i32, r2: i32) { instr_660F63(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F63_mem(addr: i32, r: i32) {
-    instr_660F63(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F63(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F64(source: u64, r: i32) {
@@ -2329,7 +2538,6 @@ pub unsafe fn instr_0F64_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F64(source: reg128, r: i32) {
     // pcmpgtb xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..16 {
@@ -2339,7 +2547,7 @@ pub unsafe fn instr_660F64(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F64_reg(r1: i32, r2: i32) { instr_660F64(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F64_mem(addr: i32, r: i32) {
-    instr_660F64(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F64(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F65(source: u64, r: i32) {
@@ -2360,7 +2568,6 @@ pub unsafe fn instr_0F65_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F65(source: reg128, r: i32) {
     // pcmpgtw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -2370,7 +2577,7 @@ pub unsafe fn instr_660F65(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F65_reg(r1: i32, r2: i32) { instr_660F65(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F65_mem(addr: i32, r: i32) {
-    instr_660F65(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F65(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F66(source: u64, r: i32) {
@@ -2391,7 +2598,6 @@ pub unsafe fn instr_0F66_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F66(source: reg128, r: i32) {
     // pcmpgtd xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     write_xmm128(
         r,
@@ -2403,7 +2609,7 @@ pub unsafe fn instr_660F66(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F66_reg(r1: i32, r2: i32) { instr_660F66(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F66_mem(addr: i32, r: i32) {
-    instr_660F66(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F66(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F67(source: u64, r: i32) {
@@ -2425,7 +2631,6 @@ pub unsafe fn instr_0F67_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F67(source: reg128, r: i32) {
     // packuswb xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -2436,7 +2641,7 @@ pub unsafe fn instr_660F67(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F67_reg(r1: i32, r2: i32) { instr_660F67(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F67_mem(addr: i32, r: i32) {
-    instr_660F67(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F67(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F68(source: u64, r: i32) {
@@ -2458,7 +2663,6 @@ pub unsafe fn instr_0F68_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F68(source: reg128, r: i32) {
     // punpckhbw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -2469,7 +2673,7 @@ pub unsafe fn instr_660F68(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F68_reg(r1: i32, r2: i32) { instr_660F68(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F68_mem(addr: i32, r: i32) {
-    instr_660F68(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F68(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F69(source: u64, r: i32) {
@@ -2487,7 +2691,6 @@ pub unsafe fn instr_0F69_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F69(source: reg128, r: i32) {
     // punpckhwd xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..4 {
@@ -2498,7 +2701,7 @@ pub unsafe fn instr_660F69(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F69_reg(r1: i32, r2: i32) { instr_660F69(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F69_mem(addr: i32, r: i32) {
-    instr_660F69(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F69(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F6A(source: u64, r: i32) {
@@ -2514,7 +2717,6 @@ pub unsafe fn instr_0F6A_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F6A(source: reg128, r: i32) {
     // punpckhdq xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     write_xmm128(
         r,
@@ -2526,7 +2728,7 @@ pub unsafe fn instr_660F6A(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F6A_reg(r1: i32, r2: i32) { instr_660F6A(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F6A_mem(addr: i32, r: i32) {
-    instr_660F6A(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F6A(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F6B(source: u64, r: i32) {
@@ -2548,7 +2750,6 @@ pub unsafe fn instr_0F6B_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F6B(source: reg128, r: i32) {
     // packssdw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..4 {
@@ -2559,7 +2760,7 @@ pub unsafe fn instr_660F6B(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F6B_reg(r1: i32, r2: i32) { instr_660F6B(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F6B_mem(addr: i32, r: i32) {
-    instr_660F6B(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F6B(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F6C_mem(_addr: i32, _r: i32) { trigger_ud(); }
@@ -2568,7 +2769,6 @@ pub unsafe fn instr_0F6C_reg(_r1: i32, _r2: i32) { trigger_ud(); }
 #[no_mangle]
 pub unsafe fn instr_660F6C(source: reg128, r: i32) {
     // punpcklqdq xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     write_xmm128(
         r,
@@ -2580,7 +2780,7 @@ pub unsafe fn instr_660F6C(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F6C_reg(r1: i32, r2: i32) { instr_660F6C(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F6C_mem(addr: i32, r: i32) {
-    instr_660F6C(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F6C(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F6D_mem(_addr: i32, _r: i32) { trigger_ud(); }
@@ -2589,7 +2789,6 @@ pub unsafe fn instr_0F6D_reg(_r1: i32, _r2: i32) { trigger_ud(); }
 #[no_mangle]
 pub unsafe fn instr_660F6D(source: reg128, r: i32) {
     // punpckhqdq xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     write_xmm128(
         r,
@@ -2601,7 +2800,7 @@ pub unsafe fn instr_660F6D(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F6D_reg(r1: i32, r2: i32) { instr_660F6D(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F6D_mem(addr: i32, r: i32) {
-    instr_660F6D(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F6D(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 
 #[no_mangle]
@@ -2635,12 +2834,11 @@ pub unsafe fn instr_0F6F_mem(addr: i32, r: i32) {
 }
 pub unsafe fn instr_660F6F(source: reg128, r: i32) {
     // movdqa xmm, xmm/mem128
-    // XXX: Aligned access or #gp
     mov_rm_r128(source, r);
 }
 pub unsafe fn instr_660F6F_reg(r1: i32, r2: i32) { instr_660F6F(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F6F_mem(addr: i32, r: i32) {
-    instr_660F6F(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F6F(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 pub unsafe fn instr_F30F6F(source: reg128, r: i32) {
     // movdqu xmm, xmm/m128
@@ -2667,7 +2865,6 @@ pub unsafe fn instr_0F70_mem(addr: i32, r: i32, imm: i32) {
 }
 pub unsafe fn instr_660F70(source: reg128, r: i32, imm8: i32) {
     // pshufd xmm, xmm/mem128, imm8
-    // XXX: Aligned access or #gp
     write_xmm128(
         r,
         source.u32[(imm8 & 3) as usize] as i32,
@@ -2680,13 +2877,12 @@ pub unsafe fn instr_660F70_reg(r1: i32, r2: i32, imm: i32) {
     instr_660F70(read_xmm128s(r1), r2, imm);
 }
 pub unsafe fn instr_660F70_mem(addr: i32, r: i32, imm: i32) {
-    instr_660F70(return_on_pagefault!(safe_read128s(addr)), r, imm);
+    instr_660F70(return_on_pagefault!(safe_read128s_aligned(addr)), r, imm);
 }
 
 #[no_mangle]
 pub unsafe fn instr_F20F70(source: reg128, r: i32, imm8: i32) {
     // pshuflw xmm, xmm/m128, imm8
-    // XXX: Aligned access or #gp
     write_xmm128(
         r,
         source.u16[(imm8 & 3) as usize] as i32
@@ -2701,12 +2897,11 @@ pub unsafe fn instr_F20F70_reg(r1: i32, r2: i32, imm: i32) {
     instr_F20F70(read_xmm128s(r1), r2, imm);
 }
 pub unsafe fn instr_F20F70_mem(addr: i32, r: i32, imm: i32) {
-    instr_F20F70(return_on_pagefault!(safe_read128s(addr)), r, imm);
+    instr_F20F70(return_on_pagefault!(safe_read128s_aligned(addr)), r, imm);
 }
 #[no_mangle]
 pub unsafe fn instr_F30F70(source: reg128, r: i32, imm8: i32) {
     // pshufhw xmm, xmm/m128, imm8
-    // XXX: Aligned access or #gp
     write_xmm128(
         r,
         source.u32[0] as i32,
@@ -2721,7 +2916,7 @@ pub unsafe fn instr_F30F70_reg(r1: i32, r2: i32, imm: i32) {
     instr_F30F70(read_xmm128s(r1), r2, imm);
 }
 pub unsafe fn instr_F30F70_mem(addr: i32, r: i32, imm: i32) {
-    instr_F30F70(return_on_pagefault!(safe_read128s(addr)), r, imm);
+    instr_F30F70(return_on_pagefault!(safe_read128s_aligned(addr)), r, imm);
 }
 pub unsafe fn instr_0F71_2_mem(_addr: i32, _r: i32) { trigger_ud(); }
 pub unsafe fn instr_0F71_4_mem(_addr: i32, _r: i32) { trigger_ud(); }
@@ -2879,7 +3074,6 @@ pub unsafe fn instr_0F74_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F74(source: reg128, r: i32) {
     // pcmpeqb xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..16 {
@@ -2889,7 +3083,7 @@ pub unsafe fn instr_660F74(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F74_reg(r1: i32, r2: i32) { instr_660F74(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F74_mem(addr: i32, r: i32) {
-    instr_660F74(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F74(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F75(source: u64, r: i32) {
@@ -2910,7 +3104,6 @@ pub unsafe fn instr_0F75_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F75(source: reg128, r: i32) {
     // pcmpeqw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -2921,7 +3114,7 @@ pub unsafe fn instr_660F75(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F75_reg(r1: i32, r2: i32) { instr_660F75(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F75_mem(addr: i32, r: i32) {
-    instr_660F75(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F75(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F76(source: u64, r: i32) {
@@ -2942,7 +3135,6 @@ pub unsafe fn instr_0F76_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660F76(source: reg128, r: i32) {
     // pcmpeqd xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..4 {
@@ -2952,7 +3144,7 @@ pub unsafe fn instr_660F76(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660F76_reg(r1: i32, r2: i32) { instr_660F76(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F76_mem(addr: i32, r: i32) {
-    instr_660F76(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F76(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0F77() {
@@ -2978,79 +3170,87 @@ pub unsafe fn instr_0F7D() { unimplemented_sse(); }
 pub unsafe fn instr_660F7C(source: reg128, r: i32) {
     // haddpd xmm1, xmm2/m128
     let destination = read_xmm128s(r);
-    write_xmm_reg128(
-        r,
-        reg128 {
-            f64: [
-                destination.f64[0] + destination.f64[1],
-                source.f64[0] + source.f64[1],
-            ],
-        },
-    );
+    sse_fp_begin();
+    let result = reg128 {
+        f64: [
+            sse_add_f64(destination.f64[0], destination.f64[1]),
+            sse_add_f64(source.f64[0], source.f64[1]),
+        ],
+    };
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660F7C_reg(r1: i32, r2: i32) { instr_660F7C(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F7C_mem(addr: i32, r: i32) {
-    instr_660F7C(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F7C(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_F20F7C(source: reg128, r: i32) {
-    // haddps xmm, xmm/mem128
+    // haddps xmm1, xmm2/m128
     let destination = read_xmm128s(r);
-    write_xmm_reg128(
-        r,
-        reg128 {
-            f32: [
-                destination.f32[0] + destination.f32[1],
-                destination.f32[2] + destination.f32[3],
-                source.f32[0] + source.f32[1],
-                source.f32[2] + source.f32[3],
-            ],
-        },
-    );
+    sse_fp_begin();
+    let result = reg128 {
+        f32: [
+            sse_add_f32(destination.f32[0], destination.f32[1]),
+            sse_add_f32(destination.f32[2], destination.f32[3]),
+            sse_add_f32(source.f32[0], source.f32[1]),
+            sse_add_f32(source.f32[2], source.f32[3]),
+        ],
+    };
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_F20F7C_reg(r1: i32, r2: i32) { instr_F20F7C(read_xmm128s(r1), r2); }
 pub unsafe fn instr_F20F7C_mem(addr: i32, r: i32) {
-    instr_F20F7C(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_F20F7C(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 
 #[no_mangle]
 pub unsafe fn instr_660F7D(source: reg128, r: i32) {
     // hsubpd xmm1, xmm2/m128
     let destination = read_xmm128s(r);
-    write_xmm_reg128(
-        r,
-        reg128 {
-            f64: [
-                destination.f64[0] - destination.f64[1],
-                source.f64[0] - source.f64[1],
-            ],
-        },
-    );
+    sse_fp_begin();
+    let result = reg128 {
+        f64: [
+            sse_sub_f64(destination.f64[0], destination.f64[1]),
+            sse_sub_f64(source.f64[0], source.f64[1]),
+        ],
+    };
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660F7D_reg(r1: i32, r2: i32) { instr_660F7D(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660F7D_mem(addr: i32, r: i32) {
-    instr_660F7D(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660F7D(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 
 #[no_mangle]
 pub unsafe fn instr_F20F7D(source: reg128, r: i32) {
     // hsubps xmm1, xmm2/m128
     let destination = read_xmm128s(r);
-    write_xmm_reg128(
-        r,
-        reg128 {
-            f32: [
-                destination.f32[0] - destination.f32[1],
-                destination.f32[2] - destination.f32[3],
-                source.f32[0] - source.f32[1],
-                source.f32[2] - source.f32[3],
-            ],
-        },
-    );
+    sse_fp_begin();
+    let result = reg128 {
+        f32: [
+            sse_sub_f32(destination.f32[0], destination.f32[1]),
+            sse_sub_f32(destination.f32[2], destination.f32[3]),
+            sse_sub_f32(source.f32[0], source.f32[1]),
+            sse_sub_f32(source.f32[2], source.f32[3]),
+        ],
+    };
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_F20F7D_reg(r1: i32, r2: i32) { instr_F20F7D(read_xmm128s(r1), r2); }
 pub unsafe fn instr_F20F7D_mem(addr: i32, r: i32) {
-    instr_F20F7D(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_F20F7D(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 
 #[no_mangle]
@@ -3101,12 +3301,10 @@ pub unsafe fn instr_0F7F_reg(r1: i32, r2: i32) {
 }
 pub unsafe fn instr_660F7F_mem(addr: i32, r: i32) {
     // movdqa xmm/m128, xmm
-    // XXX: Aligned write or #gp
-    mov_r_m128(addr, r);
+    mov_r_m128_aligned(addr, r);
 }
 pub unsafe fn instr_660F7F_reg(r1: i32, r2: i32) {
     // movdqa xmm/m128, xmm
-    // XXX: Aligned access or #gp
     mov_r_r128(r1, r2);
 }
 pub unsafe fn instr_F30F7F_mem(addr: i32, r: i32) {
@@ -3238,15 +3436,19 @@ pub unsafe fn instr_0FA2() {
         1 => {
             // pentium
             eax = 3 | 6 << 4 | 15 << 8;
-            ebx = 1 << 16 | 8 << 8; // cpu count, clflush size
-            ecx = 1 << 0 | 1 << 23 | 1 << 30; // sse3, popcnt, rdrand
-            let vme = 0 << 1;
+            // apic id, cpu count, clflush size
+            ebx = (::cpu::smp::smp_current_cpu() as i32) << 24
+                | (::cpu::smp::smp_get_cpu_count() as i32) << 16
+                | 8 << 8;
+            ecx = 1 << 0 | 1 << 3 | 1 << 23 | 1 << 30; // sse3, monitor, popcnt, rdrand
+            let vme = 1 << 1;
             if ::config::VMWARE_HYPERVISOR_PORT {
                 ecx |= 1 << 31
             }; // hypervisor
             edx = (if true /* have fpu */ { 1 } else {  0 }) |      // fpu
                     vme | 1 << 3 | 1 << 4 | 1 << 5 | 1 << 6 |  // vme, pse, tsc, msr, pae
                     1 << 8 | 1 << 11 | 1 << 13 | 1 << 15 | // cx8, sep, pge, cmov
+                    1 << 17 | // pse-36
                     1 << 23 | 1 << 24 | 1 << 25 | 1 << 26; // mmx, fxsr, sse1, sse2
 
             if *acpi_enabled
@@ -3300,18 +3502,23 @@ pub unsafe fn instr_0FA2() {
         7 => {
             if read_reg32(ECX) == 0 {
                 eax = 0; // maximum supported sub-level
-                ebx = 1 << 9; // enhanced REP MOVSB/STOSB
-                ecx = 0;
+                ebx = 1 << 9 | 1 << 10; // enhanced REP MOVSB/STOSB, invpcid
+                ecx = 1 << 2; // umip
                 edx = 0;
             }
         },
 
         0x80000000 => {
             // maximum supported extended level
-            eax = 5;
+            eax = 8;
             // other registers are reserved
         },
 
+        0x80000008 => {
+            // physical and linear address size
+            eax = ::cpu::memory::PHYSICAL_ADDRESS_BITS as i32 | 32 << 8;
+        },
+
         0x40000000 => {
             // hypervisor
             if ::config::VMWARE_HYPERVISOR_PORT {
@@ -3759,51 +3966,56 @@ pub unsafe fn instr_0FC2(source: reg128, r: i32, imm8: i32) {
     // cmpps xmm, xmm/m128
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
+    sse_fp_begin();
     for i in 0..4 {
-        result.i32[i] = if sse_comparison(imm8, destination.f32[i] as f64, source.f32[i] as f64) {
+        result.i32[i] = if sse_comparison_f32(imm8, destination.f32[i], source.f32[i]) {
             -1
         }
         else {
             0
         };
     }
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_0FC2_reg(r1: i32, r2: i32, imm: i32) { instr_0FC2(read_xmm128s(r1), r2, imm); }
 pub unsafe fn instr_0FC2_mem(addr: i32, r: i32, imm: i32) {
-    instr_0FC2(return_on_pagefault!(safe_read128s(addr)), r, imm);
+    instr_0FC2(return_on_pagefault!(safe_read128s_aligned(addr)), r, imm);
 }
 #[no_mangle]
 pub unsafe fn instr_660FC2(source: reg128, r: i32, imm8: i32) {
     // cmppd xmm, xmm/m128
     let destination = read_xmm128s(r);
+    sse_fp_begin();
     let result = reg128 {
         i64: [
-            (if sse_comparison(imm8, destination.f64[0], source.f64[0]) { -1 } else { 0 }) as i64,
-            (if sse_comparison(imm8, destination.f64[1], source.f64[1]) { -1 } else { 0 }) as i64,
+            -(sse_comparison_f64(imm8, destination.f64[0], source.f64[0]) as i64),
+            -(sse_comparison_f64(imm8, destination.f64[1], source.f64[1]) as i64),
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660FC2_reg(r1: i32, r2: i32, imm: i32) {
     instr_660FC2(read_xmm128s(r1), r2, imm);
 }
 pub unsafe fn instr_660FC2_mem(addr: i32, r: i32, imm: i32) {
-    instr_660FC2(return_on_pagefault!(safe_read128s(addr)), r, imm);
+    instr_660FC2(return_on_pagefault!(safe_read128s_aligned(addr)), r, imm);
 }
 #[no_mangle]
 pub unsafe fn instr_F20FC2(source: u64, r: i32, imm8: i32) {
     // cmpsd xmm, xmm/m64
     let destination = read_xmm64s(r);
-    write_xmm64(
-        r,
-        if sse_comparison(imm8, f64::from_bits(destination), f64::from_bits(source)) {
-            (-1i32) as u64
-        }
-        else {
-            0
-        },
-    );
+    sse_fp_begin();
+    let result = sse_comparison_f64(imm8, f64::from_bits(destination), f64::from_bits(source));
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm64(r, if result { (-1i32) as u64 } else { 0 });
 }
 pub unsafe fn instr_F20FC2_reg(r1: i32, r2: i32, imm: i32) {
     instr_F20FC2(read_xmm64s(r1), r2, imm);
@@ -3816,8 +4028,12 @@ pub unsafe fn instr_F30FC2(source: i32, r: i32, imm8: i32) {
     // cmpss xmm, xmm/m32
     let destination = read_xmm_f32(r);
     let source: f32 = std::mem::transmute(source);
-    let result = if sse_comparison(imm8, destination as f64, source as f64) { -1 } else { 0 };
-    write_xmm32(r, result);
+    sse_fp_begin();
+    let result = sse_comparison_f32(imm8, destination, source);
+    if !sse_fp_end() {
+        return;
+    }
+    write_xmm32(r, if result { -1 } else { 0 });
 }
 pub unsafe fn instr_F30FC2_reg(r1: i32, r2: i32, imm: i32) {
     instr_F30FC2(read_xmm64s(r1) as i32, r2, imm);
@@ -3877,7 +4093,6 @@ pub unsafe fn instr_660FC5_reg(r1: i32, r2: i32, imm8: i32) {
 #[no_mangle]
 pub unsafe fn instr_0FC6(source: reg128, r: i32, imm8: i32) {
     // shufps xmm, xmm/mem128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     write_xmm128(
         r,
@@ -3889,7 +4104,7 @@ pub unsafe fn instr_0FC6(source: reg128, r: i32, imm8: i32) {
 }
 pub unsafe fn instr_0FC6_reg(r1: i32, r2: i32, imm: i32) { instr_0FC6(read_xmm128s(r1), r2, imm); }
 pub unsafe fn instr_0FC6_mem(addr: i32, r: i32, imm: i32) {
-    instr_0FC6(return_on_pagefault!(safe_read128s(addr)), r, imm);
+    instr_0FC6(return_on_pagefault!(safe_read128s_aligned(addr)), r, imm);
 }
 
 #[no_mangle]
@@ -3908,7 +4123,7 @@ pub unsafe fn instr_660FC6_reg(r1: i32, r2: i32, imm: i32) {
     instr_660FC6(read_xmm128s(r1), r2, imm);
 }
 pub unsafe fn instr_660FC6_mem(addr: i32, r: i32, imm: i32) {
-    instr_660FC6(return_on_pagefault!(safe_read128s(addr)), r, imm);
+    instr_660FC6(return_on_pagefault!(safe_read128s_aligned(addr)), r, imm);
 }
 
 pub unsafe fn instr16_0FC7_1_reg(_r: i32) { trigger_ud(); }
@@ -3990,12 +4205,11 @@ pub unsafe fn instr_0FD1_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FD1(source: reg128, r: i32) {
     // psrlw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     psrlw_r128(r, source.u64[0]);
 }
 pub unsafe fn instr_660FD1_reg(r1: i32, r2: i32) { instr_660FD1(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FD1_mem(addr: i32, r: i32) {
-    instr_660FD1(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FD1(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FD2(source: u64, r: i32) {
@@ -4009,12 +4223,11 @@ pub unsafe fn instr_0FD2_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FD2(source: reg128, r: i32) {
     // psrld xmm, xmm/m128
-    // XXX: Aligned access or #gp
     psrld_r128(r, source.u64[0]);
 }
 pub unsafe fn instr_660FD2_reg(r1: i32, r2: i32) { instr_660FD2(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FD2_mem(addr: i32, r: i32) {
-    instr_660FD2(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FD2(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FD3(source: u64, r: i32) {
@@ -4032,7 +4245,7 @@ pub unsafe fn instr_660FD3(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FD3_reg(r1: i32, r2: i32) { instr_660FD3(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FD3_mem(addr: i32, r: i32) {
-    instr_660FD3(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FD3(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FD4(source: u64, r: i32) {
@@ -4048,7 +4261,6 @@ pub unsafe fn instr_0FD4_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FD4(source: reg128, r: i32) {
     // paddq xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     result.u64[0] = destination.u64[0] + source.u64[0];
@@ -4057,7 +4269,7 @@ pub unsafe fn instr_660FD4(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FD4_reg(r1: i32, r2: i32) { instr_660FD4(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FD4_mem(addr: i32, r: i32) {
-    instr_660FD4(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FD4(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FD5(source: u64, r: i32) {
@@ -4078,7 +4290,6 @@ pub unsafe fn instr_0FD5_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FD5(source: reg128, r: i32) {
     // pmullw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -4088,7 +4299,7 @@ pub unsafe fn instr_660FD5(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FD5_reg(r1: i32, r2: i32) { instr_660FD5(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FD5_mem(addr: i32, r: i32) {
-    instr_660FD5(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FD5(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 
 #[no_mangle]
@@ -4175,7 +4386,7 @@ pub unsafe fn instr_660FD8(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FD8_reg(r1: i32, r2: i32) { instr_660FD8(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FD8_mem(addr: i32, r: i32) {
-    instr_660FD8(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FD8(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FD9(source: u64, r: i32) {
@@ -4205,7 +4416,7 @@ pub unsafe fn instr_660FD9(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FD9_reg(r1: i32, r2: i32) { instr_660FD9(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FD9_mem(addr: i32, r: i32) {
-    instr_660FD9(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FD9(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FDA(source: u64, r: i32) {
@@ -4226,7 +4437,6 @@ pub unsafe fn instr_0FDA_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FDA(source: reg128, r: i32) {
     // pminub xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { u8: [0; 16] };
     for i in 0..16 {
@@ -4236,7 +4446,7 @@ pub unsafe fn instr_660FDA(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FDA_reg(r1: i32, r2: i32) { instr_660FDA(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FDA_mem(addr: i32, r: i32) {
-    instr_660FDA(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FDA(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FDB(source: u64, r: i32) {
@@ -4252,12 +4462,11 @@ pub unsafe fn instr_0FDB_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FDB(source: reg128, r: i32) {
     // pand xmm, xmm/m128
-    // XXX: Aligned access or #gp
     pand_r128(source, r);
 }
 pub unsafe fn instr_660FDB_reg(r1: i32, r2: i32) { instr_660FDB(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FDB_mem(addr: i32, r: i32) {
-    instr_660FDB(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FDB(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FDC(source: u64, r: i32) {
@@ -4278,7 +4487,6 @@ pub unsafe fn instr_0FDC_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FDC(source: reg128, r: i32) {
     // paddusb xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..16 {
@@ -4288,7 +4496,7 @@ pub unsafe fn instr_660FDC(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FDC_reg(r1: i32, r2: i32) { instr_660FDC(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FDC_mem(addr: i32, r: i32) {
-    instr_660FDC(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FDC(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FDD(source: u64, r: i32) {
@@ -4309,7 +4517,6 @@ pub unsafe fn instr_0FDD_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FDD(source: reg128, r: i32) {
     // paddusw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -4319,7 +4526,7 @@ pub unsafe fn instr_660FDD(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FDD_reg(r1: i32, r2: i32) { instr_660FDD(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FDD_mem(addr: i32, r: i32) {
-    instr_660FDD(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FDD(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FDE(source: u64, r: i32) {
@@ -4340,7 +4547,6 @@ pub unsafe fn instr_0FDE_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FDE(source: reg128, r: i32) {
     // pmaxub xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..16 {
@@ -4350,7 +4556,7 @@ pub unsafe fn instr_660FDE(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FDE_reg(r1: i32, r2: i32) { instr_660FDE(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FDE_mem(addr: i32, r: i32) {
-    instr_660FDE(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FDE(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FDF(source: u64, r: i32) {
@@ -4366,12 +4572,11 @@ pub unsafe fn instr_0FDF_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FDF(source: reg128, r: i32) {
     // pandn xmm, xmm/m128
-    // XXX: Aligned access or #gp
     pandn_r128(source, r);
 }
 pub unsafe fn instr_660FDF_reg(r1: i32, r2: i32) { instr_660FDF(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FDF_mem(addr: i32, r: i32) {
-    instr_660FDF(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FDF(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FE0(source: u64, r: i32) {
@@ -4392,7 +4597,6 @@ pub unsafe fn instr_0FE0_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FE0(source: reg128, r: i32) {
     // pavgb xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..16 {
@@ -4402,7 +4606,7 @@ pub unsafe fn instr_660FE0(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FE0_reg(r1: i32, r2: i32) { instr_660FE0(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FE0_mem(addr: i32, r: i32) {
-    instr_660FE0(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FE0(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FE1(source: u64, r: i32) {
@@ -4416,12 +4620,11 @@ pub unsafe fn instr_0FE1_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FE1(source: reg128, r: i32) {
     // psraw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     psraw_r128(r, source.u64[0]);
 }
 pub unsafe fn instr_660FE1_reg(r1: i32, r2: i32) { instr_660FE1(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FE1_mem(addr: i32, r: i32) {
-    instr_660FE1(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FE1(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FE2(source: u64, r: i32) {
@@ -4435,12 +4638,11 @@ pub unsafe fn instr_0FE2_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FE2(source: reg128, r: i32) {
     // psrad xmm, xmm/m128
-    // XXX: Aligned access or #gp
     psrad_r128(r, source.u64[0]);
 }
 pub unsafe fn instr_660FE2_reg(r1: i32, r2: i32) { instr_660FE2(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FE2_mem(addr: i32, r: i32) {
-    instr_660FE2(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FE2(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FE3(source: u64, r: i32) {
@@ -4461,7 +4663,6 @@ pub unsafe fn instr_0FE3_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FE3(source: reg128, r: i32) {
     // pavgw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let mut destination = read_xmm128s(r);
     for i in 0..8 {
         destination.u16[i] = (destination.u16[i] as i32 + source.u16[i] as i32 + 1 >> 1) as u16;
@@ -4470,7 +4671,7 @@ pub unsafe fn instr_660FE3(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FE3_reg(r1: i32, r2: i32) { instr_660FE3(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FE3_mem(addr: i32, r: i32) {
-    instr_660FE3(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FE3(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FE4(source: u64, r: i32) {
@@ -4491,7 +4692,6 @@ pub unsafe fn instr_0FE4_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FE4(source: reg128, r: i32) {
     // pmulhuw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -4501,7 +4701,7 @@ pub unsafe fn instr_660FE4(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FE4_reg(r1: i32, r2: i32) { instr_660FE4(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FE4_mem(addr: i32, r: i32) {
-    instr_660FE4(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FE4(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FE5(source: u64, r: i32) {
@@ -4522,7 +4722,6 @@ pub unsafe fn instr_0FE5_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FE5(source: reg128, r: i32) {
     // pmulhw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -4532,7 +4731,7 @@ pub unsafe fn instr_660FE5(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FE5_reg(r1: i32, r2: i32) { instr_660FE5(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FE5_mem(addr: i32, r: i32) {
-    instr_660FE5(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FE5(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 
 #[no_mangle]
@@ -4543,6 +4742,7 @@ pub unsafe fn instr_0FE6_reg(_r1: i32, _r2: i32) { trigger_ud(); }
 #[no_mangle]
 pub unsafe fn instr_660FE6(source: reg128, r: i32) {
     // cvttpd2dq xmm1, xmm2/m128
+    sse_fp_begin();
     let result = reg128 {
         i32: [
             sse_convert_with_truncation_f64_to_i32(source.f64[0]),
@@ -4551,29 +4751,35 @@ pub unsafe fn instr_660FE6(source: reg128, r: i32) {
             0,
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_660FE6_mem(addr: i32, r: i32) {
-    instr_660FE6(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FE6(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 pub unsafe fn instr_660FE6_reg(r1: i32, r2: i32) { instr_660FE6(read_xmm128s(r1), r2); }
 
 #[no_mangle]
 pub unsafe fn instr_F20FE6(source: reg128, r: i32) {
     // cvtpd2dq xmm1, xmm2/m128
+    sse_fp_begin();
     let result = reg128 {
         i32: [
-            // XXX: Precision exception
             sse_convert_f64_to_i32(source.f64[0]),
             sse_convert_f64_to_i32(source.f64[1]),
             0,
             0,
         ],
     };
+    if !sse_fp_end() {
+        return;
+    }
     write_xmm_reg128(r, result);
 }
 pub unsafe fn instr_F20FE6_mem(addr: i32, r: i32) {
-    instr_F20FE6(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_F20FE6(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 pub unsafe fn instr_F20FE6_reg(r1: i32, r2: i32) { instr_F20FE6(read_xmm128s(r1), r2); }
 
@@ -4604,7 +4810,7 @@ pub unsafe fn instr_0FE7_reg(_r1: i32, _r2: i32) { trigger_ud(); }
 pub unsafe fn instr_660FE7_reg(_r1: i32, _r2: i32) { trigger_ud(); }
 pub unsafe fn instr_660FE7_mem(addr: i32, r: i32) {
     // movntdq m128, xmm
-    mov_r_m128(addr, r);
+    mov_r_m128_aligned(addr, r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FE8(source: u64, r: i32) {
@@ -4625,7 +4831,6 @@ pub unsafe fn instr_0FE8_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FE8(source: reg128, r: i32) {
     // psubsb xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..16 {
@@ -4635,7 +4840,7 @@ pub unsafe fn instr_660FE8(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FE8_reg(r1: i32, r2: i32) { instr_660FE8(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FE8_mem(addr: i32, r: i32) {
-    instr_660FE8(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FE8(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FE9(source: u64, r: i32) {
@@ -4656,7 +4861,6 @@ pub unsafe fn instr_0FE9_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FE9(source: reg128, r: i32) {
     // psubsw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -4666,7 +4870,7 @@ pub unsafe fn instr_660FE9(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FE9_reg(r1: i32, r2: i32) { instr_660FE9(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FE9_mem(addr: i32, r: i32) {
-    instr_660FE9(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FE9(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FEA(source: u64, r: i32) {
@@ -4687,7 +4891,6 @@ pub unsafe fn instr_0FEA_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FEA(source: reg128, r: i32) {
     // pminsw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -4697,7 +4900,7 @@ pub unsafe fn instr_660FEA(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FEA_reg(r1: i32, r2: i32) { instr_660FEA(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FEA_mem(addr: i32, r: i32) {
-    instr_660FEA(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FEA(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FEB(source: u64, r: i32) {
@@ -4713,12 +4916,11 @@ pub unsafe fn instr_0FEB_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FEB(source: reg128, r: i32) {
     // por xmm, xmm/m128
-    // XXX: Aligned access or #gp
     por_r128(source, r);
 }
 pub unsafe fn instr_660FEB_reg(r1: i32, r2: i32) { instr_660FEB(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FEB_mem(addr: i32, r: i32) {
-    instr_660FEB(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FEB(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FEC(source: u64, r: i32) {
@@ -4739,7 +4941,6 @@ pub unsafe fn instr_0FEC_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FEC(source: reg128, r: i32) {
     // paddsb xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..16 {
@@ -4749,7 +4950,7 @@ pub unsafe fn instr_660FEC(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FEC_reg(r1: i32, r2: i32) { instr_660FEC(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FEC_mem(addr: i32, r: i32) {
-    instr_660FEC(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FEC(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FED(source: u64, r: i32) {
@@ -4770,7 +4971,6 @@ pub unsafe fn instr_0FED_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FED(source: reg128, r: i32) {
     // paddsw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -4780,7 +4980,7 @@ pub unsafe fn instr_660FED(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FED_reg(r1: i32, r2: i32) { instr_660FED(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FED_mem(addr: i32, r: i32) {
-    instr_660FED(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FED(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FEE(source: u64, r: i32) {
@@ -4801,7 +5001,6 @@ pub unsafe fn instr_0FEE_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FEE(source: reg128, r: i32) {
     // pmaxsw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -4811,7 +5010,7 @@ pub unsafe fn instr_660FEE(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FEE_reg(r1: i32, r2: i32) { instr_660FEE(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FEE_mem(addr: i32, r: i32) {
-    instr_660FEE(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FEE(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FEF(source: u64, r: i32) {
@@ -4827,12 +5026,11 @@ pub unsafe fn instr_0FEF_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FEF(source: reg128, r: i32) {
     // pxor xmm, xmm/m128
-    // XXX: Aligned access or #gp
     pxor_r128(source, r);
 }
 pub unsafe fn instr_660FEF_reg(r1: i32, r2: i32) { instr_660FEF(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FEF_mem(addr: i32, r: i32) {
-    instr_660FEF(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FEF(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FF0() { unimplemented_sse(); }
@@ -4848,12 +5046,11 @@ pub unsafe fn instr_0FF1_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FF1(source: reg128, r: i32) {
     // psllw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     psllw_r128(r, source.u64[0]);
 }
 pub unsafe fn instr_660FF1_reg(r1: i32, r2: i32) { instr_660FF1(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FF1_mem(addr: i32, r: i32) {
-    instr_660FF1(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FF1(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FF2(source: u64, r: i32) {
@@ -4867,12 +5064,11 @@ pub unsafe fn instr_0FF2_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FF2(source: reg128, r: i32) {
     // pslld xmm, xmm/m128
-    // XXX: Aligned access or #gp
     pslld_r128(r, source.u64[0]);
 }
 pub unsafe fn instr_660FF2_reg(r1: i32, r2: i32) { instr_660FF2(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FF2_mem(addr: i32, r: i32) {
-    instr_660FF2(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FF2(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FF3(source: u64, r: i32) {
@@ -4886,12 +5082,11 @@ pub unsafe fn instr_0FF3_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FF3(source: reg128, r: i32) {
     // psllq xmm, xmm/m128
-    // XXX: Aligned access or #gp
     psllq_r128(r, source.u64[0]);
 }
 pub unsafe fn instr_660FF3_reg(r1: i32, r2: i32) { instr_660FF3(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FF3_mem(addr: i32, r: i32) {
-    instr_660FF3(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FF3(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FF4(source: u64, r: i32) {
@@ -4907,7 +5102,6 @@ pub unsafe fn instr_0FF4_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FF4(source: reg128, r: i32) {
     // pmuludq xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     result.u64[0] = source.u32[0] as u64 * destination.u32[0] as u64;
@@ -4916,7 +5110,7 @@ pub unsafe fn instr_660FF4(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FF4_reg(r1: i32, r2: i32) { instr_660FF4(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FF4_mem(addr: i32, r: i32) {
-    instr_660FF4(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FF4(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FF5(source: u64, r: i32) {
@@ -4939,7 +5133,6 @@ pub unsafe fn instr_0FF5_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FF5(source: reg128, r: i32) {
     // pmaddwd xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..4 {
@@ -4950,7 +5143,7 @@ pub unsafe fn instr_660FF5(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FF5_reg(r1: i32, r2: i32) { instr_660FF5(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FF5_mem(addr: i32, r: i32) {
-    instr_660FF5(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FF5(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FF6(source: u64, r: i32) {
@@ -4971,7 +5164,6 @@ pub unsafe fn instr_0FF6_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FF6(source: reg128, r: i32) {
     // psadbw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut sum0 = 0;
     let mut sum1 = 0;
@@ -4983,7 +5175,7 @@ pub unsafe fn instr_660FF6(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FF6_reg(r1: i32, r2: i32) { instr_660FF6(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FF6_mem(addr: i32, r: i32) {
-    instr_660FF6(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FF6(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 
 pub unsafe fn instr_0FF7_mem(_addr: i32, _r: i32) { trigger_ud(); }
@@ -5059,7 +5251,6 @@ pub unsafe fn instr_0FF8_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FF8(source: reg128, r: i32) {
     // psubb xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..16 {
@@ -5069,7 +5260,7 @@ pub unsafe fn instr_660FF8(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FF8_reg(r1: i32, r2: i32) { instr_660FF8(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FF8_mem(addr: i32, r: i32) {
-    instr_660FF8(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FF8(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FF9(source: u64, r: i32) {
@@ -5090,7 +5281,6 @@ pub unsafe fn instr_0FF9_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FF9(source: reg128, r: i32) {
     // psubw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -5100,7 +5290,7 @@ pub unsafe fn instr_660FF9(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FF9_reg(r1: i32, r2: i32) { instr_660FF9(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FF9_mem(addr: i32, r: i32) {
-    instr_660FF9(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FF9(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FFA(source: u64, r: i32) {
@@ -5121,7 +5311,6 @@ pub unsafe fn instr_0FFA_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FFA(source: reg128, r: i32) {
     // psubd xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     write_xmm128(
         r,
@@ -5133,7 +5322,7 @@ pub unsafe fn instr_660FFA(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FFA_reg(r1: i32, r2: i32) { instr_660FFA(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FFA_mem(addr: i32, r: i32) {
-    instr_660FFA(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FFA(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FFB(source: u64, r: i32) {
@@ -5148,7 +5337,6 @@ pub unsafe fn instr_0FFB_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FFB(source: reg128, r: i32) {
     // psubq xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let mut destination = read_xmm128s(r);
     destination.u64[0] = destination.u64[0] - source.u64[0];
     destination.u64[1] = destination.u64[1] - source.u64[1];
@@ -5156,7 +5344,7 @@ pub unsafe fn instr_660FFB(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FFB_reg(r1: i32, r2: i32) { instr_660FFB(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FFB_mem(addr: i32, r: i32) {
-    instr_660FFB(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FFB(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FFC(source: u64, r: i32) {
@@ -5177,7 +5365,6 @@ pub unsafe fn instr_0FFC_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FFC(source: reg128, r: i32) {
     // paddb xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..16 {
@@ -5187,7 +5374,7 @@ pub unsafe fn instr_660FFC(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FFC_reg(r1: i32, r2: i32) { instr_660FFC(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FFC_mem(addr: i32, r: i32) {
-    instr_660FFC(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FFC(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FFD(source: u64, r: i32) {
@@ -5208,7 +5395,6 @@ pub unsafe fn instr_0FFD_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FFD(source: reg128, r: i32) {
     // paddw xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     for i in 0..8 {
@@ -5218,7 +5404,7 @@ pub unsafe fn instr_660FFD(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FFD_reg(r1: i32, r2: i32) { instr_660FFD(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FFD_mem(addr: i32, r: i32) {
-    instr_660FFD(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FFD(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FFE(source: u64, r: i32) {
@@ -5239,7 +5425,6 @@ pub unsafe fn instr_0FFE_mem(addr: i32, r: i32) {
 #[no_mangle]
 pub unsafe fn instr_660FFE(source: reg128, r: i32) {
     // paddd xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let dword0 = destination.i32[0] + source.i32[0];
     let dword1 = destination.i32[1] + source.i32[1];
@@ -5249,7 +5434,7 @@ pub unsafe fn instr_660FFE(source: reg128, r: i32) {
 }
 pub unsafe fn instr_660FFE_reg(r1: i32, r2: i32) { instr_660FFE(read_xmm128s(r1), r2); }
 pub unsafe fn instr_660FFE_mem(addr: i32, r: i32) {
-    instr_660FFE(return_on_pagefault!(safe_read128s(addr)), r);
+    instr_660FFE(return_on_pagefault!(safe_read128s_aligned(addr)), r);
 }
 #[no_mangle]
 pub unsafe fn instr_0FFF() {
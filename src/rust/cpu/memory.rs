@@ -15,7 +15,6 @@ mod ext {
 use cpu::cpu::reg128;
 use cpu::global_pointers::memory_size;
 use cpu::vga;
-use page::Page;
 
 use std::alloc;
 use std::ptr;
@@ -28,13 +27,38 @@ pub fn allocate_memory(size: u32) -> u32 {
     unsafe {
         dbg_assert!(mem8.is_null());
     };
+    dbg_assert!(size <= MAX_MEMORY_SIZE);
     dbg_log!("Allocate memory size={}m", size >> 20);
-    let layout = alloc::Layout::from_size_align(size as usize, 0x1000).unwrap();
-    let ptr = unsafe { alloc::alloc(layout) as u32 };
+    let ptr = allocate_pages(size);
+    if ptr.is_null() {
+        // the wasm memory couldn't grow (browsers limit it to 4 GiB or less)
+        dbg_log!("Allocating memory failed");
+        return 0;
+    }
     unsafe {
-        mem8 = ptr as *mut u8;
+        mem8 = ptr;
     };
-    ptr
+    ptr as u32
+}
+
+// std::alloc is limited to allocations of isize::MAX bytes (2 GiB on wasm32), so the wasm memory
+// is grown directly instead. The allocator only uses the parts of the memory that it has grown
+#[cfg(target_arch = "wasm32")]
+fn allocate_pages(size: u32) -> *mut u8 {
+    let pages = (size as usize + 0xFFFF) >> 16;
+    let previous_pages = std::arch::wasm32::memory_grow(0, pages);
+    if previous_pages == usize::MAX {
+        ptr::null_mut()
+    }
+    else {
+        (previous_pages << 16) as *mut u8
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn allocate_pages(size: u32) -> *mut u8 {
+    let layout = alloc::Layout::from_size_align(size as usize, 0x1000).unwrap();
+    unsafe { alloc::alloc(layout) }
 }
 
 #[no_mangle]
@@ -74,6 +98,44 @@ pub fn in_svga_lfb(addr: u32) -> bool {
     addr >= VGA_LFB_ADDRESS && addr <= unsafe { VGA_LFB_ADDRESS + (vga_memory_size - 1) }
 }
 
+pub const PHYSICAL_ADDRESS_BITS: u32 = 36;
+
+// Ram beyond HIGH_MEMORY_REMAP_START is visible to the guest at HIGH_MEMORY_ADDRESS (above 4 GiB),
+// leaving the range below VGA_LFB_ADDRESS as a hole. In mem8 it's stored contiguously after the
+// low part, so addresses passed to read8 and friends still fit into 32 bits
+pub const HIGH_MEMORY_REMAP_START: u32 = 0xC0000000;
+pub const HIGH_MEMORY_ADDRESS: u64 = 1 << 32;
+
+// Must not overlap with memory-mapped devices (see in_mapped_range)
+pub const MAX_MEMORY_SIZE: u32 = VGA_LFB_ADDRESS - 0x20000;
+
+// Physical addresses that aren't backed by ram are redirected here, where the handlers for
+// unmapped memory are registered
+const UNBACKED_PAGE: u32 = MAX_MEMORY_SIZE;
+
+/// Translate a guest physical address (up to PHYSICAL_ADDRESS_BITS wide) into the 32-bit address
+/// used for accessing mem8 and the mmap handlers
+pub fn map_physical_address(addr: u64) -> u32 {
+    dbg_assert!(addr >> PHYSICAL_ADDRESS_BITS == 0);
+    let size = unsafe { *memory_size };
+    if addr >= HIGH_MEMORY_ADDRESS {
+        let offset = addr - HIGH_MEMORY_ADDRESS + HIGH_MEMORY_REMAP_START as u64;
+        if offset < size as u64 {
+            offset as u32
+        }
+        else {
+            UNBACKED_PAGE | addr as u32 & 0xFFF
+        }
+    }
+    else if addr as u32 >= HIGH_MEMORY_REMAP_START && (addr as u32) < size {
+        // the part of ram that has been moved above 4 GiB
+        UNBACKED_PAGE | addr as u32 & 0xFFF
+    }
+    else {
+        addr as u32
+    }
+}
+
 #[no_mangle]
 pub fn read8(addr: u32) -> i32 {
     if in_mapped_range(addr) {
@@ -88,7 +150,7 @@ pub fn read8(addr: u32) -> i32 {
         read8_no_mmap_check(addr)
     }
 }
-pub fn read8_no_mmap_check(addr: u32) -> i32 { unsafe { *mem8.offset(addr as isize) as i32 } }
+pub fn read8_no_mmap_check(addr: u32) -> i32 { unsafe { *mem8.add(addr as usize) as i32 } }
 
 #[no_mangle]
 pub fn read16(addr: u32) -> i32 {
@@ -108,7 +170,7 @@ pub fn read16(addr: u32) -> i32 {
     }
 }
 pub fn read16_no_mmap_check(addr: u32) -> i32 {
-    unsafe { ptr::read_unaligned(mem8.offset(addr as isize) as *const u16) as i32 }
+    unsafe { ptr::read_unaligned(mem8.add(addr as usize) as *const u16) as i32 }
 }
 
 #[no_mangle]
@@ -128,7 +190,7 @@ pub fn read32s(addr: u32) -> i32 {
     }
 }
 pub fn read32_no_mmap_check(addr: u32) -> i32 {
-    unsafe { ptr::read_unaligned(mem8.offset(addr as isize) as *const i32) }
+    unsafe { ptr::read_unaligned(mem8.add(addr as usize) as *const i32) }
 }
 
 pub unsafe fn read64s(addr: u32) -> i64 {
@@ -141,7 +203,7 @@ pub unsafe fn read64s(addr: u32) -> i64 {
         }
     }
     else {
-        ptr::read_unaligned(mem8.offset(addr as isize) as *const i64)
+        ptr::read_unaligned(mem8.add(addr as usize) as *const i64)
     }
 }
 
@@ -162,7 +224,7 @@ pub unsafe fn read128(addr: u32) -> reg128 {
         }
     }
     else {
-        ptr::read_unaligned(mem8.offset(addr as isize) as *const reg128)
+        ptr::read_unaligned(mem8.add(addr as usize) as *const reg128)
     }
 }
 
@@ -172,13 +234,13 @@ pub unsafe fn write8(addr: u32, value: i32) {
         mmap_write8(addr, value & 0xFF);
     }
     else {
-        ::jit::jit_dirty_page(::jit::get_jit_state(), Page::page_of(addr));
+        ::jit::jit_dirty_write(::jit::get_jit_state(), addr, &[value as u8]);
         write8_no_mmap_or_dirty_check(addr, value);
     };
 }
 
 pub unsafe fn write8_no_mmap_or_dirty_check(addr: u32, value: i32) {
-    *mem8.offset(addr as isize) = value as u8
+    *mem8.add(addr as usize) = value as u8
 }
 
 #[no_mangle]
@@ -187,12 +249,12 @@ pub unsafe fn write16(addr: u32, value: i32) {
         mmap_write16(addr, value & 0xFFFF);
     }
     else {
-        ::jit::jit_dirty_cache_small(addr, addr + 2);
+        ::jit::jit_dirty_write(::jit::get_jit_state(), addr, &(value as u16).to_le_bytes());
         write16_no_mmap_or_dirty_check(addr, value);
     };
 }
 pub unsafe fn write16_no_mmap_or_dirty_check(addr: u32, value: i32) {
-    ptr::write_unaligned(mem8.offset(addr as isize) as *mut u16, value as u16)
+    ptr::write_unaligned(mem8.add(addr as usize) as *mut u16, value as u16)
 }
 
 #[no_mangle]
@@ -201,33 +263,33 @@ pub unsafe fn write32(addr: u32, value: i32) {
         mmap_write32(addr, value);
     }
     else {
-        ::jit::jit_dirty_cache_small(addr, addr + 4);
+        ::jit::jit_dirty_write(::jit::get_jit_state(), addr, &value.to_le_bytes());
         write32_no_mmap_or_dirty_check(addr, value);
     };
 }
 
 pub unsafe fn write32_no_mmap_or_dirty_check(addr: u32, value: i32) {
-    ptr::write_unaligned(mem8.offset(addr as isize) as *mut i32, value)
+    ptr::write_unaligned(mem8.add(addr as usize) as *mut i32, value)
 }
 
 pub unsafe fn write64_no_mmap_or_dirty_check(addr: u32, value: u64) {
-    ptr::write_unaligned(mem8.offset(addr as isize) as *mut u64, value)
+    ptr::write_unaligned(mem8.add(addr as usize) as *mut u64, value)
 }
 
 pub unsafe fn write128_no_mmap_or_dirty_check(addr: u32, value: reg128) {
-    ptr::write_unaligned(mem8.offset(addr as isize) as *mut reg128, value)
+    ptr::write_unaligned(mem8.add(addr as usize) as *mut reg128, value)
 }
 
 pub unsafe fn memset_no_mmap_or_dirty_check(addr: u32, value: u8, count: u32) {
-    ptr::write_bytes(mem8.offset(addr as isize), value, count as usize);
+    ptr::write_bytes(mem8.add(addr as usize), value, count as usize);
 }
 
 pub unsafe fn memcpy_no_mmap_or_dirty_check(src_addr: u32, dst_addr: u32, count: u32) {
     dbg_assert!(src_addr < *memory_size);
     dbg_assert!(dst_addr < *memory_size);
     ptr::copy(
-        mem8.offset(src_addr as isize),
-        mem8.offset(dst_addr as isize),
+        mem8.add(src_addr as usize),
+        mem8.add(dst_addr as usize),
         count as usize,
     )
 }
@@ -236,7 +298,7 @@ pub unsafe fn memcpy_into_svga_lfb(src_addr: u32, dst_addr: u32, count: u32) {
     dbg_assert!(src_addr < *memory_size);
     dbg_assert!(in_svga_lfb(dst_addr));
     ptr::copy_nonoverlapping(
-        mem8.offset(src_addr as isize),
+        mem8.add(src_addr as usize),
         vga_mem8.offset((dst_addr - VGA_LFB_ADDRESS) as isize),
         count as usize,
     )
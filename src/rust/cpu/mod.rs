@@ -9,6 +9,7 @@ pub mod memory;
 pub mod misc_instr;
 pub mod modrm;
 pub mod pic;
+pub mod smp;
 pub mod sse_instr;
 pub mod string;
 pub mod vga;
@@ -0,0 +1,360 @@
+#![allow(non_upper_case_globals)]
+
+// Symmetric multiprocessing
+//
+// Additional cpus are time-sliced on the host thread. The architectural state of the cpu that is
+// currently running lives at the fixed addresses in global_pointers (generated code accesses it
+// through constant offsets), the state of the other cpus is swapped out into a CpuSlot. Since
+// compiled code only refers to these addresses, the jit cache (keyed by physical address and
+// state flags) is shared between all cpus.
+//
+// Interrupts for a cpu that isn't running are collected in its local apic (see apic.js) and
+// handled when the cpu is switched in. The same goes for INIT and SIPI messages.
+
+use cpu::cpu::{
+    a20_enabled, do_many_cycles_native, full_clear_tlb, get_seg_cs, handle_irqs, in_mwait,
+    jit_block_boundary, monitor_address, reset_cpu, restore_tlb_entry, run_hardware_timers,
+    switch_cs_real_mode, tlb_data, tsc_offset, valid_tlb_entries, valid_tlb_entries_count,
+    FLAG_INTERRUPT, TIME_PER_FRAME,
+};
+use cpu::cpu::microtick;
+use cpu::fpu::fpu_ferr_irq_raised;
+use cpu::sse_instr::sse_exception_pending;
+use cpu::global_pointers::*;
+use page::Page;
+use profiler;
+use profiler::stat::SMP_CPU_SWITCH;
+
+use std::ptr;
+
+pub const MAX_CPUS: u32 = 16;
+
+// The part of the memory layout in global_pointers that belongs to one cpu, from reg32 to the end
+// of fpu_st
+const STATE_START: u32 = 64;
+const STATE_END: u32 = 1152 + 8 * 16;
+const STATE_SIZE: usize = (STATE_END - STATE_START) as usize;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum RunState {
+    WaitForSipi,
+    Running,
+}
+
+struct CpuSlot {
+    state: [u8; STATE_SIZE],
+    // (virtual page, entry) pairs of the tlb while the cpu is switched out
+    tlb_entries: Vec<(i32, i32)>,
+    monitor_address: Option<u32>,
+    in_mwait: bool,
+    // per-cpu state outside of the swapped part of global_pointers
+    sse_exception_pending: bool,
+    fpu_ferr_irq_raised: bool,
+    jit_block_boundary: bool,
+    run_state: RunState,
+    pending_init: bool,
+    pending_startup: Option<u8>,
+}
+
+impl CpuSlot {
+    fn new(run_state: RunState) -> CpuSlot {
+        CpuSlot {
+            state: [0; STATE_SIZE],
+            tlb_entries: Vec::new(),
+            monitor_address: None,
+            in_mwait: false,
+            sse_exception_pending: false,
+            fpu_ferr_irq_raised: false,
+            jit_block_boundary: false,
+            run_state,
+            pending_init: false,
+            pending_startup: None,
+        }
+    }
+}
+
+static mut cpu_count: u32 = 1;
+static mut current_cpu: u32 = 0;
+static mut slots: Vec<CpuSlot> = Vec::new();
+
+fn get_slots() -> &'static mut Vec<CpuSlot> { unsafe { &mut *ptr::addr_of_mut!(slots) } }
+
+#[no_mangle]
+pub fn smp_get_cpu_count() -> u32 { unsafe { cpu_count } }
+
+#[no_mangle]
+pub fn smp_current_cpu() -> u32 { unsafe { current_cpu } }
+
+pub fn is_bootstrap_processor() -> bool { unsafe { current_cpu == 0 } }
+
+/// Set the number of cpus. Must be called after reset_cpu, while the bootstrap processor is
+/// switched in. The application processors start out waiting for a startup ipi
+#[no_mangle]
+pub fn smp_set_cpu_count(count: u32) {
+    unsafe {
+        dbg_assert!((1..=MAX_CPUS).contains(&count));
+        dbg_assert!(current_cpu == 0);
+        cpu_count = count;
+        get_slots().clear();
+        for i in 0..count {
+            let run_state = if i == 0 { RunState::Running } else { RunState::WaitForSipi };
+            let mut slot = CpuSlot::new(run_state);
+            // the bootstrap processor has just been reset, so this is the reset state
+            save_state(&mut slot);
+            get_slots().push(slot);
+        }
+    }
+}
+
+/// Called after the current cpu has been reset as part of a system reset: All other cpus are reset
+/// too, and only the bootstrap processor keeps running
+#[no_mangle]
+pub fn smp_reset() {
+    unsafe {
+        for i in 0..cpu_count {
+            let slot = &mut get_slots()[i as usize];
+            slot.pending_startup = None;
+            if i == current_cpu {
+                slot.pending_init = false;
+                if i != 0 {
+                    slot.run_state = RunState::WaitForSipi;
+                    // stop executing at the end of the current instruction
+                    *in_hlt = true;
+                }
+            }
+            else {
+                slot.pending_init = true;
+            }
+        }
+    }
+}
+
+#[no_mangle]
+pub fn smp_send_init(cpu: u32) {
+    unsafe {
+        dbg_assert!(cpu < cpu_count);
+        dbg_log!("smp: INIT to cpu {}", cpu);
+        let slot = &mut get_slots()[cpu as usize];
+        slot.pending_init = true;
+        slot.pending_startup = None;
+    }
+}
+
+#[no_mangle]
+pub fn smp_send_startup(cpu: u32, vector: u8) {
+    unsafe {
+        dbg_assert!(cpu < cpu_count);
+        dbg_log!("smp: SIPI to cpu {} vector={:x}", cpu, vector);
+        get_slots()[cpu as usize].pending_startup = Some(vector);
+    }
+}
+
+/// Pointer to the saved state of a cpu that is switched out, for saving and restoring state
+#[no_mangle]
+pub fn smp_get_cpu_state(cpu: u32) -> u32 {
+    unsafe {
+        dbg_assert!(cpu != current_cpu && cpu < cpu_count);
+        get_slots()[cpu as usize].state.as_ptr() as u32
+    }
+}
+
+#[no_mangle]
+pub fn smp_get_cpu_state_size() -> u32 { STATE_SIZE as u32 }
+
+#[no_mangle]
+pub fn smp_is_running(cpu: u32) -> bool {
+    unsafe {
+        dbg_assert!(cpu < cpu_count);
+        get_slots()[cpu as usize].run_state == RunState::Running
+    }
+}
+
+/// Called after the state of a switched out cpu has been written through smp_get_cpu_state
+#[no_mangle]
+pub fn smp_set_running(cpu: u32, running: bool) {
+    unsafe {
+        dbg_assert!(cpu != current_cpu && cpu < cpu_count);
+        let slot = &mut get_slots()[cpu as usize];
+        slot.run_state = if running { RunState::Running } else { RunState::WaitForSipi };
+        slot.tlb_entries.clear();
+        slot.monitor_address = None;
+        slot.in_mwait = false;
+        slot.sse_exception_pending = false;
+        slot.fpu_ferr_irq_raised = false;
+        slot.jit_block_boundary = false;
+        slot.pending_init = false;
+        slot.pending_startup = None;
+    }
+}
+
+fn monitors_page(slot: &CpuSlot, page: Page) -> bool {
+    match slot.monitor_address {
+        Some(addr) => Page::page_of(addr) == page,
+        None => false,
+    }
+}
+
+/// Whether a cpu that is switched out has armed monitor on the physical page (the monitor of the
+/// current cpu is in cpu::monitor_address)
+pub fn is_monitored_by_other_cpu(page: Page) -> bool {
+    let current = unsafe { current_cpu } as usize;
+    get_slots()
+        .iter()
+        .enumerate()
+        .any(|(cpu, slot)| cpu != current && monitors_page(slot, page))
+}
+
+/// Disarm the monitors of the switched out cpus on the physical page and wake them up from
+/// mwait. Returns whether any of them had armed monitor on it
+pub fn monitor_write_hook_other_cpus(page: Page) -> bool {
+    let current = unsafe { current_cpu } as usize;
+    let mut was_monitored = false;
+    for (cpu, slot) in get_slots().iter_mut().enumerate() {
+        if cpu != current && monitors_page(slot, page) {
+            slot.monitor_address = None;
+            if slot.in_mwait {
+                slot.in_mwait = false;
+                slot.state[in_hlt as usize - STATE_START as usize] = 0;
+            }
+            was_monitored = true;
+        }
+    }
+    was_monitored
+}
+
+unsafe fn save_state(slot: &mut CpuSlot) {
+    ptr::copy_nonoverlapping(STATE_START as *const u8, slot.state.as_mut_ptr(), STATE_SIZE);
+}
+
+unsafe fn load_state(slot: &CpuSlot) {
+    // These live in the same area, but are shared between cpus
+    let shared = (
+        *apic_enabled,
+        *acpi_enabled,
+        *memory_size,
+        *svga_dirty_bitmap_min_offset,
+        *svga_dirty_bitmap_max_offset,
+    );
+    ptr::copy_nonoverlapping(slot.state.as_ptr(), STATE_START as *mut u8, STATE_SIZE);
+    *apic_enabled = shared.0;
+    *acpi_enabled = shared.1;
+    *memory_size = shared.2;
+    *svga_dirty_bitmap_min_offset = shared.3;
+    *svga_dirty_bitmap_max_offset = shared.4;
+}
+
+unsafe fn switch_to(cpu: u32) {
+    if cpu == current_cpu {
+        return;
+    }
+    profiler::stat_increment(SMP_CPU_SWITCH);
+
+    let old = &mut get_slots()[current_cpu as usize];
+    save_state(old);
+    old.tlb_entries.clear();
+    for i in 0..valid_tlb_entries_count {
+        let page = valid_tlb_entries[i as usize];
+        let entry = tlb_data[page as usize];
+        // valid_tlb_entries may contain duplicates and invalidated pages, clearing the entry here
+        // skips them (full_clear_tlb below takes care of the rest)
+        if entry != 0 {
+            old.tlb_entries.push((page, entry));
+            tlb_data[page as usize] = 0;
+        }
+    }
+    old.monitor_address = monitor_address;
+    old.in_mwait = in_mwait;
+    old.sse_exception_pending = sse_exception_pending;
+    old.fpu_ferr_irq_raised = fpu_ferr_irq_raised;
+    old.jit_block_boundary = jit_block_boundary;
+
+    full_clear_tlb();
+
+    current_cpu = cpu;
+    let new = &get_slots()[current_cpu as usize];
+    load_state(new);
+    for &(page, entry) in new.tlb_entries.iter() {
+        restore_tlb_entry(page, entry);
+    }
+    monitor_address = new.monitor_address;
+    in_mwait = new.in_mwait;
+    sse_exception_pending = new.sse_exception_pending;
+    fpu_ferr_irq_raised = new.fpu_ferr_irq_raised;
+    jit_block_boundary = new.jit_block_boundary;
+    *last_virt_eip = -1;
+}
+
+unsafe fn handle_pending_ipis() {
+    let slot = &mut get_slots()[current_cpu as usize];
+
+    if slot.pending_init {
+        slot.pending_init = false;
+        // the a20 gate isn't part of the cpu, and INIT doesn't reset the time stamp counter (which
+        // is shared between all cpus)
+        let a20 = a20_enabled;
+        let tsc = tsc_offset;
+        reset_cpu();
+        a20_enabled = a20;
+        tsc_offset = tsc;
+        monitor_address = None;
+        in_mwait = false;
+        // the bootstrap processor restarts at the reset vector
+        slot.run_state = if current_cpu == 0 { RunState::Running } else { RunState::WaitForSipi };
+    }
+
+    if let Some(vector) = slot.pending_startup.take() {
+        if slot.run_state == RunState::WaitForSipi {
+            slot.run_state = RunState::Running;
+            switch_cs_real_mode((vector as i32) << 8);
+            *instruction_pointer = get_seg_cs();
+            *in_hlt = false;
+        }
+    }
+}
+
+/// Like cpu::main_loop, but runs all cpus in turn. Returns with the bootstrap processor switched
+/// in, so that state can be saved between calls
+pub fn main_loop(start: f64) -> f64 {
+    unsafe {
+        let mut t;
+
+        loop {
+            let mut any_running = false;
+
+            for cpu in 0..cpu_count {
+                switch_to(cpu);
+                handle_pending_ipis();
+
+                if get_slots()[cpu as usize].run_state != RunState::Running {
+                    continue;
+                }
+
+                // Interrupts may have arrived while the cpu was switched out
+                if *flags & FLAG_INTERRUPT != 0 {
+                    handle_irqs();
+                }
+                if *in_hlt {
+                    continue;
+                }
+
+                do_many_cycles_native();
+                any_running = true;
+            }
+
+            let now = microtick();
+            t = run_hardware_timers(*acpi_enabled, now);
+
+            if !any_running {
+                break;
+            }
+
+            if now - start > TIME_PER_FRAME {
+                t = 0.0;
+                break;
+            }
+        }
+
+        switch_to(0);
+        t
+    }
+}
@@ -1,5 +1,9 @@
+#![allow(non_upper_case_globals)]
+
 use cpu::cpu::*;
-use cpu::global_pointers::mxcsr;
+use cpu::global_pointers::*;
+use softfloat;
+use softfloat::F80;
 
 pub unsafe fn mov_r_m64(addr: i32, r: i32) {
     // mov* m64, mm
@@ -22,6 +26,11 @@ pub unsafe fn mov_r_m128(addr: i32, r: i32) {
     let data = read_xmm128s(r);
     return_on_pagefault!(safe_write128(addr, data));
 }
+pub unsafe fn mov_r_m128_aligned(addr: i32, r: i32) {
+    // movaps/movnt* m128, xmm
+    let data = read_xmm128s(r);
+    return_on_pagefault!(safe_write128_aligned(addr, data));
+}
 pub unsafe fn mov_rm_r128(source: reg128, r: i32) {
     // mov* xmm, xmm/m128
     write_xmm_reg128(r, source);
@@ -34,7 +43,6 @@ pub unsafe fn movh_r128_m64(addr: i32, r: i32) {
 
 pub unsafe fn pand_r128(source: reg128, r: i32) {
     // pand xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     result.u64[0] = source.u64[0] & destination.u64[0];
@@ -43,7 +51,6 @@ pub unsafe fn pand_r128(source: reg128, r: i32) {
 }
 pub unsafe fn pandn_r128(source: reg128, r: i32) {
     // pandn xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     result.u64[0] = source.u64[0] & !destination.u64[0];
@@ -52,7 +59,6 @@ pub unsafe fn pandn_r128(source: reg128, r: i32) {
 }
 pub unsafe fn pxor_r128(source: reg128, r: i32) {
     // pxor xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     result.u64[0] = source.u64[0] ^ destination.u64[0];
@@ -61,7 +67,6 @@ pub unsafe fn pxor_r128(source: reg128, r: i32) {
 }
 pub unsafe fn por_r128(source: reg128, r: i32) {
     // por xmm, xmm/m128
-    // XXX: Aligned access or #gp
     let destination = read_xmm128s(r);
     let mut result = reg128 { i8: [0; 16] };
     result.u64[0] = source.u64[0] | destination.u64[0];
@@ -267,95 +272,271 @@ pub unsafe fn psllq_r128(r: i32, shift: u64) {
     write_xmm_reg128(r, result);
 }
 
-pub unsafe fn sse_comparison(op: i32, x: f64, y: f64) -> bool {
-    // TODO: Signaling
-    match op & 7 {
-        0 => return x == y,
-        1 => return x < y,
-        2 => return x <= y,
-        3 => return x.is_nan() || y.is_nan(),
-        4 => return x != y || x.is_nan() || y.is_nan(),
-        5 => return x >= y || x.is_nan() || y.is_nan(),
-        6 => return x > y || x.is_nan() || y.is_nan(),
-        7 => return !x.is_nan() && !y.is_nan(),
-        _ => {
-            dbg_assert!(false);
-            return false;
-        },
-    };
+// Floating point operations of the sse instructions. An instruction brackets its operations with
+// sse_fp_begin and sse_fp_end, in between they round according to MXCSR, honour denormals-are-zero
+// and flush-to-zero, and accumulate the exception flags
+
+/// Set when an instruction detected an unmasked exception. It returns without writing its
+/// destination, the exception is raised afterwards by sse_exception_test (interpreter) or
+/// codegen::gen_sse_exception_test (jit)
+pub static mut sse_exception_pending: bool = false;
+
+static mut sse_fp_flags: i32 = 0;
+static mut sse_fp_saved_rounding_mode: u8 = 0;
+
+unsafe fn sse_rounding_mode() -> u8 {
+    match *mxcsr >> MXCSR_RC_SHIFT & 3 {
+        0 => 0,
+        1 => 2,
+        2 => 3,
+        _ => 1,
+    }
 }
-pub unsafe fn sse_min(x: f64, y: f64) -> f64 {
-    // if both x and y are 0 or x is nan, y is returned
-    return if x < y { x } else { y };
+
+pub unsafe fn sse_fp_begin() {
+    sse_fp_saved_rounding_mode = softfloat::swap_rounding_mode(sse_rounding_mode());
+    F80::clear_exception_flags();
+    sse_fp_flags = 0;
 }
-pub unsafe fn sse_max(x: f64, y: f64) -> f64 {
-    // if both x and y are 0 or x is nan, y is returned
-    return if x > y { x } else { y };
+
+/// Record the exceptions of the current instruction in MXCSR. Returns false if one of them is
+/// unmasked, in which case the destination must be left unchanged
+pub unsafe fn sse_fp_end() -> bool {
+    softfloat::swap_rounding_mode(sse_fp_saved_rounding_mode);
+
+    // softfloat's flags are translated to the x87 layout, which MXCSR shares
+    let mut exceptions = sse_fp_flags | F80::get_exception_flags() as i32;
+    let unmasked = exceptions & !(*mxcsr >> MXCSR_EXCEPTION_MASK_SHIFT);
+
+    // invalid operand, denormal operand and divide by zero are detected before the result is
+    // computed: When one of them is unmasked, overflow, underflow and precision aren't reported
+    let pre_computation = MXCSR_IE | MXCSR_DE | MXCSR_ZE;
+    if unmasked & pre_computation != 0 {
+        exceptions &= pre_computation;
+    }
+
+    *mxcsr |= exceptions;
+
+    if unmasked & exceptions != 0 {
+        sse_exception_pending = true;
+        return false;
+    }
+    return true;
 }
 
-#[no_mangle]
-pub unsafe fn sse_convert_with_truncation_f32_to_i32(x: f32) -> i32 {
-    let x = x.trunc();
-    if x >= -2147483648.0 && x < 2147483648.0 {
-        return x as i64 as i32;
+pub unsafe fn sse_exception_test() {
+    if sse_exception_pending {
+        trigger_xm();
     }
-    else {
-        // TODO: Signal
-        return -0x80000000;
-    };
 }
-#[no_mangle]
-pub unsafe fn sse_convert_f32_to_i32(x: f32) -> i32 {
-    let x = sse_integer_round(x as f64);
-    if x >= -2147483648.0 && x < 2147483648.0 {
-        return x as i64 as i32;
+
+unsafe fn sse_operand_f32(x: f32) -> u32 {
+    let x = x.to_bits();
+    if x & 0x7F80_0000 == 0 && x & 0x7F_FFFF != 0 {
+        if *mxcsr & MXCSR_DAZ != 0 {
+            return x & 0x8000_0000;
+        }
+        sse_fp_flags |= MXCSR_DE;
     }
-    else {
-        // TODO: Signal
-        return -0x80000000;
-    };
+    x
+}
+unsafe fn sse_operand_f64(x: f64) -> u64 {
+    let x = x.to_bits();
+    if x & 0x7FF0_0000_0000_0000 == 0 && x & 0xF_FFFF_FFFF_FFFF != 0 {
+        if *mxcsr & MXCSR_DAZ != 0 {
+            return x & 0x8000_0000_0000_0000;
+        }
+        sse_fp_flags |= MXCSR_DE;
+    }
+    x
 }
 
-#[no_mangle]
-pub unsafe fn sse_convert_with_truncation_f64_to_i32(x: f64) -> i32 {
-    let x = x.trunc();
-    if x >= -2147483648.0 && x < 2147483648.0 {
-        return x as i64 as i32;
+unsafe fn sse_flush_to_zero() -> bool {
+    *mxcsr & MXCSR_FZ != 0 && *mxcsr & MXCSR_UE << MXCSR_EXCEPTION_MASK_SHIFT != 0
+}
+unsafe fn sse_result_f32(x: u32) -> f32 {
+    if x & 0x7F80_0000 == 0 && x & 0x7F_FFFF != 0 && sse_flush_to_zero() {
+        sse_fp_flags |= MXCSR_UE | MXCSR_PE;
+        return f32::from_bits(x & 0x8000_0000);
     }
-    else {
-        // TODO: Signal
-        return -0x80000000;
-    };
+    f32::from_bits(x)
 }
-#[no_mangle]
-pub unsafe fn sse_convert_f64_to_i32(x: f64) -> i32 {
-    let x = sse_integer_round(x);
-    if x >= -2147483648.0 && x < 2147483648.0 {
-        return x as i64 as i32;
+unsafe fn sse_result_f64(x: u64) -> f64 {
+    if x & 0x7FF0_0000_0000_0000 == 0 && x & 0xF_FFFF_FFFF_FFFF != 0 && sse_flush_to_zero() {
+        sse_fp_flags |= MXCSR_UE | MXCSR_PE;
+        return f64::from_bits(x & 0x8000_0000_0000_0000);
     }
-    else {
-        // TODO: Signal
-        return -0x80000000;
-    };
+    f64::from_bits(x)
 }
 
-pub unsafe fn sse_integer_round(f: f64) -> f64 {
-    // see fpu_integer_round
-    let rc = *mxcsr >> MXCSR_RC_SHIFT & 3;
-    if rc == 0 {
-        // Round to nearest, or even if equidistant
-        let mut rounded = f.round();
-        let diff = rounded - f;
-        if diff == 0.5 || diff == -0.5 {
-            rounded = 2.0 * (f * 0.5).round()
-        }
-        return rounded;
+pub unsafe fn sse_add_f32(x: f32, y: f32) -> f32 {
+    sse_result_f32(softfloat::f32_add(sse_operand_f32(x), sse_operand_f32(y)))
+}
+pub unsafe fn sse_sub_f32(x: f32, y: f32) -> f32 {
+    sse_result_f32(softfloat::f32_sub(sse_operand_f32(x), sse_operand_f32(y)))
+}
+pub unsafe fn sse_mul_f32(x: f32, y: f32) -> f32 {
+    sse_result_f32(softfloat::f32_mul(sse_operand_f32(x), sse_operand_f32(y)))
+}
+pub unsafe fn sse_div_f32(x: f32, y: f32) -> f32 {
+    sse_result_f32(softfloat::f32_div(sse_operand_f32(x), sse_operand_f32(y)))
+}
+pub unsafe fn sse_sqrt_f32(x: f32) -> f32 {
+    sse_result_f32(softfloat::f32_sqrt(sse_operand_f32(x)))
+}
+
+pub unsafe fn sse_add_f64(x: f64, y: f64) -> f64 {
+    sse_result_f64(softfloat::f64_add(sse_operand_f64(x), sse_operand_f64(y)))
+}
+pub unsafe fn sse_sub_f64(x: f64, y: f64) -> f64 {
+    sse_result_f64(softfloat::f64_sub(sse_operand_f64(x), sse_operand_f64(y)))
+}
+pub unsafe fn sse_mul_f64(x: f64, y: f64) -> f64 {
+    sse_result_f64(softfloat::f64_mul(sse_operand_f64(x), sse_operand_f64(y)))
+}
+pub unsafe fn sse_div_f64(x: f64, y: f64) -> f64 {
+    sse_result_f64(softfloat::f64_div(sse_operand_f64(x), sse_operand_f64(y)))
+}
+pub unsafe fn sse_sqrt_f64(x: f64) -> f64 {
+    sse_result_f64(softfloat::f64_sqrt(sse_operand_f64(x)))
+}
+
+// min and max return the second operand if either is a nan (raising invalid even for quiet nans)
+// or if both are zero
+pub unsafe fn sse_min_f32(x: f32, y: f32) -> f32 {
+    let (x, y) = (sse_operand_f32(x), sse_operand_f32(y));
+    f32::from_bits(if softfloat::f32_lt(x, y) { x } else { y })
+}
+pub unsafe fn sse_max_f32(x: f32, y: f32) -> f32 {
+    let (x, y) = (sse_operand_f32(x), sse_operand_f32(y));
+    f32::from_bits(if softfloat::f32_lt(y, x) { x } else { y })
+}
+pub unsafe fn sse_min_f64(x: f64, y: f64) -> f64 {
+    let (x, y) = (sse_operand_f64(x), sse_operand_f64(y));
+    f64::from_bits(if softfloat::f64_lt(x, y) { x } else { y })
+}
+pub unsafe fn sse_max_f64(x: f64, y: f64) -> f64 {
+    let (x, y) = (sse_operand_f64(x), sse_operand_f64(y));
+    f64::from_bits(if softfloat::f64_lt(y, x) { x } else { y })
+}
+
+// Predicates of cmpps and friends. The ordered less-than predicates (1, 2, 5 and 6) raise invalid
+// on quiet nans, the others only on signalling nans
+pub unsafe fn sse_comparison_f32(op: i32, x: f32, y: f32) -> bool {
+    let (x, y) = (sse_operand_f32(x), sse_operand_f32(y));
+    let unordered = f32::from_bits(x).is_nan() || f32::from_bits(y).is_nan();
+    match op & 7 {
+        0 => softfloat::f32_eq(x, y),
+        1 => softfloat::f32_lt(x, y),
+        2 => softfloat::f32_le(x, y),
+        3 => !softfloat::f32_eq(x, y) && unordered,
+        4 => !softfloat::f32_eq(x, y),
+        5 => !softfloat::f32_lt(x, y),
+        6 => !softfloat::f32_le(x, y),
+        7 => softfloat::f32_eq(x, y) || !unordered,
+        _ => {
+            dbg_assert!(false);
+            false
+        },
+    }
+}
+pub unsafe fn sse_comparison_f64(op: i32, x: f64, y: f64) -> bool {
+    let (x, y) = (sse_operand_f64(x), sse_operand_f64(y));
+    let unordered = f64::from_bits(x).is_nan() || f64::from_bits(y).is_nan();
+    match op & 7 {
+        0 => softfloat::f64_eq(x, y),
+        1 => softfloat::f64_lt(x, y),
+        2 => softfloat::f64_le(x, y),
+        3 => !softfloat::f64_eq(x, y) && unordered,
+        4 => !softfloat::f64_eq(x, y),
+        5 => !softfloat::f64_lt(x, y),
+        6 => !softfloat::f64_le(x, y),
+        7 => softfloat::f64_eq(x, y) || !unordered,
+        _ => {
+            dbg_assert!(false);
+            false
+        },
+    }
+}
+
+/// The ZF, PF and CF bits set by comiss (signalling) and ucomiss (quiet)
+pub unsafe fn sse_compare_f32(x: f32, y: f32, signalling: bool) -> i32 {
+    let (x, y) = (sse_operand_f32(x), sse_operand_f32(y));
+    let less = if signalling { softfloat::f32_lt(x, y) } else { softfloat::f32_lt_quiet(x, y) };
+    let unordered = f32::from_bits(x).is_nan() || f32::from_bits(y).is_nan();
+    sse_compare_result(softfloat::f32_eq(x, y), less, unordered)
+}
+pub unsafe fn sse_compare_f64(x: f64, y: f64, signalling: bool) -> i32 {
+    let (x, y) = (sse_operand_f64(x), sse_operand_f64(y));
+    let less = if signalling { softfloat::f64_lt(x, y) } else { softfloat::f64_lt_quiet(x, y) };
+    let unordered = f64::from_bits(x).is_nan() || f64::from_bits(y).is_nan();
+    sse_compare_result(softfloat::f64_eq(x, y), less, unordered)
+}
+fn sse_compare_result(equal: bool, less: bool, unordered: bool) -> i32 {
+    if unordered {
+        FLAG_ZERO | FLAG_PARITY | FLAG_CARRY
+    }
+    else if equal {
+        FLAG_ZERO
     }
-    else if rc == 1 || rc == 3 && f > 0.0 {
-        // rc=3 is truncate -> floor for positive numbers
-        return f.floor();
+    else if less {
+        FLAG_CARRY
     }
     else {
-        return f.ceil();
-    };
+        0
+    }
+}
+
+pub unsafe fn sse_convert_f32_to_f64(x: f32) -> f64 {
+    f64::from_bits(softfloat::f32_to_f64(sse_operand_f32(x)))
+}
+pub unsafe fn sse_convert_f64_to_f32(x: f64) -> f32 {
+    sse_result_f32(softfloat::f64_to_f32(sse_operand_f64(x)))
+}
+pub unsafe fn sse_convert_i32_to_f32(x: i32) -> f32 { f32::from_bits(softfloat::i32_to_f32(x)) }
+
+// Conversions to integers return 0x80000000 for nans and out of range values, raising invalid.
+// Denormals don't need special treatment here, they convert to zero
+pub unsafe fn sse_convert_f32_to_i32(x: f32) -> i32 {
+    softfloat::f32_to_i32(x.to_bits(), sse_rounding_mode(), true)
+}
+pub unsafe fn sse_convert_with_truncation_f32_to_i32(x: f32) -> i32 {
+    softfloat::f32_to_i32(x.to_bits(), 1, true)
+}
+pub unsafe fn sse_convert_f64_to_i32(x: f64) -> i32 {
+    softfloat::f64_to_i32(x.to_bits(), sse_rounding_mode(), true)
+}
+pub unsafe fn sse_convert_with_truncation_f64_to_i32(x: f64) -> i32 {
+    softfloat::f64_to_i32(x.to_bits(), 1, true)
+}
+
+// Scalar conversions to r32, called directly by the jit. The result is only valid if
+// sse_exception_pending isn't set
+#[no_mangle]
+pub unsafe fn cvtss2si(source: f32) -> i32 {
+    sse_fp_begin();
+    let result = sse_convert_f32_to_i32(source);
+    sse_fp_end();
+    result
+}
+#[no_mangle]
+pub unsafe fn cvttss2si(source: f32) -> i32 {
+    sse_fp_begin();
+    let result = sse_convert_with_truncation_f32_to_i32(source);
+    sse_fp_end();
+    result
+}
+#[no_mangle]
+pub unsafe fn cvtsd2si(source: f64) -> i32 {
+    sse_fp_begin();
+    let result = sse_convert_f64_to_i32(source);
+    sse_fp_end();
+    result
+}
+#[no_mangle]
+pub unsafe fn cvttsd2si(source: f64) -> i32 {
+    sse_fp_begin();
+    let result = sse_convert_with_truncation_f64_to_i32(source);
+    sse_fp_end();
+    result
 }
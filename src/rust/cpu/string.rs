@@ -23,7 +23,6 @@ use cpu::memory::{
     memset_no_mmap_or_dirty_check, read16_no_mmap_check, read32_no_mmap_check, read8_no_mmap_check,
     write16_no_mmap_or_dirty_check, write32_no_mmap_or_dirty_check, write8_no_mmap_or_dirty_check,
 };
-use page::Page;
 
 fn count_until_end_of_page(direction: i32, size: i32, addr: u32) -> u32 {
     (if direction == 1 {
@@ -239,7 +238,14 @@ unsafe fn string_instruction(
         dbg_assert!(count_until_end_of_page > 0);
 
         if !skip_dirty_page {
-            ::jit::jit_dirty_page(::jit::get_jit_state(), Page::page_of(phys_dst));
+            let len = count_until_end_of_page * size_bytes as u32;
+            let start = if direction == 1 {
+                phys_dst
+            }
+            else {
+                phys_dst + size_bytes as u32 - len
+            };
+            ::jit::jit_dirty_range(::jit::get_jit_state(), start, start + len);
         }
 
         let mut rep_cmp_finished = false;
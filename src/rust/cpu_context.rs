@@ -2,6 +2,9 @@ use cpu::memory;
 use prefix::{PREFIX_MASK_ADDRSIZE, PREFIX_MASK_OPSIZE};
 use state_flags::CachedStateFlags;
 
+/// Decodes instructions from physical memory. The last instruction of a block may straddle a page
+/// boundary if the next virtual page maps to the next physical page (see jit_find_basic_blocks),
+/// so reads can continue into the following physical page
 #[derive(Clone)]
 pub struct CpuContext {
     pub eip: u32,
@@ -11,14 +14,8 @@ pub struct CpuContext {
 }
 
 impl CpuContext {
-    pub fn advance16(&mut self) {
-        dbg_assert!(self.eip & 0xFFF < 0xFFE);
-        self.eip += 2;
-    }
-    pub fn advance32(&mut self) {
-        dbg_assert!(self.eip & 0xFFF < 0xFFC);
-        self.eip += 4;
-    }
+    pub fn advance16(&mut self) { self.eip += 2; }
+    pub fn advance32(&mut self) { self.eip += 4; }
     #[allow(unused)]
     pub fn advance_moffs(&mut self) {
         if self.asize_32() {
@@ -30,20 +27,17 @@ impl CpuContext {
     }
 
     pub fn read_imm8(&mut self) -> u8 {
-        dbg_assert!(self.eip & 0xFFF < 0xFFF);
         let v = memory::read8(self.eip) as u8;
         self.eip += 1;
         v
     }
     pub fn read_imm8s(&mut self) -> i8 { self.read_imm8() as i8 }
     pub fn read_imm16(&mut self) -> u16 {
-        dbg_assert!(self.eip & 0xFFF < 0xFFE);
         let v = memory::read16(self.eip) as u16;
         self.eip += 2;
         v
     }
     pub fn read_imm32(&mut self) -> u32 {
-        dbg_assert!(self.eip & 0xFFF < 0xFFC);
         let v = memory::read32s(self.eip) as u32;
         self.eip += 4;
         v
@@ -3,7 +3,7 @@ use std::iter::FromIterator;
 use std::mem;
 use std::ptr::NonNull;
 
-use analysis::AnalysisType;
+use analysis::{AnalysisType, FlagsEffect};
 use codegen;
 use control_flow;
 use control_flow::WasmStructure;
@@ -12,13 +12,14 @@ use cpu::global_pointers;
 use cpu::memory;
 use cpu_context::CpuContext;
 use jit_instructions;
+use leb;
 use opstats;
 use page::Page;
 use profiler;
 use profiler::stat;
 use state_flags::CachedStateFlags;
 use util::SafeToU16;
-use wasmgen::wasm_builder::{Label, WasmBuilder, WasmLocal};
+use wasmgen::wasm_builder::{Label, WasmBuilder, WasmLocal, WasmLocalF64, WasmLocalI64};
 
 #[derive(Copy, Clone, Eq, Hash, PartialEq)]
 #[repr(transparent)]
@@ -68,20 +69,56 @@ static mut MAX_PAGES: u32 = 3;
 
 static mut JIT_USE_LOOP_SAFETY: bool = true;
 
+// Generate wasm simd instructions for packed sse instructions. Set from js if the host supports
+// simd, otherwise these instructions call into the interpreter's implementation
+static mut JIT_USE_SIMD: bool = false;
+pub fn jit_use_simd() -> bool { unsafe { JIT_USE_SIMD } }
+
+// Generate memory.copy and memory.fill for rep movs and rep stos. Set from js if the host supports
+// bulk memory operations, otherwise these instructions always call into the interpreter
+static mut JIT_USE_BULK_MEMORY: bool = false;
+pub fn jit_use_bulk_memory() -> bool { unsafe { JIT_USE_BULK_MEMORY } }
+
+// Keep a copy of generated modules, so that they can be exported by jit_cache_export
+static mut JIT_CACHE_RECORDING: bool = false;
+
 pub static mut MAX_EXTRA_BASIC_BLOCKS: u32 = 250;
 
 pub const JIT_THRESHOLD: u32 = 200 * 1000;
 
+// Pages reaching this threshold are compiled using the baseline tier
+pub const JIT_THRESHOLD_BASELINE: u32 = 20 * 1000;
+
+// Number of runs after which a module from the baseline tier is compiled again using the optimising
+// tier
+pub const JIT_PROMOTION_THRESHOLD: u32 = 2000;
+
 // less branches will generate if-else, more will generate brtable
 pub const BRTABLE_CUTOFF: usize = 10;
 
 // needs to be synced to const.js
 pub const WASM_TABLE_SIZE: u32 = 900;
 
-pub const CHECK_JIT_STATE_INVARIANTS: bool = false;
+// Check the consistency of the jit state after every change, slow. Set from js for testing
+static mut CHECK_JIT_STATE_INVARIANTS: bool = false;
+pub fn check_jit_state_invariants_enabled() -> bool { unsafe { CHECK_JIT_STATE_INVARIANTS } }
+
+// Number of modules that are evicted at once when the wasm table is full. Evicting several modules
+// at a time amortises the cost of finding the coldest ones
+const EVICTION_BATCH_SIZE: usize = (WASM_TABLE_SIZE / 8) as usize;
+
+// Number of targets recorded for each indirect jump, and compared against in the generated code
+const INLINE_CACHE_SIZE: usize = 4;
+
+// Number of return addresses kept in locals of the generated code
+const RETURN_STACK_SIZE: usize = 4;
 
 const MAX_INSTRUCTION_LENGTH: u32 = 16;
 
+// Code is tracked in chunks of 64 bytes, so that the chunks of a page fit into a u64. Writes to
+// chunks of a page that don't contain compiled code don't invalidate it
+const CODE_CHUNK_SHIFT: u32 = 6;
+
 #[allow(non_upper_case_globals)]
 static mut jit_state: NonNull<JitState> =
     unsafe { NonNull::new_unchecked(mem::align_of::<JitState>() as *mut _) };
@@ -109,6 +146,24 @@ struct PageInfo {
     state_flags: CachedStateFlags,
 }
 
+struct CachedPage {
+    page: Page,
+    // hash of the contents of the page, only computed on export
+    hash: u64,
+    code_chunks: u64,
+    entry_points: Vec<(u16, u16)>,
+}
+
+/// A module together with the physical pages it has been generated from, for the persistent cache
+struct CachedModule {
+    state_flags: CachedStateFlags,
+    tier: CompilationTier,
+    pages: Vec<CachedPage>,
+    // offsets of the wasm table index in the code
+    relocations: Vec<u32>,
+    code: Vec<u8>,
+}
+
 enum CompilingPageState {
     Compiling { pages: HashMap<Page, PageInfo> },
     CompilingWritten,
@@ -123,22 +178,47 @@ pub struct JitState {
     // or HashSet<u32> rather than nested
     entry_points: HashMap<Page, (u32, HashSet<u16>)>,
     pages: HashMap<Page, PageInfo>,
+    // bitmap of the chunks of each page that contain code of a compiled module (including hidden
+    // modules and the module that is being compiled), see CODE_CHUNK_SHIFT
+    code_chunks: HashMap<Page, u64>,
     wasm_table_index_free_list: Vec<WasmTableIndex>,
+    // number of runs of each module since it was compiled, halved on every eviction so that
+    // modules that are no longer used eventually become cold
+    wasm_table_index_usage: Vec<u32>,
+    // modules compiled since the last eviction, which haven't had the chance to be used yet
+    wasm_table_index_fresh: Vec<bool>,
+    // also set to optimising once a module has been scheduled for promotion
+    wasm_table_index_tier: Vec<CompilationTier>,
     compiling: Option<(WasmTableIndex, CompilingPageState)>,
+
+    // physical targets of indirect jumps observed by the interpreter, most recent first, indexed
+    // by the page and offset of the jumping instruction; used to generate inline caches
+    indirect_jump_targets: HashMap<Page, HashMap<u16, Vec<u32>>>,
+
+    // persistent cache, see jit_cache_export and jit_cache_import
+    recorded_modules: HashMap<WasmTableIndex, CachedModule>,
+    imported_modules: Vec<CachedModule>,
+    imported_modules_by_page: HashMap<Page, Vec<usize>>,
+    // index into imported_modules of the module that is being compiled, if it comes from the cache
+    installing_cached_module: Option<usize>,
+    cache_buffer: Vec<u8>,
 }
 
 pub fn check_jit_state_invariants(ctx: &mut JitState) {
-    if !CHECK_JIT_STATE_INVARIANTS {
+    if !check_jit_state_invariants_enabled() {
         return;
     }
 
     match &ctx.compiling {
         Some((_, CompilingPageState::Compiling { pages })) => {
             dbg_assert!(pages.keys().all(|page| ctx.entry_points.contains_key(page)));
+            dbg_assert!(pages.keys().all(|page| ctx.code_chunks.contains_key(page)));
         },
         _ => {},
     }
 
+    dbg_assert!(ctx.pages.keys().all(|page| ctx.code_chunks.contains_key(page)));
+
     let free: HashSet<WasmTableIndex> =
         HashSet::from_iter(ctx.wasm_table_index_free_list.iter().cloned());
     let used = HashSet::from_iter(ctx.pages.values().map(|info| info.wasm_table_index));
@@ -146,6 +226,10 @@ pub fn check_jit_state_invariants(ctx: &mut JitState) {
     dbg_assert!(free.intersection(&used).next().is_none());
     dbg_assert!(used.intersection(&compiling).next().is_none());
     dbg_assert!(free.len() + used.len() + compiling.len() == (WASM_TABLE_SIZE - 1) as usize);
+    dbg_assert!(ctx
+        .recorded_modules
+        .keys()
+        .all(|index| used.contains(index) || compiling.contains(index)));
 
     match &ctx.compiling {
         Some((_, CompilingPageState::Compiling { pages })) => {
@@ -186,13 +270,33 @@ impl JitState {
 
             entry_points: HashMap::new(),
             pages: HashMap::new(),
+            code_chunks: HashMap::new(),
 
             wasm_table_index_free_list: Vec::from_iter(wasm_table_indices),
+            wasm_table_index_usage: vec![0; WASM_TABLE_SIZE as usize],
+            wasm_table_index_fresh: vec![false; WASM_TABLE_SIZE as usize],
+            wasm_table_index_tier: vec![CompilationTier::Optimising; WASM_TABLE_SIZE as usize],
             compiling: None,
+
+            indirect_jump_targets: HashMap::new(),
+
+            recorded_modules: HashMap::new(),
+            imported_modules: Vec::new(),
+            imported_modules_by_page: HashMap::new(),
+            installing_cached_module: None,
+            cache_buffer: Vec::new(),
         }
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CompilationTier {
+    /// Code of a single page, jumps exit the module, no loops
+    Baseline,
+    /// Follows jumps into up to MAX_PAGES pages, generates loops
+    Optimising,
+}
+
 #[derive(PartialEq, Eq)]
 pub enum BasicBlockType {
     Normal {
@@ -220,7 +324,17 @@ pub struct BasicBlock {
     pub is_entry_block: bool,
     pub ty: BasicBlockType,
     pub has_sti: bool,
+    // near call or return, used for the return stack
+    pub ends_with_call: bool,
+    pub ends_with_return: bool,
     pub number_of_instructions: u32,
+    pub flags_effects: Vec<FlagsEffect>,
+    // for each instruction, whether the flags it computes are overwritten before being observed,
+    // see control_flow::flags_liveness
+    pub flags_unused: Vec<bool>,
+    // the xmm registers that instructions in this block may access, see
+    // analysis::xmm_registers_used
+    pub xmm_registers_used: u8,
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -247,6 +361,16 @@ pub enum InstructionOperand {
     Immediate(i32),
     Other,
 }
+impl InstructionOperandDest {
+    fn unsafe_clone(&self) -> InstructionOperandDest {
+        match self {
+            InstructionOperandDest::WasmLocal(l) => {
+                InstructionOperandDest::WasmLocal(l.unsafe_clone())
+            },
+            InstructionOperandDest::Other => InstructionOperandDest::Other,
+        }
+    }
+}
 impl InstructionOperand {
     pub fn is_zero(&self) -> bool {
         match self {
@@ -254,6 +378,13 @@ impl InstructionOperand {
             _ => false,
         }
     }
+    fn unsafe_clone(&self) -> InstructionOperand {
+        match self {
+            InstructionOperand::WasmLocal(l) => InstructionOperand::WasmLocal(l.unsafe_clone()),
+            &InstructionOperand::Immediate(i) => InstructionOperand::Immediate(i),
+            InstructionOperand::Other => InstructionOperand::Other,
+        }
+    }
 }
 impl Into<InstructionOperand> for InstructionOperandDest {
     fn into(self: InstructionOperandDest) -> InstructionOperand {
@@ -296,16 +427,83 @@ pub enum Instruction {
     },
     Other,
 }
+impl Instruction {
+    // safe as long as the operands only refer to register locals, which are alive for the duration
+    // of the entire function (see local_to_instruction_operand)
+    fn unsafe_clone(&self) -> Instruction {
+        match self {
+            Instruction::Cmp {
+                dest,
+                source,
+                opsize,
+            } => Instruction::Cmp {
+                dest: dest.unsafe_clone(),
+                source: source.unsafe_clone(),
+                opsize: *opsize,
+            },
+            Instruction::Sub {
+                dest,
+                source,
+                opsize,
+                is_dec,
+            } => Instruction::Sub {
+                dest: dest.unsafe_clone(),
+                source: source.unsafe_clone(),
+                opsize: *opsize,
+                is_dec: *is_dec,
+            },
+            Instruction::Add {
+                dest,
+                source,
+                opsize,
+                is_inc,
+            } => Instruction::Add {
+                dest: dest.unsafe_clone(),
+                source: source.unsafe_clone(),
+                opsize: *opsize,
+                is_inc: *is_inc,
+            },
+            Instruction::AdcSbb {
+                dest,
+                source,
+                opsize,
+            } => Instruction::AdcSbb {
+                dest: dest.unsafe_clone(),
+                source: source.unsafe_clone(),
+                opsize: *opsize,
+            },
+            Instruction::NonZeroShift { dest, opsize } => Instruction::NonZeroShift {
+                dest: dest.unsafe_clone(),
+                opsize: *opsize,
+            },
+            Instruction::Bitwise { dest, opsize } => Instruction::Bitwise {
+                dest: dest.unsafe_clone(),
+                opsize: *opsize,
+            },
+            Instruction::Other => Instruction::Other,
+        }
+    }
+}
 
 pub struct JitContext<'a> {
     pub cpu: &'a mut CpuContext,
     pub builder: &'a mut WasmBuilder,
     pub register_locals: &'a mut Vec<WasmLocal>,
+    // low and high quadword of the xmm registers used in this module, see
+    // codegen::gen_get_reg_xmm_low
+    pub xmm_register_locals: &'a mut Vec<Option<(WasmLocalI64, WasmLocalI64)>>,
     pub start_of_current_instruction: u32,
     pub exit_with_fault_label: Label,
     pub exit_label: Label,
     pub current_instruction: Instruction,
     pub previous_instruction: Instruction,
+    // the flags computed by the current instruction are overwritten before they are observed
+    pub flags_unused: bool,
+    // the current instruction keeps the x87 registers it uses in fpu_stack_locals, see
+    // codegen::gen_fpu_get_sti_f64
+    pub fpu_f64: bool,
+    // st(0) to st(7): the local holding the value and whether it needs to be written back
+    pub fpu_stack_locals: Vec<Option<(WasmLocalF64, bool)>>,
     pub instruction_counter: WasmLocal,
 }
 impl<'a> JitContext<'a> {
@@ -318,6 +516,19 @@ pub fn is_near_end_of_page(address: u32) -> bool {
     address & 0xFFF >= 0x1000 - MAX_INSTRUCTION_LENGTH
 }
 
+fn is_near_return(phys_address: u32) -> bool {
+    let mut address = phys_address;
+    loop {
+        match memory::read8(address) {
+            0x26 | 0x2E | 0x36 | 0x3E | 0x64 | 0x65 | 0x66 | 0x67 | 0xF0 | 0xF2 | 0xF3 => {
+                address += 1
+            },
+            0xC2 | 0xC3 => return true,
+            _ => return false,
+        }
+    }
+}
+
 pub fn jit_find_cache_entry(phys_address: u32, state_flags: CachedStateFlags) -> CachedCode {
     // TODO: dedup with jit_find_cache_entry_in_page?
     // NOTE: This is currently only used for invariant/missed-entry-point checking
@@ -383,51 +594,48 @@ fn jit_find_basic_blocks(
     ctx: &mut JitState,
     entry_points: HashSet<i32>,
     cpu: CpuContext,
+    tier: CompilationTier,
 ) -> Vec<BasicBlock> {
-    fn follow_jump(
-        virt_target: i32,
+    fn add_page(
+        phys_page: Page,
+        virt_page_address: i32,
         ctx: &mut JitState,
         pages: &mut HashSet<Page>,
         page_blacklist: &mut HashSet<Page>,
         max_pages: u32,
+        tier: CompilationTier,
         marked_as_entry: &mut HashSet<i32>,
         to_visit_stack: &mut Vec<i32>,
-    ) -> Option<u32> {
-        if is_near_end_of_page(virt_target as u32) {
-            return None;
-        }
-        let phys_target = match cpu::translate_address_read_no_side_effects(virt_target) {
-            Err(()) => {
-                dbg_log!("Not analysing {:x} (page not mapped)", virt_target);
-                return None;
-            },
-            Ok(t) => t,
-        };
-
-        let phys_page = Page::page_of(phys_target);
-
+    ) -> bool {
         if !pages.contains(&phys_page) && pages.len() as u32 == max_pages
             || page_blacklist.contains(&phys_page)
         {
-            return None;
+            return false;
         }
 
         if !pages.contains(&phys_page) {
             // page seen for the first time, handle entry points
             if let Some((hotness, entry_points)) = ctx.entry_points.get_mut(&phys_page) {
-                let existing_entry_points = match ctx.pages.get(&phys_page) {
-                    Some(PageInfo { entry_points, .. }) => {
-                        HashSet::from_iter(entry_points.iter().map(|x| x.0))
-                    },
-                    None => HashSet::new(),
+                let (existing_entry_points, existing_tier) = match ctx.pages.get(&phys_page) {
+                    Some(PageInfo {
+                        entry_points,
+                        wasm_table_index,
+                        ..
+                    }) => (
+                        HashSet::from_iter(entry_points.iter().map(|x| x.0)),
+                        Some(ctx.wasm_table_index_tier[wasm_table_index.to_u16() as usize]),
+                    ),
+                    None => (HashSet::new(), None),
                 };
 
-                if entry_points
-                    .iter()
-                    .all(|entry_point| existing_entry_points.contains(entry_point))
+                // Pages compiled at a lower tier are recompiled even without new entry points
+                if existing_tier.map_or(true, |existing_tier| existing_tier >= tier)
+                    && entry_points
+                        .iter()
+                        .all(|entry_point| existing_entry_points.contains(entry_point))
                 {
                     page_blacklist.insert(phys_page);
-                    return None;
+                    return false;
                 }
 
                 // XXX: Remove this paragraph
@@ -440,7 +648,7 @@ fn jit_find_basic_blocks(
                 *hotness = 0;
 
                 for &addr_low in entry_points.iter() {
-                    let addr = virt_target & !0xFFF | addr_low as i32;
+                    let addr = virt_page_address | addr_low as i32;
                     to_visit_stack.push(addr);
                     marked_as_entry.insert(addr);
                 }
@@ -448,13 +656,51 @@ fn jit_find_basic_blocks(
             else {
                 // no entry points: ignore this page?
                 page_blacklist.insert(phys_page);
-                return None;
+                return false;
             }
 
             pages.insert(phys_page);
             dbg_assert!(pages.len() as u32 <= max_pages);
         }
 
+        true
+    }
+
+    fn follow_jump(
+        virt_target: i32,
+        ctx: &mut JitState,
+        pages: &mut HashSet<Page>,
+        page_blacklist: &mut HashSet<Page>,
+        max_pages: u32,
+        tier: CompilationTier,
+        marked_as_entry: &mut HashSet<i32>,
+        to_visit_stack: &mut Vec<i32>,
+    ) -> Option<u32> {
+        if is_near_end_of_page(virt_target as u32) {
+            return None;
+        }
+        let phys_target = match cpu::translate_address_read_no_side_effects(virt_target) {
+            Err(()) => {
+                dbg_log!("Not analysing {:x} (page not mapped)", virt_target);
+                return None;
+            },
+            Ok(t) => t,
+        };
+
+        if !add_page(
+            Page::page_of(phys_target),
+            virt_target & !0xFFF,
+            ctx,
+            pages,
+            page_blacklist,
+            max_pages,
+            tier,
+            marked_as_entry,
+            to_visit_stack,
+        ) {
+            return None;
+        }
+
         to_visit_stack.push(virt_target);
         Some(phys_target)
     }
@@ -466,7 +712,14 @@ fn jit_find_basic_blocks(
     let mut page_blacklist = HashSet::new();
 
     // 16-bit doesn't not work correctly, most likely due to instruction pointer wrap-around
-    let max_pages = if cpu.state_flags.is_32() { unsafe { MAX_PAGES } } else { 1 };
+    let max_pages = if cpu.state_flags.is_32() && tier == CompilationTier::Optimising {
+        unsafe { MAX_PAGES }
+    }
+    else {
+        1
+    };
+    // The baseline tier doesn't follow jumps, so that all edges go forward
+    let follow_jumps = tier == CompilationTier::Optimising;
 
     for virt_addr in entry_points {
         let ok = follow_jump(
@@ -475,6 +728,7 @@ fn jit_find_basic_blocks(
             &mut pages,
             &mut page_blacklist,
             max_pages,
+            tier,
             &mut marked_as_entry,
             &mut to_visit_stack,
         );
@@ -502,6 +756,7 @@ fn jit_find_basic_blocks(
         }
 
         let mut current_address = phys_addr;
+        let mut previous_instruction_addr = 0;
         let mut current_block = BasicBlock {
             addr: current_address,
             virt_addr: to_visit,
@@ -510,7 +765,12 @@ fn jit_find_basic_blocks(
             ty: BasicBlockType::Exit,
             is_entry_block: false,
             has_sti: false,
+            ends_with_call: false,
+            ends_with_return: false,
             number_of_instructions: 0,
+            flags_effects: Vec::new(),
+            flags_unused: Vec::new(),
+            xmm_registers_used: 0,
         };
         loop {
             let addr_before_instruction = current_address;
@@ -523,8 +783,54 @@ fn jit_find_basic_blocks(
             let has_next_instruction = !analysis.no_next_instruction;
             current_address = cpu.eip;
 
-            dbg_assert!(Page::page_of(current_address) == Page::page_of(addr_before_instruction));
-            let current_virt_addr = to_visit & !0xFFF | current_address as i32 & 0xFFF;
+            current_block.flags_effects.push(
+                if Page::page_of(current_address - 1) != Page::page_of(phys_addr) {
+                    // the generated code checks the mapping of the next page before running this
+                    // instruction, and may exit
+                    FlagsEffect::Use
+                }
+                else {
+                    ::analysis::flags_effect(CpuContext {
+                        eip: addr_before_instruction,
+                        ..cpu.clone()
+                    })
+                },
+            );
+
+            current_block.xmm_registers_used |= ::analysis::xmm_registers_used(CpuContext {
+                eip: addr_before_instruction,
+                ..cpu.clone()
+            });
+
+            let current_virt_addr = to_visit.wrapping_add((current_address - phys_addr) as i32);
+
+            if Page::page_of(current_address) != Page::page_of(phys_addr) {
+                // The instruction ends in the next page, which maps contiguously (see below)
+                if analysis.ty == AnalysisType::Normal {
+                    // Continue in a new block, so that the page switch is checked
+                    current_block.last_instruction_addr = addr_before_instruction;
+                    current_block.end_addr = current_address;
+                    current_block.ty = BasicBlockType::Normal {
+                        next_block_addr: Some(current_address),
+                        // eip is updated relative to the page the block starts in
+                        jump_offset: 0x1000,
+                        jump_offset_is_32: true,
+                    };
+                    to_visit_stack.push(current_virt_addr);
+                    profiler::stat_increment(stat::COMPILE_CONTINUED_INTO_NEXT_PAGE);
+                }
+                else {
+                    // Jumps and block boundaries compute their targets from the low bits of eip,
+                    // leave them to the interpreter
+                    dbg_assert!(current_block.number_of_instructions > 1);
+                    current_block.number_of_instructions -= 1;
+                    current_block.flags_effects.pop();
+                    current_block.last_instruction_addr = previous_instruction_addr;
+                    current_block.end_addr = addr_before_instruction;
+                    profiler::stat_increment(stat::COMPILE_CUT_OFF_AT_END_OF_PAGE);
+                }
+                break;
+            }
 
             match analysis.ty {
                 AnalysisType::Normal | AnalysisType::STI => {
@@ -595,15 +901,21 @@ fn jit_find_basic_blocks(
 
                     current_block.ty = BasicBlockType::ConditionalJump {
                         next_block_addr,
-                        next_block_branch_taken_addr: follow_jump(
-                            jump_target,
-                            ctx,
-                            &mut pages,
-                            &mut page_blacklist,
-                            max_pages,
-                            &mut marked_as_entry,
-                            &mut to_visit_stack,
-                        ),
+                        next_block_branch_taken_addr: if follow_jumps {
+                            follow_jump(
+                                jump_target,
+                                ctx,
+                                &mut pages,
+                                &mut page_blacklist,
+                                max_pages,
+                                tier,
+                                &mut marked_as_entry,
+                                &mut to_visit_stack,
+                            )
+                        }
+                        else {
+                            None
+                        },
                         condition,
                         jump_offset: offset,
                         jump_offset_is_32: is_32,
@@ -634,18 +946,25 @@ fn jit_find_basic_blocks(
                         // Execution will eventually come back to the next instruction (CALL)
                         marked_as_entry.insert(current_virt_addr);
                         to_visit_stack.push(current_virt_addr);
+                        current_block.ends_with_call = true;
                     }
 
                     current_block.ty = BasicBlockType::Normal {
-                        next_block_addr: follow_jump(
-                            jump_target,
-                            ctx,
-                            &mut pages,
-                            &mut page_blacklist,
-                            max_pages,
-                            &mut marked_as_entry,
-                            &mut to_visit_stack,
-                        ),
+                        next_block_addr: if follow_jumps {
+                            follow_jump(
+                                jump_target,
+                                ctx,
+                                &mut pages,
+                                &mut page_blacklist,
+                                max_pages,
+                                tier,
+                                &mut marked_as_entry,
+                                &mut to_visit_stack,
+                            )
+                        }
+                        else {
+                            None
+                        },
                         jump_offset: offset,
                         jump_offset_is_32: is_32,
                     };
@@ -668,6 +987,14 @@ fn jit_find_basic_blocks(
 
                     if analysis.absolute_jump {
                         current_block.ty = BasicBlockType::AbsoluteEip;
+
+                        if has_next_instruction {
+                            // call r/m
+                            current_block.ends_with_call = true;
+                        }
+                        else if is_near_return(addr_before_instruction) {
+                            current_block.ends_with_return = true;
+                        }
                     }
 
                     current_block.last_instruction_addr = addr_before_instruction;
@@ -677,11 +1004,37 @@ fn jit_find_basic_blocks(
             }
 
             if is_near_end_of_page(current_address) {
-                current_block.last_instruction_addr = addr_before_instruction;
-                current_block.end_addr = current_address;
-                profiler::stat_increment(stat::COMPILE_CUT_OFF_AT_END_OF_PAGE);
-                break;
+                // The next instruction may cross into the next virtual page. Only continue if that
+                // page maps to the next physical page, so that the instruction can be decoded from
+                // physical memory, and if it can be added to this module. The generated code checks
+                // the mapping before running the instruction
+                let next_virt_page = (current_virt_addr & !0xFFF).wrapping_add(0x1000);
+                let next_phys_page = Page::page_of(current_address).to_address().wrapping_add(0x1000);
+                let continue_into_next_page = follow_jumps
+                    && !current_block.has_sti
+                    && cpu::translate_address_read_no_side_effects(next_virt_page)
+                        == Ok(next_phys_page)
+                    && add_page(
+                        Page::page_of(next_phys_page),
+                        next_virt_page,
+                        ctx,
+                        &mut pages,
+                        &mut page_blacklist,
+                        max_pages,
+                        tier,
+                        &mut marked_as_entry,
+                        &mut to_visit_stack,
+                    );
+
+                if !continue_into_next_page {
+                    current_block.last_instruction_addr = addr_before_instruction;
+                    current_block.end_addr = current_address;
+                    profiler::stat_increment(stat::COMPILE_CUT_OFF_AT_END_OF_PAGE);
+                    break;
+                }
             }
+
+            previous_instruction_addr = addr_before_instruction;
         }
 
         let previous_block = basic_blocks
@@ -745,7 +1098,7 @@ fn jit_find_basic_blocks(
 
 #[no_mangle]
 #[cfg(debug_assertions)]
-pub fn jit_force_generate_unsafe(virt_addr: i32) {
+pub fn jit_force_generate_unsafe(virt_addr: i32, baseline: bool) {
     dbg_assert!(
         !is_near_end_of_page(virt_addr as u32),
         "cannot force compile near end of page"
@@ -755,11 +1108,44 @@ pub fn jit_force_generate_unsafe(virt_addr: i32) {
         cpu::translate_address_read(virt_addr).unwrap(),
         cpu::get_seg_cs() as u32,
         cpu::get_state_flags(),
-        JIT_THRESHOLD,
+        if baseline { JIT_THRESHOLD_BASELINE } else { JIT_THRESHOLD },
     );
     dbg_assert!(get_jit_state().compiling.is_some());
 }
 
+/// Allocate an index in the wasm table, evicting modules if necessary
+fn allocate_wasm_table_index(ctx: &mut JitState) -> WasmTableIndex {
+    if ctx.wasm_table_index_free_list.is_empty() {
+        dbg_log!("wasm_table_index_free_list empty, evicting cold modules");
+
+        // When no free slots are available, delete the least used modules. We could increase the
+        // size of the table, but this way the initial size acts as an upper bound for the
+        // number of wasm modules that we generate, which we want anyway to avoid getting our
+        // tab killed by browsers due to memory constraints.
+        evict_cold_modules(ctx);
+
+        profiler::stat_increment(stat::INVALIDATE_MODULES_NO_FREE_WASM_INDICES);
+
+        dbg_log!(
+            "after evict_cold_modules: {} free",
+            ctx.wasm_table_index_free_list.len(),
+        );
+
+        // This assertion can fail if all entries are pending (not possible unless
+        // WASM_TABLE_SIZE is set very low)
+        dbg_assert!(!ctx.wasm_table_index_free_list.is_empty());
+    }
+
+    let wasm_table_index = ctx
+        .wasm_table_index_free_list
+        .pop()
+        .expect("allocate wasm table index");
+    dbg_assert!(wasm_table_index != WasmTableIndex(0));
+    ctx.wasm_table_index_usage[wasm_table_index.to_u16() as usize] = 0;
+    ctx.wasm_table_index_fresh[wasm_table_index.to_u16() as usize] = true;
+    wasm_table_index
+}
+
 #[inline(never)]
 fn jit_analyze_and_generate(
     ctx: &mut JitState,
@@ -767,6 +1153,7 @@ fn jit_analyze_and_generate(
     phys_entry_point: u32,
     cs_offset: u32,
     state_flags: CachedStateFlags,
+    tier: CompilationTier,
 ) {
     let page = Page::page_of(phys_entry_point);
 
@@ -777,14 +1164,22 @@ fn jit_analyze_and_generate(
         Some(entry_points) => entry_points,
     };
 
-    let existing_entry_points = match ctx.pages.get(&page) {
-        Some(PageInfo { entry_points, .. }) => HashSet::from_iter(entry_points.iter().map(|x| x.0)),
-        None => HashSet::new(),
+    let (existing_entry_points, existing_tier) = match ctx.pages.get(&page) {
+        Some(PageInfo {
+            entry_points,
+            wasm_table_index,
+            ..
+        }) => (
+            HashSet::from_iter(entry_points.iter().map(|x| x.0)),
+            Some(ctx.wasm_table_index_tier[wasm_table_index.to_u16() as usize]),
+        ),
+        None => (HashSet::new(), None),
     };
 
-    if entry_points
-        .iter()
-        .all(|entry_point| existing_entry_points.contains(entry_point))
+    if existing_tier.map_or(true, |existing_tier| existing_tier >= tier)
+        && entry_points
+            .iter()
+            .all(|entry_point| existing_entry_points.contains(entry_point))
     {
         profiler::stat_increment(stat::COMPILE_SKIPPED_NO_NEW_ENTRY_POINTS);
         return;
@@ -802,6 +1197,9 @@ fn jit_analyze_and_generate(
     //dbg_assert!(entry_points.union(&existing_entry_points).count() == entry_points.len());
 
     profiler::stat_increment(stat::COMPILE);
+    if tier == CompilationTier::Baseline {
+        profiler::stat_increment(stat::COMPILE_BASELINE);
+    }
 
     let cpu = CpuContext {
         eip: 0,
@@ -818,14 +1216,16 @@ fn jit_analyze_and_generate(
         .iter()
         .map(|e| virt_page.to_address() as i32 | *e as i32)
         .collect();
-    let basic_blocks = jit_find_basic_blocks(ctx, entry_points, cpu.clone());
+    let mut basic_blocks = jit_find_basic_blocks(ctx, entry_points, cpu.clone(), tier);
 
     let mut pages = HashSet::new();
+    let mut code_chunks = HashMap::new();
 
     for b in basic_blocks.iter() {
-        // Remove this assertion once page-crossing jit is enabled
-        dbg_assert!(Page::page_of(b.addr) == Page::page_of(b.end_addr));
+        // The last instruction of a block may end in the next page
         pages.insert(Page::page_of(b.addr));
+        pages.insert(Page::page_of(b.end_addr - 1));
+        mark_code_chunks(&mut code_chunks, b.addr, b.end_addr);
     }
 
     let print = false;
@@ -881,7 +1281,10 @@ fn jit_analyze_and_generate(
     }
 
     let graph = control_flow::make_graph(&basic_blocks);
-    let mut structure = control_flow::loopify(&graph);
+    let mut structure = match tier {
+        CompilationTier::Baseline => control_flow::linearize(&graph),
+        CompilationTier::Optimising => control_flow::loopify(&graph),
+    };
 
     if print {
         dbg_log!("before blockify:");
@@ -905,33 +1308,10 @@ fn jit_analyze_and_generate(
         }
     }
 
-    if ctx.wasm_table_index_free_list.is_empty() {
-        dbg_log!("wasm_table_index_free_list empty, clearing cache");
-
-        // When no free slots are available, delete all cached modules. We could increase the
-        // size of the table, but this way the initial size acts as an upper bound for the
-        // number of wasm modules that we generate, which we want anyway to avoid getting our
-        // tab killed by browsers due to memory constraints.
-        jit_clear_cache(ctx);
-
-        profiler::stat_increment(stat::INVALIDATE_ALL_MODULES_NO_FREE_WASM_INDICES);
-
-        dbg_log!(
-            "after jit_clear_cache: {} free",
-            ctx.wasm_table_index_free_list.len(),
-        );
-
-        // This assertion can fail if all entries are pending (not possible unless
-        // WASM_TABLE_SIZE is set very low)
-        dbg_assert!(!ctx.wasm_table_index_free_list.is_empty());
-    }
+    control_flow::flags_liveness(&mut basic_blocks, &structure);
 
-    // allocate an index in the wasm table
-    let wasm_table_index = ctx
-        .wasm_table_index_free_list
-        .pop()
-        .expect("allocate wasm table index");
-    dbg_assert!(wasm_table_index != WasmTableIndex(0));
+    let wasm_table_index = allocate_wasm_table_index(ctx);
+    ctx.wasm_table_index_tier[wasm_table_index.to_u16() as usize] = tier;
 
     dbg_assert!(!pages.is_empty());
     dbg_assert!(pages.len() <= unsafe { MAX_PAGES } as usize);
@@ -939,9 +1319,25 @@ fn jit_analyze_and_generate(
     let basic_block_by_addr: HashMap<u32, BasicBlock> =
         basic_blocks.into_iter().map(|b| (b.addr, b)).collect();
 
+    let mut inline_cache_targets = HashMap::new();
+    for b in basic_block_by_addr.values() {
+        if b.ty != BasicBlockType::AbsoluteEip {
+            continue;
+        }
+        let site = b.last_instruction_addr;
+        if let Some(targets) = ctx
+            .indirect_jump_targets
+            .get(&Page::page_of(site))
+            .and_then(|targets| targets.get(&(site as u16 & 0xFFF)))
+        {
+            inline_cache_targets.insert(site, targets.clone());
+        }
+    }
+
     let entries = jit_generate_module(
         structure,
         &basic_block_by_addr,
+        &inline_cache_targets,
         cpu,
         &mut ctx.wasm_builder,
         wasm_table_index,
@@ -961,6 +1357,16 @@ fn jit_analyze_and_generate(
             });
         code.entry_points.push((addr as u16 & 0xFFF, state));
     }
+    for &page in &pages {
+        // Pages that are only reached by crossing from another page have no entries, but the
+        // module still needs to be invalidated when they are written
+        page_info.entry(page).or_insert_with(|| PageInfo {
+            wasm_table_index,
+            state_flags,
+            entry_points: Vec::new(),
+            hidden_wasm_table_indices: Vec::new(),
+        });
+    }
 
     profiler::stat_increment_by(
         stat::COMPILE_WASM_TOTAL_BYTES,
@@ -972,10 +1378,33 @@ fn jit_analyze_and_generate(
         ctx.entry_points
             .entry(p)
             .or_insert_with(|| (0, HashSet::new()));
+        *ctx.code_chunks.entry(p).or_insert(0) |= code_chunks[&p];
     }
 
     cpu::tlb_set_has_code_multiple(&pages, true);
 
+    if unsafe { JIT_CACHE_RECORDING } {
+        let module = CachedModule {
+            state_flags,
+            tier,
+            pages: pages
+                .iter()
+                .map(|&page| CachedPage {
+                    page,
+                    hash: 0,
+                    code_chunks: code_chunks[&page],
+                    entry_points: page_info
+                        .get(&page)
+                        .map(|info| info.entry_points.clone())
+                        .unwrap_or_default(),
+                })
+                .collect(),
+            relocations: ctx.wasm_builder.get_relocations().to_vec(),
+            code: ctx.wasm_builder.get_output().to_vec(),
+        };
+        ctx.recorded_modules.insert(wasm_table_index, module);
+    }
+
     dbg_assert!(ctx.compiling.is_none());
     ctx.compiling = Some((
         wasm_table_index,
@@ -1011,6 +1440,8 @@ pub fn codegen_finalize_finished(
         Page::page_of(phys_addr).to_address()
     );
 
+    ctx.installing_cached_module = None;
+
     let pages = match mem::replace(&mut ctx.compiling, None) {
         None => {
             dbg_assert!(false);
@@ -1080,6 +1511,45 @@ pub fn codegen_finalize_finished(
     check_jit_state_invariants(ctx);
 }
 
+/// Called instead of codegen_finalize_finished if the module couldn't be instantiated (for example
+/// if the host is out of memory). The code keeps running in the interpreter until it is compiled
+/// again
+#[no_mangle]
+pub fn codegen_finalize_failed(wasm_table_index: WasmTableIndex, phys_addr: u32) {
+    let ctx = get_jit_state();
+
+    dbg_log!(
+        "Failed to instantiate module for page at {:x}",
+        Page::page_of(phys_addr).to_address()
+    );
+
+    match ctx.compiling.take() {
+        None => {
+            dbg_assert!(false);
+            return;
+        },
+        Some((in_progress_wasm_table_index, _)) => {
+            dbg_assert!(wasm_table_index == in_progress_wasm_table_index);
+        },
+    }
+
+    if let Some(index) = ctx.installing_cached_module.take() {
+        // Don't install it again
+        for p in &ctx.imported_modules[index].pages {
+            if let Some(candidates) = ctx.imported_modules_by_page.get_mut(&p.page) {
+                candidates.retain(|&i| i != index);
+                if candidates.is_empty() {
+                    ctx.imported_modules_by_page.remove(&p.page);
+                }
+            }
+        }
+    }
+
+    profiler::stat_increment(stat::COMPILE_FAILED);
+    free_wasm_table_index(ctx, wasm_table_index);
+    check_jit_state_invariants(ctx);
+}
+
 pub fn update_tlb_code(virt_page: Page, phys_page: Page) {
     let ctx = get_jit_state();
 
@@ -1128,9 +1598,63 @@ pub fn set_tlb_code(
     }
 }
 
+fn gen_return_stack_push(
+    builder: &mut WasmBuilder,
+    return_stack: &Vec<(WasmLocal, WasmLocal)>,
+    return_address: u32,
+    target_index: i32,
+) {
+    for i in (1..return_stack.len()).rev() {
+        builder.get_local(&return_stack[i - 1].0);
+        builder.set_local(&return_stack[i].0);
+        builder.get_local(&return_stack[i - 1].1);
+        builder.set_local(&return_stack[i].1);
+    }
+    codegen::gen_get_eip(builder);
+    builder.const_i32(!0xFFF);
+    builder.and_i32();
+    builder.const_i32(return_address as i32 & 0xFFF);
+    builder.or_i32();
+    builder.set_local(&return_stack[0].0);
+    builder.const_i32(target_index);
+    builder.set_local(&return_stack[0].1);
+}
+
+/// Pops the top of the return stack into target_block and leaves whether it matches the current
+/// eip on the stack
+fn gen_return_stack_pop(
+    builder: &mut WasmBuilder,
+    return_stack: &Vec<(WasmLocal, WasmLocal)>,
+    target_block: &WasmLocal,
+) {
+    codegen::gen_get_eip(builder);
+    builder.get_local(&return_stack[0].0);
+    builder.eq_i32();
+    // empty slots have a target index of -1
+    builder.get_local(&return_stack[0].1);
+    builder.const_i32(0);
+    builder.ge_i32();
+    builder.and_i32();
+
+    builder.get_local(&return_stack[0].1);
+    builder.set_local(target_block);
+
+    for i in 1..return_stack.len() {
+        builder.get_local(&return_stack[i].0);
+        builder.set_local(&return_stack[i - 1].0);
+        builder.get_local(&return_stack[i].1);
+        builder.set_local(&return_stack[i - 1].1);
+    }
+    builder.const_i32(-1);
+    builder.set_local(&return_stack[return_stack.len() - 1].0);
+    builder.const_i32(-1);
+    builder.set_local(&return_stack[return_stack.len() - 1].1);
+}
+
 fn jit_generate_module(
     structure: Vec<WasmStructure>,
     basic_blocks: &HashMap<u32, BasicBlock>,
+    inline_cache_targets: &HashMap<u32, Vec<u32>>,
     mut cpu: CpuContext,
     builder: &mut WasmBuilder,
     wasm_table_index: WasmTableIndex,
@@ -1145,9 +1669,45 @@ fn jit_generate_module(
         })
         .collect();
 
+    let xmm_registers_used = basic_blocks
+        .values()
+        .fold(0, |mask, block| mask | block.xmm_registers_used);
+    let mut xmm_register_locals = (0..8)
+        .map(|i| {
+            if xmm_registers_used & 1 << i == 0 {
+                return None;
+            }
+            builder.load_fixed_i64(global_pointers::get_reg_xmm_offset(i));
+            let low = builder.set_new_local_i64();
+            builder.load_fixed_i64(global_pointers::get_reg_xmm_offset(i) + 8);
+            let high = builder.set_new_local_i64();
+            Some((low, high))
+        })
+        .collect();
+
     builder.const_i32(0);
     let instruction_counter = builder.set_new_local();
 
+    // Calls within this module push their return address and the dispatcher target of the
+    // following block, so that returns can jump there directly without a lookup
+    let return_stack: Vec<(WasmLocal, WasmLocal)> =
+        if basic_blocks.values().any(|b| b.ends_with_return)
+            && basic_blocks.values().any(|b| b.ends_with_call)
+        {
+            (0..RETURN_STACK_SIZE)
+                .map(|_| {
+                    builder.const_i32(-1);
+                    let return_address = builder.set_new_local();
+                    builder.const_i32(-1);
+                    let target_index = builder.set_new_local();
+                    (return_address, target_index)
+                })
+                .collect()
+        }
+        else {
+            Vec::new()
+        };
+
     let exit_label = builder.block_void();
     let exit_with_fault_label = builder.block_void();
     let main_loop_label = builder.loop_void();
@@ -1171,11 +1731,15 @@ fn jit_generate_module(
         cpu: &mut cpu,
         builder,
         register_locals: &mut register_locals,
+        xmm_register_locals: &mut xmm_register_locals,
         start_of_current_instruction: 0,
         exit_with_fault_label,
         exit_label,
         current_instruction: Instruction::Other,
         previous_instruction: Instruction::Other,
+        flags_unused: false,
+        fpu_f64: false,
+        fpu_stack_locals: (0..8).map(|_| None).collect(),
         instruction_counter,
     };
 
@@ -1211,15 +1775,39 @@ fn jit_generate_module(
     for (i, &addr) in entry_blocks.iter().enumerate() {
         index_for_addr.insert(addr, i as i32);
     }
-    for b in basic_blocks.values() {
-        if !index_for_addr.contains_key(&b.addr) {
+    // sorted, so that the generated code doesn't depend on the iteration order of the hash map
+    let mut addrs: Vec<u32> = basic_blocks.keys().copied().collect();
+    addrs.sort_unstable();
+    for addr in addrs {
+        if !index_for_addr.contains_key(&addr) {
             let i = index_for_addr.len();
-            index_for_addr.insert(b.addr, i as i32);
+            index_for_addr.insert(addr, i as i32);
         }
     }
 
     let mut label_for_addr: HashMap<u32, (Label, Option<i32>)> = HashMap::new();
 
+    // Blocks that are only reached through a single edge within this module can start with the
+    // flags producer of their predecessor, so that conditions can be computed from its operands
+    let mut predecessor_count: HashMap<u32, u32> = HashMap::new();
+    for b in basic_blocks.values() {
+        let successors = match b.ty {
+            BasicBlockType::Normal {
+                next_block_addr, ..
+            } => [next_block_addr, None],
+            BasicBlockType::ConditionalJump {
+                next_block_addr,
+                next_block_branch_taken_addr,
+                ..
+            } => [next_block_addr, next_block_branch_taken_addr],
+            BasicBlockType::AbsoluteEip | BasicBlockType::Exit => [None, None],
+        };
+        for &addr in successors.iter().flatten() {
+            *predecessor_count.entry(addr).or_insert(0) += 1;
+        }
+    }
+    let mut flags_producer_for_addr: HashMap<u32, Instruction> = HashMap::new();
+
     enum Work {
         WasmStructure(WasmStructure),
         BlockEnd {
@@ -1248,15 +1836,78 @@ fn jit_generate_module(
         match block {
             Work::WasmStructure(WasmStructure::BasicBlock(addr)) => {
                 let block = basic_blocks.get(&addr).unwrap();
-                jit_generate_basic_block(ctx, block);
 
-                if block.has_sti {
-                    match block.ty {
-                        BasicBlockType::ConditionalJump {
-                            condition,
-                            jump_offset,
-                            jump_offset_is_32,
-                            ..
+                if block.ends_with_call && !block.has_sti && !return_stack.is_empty() {
+                    let call_stays_in_module = match block.ty {
+                        BasicBlockType::Normal {
+                            next_block_addr, ..
+                        } => next_block_addr.is_some(),
+                        _ => true,
+                    };
+                    let return_index = if Page::page_of(block.end_addr) == Page::page_of(addr) {
+                        index_for_addr
+                            .get(&block.end_addr)
+                            .filter(|&&index| (index as usize) < entry_blocks.len())
+                    }
+                    else {
+                        None
+                    };
+                    if let (true, Some(&return_index)) = (call_stays_in_module, return_index) {
+                        gen_return_stack_push(
+                            ctx.builder,
+                            &return_stack,
+                            block.end_addr,
+                            return_index,
+                        );
+                    }
+                }
+
+                ctx.previous_instruction = match flags_producer_for_addr.get(&addr) {
+                    Some(instruction) => instruction.unsafe_clone(),
+                    None => Instruction::Other,
+                };
+
+                jit_generate_basic_block(ctx, block);
+
+                let flags_producer = match block.ty {
+                    BasicBlockType::Normal {
+                        next_block_addr: Some(next_block_addr),
+                        ..
+                    } if !block.ends_with_call => Some((
+                        ctx.current_instruction.unsafe_clone(),
+                        [Some(next_block_addr), None],
+                    )),
+                    BasicBlockType::ConditionalJump {
+                        next_block_addr,
+                        next_block_branch_taken_addr,
+                        condition,
+                        ..
+                    } if condition & 0xF0 != 0xE0 => Some((
+                        // jcc doesn't produce flags, the condition was computed from the
+                        // instruction before it
+                        ctx.previous_instruction.unsafe_clone(),
+                        [next_block_addr, next_block_branch_taken_addr],
+                    )),
+                    _ => None,
+                };
+                if let (false, Some((instruction, successors))) = (block.has_sti, flags_producer) {
+                    for &next_block_addr in successors.iter().flatten() {
+                        if predecessor_count.get(&next_block_addr) == Some(&1)
+                            && !entry_blocks.contains(&next_block_addr)
+                        {
+                            flags_producer_for_addr
+                                .insert(next_block_addr, instruction.unsafe_clone());
+                        }
+                    }
+                }
+
+                if block.has_sti {
+                    match block.ty {
+                        BasicBlockType::ConditionalJump {
+                            condition,
+                            jump_offset,
+                            jump_offset_is_32,
+                            ..
                         } => {
                             codegen::gen_set_eip_low_bits(
                                 ctx.builder,
@@ -1311,9 +1962,74 @@ fn jit_generate_module(
                         ctx.builder.br(ctx.exit_label);
                     },
                     BasicBlockType::AbsoluteEip => {
+                        if block.ends_with_return && !return_stack.is_empty() {
+                            gen_return_stack_pop(ctx.builder, &return_stack, target_block);
+                            if cfg!(feature = "profiler") {
+                                ctx.builder.if_void();
+                                codegen::gen_profiler_stat_increment(
+                                    ctx.builder,
+                                    stat::RETURN_STACK_HIT,
+                                );
+                                ctx.builder.br(main_loop_label);
+                                ctx.builder.block_end();
+                                codegen::gen_profiler_stat_increment(
+                                    ctx.builder,
+                                    stat::RETURN_STACK_MISS,
+                                );
+                            }
+                            else {
+                                ctx.builder.br_if(main_loop_label);
+                            }
+                        }
+
+                        // Compare the target to the ones recorded by the interpreter
+                        let cached_targets: Vec<(u32, i32)> = inline_cache_targets
+                            .get(&block.last_instruction_addr)
+                            .map_or(Vec::new(), |targets| {
+                                targets
+                                    .iter()
+                                    .filter_map(|target| {
+                                        index_for_addr
+                                            .get(target)
+                                            .filter(|&&index| (index as usize) < entry_blocks.len())
+                                            .map(|&index| (*target, index))
+                                    })
+                                    .collect()
+                            });
+                        if !cached_targets.is_empty() {
+                            codegen::gen_get_eip(ctx.builder);
+                            let address_local = ctx.builder.set_new_local();
+                            codegen::gen_get_phys_eip_plus_mem(ctx, &address_local);
+                            ctx.builder.free_local(address_local);
+                            let phys_eip_local = ctx.builder.set_new_local();
+
+                            for (target, index) in cached_targets {
+                                ctx.builder.get_local(&phys_eip_local);
+                                ctx.builder
+                                    .const_i32(target as i32 + unsafe { memory::mem8 } as i32);
+                                ctx.builder.eq_i32();
+                                ctx.builder.if_void();
+                                ctx.builder.const_i32(index);
+                                ctx.builder.set_local(target_block);
+                                codegen::gen_profiler_stat_increment(
+                                    ctx.builder,
+                                    stat::INDIRECT_JUMP_INLINE_CACHE_HIT,
+                                );
+                                ctx.builder.br(main_loop_label);
+                                ctx.builder.block_end();
+                            }
+
+                            ctx.builder.free_local(phys_eip_local);
+                            codegen::gen_profiler_stat_increment(
+                                ctx.builder,
+                                stat::INDIRECT_JUMP_INLINE_CACHE_MISS,
+                            );
+                        }
+
                         // Check if we can stay in this module, if not exit
                         codegen::gen_get_eip(ctx.builder);
-                        ctx.builder.const_i32(wasm_table_index.to_u16() as i32);
+                        // relocated when the module is loaded from the persistent cache
+                        ctx.builder.const_i32_relocatable(wasm_table_index.to_u16());
                         ctx.builder.const_i32(state_flags.to_u32() as i32);
                         ctx.builder.call_fn3_ret("jit_find_cache_entry_in_page");
                         ctx.builder.tee_local(target_block);
@@ -1950,8 +2666,18 @@ fn jit_generate_module(
     for local in ctx.register_locals.drain(..) {
         ctx.builder.free_local(local);
     }
+    for (low, high) in ctx.xmm_register_locals.drain(..).flatten() {
+        ctx.builder.free_local_i64(low);
+        ctx.builder.free_local_i64(high);
+    }
+    // flushed at the end of each basic block
+    dbg_assert!(ctx.fpu_stack_locals.iter().all(|l| l.is_none()));
     ctx.builder
         .free_local(ctx.instruction_counter.unsafe_clone());
+    for (return_address, target_index) in return_stack {
+        ctx.builder.free_local(return_address);
+        ctx.builder.free_local(target_index);
+    }
 
     ctx.builder.finish();
 
@@ -2005,8 +2731,9 @@ fn jit_generate_basic_block(ctx: &mut JitContext, block: &BasicBlock) {
 
     ctx.cpu.eip = start_addr;
     ctx.current_instruction = Instruction::Other;
-    ctx.previous_instruction = Instruction::Other;
+    // ctx.previous_instruction is set by the caller
 
+    let mut instruction_index = 0;
     loop {
         let mut instruction = 0;
         if cfg!(feature = "profiler") {
@@ -2031,9 +2758,28 @@ fn jit_generate_basic_block(ctx: &mut JitContext, block: &BasicBlock) {
         let wasm_length_before = ctx.builder.instruction_body_length();
 
         ctx.start_of_current_instruction = ctx.cpu.eip;
+
+        let crosses_page = ctx.cpu.eip == last_instruction_addr
+            && Page::page_of(stop_addr - 1) != Page::page_of(start_addr);
+
+        ctx.fpu_f64 = ctx.cpu.state_flags.fpu_f64()
+            && !crosses_page
+            && ::analysis::is_fpu_f64_instruction(ctx.cpu.clone());
+        if !ctx.fpu_f64 {
+            codegen::gen_fpu_flush_f64(ctx);
+        }
+
+        if crosses_page {
+            // The last instruction continues into the next page, its bytes were read from the
+            // next physical page
+            codegen::gen_next_page_check(ctx, Page::page_of(stop_addr - 1).to_address());
+        }
         let start_eip = ctx.cpu.eip;
         let mut instruction_flags = 0;
+        ctx.flags_unused = block.flags_unused[instruction_index];
         jit_instructions::jit_instruction(ctx, &mut instruction_flags);
+        ctx.flags_unused = false;
+        instruction_index += 1;
         let end_eip = ctx.cpu.eip;
 
         let instruction_length = end_eip - start_eip;
@@ -2048,12 +2794,14 @@ fn jit_generate_basic_block(ctx: &mut JitContext, block: &BasicBlock) {
         let end_addr = ctx.cpu.eip;
 
         if end_addr == stop_addr {
-            // no page was crossed
-            dbg_assert!(Page::page_of(end_addr) == Page::page_of(start_addr));
+            // only the last instruction may end in the next page
             break;
         }
 
-        if was_block_boundary || is_near_end_of_page(end_addr) || end_addr > stop_addr {
+        if was_block_boundary
+            || is_near_end_of_page(end_addr) && Page::page_of(stop_addr) == Page::page_of(start_addr)
+            || end_addr > stop_addr
+        {
             dbg_log!(
                 "Overlapping basic blocks start={:x} expected_end={:x} end={:x} was_block_boundary={} near_end_of_page={}",
                 start_addr,
@@ -2068,6 +2816,9 @@ fn jit_generate_basic_block(ctx: &mut JitContext, block: &BasicBlock) {
 
         ctx.previous_instruction = mem::replace(&mut ctx.current_instruction, Instruction::Other);
     }
+
+    ctx.fpu_f64 = false;
+    codegen::gen_fpu_flush_f64(ctx);
 }
 
 pub fn jit_increase_hotness_and_maybe_compile(
@@ -2077,12 +2828,29 @@ pub fn jit_increase_hotness_and_maybe_compile(
     state_flags: CachedStateFlags,
     heat: u32,
 ) {
+    if memory::in_mapped_range(phys_address) {
+        // Code running from memory-mapped io (option roms, flash, vga memory) is only interpreted:
+        // Its contents aren't covered by the dirty page tracking of physical memory
+        profiler::stat_increment(stat::RUN_INTERPRETED_MAPPED_MEMORY);
+        return;
+    }
+
     if unsafe { JIT_DISABLED } {
         return
     }
 
     let ctx = get_jit_state();
     let page = Page::page_of(phys_address);
+
+    if !ctx.imported_modules_by_page.is_empty()
+        && ctx.compiling.is_none()
+        && !ctx.entry_points.contains_key(&page)
+    {
+        // First time this page runs since it has been written: Skip warm-up if a module for
+        // it is in the persistent cache
+        try_install_cached_module(ctx, page, state_flags);
+    }
+
     let (hotness, entry_points) = ctx.entry_points.entry(page).or_insert_with(|| {
         cpu::tlb_set_has_code(page, true);
         profiler::stat_increment(stat::RUN_INTERPRETED_NEW_PAGE);
@@ -2094,14 +2862,26 @@ pub fn jit_increase_hotness_and_maybe_compile(
     }
 
     *hotness += heat;
-    if *hotness >= JIT_THRESHOLD {
+    if *hotness >= JIT_THRESHOLD_BASELINE {
         if ctx.compiling.is_some() {
             return;
         }
         // only try generating if we're in the correct address space
         if cpu::translate_address_read_no_side_effects(virt_address) == Ok(phys_address) {
+            let tier = if *hotness >= JIT_THRESHOLD {
+                CompilationTier::Optimising
+            }
+            else {
+                // Don't replace code from the optimising tier when new entry points are found
+                match ctx.pages.get(&page) {
+                    Some(info) => {
+                        ctx.wasm_table_index_tier[info.wasm_table_index.to_u16() as usize]
+                    },
+                    None => CompilationTier::Baseline,
+                }
+            };
             *hotness = 0;
-            jit_analyze_and_generate(ctx, virt_address, phys_address, cs_offset, state_flags)
+            jit_analyze_and_generate(ctx, virt_address, phys_address, cs_offset, state_flags, tier)
         }
         else {
             profiler::stat_increment(stat::COMPILE_WRONG_ADDRESS_SPACE);
@@ -2110,7 +2890,7 @@ pub fn jit_increase_hotness_and_maybe_compile(
 }
 
 fn free_wasm_table_index(ctx: &mut JitState, wasm_table_index: WasmTableIndex) {
-    if CHECK_JIT_STATE_INVARIANTS {
+    if check_jit_state_invariants_enabled() {
         dbg_assert!(!ctx.wasm_table_index_free_list.contains(&wasm_table_index));
 
         match &ctx.compiling {
@@ -2148,15 +2928,177 @@ fn free_wasm_table_index(ctx: &mut JitState, wasm_table_index: WasmTableIndex) {
     }
 
     ctx.wasm_table_index_free_list.push(wasm_table_index);
+    ctx.recorded_modules.remove(&wasm_table_index);
 
     // It is not strictly necessary to clear the function, but it will fail more predictably if we
     // accidentally use the function and may garbage collect unused modules earlier
     jit_clear_func(wasm_table_index);
 }
 
+/// Remove a module from the tlb and from all pages that refer to it, and free its index
+fn free_module(ctx: &mut JitState, wasm_table_index: WasmTableIndex) {
+    for i in 0..unsafe { cpu::valid_tlb_entries_count } {
+        let page = unsafe { cpu::valid_tlb_entries[i as usize] };
+        let entry = unsafe { cpu::tlb_data[page as usize] };
+        if 0 != entry {
+            let tlb_physical_page = Page::of_u32(
+                (entry as u32 >> 12 ^ page as u32) - (unsafe { memory::mem8 } as u32 >> 12),
+            );
+            match unsafe { cpu::tlb_code[page as usize] } {
+                None => {},
+                Some(c) => unsafe {
+                    let w = c.as_ref().wasm_table_index;
+                    if wasm_table_index == w {
+                        drop(Box::from_raw(c.as_ptr()));
+                        cpu::tlb_code[page as usize] = None;
+                        if !ctx.entry_points.contains_key(&tlb_physical_page) {
+                            // XXX
+                            cpu::tlb_data[page as usize] &= !cpu::TLB_HAS_CODE;
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    ctx.pages.retain(
+        |_,
+         &mut PageInfo {
+             wasm_table_index: w,
+             ..
+         }| w != wasm_table_index,
+    );
+
+    for info in ctx.pages.values_mut() {
+        info.hidden_wasm_table_indices
+            .retain(|&w| w != wasm_table_index)
+    }
+
+    let pages = &ctx.pages;
+    let compiling_pages = match &ctx.compiling {
+        Some((_, CompilingPageState::Compiling { pages })) => Some(pages),
+        _ => None,
+    };
+    ctx.code_chunks.retain(|page, _| {
+        pages.contains_key(page) || compiling_pages.is_some_and(|p| p.contains_key(page))
+    });
+
+    free_wasm_table_index(ctx, wasm_table_index);
+}
+
+/// Free the least used modules to make space in the wasm table. Modules stay in the cache as long
+/// as they are used, while pages with modules that haven't run recently are compiled again when
+/// they become hot
+fn evict_cold_modules(ctx: &mut JitState) {
+    let mut used: Vec<WasmTableIndex> =
+        ctx.pages.values().map(|info| info.wasm_table_index).collect();
+    used.sort_unstable_by_key(|index| index.to_u16());
+    used.dedup();
+    // Modules compiled since the last eviction start with a usage of 0, they are evicted last so
+    // that they get a chance to run
+    used.sort_by_key(|index| {
+        (
+            ctx.wasm_table_index_fresh[index.to_u16() as usize],
+            ctx.wasm_table_index_usage[index.to_u16() as usize],
+        )
+    });
+
+    for &wasm_table_index in used.iter().take(EVICTION_BATCH_SIZE) {
+        if ctx.wasm_table_index_free_list.contains(&wasm_table_index) {
+            // already freed as a hidden module of a page below
+            continue;
+        }
+
+        // Older modules with code for the same pages contain stale code once the page info that
+        // refers to them is gone, so they have to go as well
+        let mut hidden = Vec::new();
+        for info in ctx.pages.values() {
+            if info.wasm_table_index == wasm_table_index {
+                hidden.extend(info.hidden_wasm_table_indices.iter().cloned());
+            }
+        }
+
+        profiler::stat_increment(stat::INVALIDATE_MODULE_EVICTED);
+        free_module(ctx, wasm_table_index);
+
+        for index in hidden {
+            if !ctx.wasm_table_index_free_list.contains(&index) {
+                profiler::stat_increment(stat::INVALIDATE_MODULE_EVICTED);
+                free_module(ctx, index);
+            }
+        }
+    }
+
+    for usage in ctx.wasm_table_index_usage.iter_mut() {
+        *usage /= 2;
+    }
+    for fresh in ctx.wasm_table_index_fresh.iter_mut() {
+        *fresh = false;
+    }
+
+    check_jit_state_invariants(ctx);
+}
+
+/// Called by the interpreter after a block boundary, used to generate inline caches for indirect
+/// jumps
+pub fn record_indirect_jump(site_phys_address: u32, target_phys_address: u32) {
+    let ctx = get_jit_state();
+    let targets = ctx
+        .indirect_jump_targets
+        .entry(Page::page_of(site_phys_address))
+        .or_insert_with(HashMap::new)
+        .entry(site_phys_address as u16 & 0xFFF)
+        .or_insert_with(Vec::new);
+    if let Some(i) = targets.iter().position(|&t| t == target_phys_address) {
+        targets.remove(i);
+    }
+    else if targets.len() == INLINE_CACHE_SIZE {
+        targets.pop();
+    }
+    targets.insert(0, target_phys_address);
+}
+
+/// Called when a module is run from the cache, used to find modules to evict. Returns true if the
+/// module should be promoted to the optimising tier
+pub fn record_module_use(wasm_table_index: u16) -> bool {
+    let ctx = get_jit_state();
+    let usage = &mut ctx.wasm_table_index_usage[wasm_table_index as usize];
+    *usage = usage.saturating_add(1);
+    *usage >= JIT_PROMOTION_THRESHOLD
+        && ctx.wasm_table_index_tier[wasm_table_index as usize] == CompilationTier::Baseline
+}
+
+/// Compile the page of the entry point of a module from the baseline tier again using the
+/// optimising tier. The new module replaces the old one once it has been compiled
+pub fn jit_promote_module(
+    wasm_table_index: u16,
+    virt_address: i32,
+    phys_address: u32,
+    cs_offset: u32,
+    state_flags: CachedStateFlags,
+) {
+    let ctx = get_jit_state();
+    if ctx.compiling.is_some() {
+        // try again on the next run
+        return;
+    }
+    profiler::stat_increment(stat::COMPILE_PROMOTE_MODULE);
+    jit_analyze_and_generate(
+        ctx,
+        virt_address,
+        phys_address,
+        cs_offset,
+        state_flags,
+        CompilationTier::Optimising,
+    );
+    // don't try again if nothing was compiled
+    ctx.wasm_table_index_tier[wasm_table_index as usize] = CompilationTier::Optimising;
+}
+
 /// Register a write in this page: Delete all present code
 pub fn jit_dirty_page(ctx: &mut JitState, page: Page) {
     let mut did_have_code = false;
+    let was_monitored = cpu::monitor_write_hook(page);
 
     if let Some(PageInfo {
         wasm_table_index,
@@ -2168,53 +3110,15 @@ pub fn jit_dirty_page(ctx: &mut JitState, page: Page) {
         profiler::stat_increment(stat::INVALIDATE_PAGE_HAD_CODE);
         did_have_code = true;
 
-        free(ctx, wasm_table_index);
+        free_module(ctx, wasm_table_index);
         for wasm_table_index in hidden_wasm_table_indices {
-            free(ctx, wasm_table_index);
-        }
-
-        fn free(ctx: &mut JitState, wasm_table_index: WasmTableIndex) {
-            for i in 0..unsafe { cpu::valid_tlb_entries_count } {
-                let page = unsafe { cpu::valid_tlb_entries[i as usize] };
-                let entry = unsafe { cpu::tlb_data[page as usize] };
-                if 0 != entry {
-                    let tlb_physical_page = Page::of_u32(
-                        (entry as u32 >> 12 ^ page as u32) - (unsafe { memory::mem8 } as u32 >> 12),
-                    );
-                    match unsafe { cpu::tlb_code[page as usize] } {
-                        None => {},
-                        Some(c) => unsafe {
-                            let w = c.as_ref().wasm_table_index;
-                            if wasm_table_index == w {
-                                drop(Box::from_raw(c.as_ptr()));
-                                cpu::tlb_code[page as usize] = None;
-                                if !ctx.entry_points.contains_key(&tlb_physical_page) {
-                                    // XXX
-                                    cpu::tlb_data[page as usize] &= !cpu::TLB_HAS_CODE;
-                                }
-                            }
-                        },
-                    }
-                }
-            }
-
-            ctx.pages.retain(
-                |_,
-                 &mut PageInfo {
-                     wasm_table_index: w,
-                     ..
-                 }| w != wasm_table_index,
-            );
-
-            for info in ctx.pages.values_mut() {
-                info.hidden_wasm_table_indices
-                    .retain(|&w| w != wasm_table_index)
-            }
-
-            free_wasm_table_index(ctx, wasm_table_index);
+            free_module(ctx, wasm_table_index);
         }
     }
 
+    ctx.indirect_jump_targets.remove(&page);
+    ctx.code_chunks.remove(&page);
+
     match ctx.entry_points.remove(&page) {
         None => {},
         Some(_) => {
@@ -2243,7 +3147,7 @@ pub fn jit_dirty_page(ctx: &mut JitState, page: Page) {
 
     dbg_assert!(!jit_page_has_code_ctx(ctx, page));
 
-    if did_have_code {
+    if did_have_code || was_monitored {
         cpu::tlb_set_has_code(page, false);
     }
 
@@ -2252,33 +3156,93 @@ pub fn jit_dirty_page(ctx: &mut JitState, page: Page) {
     }
 }
 
-#[no_mangle]
-pub fn jit_dirty_cache(start_addr: u32, end_addr: u32) {
+/// Bitmap of the chunks touched by the bytes from start to end (exclusive) of a page
+fn code_chunk_mask(start: u32, end: u32) -> u64 {
+    dbg_assert!(start < end && end <= 0x1000);
+    let first = start >> CODE_CHUNK_SHIFT;
+    let last = (end - 1) >> CODE_CHUNK_SHIFT;
+    (u64::MAX >> (63 - last)) & (u64::MAX << first)
+}
+
+/// Mark the chunks of the physical address range from start to end (exclusive) as containing code
+fn mark_code_chunks(code_chunks: &mut HashMap<Page, u64>, start: u32, end: u32) {
+    let mut addr = start;
+    while addr < end {
+        let page = Page::page_of(addr);
+        let until = u32::min(end, page.to_address() + 0x1000);
+        *code_chunks.entry(page).or_insert(0) |=
+            code_chunk_mask(addr & 0xFFF, until - page.to_address());
+        addr = until;
+    }
+}
+
+/// Register a write to the given range of a page, which may only span a single page. Code in the
+/// page is only deleted if the range overlaps chunks that contain compiled code, pages with
+/// entry points but without compiled code are always reset
+pub fn jit_dirty_range(ctx: &mut JitState, start_addr: u32, end_addr: u32) {
     dbg_assert!(start_addr < end_addr);
+    let page = Page::page_of(start_addr);
+    dbg_assert!(page == Page::page_of(end_addr - 1));
 
-    let start_page = Page::page_of(start_addr);
-    let end_page = Page::page_of(end_addr - 1);
+    match ctx.code_chunks.get(&page) {
+        Some(&chunks)
+            if chunks & code_chunk_mask(start_addr & 0xFFF, end_addr - page.to_address()) == 0 =>
+        {
+            profiler::stat_increment(stat::DIRTY_PAGE_DID_NOT_WRITE_CODE);
+            cpu::monitor_write_hook(page);
+        },
+        _ => jit_dirty_page(ctx, page),
+    }
+}
 
-    for page in start_page.to_u32()..end_page.to_u32() + 1 {
-        jit_dirty_page(get_jit_state(), Page::page_of(page << 12));
+/// Register a write of the given bytes to physical memory, before it is performed. Like
+/// jit_dirty_range, but also keeps the code if the write doesn't change any bytes of it
+pub fn jit_dirty_write(ctx: &mut JitState, addr: u32, bytes: &[u8]) {
+    let len = bytes.len() as u32;
+    dbg_assert!(len > 0);
+
+    if addr & 0xFFF > 0x1000 - len {
+        // Note: This can't happen when paging is enabled, as writes across boundaries are split
+        //       up on two pages
+        let low_len = (0x1000 - (addr & 0xFFF)) as usize;
+        jit_dirty_write(ctx, addr, &bytes[..low_len]);
+        jit_dirty_write(ctx, addr + low_len as u32, &bytes[low_len..]);
+        return;
+    }
+
+    let page = Page::page_of(addr);
+    if let Some(&chunks) = ctx.code_chunks.get(&page) {
+        if chunks & code_chunk_mask(addr & 0xFFF, (addr & 0xFFF) + len) != 0 {
+            dbg_assert!(!memory::in_mapped_range(addr));
+            let current = unsafe {
+                std::slice::from_raw_parts(memory::mem8.add(addr as usize), len as usize)
+            };
+            if current == bytes {
+                profiler::stat_increment(stat::DIRTY_PAGE_CODE_UNCHANGED);
+                cpu::monitor_write_hook(page);
+                return;
+            }
+        }
     }
+
+    jit_dirty_range(ctx, addr, addr + len);
 }
 
-/// dirty pages in the range of start_addr and end_addr, which must span at most two pages
-pub fn jit_dirty_cache_small(start_addr: u32, end_addr: u32) {
+#[no_mangle]
+pub fn jit_dirty_cache(start_addr: u32, end_addr: u32) {
     dbg_assert!(start_addr < end_addr);
 
     let start_page = Page::page_of(start_addr);
     let end_page = Page::page_of(end_addr - 1);
 
     let ctx = get_jit_state();
-    jit_dirty_page(ctx, start_page);
-
-    // Note: This can't happen when paging is enabled, as writes across
-    //       boundaries are split up on two pages
-    if start_page != end_page {
-        dbg_assert!(start_page.to_u32() + 1 == end_page.to_u32());
-        jit_dirty_page(ctx, end_page);
+    for page in start_page.to_u32()..end_page.to_u32() + 1 {
+        let page = Page::page_of(page << 12);
+        jit_dirty_range(
+            ctx,
+            u32::max(start_addr, page.to_address()),
+            u32::min(end_addr - 1, page.to_address() | 0xFFF) + 1,
+        );
     }
 }
 
@@ -2298,6 +3262,8 @@ pub fn jit_clear_cache(ctx: &mut JitState) {
     for page in pages_with_code {
         jit_dirty_page(ctx, page);
     }
+
+    ctx.indirect_jump_targets.clear();
 }
 
 pub fn jit_page_has_code(page: Page) -> bool { jit_page_has_code_ctx(get_jit_state(), page) }
@@ -2308,12 +3274,7 @@ pub fn jit_page_has_code_ctx(ctx: &mut JitState, page: Page) -> bool {
 
 #[no_mangle]
 pub fn jit_get_wasm_table_index_free_list_count() -> u32 {
-    if cfg!(feature = "profiler") {
-        get_jit_state().wasm_table_index_free_list.len() as u32
-    }
-    else {
-        0
-    }
+    get_jit_state().wasm_table_index_free_list.len() as u32
 }
 #[no_mangle]
 pub fn jit_get_cache_size() -> u32 {
@@ -2329,6 +3290,356 @@ pub fn jit_get_cache_size() -> u32 {
     }
 }
 
+// Persistent cache
+//
+// Generated modules can be exported and imported into another instance of the same build of v86,
+// in order to skip warm-up and code generation of code that is loaded on every boot. A module is
+// installed when one of its pages is run for the first time and the contents of all the physical
+// pages it was generated from match. The format is:
+//
+// header (see jit_cache_header), module count, and for each module:
+//     state flags, tier, page count, for each page: (address, hash low, hash high, code chunks
+//     low, code chunks high, entry point count, entry points (offset | state << 16)), relocation
+//     count, relocations, code length, code
+//
+// All values are little-endian u32, the code is padded to a multiple of 4 bytes. Generated code
+// refers to mem8 and tlb_data through constant addresses and calls functions of this build, so
+// modules from another build, a different memory layout or generated with a different jit
+// configuration are rejected.
+//
+// The hashes of the page contents are the only guard against running stale code: If a page has
+// been modified since the cache was exported and the 64-bit FNV-1a of its new contents collides
+// with the old one, the module is installed and runs code that no longer matches memory. FNV-1a
+// isn't collision resistant, so collisions can be constructed by anyone who controls the memory
+// contents, and caches should only be imported for guests that are trusted as much as the cache.
+
+const JIT_CACHE_MAGIC: u32 = 0x6A363876; // "v86j"
+const JIT_CACHE_VERSION: u32 = 7;
+
+fn jit_cache_header() -> Vec<u32> {
+    let build_hash = env!("V86_BUILD_HASH").parse::<u32>().unwrap();
+    unsafe {
+        vec![
+            JIT_CACHE_MAGIC,
+            JIT_CACHE_VERSION,
+            build_hash,
+            memory::mem8 as u32,
+            std::ptr::addr_of!(cpu::tlb_data) as u32,
+            MAX_PAGES,
+            JIT_USE_LOOP_SAFETY as u32,
+            MAX_EXTRA_BASIC_BLOCKS,
+            JIT_USE_SIMD as u32,
+            JIT_USE_BULK_MEMORY as u32,
+        ]
+    }
+}
+
+/// 64-bit FNV-1a of the contents of a physical page. Not collision resistant, see the comment on
+/// the persistent cache above
+fn hash_page(page: Page) -> u64 {
+    dbg_assert!(!memory::in_mapped_range(page.to_address()));
+    let bytes =
+        unsafe { std::slice::from_raw_parts(memory::mem8.add(page.to_address() as usize), 4096) };
+    let mut hash: u64 = 0xCBF29CE484222325;
+    for &b in bytes {
+        hash = (hash ^ b as u64).wrapping_mul(0x100000001B3);
+    }
+    hash
+}
+
+/// Install a module from the persistent cache that covers the given page, as if it had just been
+/// compiled. Returns false if no matching module exists
+fn try_install_cached_module(
+    ctx: &mut JitState,
+    page: Page,
+    state_flags: CachedStateFlags,
+) -> bool {
+    dbg_assert!(ctx.compiling.is_none());
+
+    let index = match ctx.imported_modules_by_page.get(&page) {
+        None => return false,
+        Some(candidates) => candidates.iter().copied().find(|&i| {
+            let module = &ctx.imported_modules[i];
+            module.state_flags == state_flags
+                && module.pages.iter().all(|p| {
+                    !memory::in_mapped_range(p.page.to_address()) && hash_page(p.page) == p.hash
+                })
+        }),
+    };
+    let index = match index {
+        None => {
+            profiler::stat_increment(stat::JIT_CACHE_MISS);
+            return false;
+        },
+        Some(index) => index,
+    };
+
+    let wasm_table_index = allocate_wasm_table_index(ctx);
+
+    let module = &ctx.imported_modules[index];
+    ctx.wasm_table_index_tier[wasm_table_index.to_u16() as usize] = module.tier;
+    let mut code = module.code.clone();
+    for &offset in &module.relocations {
+        leb::write_fixed_leb16_at_idx(&mut code, offset as usize, wasm_table_index.to_u16());
+    }
+
+    let mut pages = HashSet::new();
+    let mut page_info = HashMap::new();
+    for p in &module.pages {
+        pages.insert(p.page);
+        // Like in jit_analyze_and_generate, pages without entries still get a page info, so that
+        // the module is invalidated when they are written
+        page_info.insert(
+            p.page,
+            PageInfo {
+                wasm_table_index,
+                state_flags,
+                entry_points: p.entry_points.clone(),
+                hidden_wasm_table_indices: Vec::new(),
+            },
+        );
+        *ctx.code_chunks.entry(p.page).or_insert(0) |= p.code_chunks;
+    }
+
+    if unsafe { JIT_CACHE_RECORDING } {
+        let recorded = CachedModule {
+            state_flags,
+            tier: module.tier,
+            pages: module
+                .pages
+                .iter()
+                .map(|p| CachedPage {
+                    page: p.page,
+                    hash: 0,
+                    code_chunks: p.code_chunks,
+                    entry_points: p.entry_points.clone(),
+                })
+                .collect(),
+            relocations: module.relocations.clone(),
+            code: module.code.clone(),
+        };
+        ctx.recorded_modules.insert(wasm_table_index, recorded);
+    }
+
+    for &p in &pages {
+        ctx.entry_points
+            .entry(p)
+            .or_insert_with(|| (0, HashSet::new()));
+    }
+
+    cpu::tlb_set_has_code_multiple(&pages, true);
+
+    ctx.compiling = Some((
+        wasm_table_index,
+        CompilingPageState::Compiling { pages: page_info },
+    ));
+    ctx.installing_cached_module = Some(index);
+
+    profiler::stat_increment(stat::JIT_CACHE_HIT);
+
+    // will call codegen_finalize_finished asynchronously when finished
+    codegen_finalize(
+        wasm_table_index,
+        page.to_address(),
+        state_flags,
+        code.as_ptr() as u32,
+        code.len() as u32,
+    );
+
+    check_jit_state_invariants(ctx);
+
+    true
+}
+
+/// Write all modules that are currently installed into the cache buffer, returns its length.
+/// Requires JIT_CACHE_RECORDING to be enabled before the modules are generated
+#[no_mangle]
+pub fn jit_cache_export() -> u32 {
+    fn write_u32(out: &mut Vec<u8>, x: u32) { out.extend_from_slice(&x.to_le_bytes()) }
+
+    let ctx = get_jit_state();
+
+    let mut used: Vec<WasmTableIndex> =
+        ctx.pages.values().map(|info| info.wasm_table_index).collect();
+    used.sort_unstable_by_key(|index| index.to_u16());
+    used.dedup();
+
+    let modules: Vec<&CachedModule> = used
+        .iter()
+        .filter_map(|index| ctx.recorded_modules.get(index))
+        .collect();
+
+    let mut out = Vec::new();
+    for x in jit_cache_header() {
+        write_u32(&mut out, x);
+    }
+    write_u32(&mut out, modules.len() as u32);
+
+    for module in modules {
+        write_u32(&mut out, module.state_flags.to_u32());
+        write_u32(&mut out, module.tier as u32);
+        write_u32(&mut out, module.pages.len() as u32);
+        for p in &module.pages {
+            let hash = hash_page(p.page);
+            write_u32(&mut out, p.page.to_address());
+            write_u32(&mut out, hash as u32);
+            write_u32(&mut out, (hash >> 32) as u32);
+            write_u32(&mut out, p.code_chunks as u32);
+            write_u32(&mut out, (p.code_chunks >> 32) as u32);
+            write_u32(&mut out, p.entry_points.len() as u32);
+            for &(offset, state) in &p.entry_points {
+                write_u32(&mut out, offset as u32 | (state as u32) << 16);
+            }
+        }
+        write_u32(&mut out, module.relocations.len() as u32);
+        for &offset in &module.relocations {
+            write_u32(&mut out, offset);
+        }
+        write_u32(&mut out, module.code.len() as u32);
+        out.extend_from_slice(&module.code);
+        while out.len() % 4 != 0 {
+            out.push(0);
+        }
+    }
+
+    let len = out.len() as u32;
+    ctx.cache_buffer = out;
+    len
+}
+
+#[no_mangle]
+pub fn jit_cache_get_buffer() -> u32 { get_jit_state().cache_buffer.as_ptr() as u32 }
+
+/// Allocate the cache buffer, for writing the data passed to jit_cache_import
+#[no_mangle]
+pub fn jit_cache_allocate_buffer(len: u32) -> u32 {
+    let ctx = get_jit_state();
+    ctx.cache_buffer = vec![0; len as usize];
+    ctx.cache_buffer.as_ptr() as u32
+}
+
+/// Add the modules in the cache buffer to the persistent cache. Returns false if the data is
+/// malformed or comes from an incompatible build
+#[no_mangle]
+pub fn jit_cache_import() -> bool {
+    struct Reader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+    impl<'a> Reader<'a> {
+        fn u32(&mut self) -> Option<u32> {
+            let bytes = self.bytes(4)?;
+            Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        }
+        fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+            if self.data.len() - self.pos < len {
+                return None;
+            }
+            let result = &self.data[self.pos..self.pos + len];
+            self.pos += len;
+            Some(result)
+        }
+    }
+
+    fn read_module(r: &mut Reader) -> Option<CachedModule> {
+        let state_flags = r.u32()?;
+        if !CachedStateFlags::is_valid(state_flags) {
+            return None;
+        }
+        let tier = match r.u32()? {
+            0 => CompilationTier::Baseline,
+            1 => CompilationTier::Optimising,
+            _ => return None,
+        };
+        let page_count = r.u32()?;
+        if page_count == 0 || page_count > 0x1000 {
+            return None;
+        }
+        let mut pages = Vec::new();
+        for _ in 0..page_count {
+            let address = r.u32()?;
+            let hash = r.u32()? as u64 | (r.u32()? as u64) << 32;
+            let code_chunks = r.u32()? as u64 | (r.u32()? as u64) << 32;
+            if code_chunks == 0 {
+                return None;
+            }
+            let entry_point_count = r.u32()?;
+            let mut entry_points = Vec::new();
+            for _ in 0..entry_point_count {
+                let entry = r.u32()?;
+                entry_points.push((entry as u16 & 0xFFF, (entry >> 16) as u16));
+            }
+            pages.push(CachedPage {
+                page: Page::page_of(address),
+                hash,
+                code_chunks,
+                entry_points,
+            });
+        }
+        let relocation_count = r.u32()?;
+        let mut relocations = Vec::new();
+        for _ in 0..relocation_count {
+            relocations.push(r.u32()?);
+        }
+        let code_len = r.u32()? as usize;
+        let code = r.bytes(code_len)?.to_vec();
+        r.bytes((4 - code_len % 4) % 4)?;
+        if relocations.iter().any(|&offset| offset as usize + 2 > code.len()) {
+            return None;
+        }
+        Some(CachedModule {
+            state_flags: CachedStateFlags::of_u32(state_flags),
+            tier,
+            pages,
+            relocations,
+            code,
+        })
+    }
+
+    let ctx = get_jit_state();
+    let data = mem::replace(&mut ctx.cache_buffer, Vec::new());
+    let r = &mut Reader {
+        data: &data,
+        pos: 0,
+    };
+
+    for x in jit_cache_header() {
+        if r.u32() != Some(x) {
+            dbg_log!("jit cache: incompatible format, build, memory layout or configuration");
+            return false;
+        }
+    }
+
+    let module_count = match r.u32() {
+        None => return false,
+        Some(count) => count,
+    };
+    let mut modules = Vec::new();
+    for _ in 0..module_count {
+        match read_module(r) {
+            None => {
+                dbg_log!("jit cache: malformed module");
+                return false;
+            },
+            Some(module) => modules.push(module),
+        }
+    }
+
+    for module in modules {
+        let index = ctx.imported_modules.len();
+        for p in &module.pages {
+            ctx.imported_modules_by_page
+                .entry(p.page)
+                .or_insert_with(Vec::new)
+                .push(index);
+        }
+        ctx.imported_modules.push(module);
+    }
+
+    dbg_log!("jit cache: {} modules imported", module_count);
+    true
+}
+
 #[cfg(feature = "profiler")]
 pub fn check_missed_entry_points(phys_address: u32, state_flags: CachedStateFlags) {
     let ctx = get_jit_state();
@@ -2399,6 +3710,10 @@ pub unsafe fn set_jit_config(index: u32, value: u32) {
         1 => MAX_PAGES = value,
         2 => JIT_USE_LOOP_SAFETY = value != 0,
         3 => MAX_EXTRA_BASIC_BLOCKS = value,
+        4 => JIT_CACHE_RECORDING = value != 0,
+        5 => JIT_USE_SIMD = value != 0,
+        6 => JIT_USE_BULK_MEMORY = value != 0,
+        7 => CHECK_JIT_STATE_INVARIANTS = value != 0,
         _ => dbg_assert!(false),
     }
 }
@@ -2410,6 +3725,45 @@ pub unsafe fn get_jit_config(index: u32) -> u32 {
         1 => MAX_PAGES as u32,
         2 => JIT_USE_LOOP_SAFETY as u32,
         3 => MAX_EXTRA_BASIC_BLOCKS as u32,
+        4 => JIT_CACHE_RECORDING as u32,
+        5 => JIT_USE_SIMD as u32,
+        6 => JIT_USE_BULK_MEMORY as u32,
+        7 => CHECK_JIT_STATE_INVARIANTS as u32,
         _ => 0,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{code_chunk_mask, mark_code_chunks};
+    use page::Page;
+    use std::collections::HashMap;
+
+    #[test]
+    fn code_chunk_mask_edges() {
+        assert_eq!(code_chunk_mask(0, 1), 1);
+        assert_eq!(code_chunk_mask(63, 64), 1);
+        assert_eq!(code_chunk_mask(0, 64), 1);
+        assert_eq!(code_chunk_mask(64, 65), 1 << 1);
+        assert_eq!(code_chunk_mask(63, 65), 1 << 0 | 1 << 1);
+        assert_eq!(code_chunk_mask(4095, 4096), 1 << 63);
+        assert_eq!(code_chunk_mask(4032, 4096), 1 << 63);
+        assert_eq!(code_chunk_mask(0, 4096), u64::MAX);
+        assert_eq!(code_chunk_mask(60, 200), 1 << 0 | 1 << 1 | 1 << 2 | 1 << 3);
+        assert_eq!(code_chunk_mask(128, 192), 1 << 2);
+    }
+
+    #[test]
+    fn mark_code_chunks_across_pages() {
+        let mut code_chunks = HashMap::new();
+        mark_code_chunks(&mut code_chunks, 0x1FF0, 0x2041);
+        assert_eq!(code_chunks.len(), 2);
+        assert_eq!(code_chunks[&Page::page_of(0x1000)], 1 << 63);
+        assert_eq!(code_chunks[&Page::page_of(0x2000)], 1 << 0 | 1 << 1);
+
+        mark_code_chunks(&mut code_chunks, 0x2000, 0x2001);
+        mark_code_chunks(&mut code_chunks, 0x1040, 0x1080);
+        assert_eq!(code_chunks[&Page::page_of(0x1000)], 1 << 63 | 1 << 1);
+        assert_eq!(code_chunks[&Page::page_of(0x2000)], 1 << 0 | 1 << 1);
+    }
+}
@@ -3,19 +3,22 @@
 use codegen;
 use codegen::{BitSize, ConditionNegate};
 use cpu::cpu::{
-    FLAGS_ALL, FLAGS_DEFAULT, FLAGS_MASK, FLAG_ADJUST, FLAG_CARRY, FLAG_DIRECTION, FLAG_INTERRUPT,
-    FLAG_IOPL, FLAG_OVERFLOW, FLAG_SUB, FLAG_VM, FLAG_ZERO, OPSIZE_16, OPSIZE_32, OPSIZE_8,
+    CR4_VME, FLAGS_ALL, FLAGS_DEFAULT, FLAGS_MASK, FLAG_ADJUST, FLAG_CARRY, FLAG_DIRECTION,
+    FLAG_INTERRUPT, FLAG_IOPL, FLAG_OVERFLOW, FLAG_SUB, FLAG_VM, FLAG_ZERO, OPSIZE_16, OPSIZE_32,
+    OPSIZE_8,
 };
 use cpu::global_pointers;
+use jit;
 use jit::{Instruction, InstructionOperand, InstructionOperandDest, JitContext};
 use modrm::{jit_add_seg_offset, jit_add_seg_offset_no_override, ModrmByte};
 use prefix::SEG_PREFIX_ZERO;
-use prefix::{PREFIX_66, PREFIX_67, PREFIX_F2, PREFIX_F3};
+use prefix::{PREFIX_66, PREFIX_67, PREFIX_F2, PREFIX_F3, PREFIX_LOCK};
+use profiler;
 use regs;
 use regs::{AX, BP, BX, CX, DI, DX, SI, SP};
 use regs::{CS, DS, ES, FS, GS, SS};
 use regs::{EAX, EBP, EBX, ECX, EDI, EDX, ESI, ESP};
-use wasmgen::wasm_builder::{WasmBuilder, WasmLocal};
+use wasmgen::wasm_builder::{Label, WasmBuilder, WasmLocal};
 
 enum LocalOrImmediate<'a> {
     WasmLocal(&'a WasmLocal),
@@ -131,7 +134,8 @@ pub fn instr_67_jit(ctx: &mut JitContext, instr_flags: &mut u32) {
     jit_handle_prefix(ctx, instr_flags)
 }
 pub fn instr_F0_jit(ctx: &mut JitContext, instr_flags: &mut u32) {
-    // lock: Ignore
+    codegen::gen_profiler_stat_increment(ctx.builder, profiler::stat::LOCKED_OPERATION);
+    ctx.cpu.prefixes |= PREFIX_LOCK;
     jit_handle_prefix(ctx, instr_flags)
 }
 pub fn instr_F2_jit(ctx: &mut JitContext, instr_flags: &mut u32) {
@@ -147,35 +151,75 @@ fn sse_read_f32_xmm_mem(ctx: &mut JitContext, name: &str, modrm_byte: ModrmByte,
     codegen::gen_modrm_resolve_safe_read32(ctx, modrm_byte);
     ctx.builder.reinterpret_i32_as_f32();
     ctx.builder.const_i32(r as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
     ctx.builder.call_fn2_f32_i32(name);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 fn sse_read_f32_xmm_xmm(ctx: &mut JitContext, name: &str, r1: u32, r2: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r1) as i32);
-    ctx.builder.load_aligned_f32(0);
+    codegen::gen_get_reg_xmm_dword(ctx, r1, 0);
+    ctx.builder.reinterpret_i32_as_f32();
     ctx.builder.const_i32(r2 as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r2);
     ctx.builder.call_fn2_f32_i32(name);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r2);
 }
 
 fn sse_read64_xmm_mem(ctx: &mut JitContext, name: &str, modrm_byte: ModrmByte, r: u32) {
     codegen::gen_modrm_resolve_safe_read64(ctx, modrm_byte);
     ctx.builder.const_i32(r as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
+    ctx.builder.call_fn2_i64_i32(name);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
+}
+fn sse_read64_xmm_mem_aligned(ctx: &mut JitContext, name: &str, modrm_byte: ModrmByte, r: u32) {
+    // Only the low quadword of the m128 operand is used, but it must still be aligned
+    codegen::gen_modrm_resolve_with_local(ctx, modrm_byte, &|ctx, addr| {
+        codegen::gen_trigger_gp_if_unaligned(ctx, addr, BitSize::DQWORD);
+        codegen::gen_safe_read64(ctx, addr);
+    });
+    ctx.builder.const_i32(r as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
     ctx.builder.call_fn2_i64_i32(name);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 fn sse_read64_xmm_xmm(ctx: &mut JitContext, name: &str, r1: u32, r2: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r1) as i32);
-    ctx.builder.load_aligned_i64(0);
+    codegen::gen_get_reg_xmm_low(ctx, r1);
     ctx.builder.const_i32(r2 as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r2);
     ctx.builder.call_fn2_i64_i32(name);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r2);
 }
 
+fn sse_write128_mem_xmm(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32, aligned: bool) {
+    codegen::gen_modrm_resolve(ctx, modrm_byte);
+    let address_local = ctx.builder.set_new_local();
+    codegen::gen_get_reg_xmm_low(ctx, r);
+    let value_local_low = ctx.builder.set_new_local_i64();
+    codegen::gen_get_reg_xmm_high(ctx, r);
+    let value_local_high = ctx.builder.set_new_local_i64();
+    if aligned {
+        codegen::gen_safe_write128_aligned(
+            ctx,
+            &address_local,
+            &value_local_low,
+            &value_local_high,
+        );
+    }
+    else {
+        codegen::gen_safe_write128(ctx, &address_local, &value_local_low, &value_local_high);
+    }
+    ctx.builder.free_local(address_local);
+    ctx.builder.free_local_i64(value_local_low);
+    ctx.builder.free_local_i64(value_local_high);
+}
 fn sse_read128_xmm_mem(ctx: &mut JitContext, name: &str, modrm_byte: ModrmByte, r: u32) {
     let dest = global_pointers::sse_scratch_register as u32;
-    codegen::gen_modrm_resolve_safe_read128(ctx, modrm_byte, dest);
+    codegen::gen_modrm_resolve_safe_read128_aligned(ctx, modrm_byte, dest);
     ctx.builder.const_i32(dest as i32);
     ctx.builder.const_i32(r as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
     ctx.builder.call_fn2(name);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 fn sse_read128_xmm_mem_imm(
     ctx: &mut JitContext,
@@ -185,11 +229,13 @@ fn sse_read128_xmm_mem_imm(
     imm: u32,
 ) {
     let dest = global_pointers::sse_scratch_register as u32;
-    codegen::gen_modrm_resolve_safe_read128(ctx, modrm_byte, dest);
+    codegen::gen_modrm_resolve_safe_read128_aligned(ctx, modrm_byte, dest);
     ctx.builder.const_i32(dest as i32);
     ctx.builder.const_i32(r as i32);
     ctx.builder.const_i32(imm as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
     ctx.builder.call_fn3(name);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 fn sse_read128_xmm_xmm(ctx: &mut JitContext, name: &str, r1: u32, r2: u32) {
     // Make a copy to avoid aliasing problems: Called function expects a reg128, which must not
@@ -198,7 +244,9 @@ fn sse_read128_xmm_xmm(ctx: &mut JitContext, name: &str, r1: u32, r2: u32) {
     let dest = global_pointers::sse_scratch_register;
     ctx.builder.const_i32(dest as i32);
     ctx.builder.const_i32(r2 as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r2);
     ctx.builder.call_fn2(name);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r2);
 }
 fn sse_read128_xmm_xmm_imm(ctx: &mut JitContext, name: &str, r1: u32, r2: u32, imm: u32) {
     // Make a copy to avoid aliasing problems: Called function expects a reg128, which must not
@@ -208,22 +256,142 @@ fn sse_read128_xmm_xmm_imm(ctx: &mut JitContext, name: &str, r1: u32, r2: u32, i
     ctx.builder.const_i32(dest as i32);
     ctx.builder.const_i32(r2 as i32);
     ctx.builder.const_i32(imm as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r2);
     ctx.builder.call_fn3(name);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r2);
 }
 fn sse_mov_xmm_xmm(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r2) as i32);
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r1) as i32);
-    ctx.builder.load_aligned_i64(0);
-    ctx.builder.store_aligned_i64(0);
+    codegen::gen_get_reg_xmm_low(ctx, r1);
+    codegen::gen_set_reg_xmm_low(ctx, r2);
+    codegen::gen_get_reg_xmm_high(ctx, r1);
+    codegen::gen_set_reg_xmm_high(ctx, r2);
+}
 
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r2) as i32 + 8);
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r1) as i32 + 8);
-    ctx.builder.load_aligned_i64(0);
-    ctx.builder.store_aligned_i64(0);
+/// Packed integer and bitwise operations that map directly to a wasm simd instruction. `op` is
+/// called with the destination and the source on the stack and leaves the result. Falls back to
+/// the helper `name` if the host doesn't support simd
+fn sse_simd_xmm_mem(
+    ctx: &mut JitContext,
+    name: &str,
+    modrm_byte: ModrmByte,
+    r: u32,
+    op: &dyn Fn(&mut WasmBuilder),
+) {
+    if !jit::jit_use_simd() {
+        sse_read128_xmm_mem(ctx, name, modrm_byte, r);
+        return;
+    }
+    let source = global_pointers::sse_scratch_register as u32;
+    codegen::gen_modrm_resolve_safe_read128_aligned(ctx, modrm_byte, source);
+    codegen::gen_get_reg_xmm_v128(ctx, r);
+    ctx.builder.const_i32(source as i32);
+    ctx.builder.load_aligned_v128(0);
+    op(ctx.builder);
+    codegen::gen_set_reg_xmm_v128(ctx, r);
+}
+fn sse_simd_xmm_xmm(
+    ctx: &mut JitContext,
+    name: &str,
+    r1: u32,
+    r2: u32,
+    op: &dyn Fn(&mut WasmBuilder),
+) {
+    if !jit::jit_use_simd() {
+        sse_read128_xmm_xmm(ctx, name, r1, r2);
+        return;
+    }
+    codegen::gen_get_reg_xmm_v128(ctx, r2);
+    codegen::gen_get_reg_xmm_v128(ctx, r1);
+    op(ctx.builder);
+    codegen::gen_set_reg_xmm_v128(ctx, r2);
+}
+
+/// pandn and andnps: v128.andnot computes a & ~b, while sse inverts the destination
+fn gen_andnot_reversed(builder: &mut WasmBuilder) {
+    let source = builder.set_new_local_v128();
+    let dest = builder.set_new_local_v128();
+    builder.get_local_v128(&source);
+    builder.get_local_v128(&dest);
+    builder.andnot_v128();
+    builder.free_local_v128(source);
+    builder.free_local_v128(dest);
+}
+
+/// Shuffle lanes for the punpck and unpck families: Interleave the elements of `size` bytes from
+/// the low (or high) halves of the destination and the source
+fn interleave_lanes(size: u8, high: bool) -> [u8; 16] {
+    let mut lanes = [0; 16];
+    let base = if high { 8 } else { 0 };
+    for i in 0..16 {
+        let element = i / (2 * size);
+        let from_source = (i / size) % 2;
+        lanes[i as usize] = 16 * from_source + base + element * size + i % size;
+    }
+    lanes
+}
+fn gen_interleave(size: u8, high: bool) -> impl Fn(&mut WasmBuilder) {
+    let lanes = interleave_lanes(size, high);
+    move |builder: &mut WasmBuilder| builder.shuffle_i8x16(&lanes)
+}
+
+/// psrlw and friends: Shift each lane of `r` by an immediate. Counts of at least the lane width
+/// clear the lanes (or fill them with the sign bit), whereas wasm takes the count modulo the lane
+/// width
+fn sse_shift_imm(
+    ctx: &mut JitContext,
+    name: &str,
+    r: u32,
+    imm8: u32,
+    lane_bits: u32,
+    arithmetic: bool,
+    op: &dyn Fn(&mut WasmBuilder),
+) {
+    if !jit::jit_use_simd() {
+        ctx.builder.const_i32(r as i32);
+        ctx.builder.const_i32(imm8 as i32);
+        codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
+        ctx.builder.call_fn2(name);
+        codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
+        return;
+    }
+    if imm8 >= lane_bits && !arithmetic {
+        ctx.builder.const_v128(0);
+    }
+    else {
+        codegen::gen_get_reg_xmm_v128(ctx, r);
+        ctx.builder.const_i32(imm8.min(lane_bits - 1) as i32);
+        op(ctx.builder);
+    }
+    codegen::gen_set_reg_xmm_v128(ctx, r);
+}
+/// psrldq and pslldq: Shift the whole register by bytes, shuffling in zeroes
+fn sse_shift_bytes_imm(ctx: &mut JitContext, name: &str, r: u32, imm8: u32, right: bool) {
+    if !jit::jit_use_simd() {
+        ctx.builder.const_i32(r as i32);
+        ctx.builder.const_i32(imm8 as i32);
+        codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
+        ctx.builder.call_fn2(name);
+        codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
+        return;
+    }
+    if imm8 >= 16 {
+        ctx.builder.const_v128(0);
+    }
+    else {
+        let mut lanes = [16; 16];
+        for i in 0..16 {
+            if right && i + imm8 < 16 {
+                lanes[i as usize] = (i + imm8) as u8;
+            }
+            else if !right && i >= imm8 {
+                lanes[i as usize] = (i - imm8) as u8;
+            }
+        }
+        codegen::gen_get_reg_xmm_v128(ctx, r);
+        ctx.builder.const_v128(0);
+        ctx.builder.shuffle_i8x16(&lanes);
+    }
+    codegen::gen_set_reg_xmm_v128(ctx, r);
 }
 
 fn mmx_read64_mm_mem32(ctx: &mut JitContext, name: &str, modrm_byte: ModrmByte, r: u32) {
@@ -998,13 +1166,19 @@ fn gen_add32(ctx: &mut JitContext, dest_operand: &WasmLocal, source_operand: &Lo
         is_inc: false,
     };
 
-    codegen::gen_set_last_op1(ctx.builder, &dest_operand);
+    if !ctx.flags_unused {
+        codegen::gen_set_last_op1(ctx.builder, &dest_operand);
+    }
 
     ctx.builder.get_local(&dest_operand);
     source_operand.gen_get(ctx.builder);
     ctx.builder.add_i32();
     ctx.builder.set_local(dest_operand);
 
+    if ctx.flags_unused {
+        return;
+    }
+
     codegen::gen_set_last_result(ctx.builder, &dest_operand);
     codegen::gen_set_last_op_size_and_flags_changed(ctx.builder, OPSIZE_32, FLAGS_ALL);
 }
@@ -1054,13 +1228,19 @@ fn gen_sub32(ctx: &mut JitContext, dest_operand: &WasmLocal, source_operand: &Lo
         is_dec: false,
     };
 
-    codegen::gen_set_last_op1(ctx.builder, &dest_operand);
+    if !ctx.flags_unused {
+        codegen::gen_set_last_op1(ctx.builder, &dest_operand);
+    }
 
     ctx.builder.get_local(&dest_operand);
     source_operand.gen_get(ctx.builder);
     ctx.builder.sub_i32();
     ctx.builder.set_local(dest_operand);
 
+    if ctx.flags_unused {
+        return;
+    }
+
     codegen::gen_set_last_result(ctx.builder, &dest_operand);
     codegen::gen_set_last_op_size_and_flags_changed(ctx.builder, OPSIZE_32, FLAGS_ALL | FLAG_SUB);
 }
@@ -1077,6 +1257,10 @@ fn gen_cmp(
         opsize: size,
     };
 
+    if ctx.flags_unused {
+        return;
+    }
+
     ctx.builder.const_i32(global_pointers::last_result as i32);
     if source_operand.is_zero() {
         ctx.builder.get_local(&dest_operand);
@@ -1344,6 +1528,10 @@ fn gen_and32(ctx: &mut JitContext, dest_operand: &WasmLocal, source_operand: &Lo
     ctx.builder.and_i32();
     ctx.builder.set_local(dest_operand);
 
+    if ctx.flags_unused {
+        return;
+    }
+
     codegen::gen_set_last_result(ctx.builder, &dest_operand);
     codegen::gen_set_last_op_size_and_flags_changed(
         ctx.builder,
@@ -1370,6 +1558,10 @@ fn gen_test(
         },
     };
 
+    if ctx.flags_unused {
+        return;
+    }
+
     ctx.builder.const_i32(global_pointers::last_result as i32);
     if is_self_test {
         ctx.builder.get_local(&dest_operand);
@@ -1431,6 +1623,10 @@ fn gen_or32(ctx: &mut JitContext, dest_operand: &WasmLocal, source_operand: &Loc
     ctx.builder.or_i32();
     ctx.builder.set_local(dest_operand);
 
+    if ctx.flags_unused {
+        return;
+    }
+
     codegen::gen_set_last_result(ctx.builder, &dest_operand);
     codegen::gen_set_last_op_size_and_flags_changed(
         ctx.builder,
@@ -1482,6 +1678,10 @@ fn gen_xor32(ctx: &mut JitContext, dest_operand: &WasmLocal, source_operand: &Lo
         ctx.builder.set_local(dest_operand);
     }
 
+    if ctx.flags_unused {
+        return;
+    }
+
     codegen::gen_set_last_result(ctx.builder, &dest_operand);
     codegen::gen_set_last_op_size_and_flags_changed(
         ctx.builder,
@@ -3424,14 +3624,48 @@ pub fn instr_D7_jit(ctx: &mut JitContext) {
 }
 
 fn instr_group_D8_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, op: &str) {
-    ctx.builder.const_i32(0);
-    codegen::gen_fpu_load_m32(ctx, modrm_byte);
-    ctx.builder.call_fn3_i32_i64_i32(op)
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_load_m32_f64(ctx, modrm_byte);
+        gen_fpu_arith_f64(ctx, 0, op);
+    }
+    else {
+        ctx.builder.const_i32(0);
+        codegen::gen_fpu_load_m32(ctx, modrm_byte);
+        ctx.builder.call_fn3_i32_i64_i32(op)
+    }
 }
 fn instr_group_D8_reg_jit(ctx: &mut JitContext, r: u32, op: &str) {
-    ctx.builder.const_i32(0);
-    codegen::gen_fpu_get_sti(ctx, r);
-    ctx.builder.call_fn3_i32_i64_i32(op)
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_get_sti_f64(ctx, r);
+        gen_fpu_arith_f64(ctx, 0, op);
+    }
+    else {
+        ctx.builder.const_i32(0);
+        codegen::gen_fpu_get_sti(ctx, r);
+        ctx.builder.call_fn3_i32_i64_i32(op)
+    }
+}
+/// st(target_index) = st(0) op val, where val is an f64 on the stack and op is the name of one of
+/// fpu_fadd, fpu_fmul, fpu_fsub, fpu_fsubr, fpu_fdiv or fpu_fdivr
+fn gen_fpu_arith_f64(ctx: &mut JitContext, target_index: u32, op: &str) {
+    let val = ctx.builder.set_new_local_f64();
+    if op == "fpu_fsubr" || op == "fpu_fdivr" {
+        ctx.builder.get_local_f64(&val);
+        codegen::gen_fpu_get_sti_f64(ctx, 0);
+    }
+    else {
+        codegen::gen_fpu_get_sti_f64(ctx, 0);
+        ctx.builder.get_local_f64(&val);
+    }
+    ctx.builder.free_local_f64(val);
+    match op {
+        "fpu_fadd" => ctx.builder.add_f64(),
+        "fpu_fmul" => ctx.builder.mul_f64(),
+        "fpu_fsub" | "fpu_fsubr" => ctx.builder.sub_f64(),
+        "fpu_fdiv" | "fpu_fdivr" => ctx.builder.div_f64(),
+        _ => dbg_assert!(false),
+    }
+    codegen::gen_fpu_set_sti_f64(ctx, target_index);
 }
 
 pub fn instr_D8_0_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
@@ -3488,12 +3722,24 @@ pub fn instr_D8_7_reg_jit(ctx: &mut JitContext, r: u32) {
 }
 
 pub fn instr16_D9_0_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
-    codegen::gen_fpu_load_m32(ctx, modrm_byte);
-    ctx.builder.call_fn2_i64_i32("fpu_push");
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_load_m32_f64(ctx, modrm_byte);
+        codegen::gen_fpu_push_f64(ctx);
+    }
+    else {
+        codegen::gen_fpu_load_m32(ctx, modrm_byte);
+        ctx.builder.call_fn2_i64_i32("fpu_push");
+    }
 }
 pub fn instr16_D9_0_reg_jit(ctx: &mut JitContext, r: u32) {
-    codegen::gen_fpu_get_sti(ctx, r);
-    ctx.builder.call_fn2_i64_i32("fpu_push");
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_get_sti_f64(ctx, r);
+        codegen::gen_fpu_push_f64(ctx);
+    }
+    else {
+        codegen::gen_fpu_get_sti(ctx, r);
+        ctx.builder.call_fn2_i64_i32("fpu_push");
+    }
 }
 pub fn instr32_D9_0_reg_jit(ctx: &mut JitContext, r: u32) { instr16_D9_0_reg_jit(ctx, r) }
 pub fn instr32_D9_0_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
@@ -3505,8 +3751,16 @@ pub fn instr16_D9_1_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
     codegen::gen_trigger_ud(ctx);
 }
 pub fn instr16_D9_1_reg_jit(ctx: &mut JitContext, r: u32) {
-    ctx.builder.const_i32(r as i32);
-    ctx.builder.call_fn1("fpu_fxch");
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_get_sti_f64(ctx, r);
+        codegen::gen_fpu_get_sti_f64(ctx, 0);
+        codegen::gen_fpu_set_sti_f64(ctx, r);
+        codegen::gen_fpu_set_sti_f64(ctx, 0);
+    }
+    else {
+        ctx.builder.const_i32(r as i32);
+        ctx.builder.call_fn1("fpu_fxch");
+    }
 }
 pub fn instr32_D9_1_reg_jit(ctx: &mut JitContext, r: u32) { instr16_D9_1_reg_jit(ctx, r) }
 pub fn instr32_D9_1_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
@@ -3516,8 +3770,15 @@ pub fn instr32_D9_1_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
 pub fn instr16_D9_2_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
     codegen::gen_modrm_resolve(ctx, modrm_byte);
     let address_local = ctx.builder.set_new_local();
-    codegen::gen_fpu_get_sti(ctx, 0);
-    ctx.builder.call_fn2_i64_i32_ret("f80_to_f32");
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_get_sti_f64(ctx, 0);
+        ctx.builder.demote_f64_to_f32();
+        ctx.builder.reinterpret_f32_as_i32();
+    }
+    else {
+        codegen::gen_fpu_get_sti(ctx, 0);
+        ctx.builder.call_fn2_i64_i32_ret("f80_to_f32");
+    }
     let value_local = ctx.builder.set_new_local();
     codegen::gen_safe_write32(ctx, &address_local, &value_local);
     ctx.builder.free_local(address_local);
@@ -3534,15 +3795,13 @@ pub fn instr32_D9_2_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
 }
 
 pub fn instr16_D9_3_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
-    codegen::gen_modrm_resolve(ctx, modrm_byte);
-    let address_local = ctx.builder.set_new_local();
-    codegen::gen_fpu_get_sti(ctx, 0);
-    ctx.builder.call_fn2_i64_i32_ret("f80_to_f32");
-    let value_local = ctx.builder.set_new_local();
-    codegen::gen_safe_write32(ctx, &address_local, &value_local);
-    ctx.builder.free_local(address_local);
-    ctx.builder.free_local(value_local);
-    codegen::gen_fn0_const(ctx.builder, "fpu_pop");
+    instr16_D9_2_mem_jit(ctx, modrm_byte);
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_pop_f64(ctx);
+    }
+    else {
+        codegen::gen_fn0_const(ctx.builder, "fpu_pop");
+    }
 }
 pub fn instr16_D9_3_reg_jit(ctx: &mut JitContext, r: u32) {
     codegen::gen_fn1_const(ctx.builder, "fpu_fstp", r);
@@ -3553,6 +3812,13 @@ pub fn instr32_D9_3_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
 }
 
 pub fn instr16_D9_4_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
+    gen_fpu_env(ctx, modrm_byte, "fpu_fldenv16")
+}
+pub fn instr32_D9_4_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
+    gen_fpu_env(ctx, modrm_byte, "fpu_fldenv32")
+}
+/// fldenv/fstenv, which signal page faults through the page_fault global
+fn gen_fpu_env(ctx: &mut JitContext, modrm_byte: ModrmByte, name: &str) {
     codegen::gen_modrm_resolve(ctx, modrm_byte);
 
     codegen::gen_set_previous_eip_offset_from_eip_with_low_bits(
@@ -3561,7 +3827,7 @@ pub fn instr16_D9_4_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
     );
 
     codegen::gen_move_registers_from_locals_to_memory(ctx);
-    ctx.builder.call_fn1("fpu_fldenv32");
+    ctx.builder.call_fn1(name);
     codegen::gen_move_registers_from_memory_to_locals(ctx);
 
     codegen::gen_get_page_fault(ctx.builder);
@@ -3569,9 +3835,21 @@ pub fn instr16_D9_4_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
     codegen::gen_debug_track_jit_exit(ctx.builder, ctx.start_of_current_instruction);
     ctx.builder.br(ctx.exit_label);
     ctx.builder.block_end();
+
+    codegen::gen_exit_if_state_flags_changed(ctx);
 }
 pub fn instr16_D9_4_reg_jit(ctx: &mut JitContext, r: u32) {
     match r {
+        0 | 1 if ctx.fpu_f64 => {
+            codegen::gen_fpu_get_sti_f64(ctx, 0);
+            if r == 0 {
+                ctx.builder.neg_f64();
+            }
+            else {
+                ctx.builder.abs_f64();
+            }
+            codegen::gen_fpu_set_sti_f64(ctx, 0);
+        },
         0 | 1 | 4 | 5 => {
             ctx.builder.const_i32(r as i32);
             ctx.builder.call_fn1("instr16_D9_4_reg");
@@ -3580,18 +3858,22 @@ pub fn instr16_D9_4_reg_jit(ctx: &mut JitContext, r: u32) {
     }
 }
 pub fn instr32_D9_4_reg_jit(ctx: &mut JitContext, r: u32) { instr16_D9_4_reg_jit(ctx, r) }
-pub fn instr32_D9_4_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
-    instr16_D9_4_mem_jit(ctx, modrm_byte)
-}
 
 pub fn instr16_D9_5_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
     codegen::gen_modrm_resolve_safe_read16(ctx, modrm_byte);
     ctx.builder.call_fn1("set_control_word");
+    codegen::gen_exit_if_state_flags_changed(ctx);
 }
 pub fn instr16_D9_5_reg_jit(ctx: &mut JitContext, r: u32) {
     if r == 7 {
         codegen::gen_trigger_ud(ctx);
     }
+    else if ctx.fpu_f64 {
+        dbg_assert!(r == 0 || r == 6);
+        // fld1, fldz
+        ctx.builder.const_f64(if r == 0 { 1.0 } else { 0.0 });
+        codegen::gen_fpu_push_f64(ctx);
+    }
     else {
         codegen::gen_fn1_const(ctx.builder, "instr16_D9_5_reg", r);
     }
@@ -3602,30 +3884,15 @@ pub fn instr32_D9_5_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
 }
 
 pub fn instr16_D9_6_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
-    codegen::gen_modrm_resolve(ctx, modrm_byte);
-
-    codegen::gen_set_previous_eip_offset_from_eip_with_low_bits(
-        ctx.builder,
-        ctx.start_of_current_instruction as i32 & 0xFFF,
-    );
-
-    codegen::gen_move_registers_from_locals_to_memory(ctx);
-    ctx.builder.call_fn1("fpu_fstenv32");
-    codegen::gen_move_registers_from_memory_to_locals(ctx);
-
-    codegen::gen_get_page_fault(ctx.builder);
-    ctx.builder.if_void();
-    codegen::gen_debug_track_jit_exit(ctx.builder, ctx.start_of_current_instruction);
-    ctx.builder.br(ctx.exit_label);
-    ctx.builder.block_end();
+    gen_fpu_env(ctx, modrm_byte, "fpu_fstenv16")
+}
+pub fn instr32_D9_6_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
+    gen_fpu_env(ctx, modrm_byte, "fpu_fstenv32")
 }
 pub fn instr16_D9_6_reg_jit(ctx: &mut JitContext, r: u32) {
     codegen::gen_fn1_const(ctx.builder, "instr16_D9_6_reg", r);
 }
 pub fn instr32_D9_6_reg_jit(ctx: &mut JitContext, r: u32) { instr16_D9_6_reg_jit(ctx, r) }
-pub fn instr32_D9_6_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
-    instr16_D9_6_mem_jit(ctx, modrm_byte)
-}
 
 pub fn instr16_D9_7_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
     codegen::gen_modrm_resolve(ctx, modrm_byte);
@@ -3639,7 +3906,16 @@ pub fn instr16_D9_7_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
     ctx.builder.free_local(value_local);
 }
 pub fn instr16_D9_7_reg_jit(ctx: &mut JitContext, r: u32) {
-    codegen::gen_fn1_const(ctx.builder, "instr16_D9_7_reg", r);
+    if ctx.fpu_f64 {
+        dbg_assert!(r == 2);
+        // fsqrt
+        codegen::gen_fpu_get_sti_f64(ctx, 0);
+        ctx.builder.sqrt_f64();
+        codegen::gen_fpu_set_sti_f64(ctx, 0);
+    }
+    else {
+        codegen::gen_fn1_const(ctx.builder, "instr16_D9_7_reg", r);
+    }
 }
 pub fn instr32_D9_7_reg_jit(ctx: &mut JitContext, r: u32) { instr16_D9_7_reg_jit(ctx, r) }
 pub fn instr32_D9_7_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
@@ -3785,14 +4061,26 @@ pub fn instr_DB_6_reg_jit(ctx: &mut JitContext, r: u32) {
 }
 
 fn instr_group_DC_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, op: &str) {
-    ctx.builder.const_i32(0);
-    codegen::gen_fpu_load_m64(ctx, modrm_byte);
-    ctx.builder.call_fn3_i32_i64_i32(op)
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_load_m64_f64(ctx, modrm_byte);
+        gen_fpu_arith_f64(ctx, 0, op);
+    }
+    else {
+        ctx.builder.const_i32(0);
+        codegen::gen_fpu_load_m64(ctx, modrm_byte);
+        ctx.builder.call_fn3_i32_i64_i32(op)
+    }
 }
 fn instr_group_DC_reg_jit(ctx: &mut JitContext, r: u32, op: &str) {
-    ctx.builder.const_i32(r as i32);
-    codegen::gen_fpu_get_sti(ctx, r);
-    ctx.builder.call_fn3_i32_i64_i32(op)
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_get_sti_f64(ctx, r);
+        gen_fpu_arith_f64(ctx, r, op);
+    }
+    else {
+        ctx.builder.const_i32(r as i32);
+        codegen::gen_fpu_get_sti(ctx, r);
+        ctx.builder.call_fn3_i32_i64_i32(op)
+    }
 }
 
 pub fn instr_DC_0_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
@@ -3849,8 +4137,14 @@ pub fn instr_DC_7_reg_jit(ctx: &mut JitContext, r: u32) {
 }
 
 pub fn instr16_DD_0_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
-    codegen::gen_fpu_load_m64(ctx, modrm_byte);
-    ctx.builder.call_fn2_i64_i32("fpu_push");
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_load_m64_f64(ctx, modrm_byte);
+        codegen::gen_fpu_push_f64(ctx);
+    }
+    else {
+        codegen::gen_fpu_load_m64(ctx, modrm_byte);
+        ctx.builder.call_fn2_i64_i32("fpu_push");
+    }
 }
 pub fn instr16_DD_0_reg_jit(ctx: &mut JitContext, r: u32) {
     codegen::gen_fn1_const(ctx.builder, "fpu_ffree", r);
@@ -3882,15 +4176,27 @@ pub fn instr32_DD_1_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
 pub fn instr16_DD_2_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
     codegen::gen_modrm_resolve(ctx, modrm_byte);
     let address_local = ctx.builder.set_new_local();
-    codegen::gen_fpu_get_sti(ctx, 0);
-    ctx.builder.call_fn2_i64_i32_ret_i64("f80_to_f64");
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_get_sti_f64(ctx, 0);
+        ctx.builder.reinterpret_f64_as_i64();
+    }
+    else {
+        codegen::gen_fpu_get_sti(ctx, 0);
+        ctx.builder.call_fn2_i64_i32_ret_i64("f80_to_f64");
+    }
     let value_local = ctx.builder.set_new_local_i64();
     codegen::gen_safe_write64(ctx, &address_local, &value_local);
     ctx.builder.free_local(address_local);
     ctx.builder.free_local_i64(value_local);
 }
 pub fn instr16_DD_2_reg_jit(ctx: &mut JitContext, r: u32) {
-    codegen::gen_fn1_const(ctx.builder, "fpu_fst", r);
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_get_sti_f64(ctx, 0);
+        codegen::gen_fpu_set_sti_f64(ctx, r);
+    }
+    else {
+        codegen::gen_fn1_const(ctx.builder, "fpu_fst", r);
+    }
 }
 pub fn instr32_DD_2_reg_jit(ctx: &mut JitContext, r: u32) { instr16_DD_2_reg_jit(ctx, r) }
 pub fn instr32_DD_2_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
@@ -3898,18 +4204,22 @@ pub fn instr32_DD_2_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
 }
 
 pub fn instr16_DD_3_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
-    codegen::gen_modrm_resolve(ctx, modrm_byte);
-    let address_local = ctx.builder.set_new_local();
-    codegen::gen_fpu_get_sti(ctx, 0);
-    ctx.builder.call_fn2_i64_i32_ret_i64("f80_to_f64");
-    let value_local = ctx.builder.set_new_local_i64();
-    codegen::gen_safe_write64(ctx, &address_local, &value_local);
-    codegen::gen_fn0_const(ctx.builder, "fpu_pop");
-    ctx.builder.free_local(address_local);
-    ctx.builder.free_local_i64(value_local);
+    instr16_DD_2_mem_jit(ctx, modrm_byte);
+    if ctx.fpu_f64 {
+        codegen::gen_fpu_pop_f64(ctx);
+    }
+    else {
+        codegen::gen_fn0_const(ctx.builder, "fpu_pop");
+    }
 }
 pub fn instr16_DD_3_reg_jit(ctx: &mut JitContext, r: u32) {
-    codegen::gen_fn1_const(ctx.builder, "fpu_fstp", r);
+    if ctx.fpu_f64 {
+        instr16_DD_2_reg_jit(ctx, r);
+        codegen::gen_fpu_pop_f64(ctx);
+    }
+    else {
+        codegen::gen_fn1_const(ctx.builder, "fpu_fstp", r);
+    }
 }
 pub fn instr32_DD_3_reg_jit(ctx: &mut JitContext, r: u32) { instr16_DD_3_reg_jit(ctx, r) }
 pub fn instr32_DD_3_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
@@ -3935,10 +4245,16 @@ fn instr_group_DE_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, op: &str)
     ctx.builder.call_fn3_i32_i64_i32(op)
 }
 fn instr_group_DE_reg_jit(ctx: &mut JitContext, r: u32, op: &str) {
-    ctx.builder.const_i32(r as i32);
-    codegen::gen_fpu_get_sti(ctx, r);
-    ctx.builder.call_fn3_i32_i64_i32(op);
-    codegen::gen_fn0_const(ctx.builder, "fpu_pop")
+    if ctx.fpu_f64 {
+        instr_group_DC_reg_jit(ctx, r, op);
+        codegen::gen_fpu_pop_f64(ctx);
+    }
+    else {
+        ctx.builder.const_i32(r as i32);
+        codegen::gen_fpu_get_sti(ctx, r);
+        ctx.builder.call_fn3_i32_i64_i32(op);
+        codegen::gen_fn0_const(ctx.builder, "fpu_pop")
+    }
 }
 
 pub fn instr_DE_0_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
@@ -4553,15 +4869,28 @@ fn gen_pushf_popf_check(ctx: &mut JitContext) {
     ctx.builder.and_i32();
 }
 
+fn gen_vme_enabled(ctx: &mut JitContext) {
+    ctx.builder.load_fixed_i32(global_pointers::get_creg_offset(4));
+    ctx.builder.const_i32(CR4_VME);
+    ctx.builder.and_i32();
+}
+
 pub fn instr16_9C_jit(ctx: &mut JitContext) {
     gen_pushf_popf_check(ctx);
-    ctx.builder.if_void();
-    codegen::gen_trigger_gp(ctx, 0);
+    ctx.builder.if_i32();
+    {
+        gen_vme_enabled(ctx);
+        ctx.builder.eqz_i32();
+        ctx.builder.if_void();
+        codegen::gen_trigger_gp(ctx, 0);
+        ctx.builder.block_end();
+        ctx.builder.call_fn0_ret("get_eflags_vme");
+    }
     ctx.builder.else_();
     ctx.builder.call_fn0_ret("get_eflags");
+    ctx.builder.block_end();
     let value = ctx.builder.set_new_local();
     codegen::gen_push16(ctx, &value);
-    ctx.builder.block_end();
     ctx.builder.free_local(value);
 }
 pub fn instr32_9C_jit(ctx: &mut JitContext) {
@@ -4581,7 +4910,25 @@ pub fn instr32_9C_jit(ctx: &mut JitContext) {
 fn gen_popf(ctx: &mut JitContext, is_32: bool) {
     gen_pushf_popf_check(ctx);
     ctx.builder.if_void();
-    codegen::gen_trigger_gp(ctx, 0);
+    if !is_32 {
+        // vm86 mode extensions, see instr16_9D
+        gen_vme_enabled(ctx);
+        ctx.builder.if_void();
+        codegen::gen_pop16(ctx);
+        ctx.builder.call_fn1_ret("vme_update_flags16");
+        ctx.builder.eqz_i32();
+        ctx.builder.if_void();
+        // undo the pop, as #gp is raised before the stack pointer is changed
+        codegen::gen_adjust_stack_reg(ctx, -2i32 as u32);
+        codegen::gen_trigger_gp(ctx, 0);
+        ctx.builder.block_end();
+        ctx.builder.else_();
+        codegen::gen_trigger_gp(ctx, 0);
+        ctx.builder.block_end();
+    }
+    else {
+        codegen::gen_trigger_gp(ctx, 0);
+    }
     ctx.builder.else_();
 
     codegen::gen_get_flags(ctx.builder);
@@ -4721,6 +5068,104 @@ enum String {
     LODS,
     SCAS,
 }
+/// rep movs and rep stos with the direction flag clear: Copy or fill the whole string using
+/// memory.copy or memory.fill if source and destination each stay within a single page (see
+/// gen_translate_range_or_branch). Branches to `slow_path` otherwise, which calls the helper
+fn gen_rep_movs_stos_fast_path(ctx: &mut JitContext, ins: &String, size: u8, slow_path: Label) {
+    dbg_assert!(*ins == String::MOVS || *ins == String::STOS);
+    dbg_assert!(ctx.cpu.asize_32());
+    let size_shift = match size {
+        8 => 0,
+        16 => 1,
+        _ => 2,
+    };
+
+    codegen::gen_get_flags(ctx.builder);
+    ctx.builder.const_i32(FLAG_DIRECTION);
+    ctx.builder.and_i32();
+    ctx.builder.br_if(slow_path);
+
+    if *ins == String::STOS && size != 8 {
+        // memory.fill only writes bytes, so all bytes of the value must be equal
+        codegen::gen_get_reg32(ctx, regs::EAX);
+        if size == 16 {
+            ctx.builder.const_i32(0xFFFF);
+            ctx.builder.and_i32();
+        }
+        codegen::gen_get_reg8(ctx, regs::AL);
+        ctx.builder
+            .const_i32(if size == 16 { 0x0101 } else { 0x01010101 });
+        ctx.builder.mul_i32();
+        ctx.builder.ne_i32();
+        ctx.builder.br_if(slow_path);
+    }
+
+    // limit the count to one page before computing the length, so that it can't overflow
+    codegen::gen_get_reg32(ctx, regs::ECX);
+    let length = ctx.builder.tee_new_local();
+    ctx.builder.const_i32(0x1000 >> size_shift);
+    ctx.builder.gtu_i32();
+    ctx.builder.br_if(slow_path);
+    if size_shift != 0 {
+        ctx.builder.get_local(&length);
+        ctx.builder.const_i32(size_shift);
+        ctx.builder.shl_i32();
+        ctx.builder.set_local(&length);
+    }
+
+    codegen::gen_get_reg32(ctx, regs::EDI);
+    jit_add_seg_offset_no_override(ctx, regs::ES);
+    let dest = ctx.builder.set_new_local();
+    codegen::gen_translate_range_or_branch(ctx, &dest, &length, true, slow_path);
+
+    if *ins == String::MOVS {
+        codegen::gen_get_reg32(ctx, regs::ESI);
+        jit_add_seg_offset(ctx, regs::DS);
+        let source = ctx.builder.set_new_local();
+        codegen::gen_translate_range_or_branch(ctx, &source, &length, false, slow_path);
+
+        // memory.copy behaves like memmove, but a forward movs into an overlapping destination
+        // above the source repeats the bytes that were copied first
+        ctx.builder.get_local(&dest);
+        ctx.builder.get_local(&source);
+        ctx.builder.gtu_i32();
+        ctx.builder.get_local(&dest);
+        ctx.builder.get_local(&source);
+        ctx.builder.sub_i32();
+        ctx.builder.get_local(&length);
+        ctx.builder.ltu_i32();
+        ctx.builder.and_i32();
+        ctx.builder.br_if(slow_path);
+
+        ctx.builder.get_local(&dest);
+        ctx.builder.get_local(&source);
+        ctx.builder.get_local(&length);
+        ctx.builder.memory_copy();
+        ctx.builder.free_local(source);
+
+        codegen::gen_get_reg32(ctx, regs::ESI);
+        ctx.builder.get_local(&length);
+        ctx.builder.add_i32();
+        codegen::gen_set_reg32(ctx, regs::ESI);
+    }
+    else {
+        ctx.builder.get_local(&dest);
+        codegen::gen_get_reg32(ctx, regs::EAX);
+        ctx.builder.get_local(&length);
+        ctx.builder.memory_fill();
+    }
+    ctx.builder.free_local(dest);
+
+    codegen::gen_get_reg32(ctx, regs::EDI);
+    ctx.builder.get_local(&length);
+    ctx.builder.add_i32();
+    codegen::gen_set_reg32(ctx, regs::EDI);
+    ctx.builder.free_local(length);
+
+    ctx.builder.const_i32(0);
+    codegen::gen_set_reg32(ctx, regs::ECX);
+}
+
 fn gen_string_ins(ctx: &mut JitContext, ins: String, size: u8, prefix: u8) {
     dbg_assert!(prefix == 0 || prefix == 0xF2 || prefix == 0xF3);
     dbg_assert!(size == 8 || size == 16 || size == 32);
@@ -4929,6 +5374,20 @@ fn gen_string_ins(ctx: &mut JitContext, ins: String, size: u8, prefix: u8) {
         }
     }
 
+    let use_fast_path = (ins == String::MOVS || ins == String::STOS)
+        && prefix != 0
+        && ctx.cpu.asize_32()
+        && jit::jit_use_bulk_memory();
+    if use_fast_path {
+        let done = ctx.builder.block_void();
+        let slow_path = ctx.builder.block_void();
+        gen_rep_movs_stos_fast_path(ctx, &ins, size, slow_path);
+        codegen::gen_profiler_stat_increment(ctx.builder, profiler::stat::REP_STRING_FAST);
+        ctx.builder.br(done);
+        ctx.builder.block_end();
+        codegen::gen_profiler_stat_increment(ctx.builder, profiler::stat::REP_STRING_SLOW);
+    }
+
     let mut args = 0;
     args += 1;
     ctx.builder.const_i32(ctx.cpu.asize_32() as i32);
@@ -4988,6 +5447,10 @@ fn gen_string_ins(ctx: &mut JitContext, ins: String, size: u8, prefix: u8) {
         dbg_assert!(false);
     }
     codegen::gen_move_registers_from_memory_to_locals(ctx);
+
+    if use_fast_path {
+        ctx.builder.block_end();
+    }
 }
 
 pub fn instr_6C_jit(ctx: &mut JitContext) { gen_string_ins(ctx, String::INS, 8, 0) }
@@ -5085,6 +5548,56 @@ pub fn instr_0F31_jit(ctx: &mut JitContext) {
     ctx.builder.block_end();
 }
 
+pub fn instr_0F38_jit(ctx: &mut JitContext, _instr_flags: &mut u32) {
+    // three-byte opcodes, only invpcid is implemented, everything else raises #ud
+    let opcode = ctx.cpu.read_imm8();
+    if opcode == 0x82 && ctx.cpu.prefixes & PREFIX_66 != 0 && ctx.cpu.prefixes & PREFIX_LOCK == 0 {
+        let modrm_byte = ctx.cpu.read_imm8();
+        if modrm_byte < 0xC0 {
+            // invpcid r32, m128
+            let addr = ::modrm::decode(ctx.cpu, modrm_byte);
+            codegen::gen_move_registers_from_locals_to_memory(ctx);
+            codegen::gen_modrm_resolve(ctx, addr);
+            codegen::gen_modrm_fn1(ctx.builder, "instr_660F3882_mem", (modrm_byte >> 3 & 7) as u32);
+            codegen::gen_move_registers_from_memory_to_locals(ctx);
+            return;
+        }
+    }
+    codegen::gen_trigger_ud(ctx);
+}
+
+pub fn instr16_0F01_1_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
+    codegen::gen_move_registers_from_locals_to_memory(ctx);
+    codegen::gen_modrm_resolve(ctx, modrm_byte);
+    codegen::gen_modrm_fn0(ctx.builder, "instr16_0F01_1_mem");
+    codegen::gen_move_registers_from_memory_to_locals(ctx);
+}
+pub fn instr32_0F01_1_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte) {
+    codegen::gen_move_registers_from_locals_to_memory(ctx);
+    codegen::gen_modrm_resolve(ctx, modrm_byte);
+    codegen::gen_modrm_fn0(ctx.builder, "instr32_0F01_1_mem");
+    codegen::gen_move_registers_from_memory_to_locals(ctx);
+}
+pub fn instr16_0F01_1_reg_jit(ctx: &mut JitContext, r: u32) {
+    codegen::gen_move_registers_from_locals_to_memory(ctx);
+    if r == 0 {
+        // monitor: ds:eax, the interpreter would need the prefixes to compute the address
+        if ctx.cpu.asize_32() {
+            codegen::gen_get_reg32(ctx, regs::EAX);
+        }
+        else {
+            codegen::gen_get_reg16(ctx, regs::AX);
+        }
+        jit_add_seg_offset(ctx, regs::DS);
+        ctx.builder.call_fn1("monitor");
+    }
+    else {
+        codegen::gen_fn1_const(ctx.builder, "instr16_0F01_1_reg", r);
+    }
+    codegen::gen_move_registers_from_memory_to_locals(ctx);
+}
+pub fn instr32_0F01_1_reg_jit(ctx: &mut JitContext, r: u32) { instr16_0F01_1_reg_jit(ctx, r) }
+
 pub fn instr_0F0B_jit(ctx: &mut JitContext) { codegen::gen_trigger_ud(ctx) }
 
 pub fn instr_0F18_mem_jit(_ctx: &mut JitContext, _modrm_byte: ModrmByte, _reg: u32) {}
@@ -5375,20 +5888,32 @@ pub fn instr_0FC4_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32, imm8: u32) {
     ctx.builder.call_fn3("instr_0FC4");
 }
 
+fn gen_pinsrw(ctx: &mut JitContext, r: u32, imm8: u32) {
+    // The word is on the stack
+    let word = imm8 & 7;
+    ctx.builder.const_i32(0xFFFF);
+    ctx.builder.and_i32();
+    if word & 1 == 1 {
+        ctx.builder.const_i32(16);
+        ctx.builder.shl_i32();
+    }
+    codegen::gen_get_reg_xmm_dword(ctx, r, word >> 1);
+    ctx.builder
+        .const_i32(if word & 1 == 1 { 0xFFFF } else { 0xFFFF0000u32 as i32 });
+    ctx.builder.and_i32();
+    ctx.builder.or_i32();
+    codegen::gen_set_reg_xmm_dword(ctx, r, word >> 1);
+}
 pub fn instr_660FC4_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32, imm8: u32) {
-    ctx.builder.const_i32(0);
     codegen::gen_modrm_resolve(ctx, modrm_byte);
     let address_local = ctx.builder.set_new_local();
     codegen::gen_safe_read16(ctx, &address_local);
-    ctx.builder
-        .store_aligned_u16(global_pointers::get_reg_xmm_offset(r) + ((imm8 & 7) << 1));
+    gen_pinsrw(ctx, r, imm8);
     ctx.builder.free_local(address_local);
 }
 pub fn instr_660FC4_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32, imm8: u32) {
-    ctx.builder.const_i32(0);
     codegen::gen_get_reg32(ctx, r1);
-    ctx.builder
-        .store_aligned_u16(global_pointers::get_reg_xmm_offset(r2) + ((imm8 & 7) << 1));
+    gen_pinsrw(ctx, r2, imm8);
 }
 
 pub fn instr_0FC5_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _r: u32, _imm8: u32) {
@@ -5407,8 +5932,14 @@ pub fn instr_660FC5_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _r: u3
     codegen::gen_trigger_ud(ctx)
 }
 pub fn instr_660FC5_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32, imm8: u32) {
-    ctx.builder
-        .load_fixed_u16(global_pointers::get_reg_xmm_offset(r1) + ((imm8 & 7) << 1));
+    let word = imm8 & 7;
+    codegen::gen_get_reg_xmm_dword(ctx, r1, word >> 1);
+    if word & 1 == 1 {
+        ctx.builder.const_i32(16);
+        ctx.builder.shr_u_i32();
+    }
+    ctx.builder.const_i32(0xFFFF);
+    ctx.builder.and_i32();
     codegen::gen_set_reg32(ctx, r2);
 }
 
@@ -5475,32 +6006,36 @@ pub fn instr_660FC2_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32,
     sse_read128_xmm_mem_imm(ctx, "instr_660FC2", modrm_byte, r, imm8)
 }
 pub fn instr_F20FC2_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32, imm8: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r1) as i32);
-    ctx.builder.load_aligned_i64(0);
+    codegen::gen_get_reg_xmm_low(ctx, r1);
     ctx.builder.const_i32(r2 as i32);
     ctx.builder.const_i32(imm8 as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r2);
     ctx.builder.call_fn3_i64_i32_i32("instr_F20FC2");
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r2);
 }
 pub fn instr_F20FC2_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32, imm8: u32) {
     codegen::gen_modrm_resolve_safe_read64(ctx, modrm_byte);
     ctx.builder.const_i32(r as i32);
     ctx.builder.const_i32(imm8 as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
     ctx.builder.call_fn3_i64_i32_i32("instr_F20FC2");
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 pub fn instr_F30FC2_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32, imm8: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r1) as i32);
-    ctx.builder.load_aligned_i32(0);
+    codegen::gen_get_reg_xmm_dword(ctx, r1, 0);
     ctx.builder.const_i32(r2 as i32);
     ctx.builder.const_i32(imm8 as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r2);
     ctx.builder.call_fn3("instr_F30FC2");
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r2);
 }
 pub fn instr_F30FC2_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32, imm8: u32) {
     codegen::gen_modrm_resolve_safe_read32(ctx, modrm_byte);
     ctx.builder.const_i32(r as i32);
     ctx.builder.const_i32(imm8 as i32);
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
     ctx.builder.call_fn3("instr_F30FC2");
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 
 pub fn instr_0FC6_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32, imm8: u32) {
@@ -5698,82 +6233,64 @@ define_setcc!(0xF, instr_0F9F_mem_jit, instr_0F9F_reg_jit);
 pub fn instr_0F10_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     let dest = global_pointers::get_reg_xmm_offset(r);
     codegen::gen_modrm_resolve_safe_read128(ctx, modrm_byte, dest);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 pub fn instr_0F10_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r1, r2) }
 pub fn instr_660F10_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     let dest = global_pointers::get_reg_xmm_offset(r);
     codegen::gen_modrm_resolve_safe_read128(ctx, modrm_byte, dest);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 pub fn instr_660F10_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r1, r2) }
 pub fn instr_F20F10_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     instr_F30F7E_mem_jit(ctx, modrm_byte, r)
 }
 pub fn instr_F20F10_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder.const_i32(0);
-    ctx.builder
-        .load_fixed_i64(global_pointers::get_reg_xmm_offset(r1));
-    ctx.builder
-        .store_aligned_i64(global_pointers::get_reg_xmm_offset(r2));
+    codegen::gen_get_reg_xmm_low(ctx, r1);
+    codegen::gen_set_reg_xmm_low(ctx, r2);
 }
 pub fn instr_F30F10_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     instr_660F6E_mem_jit(ctx, modrm_byte, r)
 }
 pub fn instr_F30F10_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder.const_i32(0);
-    ctx.builder
-        .load_fixed_i32(global_pointers::get_reg_xmm_offset(r1));
-    ctx.builder
-        .store_aligned_i32(global_pointers::get_reg_xmm_offset(r2));
+    codegen::gen_get_reg_xmm_dword(ctx, r1, 0);
+    codegen::gen_set_reg_xmm_dword(ctx, r2, 0);
 }
 
 pub fn instr_0F11_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    instr_0F29_mem_jit(ctx, modrm_byte, r)
+    sse_write128_mem_xmm(ctx, modrm_byte, r, false)
 }
 pub fn instr_0F11_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r2, r1) }
 pub fn instr_660F11_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    instr_660F29_mem_jit(ctx, modrm_byte, r)
+    sse_write128_mem_xmm(ctx, modrm_byte, r, false)
 }
 pub fn instr_660F11_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r2, r1) }
 pub fn instr_F20F11_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     instr_660FD6_mem_jit(ctx, modrm_byte, r)
 }
 pub fn instr_F20F11_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder.const_i32(0);
-    ctx.builder
-        .load_fixed_i64(global_pointers::get_reg_xmm_offset(r2));
-    ctx.builder
-        .store_aligned_i64(global_pointers::get_reg_xmm_offset(r1));
+    codegen::gen_get_reg_xmm_low(ctx, r2);
+    codegen::gen_set_reg_xmm_low(ctx, r1);
 }
 pub fn instr_F30F11_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     instr_660F7E_mem_jit(ctx, modrm_byte, r)
 }
 pub fn instr_F30F11_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder.const_i32(0);
-    ctx.builder
-        .load_fixed_i32(global_pointers::get_reg_xmm_offset(r2));
-    ctx.builder
-        .store_aligned_i32(global_pointers::get_reg_xmm_offset(r1));
+    codegen::gen_get_reg_xmm_dword(ctx, r2, 0);
+    codegen::gen_set_reg_xmm_dword(ctx, r1, 0);
 }
 
 pub fn instr_0F12_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r) as i32);
     codegen::gen_modrm_resolve_safe_read64(ctx, modrm_byte);
-    ctx.builder.store_aligned_i64(0);
+    codegen::gen_set_reg_xmm_low(ctx, r);
 }
 pub fn instr_0F12_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r2) as i32);
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r1) as i32 + 8);
-    ctx.builder.load_aligned_i64(0);
-    ctx.builder.store_aligned_i64(0);
+    codegen::gen_get_reg_xmm_high(ctx, r1);
+    codegen::gen_set_reg_xmm_low(ctx, r2);
 }
 pub fn instr_660F12_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r) as i32);
     codegen::gen_modrm_resolve_safe_read64(ctx, modrm_byte);
-    ctx.builder.store_aligned_i64(0);
+    codegen::gen_set_reg_xmm_low(ctx, r);
 }
 pub fn instr_660F12_reg_jit(ctx: &mut JitContext, _r1: u32, _r2: u32) {
     codegen::gen_trigger_ud(ctx);
@@ -5806,29 +6323,29 @@ pub fn instr_660F13_reg_jit(ctx: &mut JitContext, _r1: u32, _r2: u32) {
 }
 
 pub fn instr_0F14_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read64_xmm_mem(ctx, "instr_0F14", modrm_byte, r);
+    sse_read64_xmm_mem_aligned(ctx, "instr_0F14", modrm_byte, r);
 }
 pub fn instr_0F14_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
     sse_read64_xmm_xmm(ctx, "instr_0F14", r1, r2);
 }
 pub fn instr_660F14_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read64_xmm_mem(ctx, "instr_660F14", modrm_byte, r);
+    sse_read64_xmm_mem_aligned(ctx, "instr_660F14", modrm_byte, r);
 }
 pub fn instr_660F14_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
     sse_read64_xmm_xmm(ctx, "instr_660F14", r1, r2);
 }
 
 pub fn instr_0F15_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_0F15", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_0F15", modrm_byte, r, &gen_interleave(4, true));
 }
 pub fn instr_0F15_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_0F15", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_0F15", r1, r2, &gen_interleave(4, true));
 }
 pub fn instr_660F15_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F15", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F15", modrm_byte, r, &gen_interleave(8, true));
 }
 pub fn instr_660F15_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F15", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F15", r1, r2, &gen_interleave(8, true));
 }
 
 pub fn instr_0F16_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
@@ -5854,9 +6371,7 @@ pub fn instr_F30F16_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
 pub fn instr_0F17_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     codegen::gen_modrm_resolve(ctx, modrm_byte);
     let address_local = ctx.builder.set_new_local();
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r) as i32);
-    ctx.builder.load_aligned_i64(8);
+    codegen::gen_get_reg_xmm_high(ctx, r);
     let value_local = ctx.builder.set_new_local_i64();
     codegen::gen_safe_write64(ctx, &address_local, &value_local);
     ctx.builder.free_local(address_local);
@@ -5874,31 +6389,19 @@ pub fn instr_660F17_reg_jit(ctx: &mut JitContext, _r1: u32, _r2: u32) {
 
 pub fn instr_0F28_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     let dest = global_pointers::get_reg_xmm_offset(r);
-    codegen::gen_modrm_resolve_safe_read128(ctx, modrm_byte, dest);
+    codegen::gen_modrm_resolve_safe_read128_aligned(ctx, modrm_byte, dest);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 pub fn instr_0F28_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r1, r2) }
 pub fn instr_660F28_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     let dest = global_pointers::get_reg_xmm_offset(r);
-    codegen::gen_modrm_resolve_safe_read128(ctx, modrm_byte, dest);
+    codegen::gen_modrm_resolve_safe_read128_aligned(ctx, modrm_byte, dest);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 pub fn instr_660F28_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r1, r2) }
 
 pub fn instr_0F29_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    // XXX: Aligned write or #gp
-    codegen::gen_modrm_resolve(ctx, modrm_byte);
-    let address_local = ctx.builder.set_new_local();
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r) as i32);
-    ctx.builder.load_aligned_i64(0);
-    let value_local_low = ctx.builder.set_new_local_i64();
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r) as i32 + 8);
-    ctx.builder.load_aligned_i64(0);
-    let value_local_high = ctx.builder.set_new_local_i64();
-    codegen::gen_safe_write128(ctx, &address_local, &value_local_low, &value_local_high);
-    ctx.builder.free_local(address_local);
-    ctx.builder.free_local_i64(value_local_low);
-    ctx.builder.free_local_i64(value_local_high);
+    sse_write128_mem_xmm(ctx, modrm_byte, r, true)
 }
 pub fn instr_0F29_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r2, r1) }
 
@@ -5908,36 +6411,52 @@ pub fn instr_660F29_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32)
 pub fn instr_660F29_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r2, r1) }
 
 pub fn instr_0F2A_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
     mmx_read64_mm_mem(ctx, "instr_0F2A", modrm_byte, r);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 pub fn instr_0F2A_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r2);
     mmx_read64_mm_mm(ctx, "instr_0F2A", r1, r2);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r2);
 }
 pub fn instr_660F2A_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
     mmx_read64_mm_mem(ctx, "instr_660F2A", modrm_byte, r);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 pub fn instr_660F2A_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r2);
     mmx_read64_mm_mm(ctx, "instr_660F2A", r1, r2);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r2);
 }
 pub fn instr_F20F2A_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     codegen::gen_modrm_resolve_safe_read32(ctx, modrm_byte);
     ctx.builder.const_i32(r as i32);
-    ctx.builder.call_fn2("instr_F20F2A")
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
+    ctx.builder.call_fn2("instr_F20F2A");
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 pub fn instr_F20F2A_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
     codegen::gen_get_reg32(ctx, r1);
     ctx.builder.const_i32(r2 as i32);
-    ctx.builder.call_fn2("instr_F20F2A")
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r2);
+    ctx.builder.call_fn2("instr_F20F2A");
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r2);
 }
 pub fn instr_F30F2A_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     codegen::gen_modrm_resolve_safe_read32(ctx, modrm_byte);
     ctx.builder.const_i32(r as i32);
-    ctx.builder.call_fn2("instr_F30F2A")
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r);
+    ctx.builder.call_fn2("instr_F30F2A");
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 pub fn instr_F30F2A_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
     codegen::gen_get_reg32(ctx, r1);
     ctx.builder.const_i32(r2 as i32);
-    ctx.builder.call_fn2("instr_F30F2A")
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r2);
+    ctx.builder.call_fn2("instr_F30F2A");
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r2);
 }
 
 pub fn instr_0F2B_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
@@ -5953,62 +6472,67 @@ pub fn instr_660F2B_reg_jit(ctx: &mut JitContext, _r1: u32, _r2: u32) {
     codegen::gen_trigger_ud(ctx);
 }
 
+fn sse_set_reg32_converted(ctx: &mut JitContext, r: u32) {
+    // The converted value is on the stack. Keep the old value of the register if the conversion
+    // raised an unmasked exception, which is delivered after the instruction
+    let converted = ctx.builder.set_new_local();
+    codegen::gen_get_reg32(ctx, r);
+    ctx.builder.get_local(&converted);
+    ctx.builder
+        .load_fixed_u8(std::ptr::addr_of!(::cpu::sse_instr::sse_exception_pending) as u32);
+    ctx.builder.select();
+    codegen::gen_set_reg32(ctx, r);
+    ctx.builder.free_local(converted);
+}
+
 pub fn instr_F20F2C_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     codegen::gen_modrm_resolve_safe_read64(ctx, modrm_byte);
     ctx.builder.reinterpret_i64_as_f64();
-    ctx.builder
-        .call_fn1_f64_ret("sse_convert_with_truncation_f64_to_i32");
-    codegen::gen_set_reg32(ctx, r);
+    ctx.builder.call_fn1_f64_ret("cvttsd2si");
+    sse_set_reg32_converted(ctx, r);
 }
 pub fn instr_F20F2C_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r1) as i32);
-    ctx.builder.load_aligned_f64(0);
-    ctx.builder
-        .call_fn1_f64_ret("sse_convert_with_truncation_f64_to_i32");
-    codegen::gen_set_reg32(ctx, r2);
+    codegen::gen_get_reg_xmm_low(ctx, r1);
+    ctx.builder.reinterpret_i64_as_f64();
+    ctx.builder.call_fn1_f64_ret("cvttsd2si");
+    sse_set_reg32_converted(ctx, r2);
 }
 pub fn instr_F30F2C_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     codegen::gen_modrm_resolve_safe_read32(ctx, modrm_byte);
     ctx.builder.reinterpret_i32_as_f32();
-    ctx.builder
-        .call_fn1_f32_ret("sse_convert_with_truncation_f32_to_i32");
-    codegen::gen_set_reg32(ctx, r);
+    ctx.builder.call_fn1_f32_ret("cvttss2si");
+    sse_set_reg32_converted(ctx, r);
 }
 pub fn instr_F30F2C_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r1) as i32);
-    ctx.builder.load_aligned_f32(0);
-    ctx.builder
-        .call_fn1_f32_ret("sse_convert_with_truncation_f32_to_i32");
-    codegen::gen_set_reg32(ctx, r2);
+    codegen::gen_get_reg_xmm_dword(ctx, r1, 0);
+    ctx.builder.reinterpret_i32_as_f32();
+    ctx.builder.call_fn1_f32_ret("cvttss2si");
+    sse_set_reg32_converted(ctx, r2);
 }
 
 pub fn instr_F20F2D_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     codegen::gen_modrm_resolve_safe_read64(ctx, modrm_byte);
     ctx.builder.reinterpret_i64_as_f64();
-    ctx.builder.call_fn1_f64_ret("sse_convert_f64_to_i32");
-    codegen::gen_set_reg32(ctx, r);
+    ctx.builder.call_fn1_f64_ret("cvtsd2si");
+    sse_set_reg32_converted(ctx, r);
 }
 pub fn instr_F20F2D_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r1) as i32);
-    ctx.builder.load_aligned_f64(0);
-    ctx.builder.call_fn1_f64_ret("sse_convert_f64_to_i32");
-    codegen::gen_set_reg32(ctx, r2);
+    codegen::gen_get_reg_xmm_low(ctx, r1);
+    ctx.builder.reinterpret_i64_as_f64();
+    ctx.builder.call_fn1_f64_ret("cvtsd2si");
+    sse_set_reg32_converted(ctx, r2);
 }
 pub fn instr_F30F2D_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     codegen::gen_modrm_resolve_safe_read32(ctx, modrm_byte);
     ctx.builder.reinterpret_i32_as_f32();
-    ctx.builder.call_fn1_f32_ret("sse_convert_f32_to_i32");
-    codegen::gen_set_reg32(ctx, r);
+    ctx.builder.call_fn1_f32_ret("cvtss2si");
+    sse_set_reg32_converted(ctx, r);
 }
 pub fn instr_F30F2D_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r1) as i32);
-    ctx.builder.load_aligned_f32(0);
-    ctx.builder.call_fn1_f32_ret("sse_convert_f32_to_i32");
-    codegen::gen_set_reg32(ctx, r2);
+    codegen::gen_get_reg_xmm_dword(ctx, r1, 0);
+    ctx.builder.reinterpret_i32_as_f32();
+    ctx.builder.call_fn1_f32_ret("cvtss2si");
+    sse_set_reg32_converted(ctx, r2);
 }
 
 pub fn instr_0F2E_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
@@ -6089,55 +6613,55 @@ pub fn instr_F30F53_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
 }
 
 pub fn instr_0F54_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_0F54", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_0F54", modrm_byte, r, &|b| b.and_v128());
 }
 pub fn instr_0F54_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_0F54", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_0F54", r1, r2, &|b| b.and_v128());
 }
 pub fn instr_660F54_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F54", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F54", modrm_byte, r, &|b| b.and_v128());
 }
 pub fn instr_660F54_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F54", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F54", r1, r2, &|b| b.and_v128());
 }
 
 pub fn instr_0F55_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_0F55", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_0F55", modrm_byte, r, &gen_andnot_reversed);
 }
 pub fn instr_0F55_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_0F55", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_0F55", r1, r2, &gen_andnot_reversed);
 }
 pub fn instr_660F55_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F55", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F55", modrm_byte, r, &gen_andnot_reversed);
 }
 pub fn instr_660F55_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F55", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F55", r1, r2, &gen_andnot_reversed);
 }
 
 pub fn instr_0F56_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_0F56", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_0F56", modrm_byte, r, &|b| b.or_v128());
 }
 pub fn instr_0F56_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_0F56", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_0F56", r1, r2, &|b| b.or_v128());
 }
 pub fn instr_660F56_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F56", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F56", modrm_byte, r, &|b| b.or_v128());
 }
 pub fn instr_660F56_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F56", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F56", r1, r2, &|b| b.or_v128());
 }
 
 pub fn instr_0F57_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_0F57", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_0F57", modrm_byte, r, &|b| b.xor_v128());
 }
 pub fn instr_0F57_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_0F57", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_0F57", r1, r2, &|b| b.xor_v128());
 }
 pub fn instr_660F57_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F57", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F57", modrm_byte, r, &|b| b.xor_v128());
 }
 pub fn instr_660F57_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F57", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F57", r1, r2, &|b| b.xor_v128());
 }
 
 pub fn instr_0F58_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
@@ -6410,121 +6934,120 @@ pub fn instr_0F6B_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
 
 pub fn instr_660F60_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     // Note: Only requires 64-bit read, but is allowed to do 128-bit read
-    sse_read128_xmm_mem(ctx, "instr_660F60", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F60", modrm_byte, r, &gen_interleave(1, false));
 }
 pub fn instr_660F60_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F60", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F60", r1, r2, &gen_interleave(1, false));
 }
 pub fn instr_660F61_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     // Note: Only requires 64-bit read, but is allowed to do 128-bit read
-    sse_read128_xmm_mem(ctx, "instr_660F61", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F61", modrm_byte, r, &gen_interleave(2, false));
 }
 pub fn instr_660F61_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F61", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F61", r1, r2, &gen_interleave(2, false));
 }
-pub fn instr_660F62_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    let src = global_pointers::sse_scratch_register as u32;
-    codegen::gen_modrm_resolve_safe_read128(ctx, modrm_byte, src);
-    ctx.builder.const_i32(0);
-    ctx.builder.load_fixed_i32(src + 4);
-    ctx.builder
-        .store_aligned_i32(global_pointers::get_reg_xmm_offset(r) + 12);
+fn gen_punpckldq(ctx: &mut JitContext, r: u32) {
+    // The low quadword of the source is on the stack
+    let src = ctx.builder.set_new_local_i64();
+    codegen::gen_get_reg_xmm_low(ctx, r);
+    let dest = ctx.builder.set_new_local_i64();
 
-    ctx.builder.const_i32(0);
-    ctx.builder
-        .load_fixed_i32(global_pointers::get_reg_xmm_offset(r) + 4);
-    ctx.builder
-        .store_aligned_i32(global_pointers::get_reg_xmm_offset(r) + 8);
+    ctx.builder.get_local_i64(&dest);
+    ctx.builder.const_i64(32);
+    ctx.builder.shr_u_i64();
+    ctx.builder.get_local_i64(&src);
+    ctx.builder.const_i64(0xFFFF_FFFF_0000_0000u64 as i64);
+    ctx.builder.and_i64();
+    ctx.builder.or_i64();
+    codegen::gen_set_reg_xmm_high(ctx, r);
+
+    ctx.builder.get_local_i64(&dest);
+    ctx.builder.const_i64(0xFFFF_FFFF);
+    ctx.builder.and_i64();
+    ctx.builder.get_local_i64(&src);
+    ctx.builder.const_i64(32);
+    ctx.builder.shl_i64();
+    ctx.builder.or_i64();
+    codegen::gen_set_reg_xmm_low(ctx, r);
 
-    ctx.builder.const_i32(0);
-    ctx.builder.load_fixed_i32(src + 0);
-    ctx.builder
-        .store_aligned_i32(global_pointers::get_reg_xmm_offset(r) + 4);
+    ctx.builder.free_local_i64(src);
+    ctx.builder.free_local_i64(dest);
+}
+pub fn instr_660F62_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
+    let src = global_pointers::sse_scratch_register as u32;
+    codegen::gen_modrm_resolve_safe_read128_aligned(ctx, modrm_byte, src);
+    ctx.builder.load_fixed_i64(src);
+    gen_punpckldq(ctx, r);
 }
 pub fn instr_660F62_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder.const_i32(0);
-    ctx.builder
-        .load_fixed_i32(global_pointers::get_reg_xmm_offset(r1) + 4);
-    ctx.builder
-        .store_aligned_i32(global_pointers::get_reg_xmm_offset(r2) + 12);
-
-    ctx.builder.const_i32(0);
-    ctx.builder
-        .load_fixed_i32(global_pointers::get_reg_xmm_offset(r2) + 4);
-    ctx.builder
-        .store_aligned_i32(global_pointers::get_reg_xmm_offset(r2) + 8);
-
-    ctx.builder.const_i32(0);
-    ctx.builder
-        .load_fixed_i32(global_pointers::get_reg_xmm_offset(r1) + 0);
-    ctx.builder
-        .store_aligned_i32(global_pointers::get_reg_xmm_offset(r2) + 4);
+    codegen::gen_get_reg_xmm_low(ctx, r1);
+    gen_punpckldq(ctx, r2);
 }
 pub fn instr_660F63_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F63", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F63", modrm_byte, r, &|b| b.narrow_signed_i16x8_to_i8x16());
 }
 pub fn instr_660F63_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F63", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F63", r1, r2, &|b| b.narrow_signed_i16x8_to_i8x16());
 }
 pub fn instr_660F64_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F64", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F64", modrm_byte, r, &|b| b.gt_i8x16());
 }
 pub fn instr_660F64_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F64", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F64", r1, r2, &|b| b.gt_i8x16());
 }
 pub fn instr_660F65_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F65", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F65", modrm_byte, r, &|b| b.gt_i16x8());
 }
 pub fn instr_660F65_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F65", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F65", r1, r2, &|b| b.gt_i16x8());
 }
 pub fn instr_660F66_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F66", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F66", modrm_byte, r, &|b| b.gt_i32x4());
 }
 pub fn instr_660F66_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F66", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F66", r1, r2, &|b| b.gt_i32x4());
 }
 pub fn instr_660F67_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F67", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F67", modrm_byte, r, &|b| b.narrow_unsigned_i16x8_to_i8x16());
 }
 pub fn instr_660F67_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F67", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F67", r1, r2, &|b| b.narrow_unsigned_i16x8_to_i8x16());
 }
 pub fn instr_660F68_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F68", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F68", modrm_byte, r, &gen_interleave(1, true));
 }
 pub fn instr_660F68_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F68", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F68", r1, r2, &gen_interleave(1, true));
 }
 pub fn instr_660F69_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F69", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F69", modrm_byte, r, &gen_interleave(2, true));
 }
 pub fn instr_660F69_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F69", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F69", r1, r2, &gen_interleave(2, true));
 }
 pub fn instr_660F6A_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F6A", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F6A", modrm_byte, r, &gen_interleave(4, true));
 }
 pub fn instr_660F6A_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F6A", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F6A", r1, r2, &gen_interleave(4, true));
 }
 pub fn instr_660F6B_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F6B", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F6B", modrm_byte, r, &|b| b.narrow_signed_i32x4_to_i16x8());
 }
 pub fn instr_660F6B_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F6B", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F6B", r1, r2, &|b| b.narrow_signed_i32x4_to_i16x8());
 }
 pub fn instr_660F6C_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F6C", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F6C", modrm_byte, r, &gen_interleave(8, false));
 }
 pub fn instr_660F6C_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F6C", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F6C", r1, r2, &gen_interleave(8, false));
 }
 pub fn instr_660F6D_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F6D", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F6D", modrm_byte, r, &gen_interleave(8, true));
 }
 pub fn instr_660F6D_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F6D", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F6D", r1, r2, &gen_interleave(8, true));
 }
 
 pub fn instr_0F6E_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
@@ -6539,30 +7062,21 @@ pub fn instr_0F6E_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
 }
 
 pub fn instr_660F6E_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    ctx.builder.const_i32(0);
     codegen::gen_modrm_resolve_safe_read32(ctx, modrm_byte);
     ctx.builder.extend_unsigned_i32_to_i64();
-    ctx.builder
-        .store_aligned_i64(global_pointers::get_reg_xmm_offset(r));
-    ctx.builder.const_i32(0);
+    codegen::gen_set_reg_xmm_low(ctx, r);
     ctx.builder.const_i64(0);
-    ctx.builder
-        .store_aligned_i64(global_pointers::get_reg_xmm_offset(r) + 8);
+    codegen::gen_set_reg_xmm_high(ctx, r);
 }
 pub fn instr_660F6E_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder.const_i32(0);
     codegen::gen_get_reg32(ctx, r1);
     ctx.builder.extend_unsigned_i32_to_i64();
-    ctx.builder
-        .store_aligned_i64(global_pointers::get_reg_xmm_offset(r2));
-    ctx.builder.const_i32(0);
+    codegen::gen_set_reg_xmm_low(ctx, r2);
     ctx.builder.const_i64(0);
-    ctx.builder
-        .store_aligned_i64(global_pointers::get_reg_xmm_offset(r2) + 8);
+    codegen::gen_set_reg_xmm_high(ctx, r2);
 }
 
 pub fn instr_0F6F_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    // XXX: Aligned read or #gp
     codegen::gen_modrm_resolve_safe_read64(ctx, modrm_byte);
     ctx.builder.const_i32(r as i32);
     ctx.builder.call_fn2_i64_i32("instr_0F6F")
@@ -6574,14 +7088,15 @@ pub fn instr_0F6F_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
 }
 
 pub fn instr_660F6F_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    // XXX: Aligned read or #gp
     let dest = global_pointers::get_reg_xmm_offset(r);
-    codegen::gen_modrm_resolve_safe_read128(ctx, modrm_byte, dest);
+    codegen::gen_modrm_resolve_safe_read128_aligned(ctx, modrm_byte, dest);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 pub fn instr_660F6F_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r1, r2) }
 pub fn instr_F30F6F_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     let dest = global_pointers::get_reg_xmm_offset(r);
     codegen::gen_modrm_resolve_safe_read128(ctx, modrm_byte, dest);
+    codegen::gen_move_reg_xmm_from_memory_to_local(ctx, r);
 }
 pub fn instr_F30F6F_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r1, r2) }
 
@@ -6599,26 +7114,34 @@ pub fn instr_0F70_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32, imm8: u32) {
     ctx.builder.const_i32(imm8 as i32);
     ctx.builder.call_fn3_i64_i32_i32("instr_0F70");
 }
-pub fn instr_660F70_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32, imm8: u32) {
+fn gen_pshufd_from_scratch(ctx: &mut JitContext, r: u32, imm8: u32) {
     let src = global_pointers::sse_scratch_register as u32;
-    codegen::gen_modrm_resolve_safe_read128(ctx, modrm_byte, src);
-    for i in 0..4 {
-        ctx.builder.const_i32(0);
-        ctx.builder.load_fixed_i32(src + 4 * (imm8 >> 2 * i & 3));
+    for i in 0..2 {
+        ctx.builder
+            .load_fixed_i32(src + 4 * (imm8 >> (4 * i) & 3));
+        ctx.builder.extend_unsigned_i32_to_i64();
         ctx.builder
-            .store_aligned_i32(global_pointers::get_reg_xmm_offset(r) + 4 * i);
+            .load_fixed_i32(src + 4 * (imm8 >> (4 * i + 2) & 3));
+        ctx.builder.extend_unsigned_i32_to_i64();
+        ctx.builder.const_i64(32);
+        ctx.builder.shl_i64();
+        ctx.builder.or_i64();
+        if i == 0 {
+            codegen::gen_set_reg_xmm_low(ctx, r);
+        }
+        else {
+            codegen::gen_set_reg_xmm_high(ctx, r);
+        }
     }
 }
+pub fn instr_660F70_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32, imm8: u32) {
+    let src = global_pointers::sse_scratch_register as u32;
+    codegen::gen_modrm_resolve_safe_read128_aligned(ctx, modrm_byte, src);
+    gen_pshufd_from_scratch(ctx, r, imm8);
+}
 pub fn instr_660F70_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32, imm8: u32) {
     codegen::gen_read_reg_xmm128_into_scratch(ctx, r1);
-    // TODO: perf: copy less (handle aliased src/dst), use 64-bit loads/stores if possible
-    let src = global_pointers::sse_scratch_register as u32;
-    for i in 0..4 {
-        ctx.builder.const_i32(0);
-        ctx.builder.load_fixed_i32(src + 4 * (imm8 >> 2 * i & 3));
-        ctx.builder
-            .store_aligned_i32(global_pointers::get_reg_xmm_offset(r2) + 4 * i);
-    }
+    gen_pshufd_from_scratch(ctx, r2, imm8);
 }
 pub fn instr_F20F70_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32, imm8: u32) {
     sse_read128_xmm_mem_imm(ctx, "instr_F20F70", modrm_byte, r, imm8)
@@ -6704,83 +7227,63 @@ pub fn instr_660F71_2_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _imm
     codegen::gen_trigger_ud(ctx);
 }
 pub fn instr_660F71_2_reg_jit(ctx: &mut JitContext, r: u32, imm8: u32) {
-    ctx.builder.const_i32(r as i32);
-    ctx.builder.const_i32(imm8 as i32);
-    ctx.builder.call_fn2("instr_660F71_2_reg");
+    sse_shift_imm(ctx, "instr_660F71_2_reg", r, imm8, 16, false, &|b| b.shr_u_i16x8());
 }
 pub fn instr_660F71_4_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _imm: u32) {
     codegen::gen_trigger_ud(ctx);
 }
 pub fn instr_660F71_4_reg_jit(ctx: &mut JitContext, r: u32, imm8: u32) {
-    ctx.builder.const_i32(r as i32);
-    ctx.builder.const_i32(imm8 as i32);
-    ctx.builder.call_fn2("instr_660F71_4_reg");
+    sse_shift_imm(ctx, "instr_660F71_4_reg", r, imm8, 16, true, &|b| b.shr_s_i16x8());
 }
 pub fn instr_660F71_6_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _imm: u32) {
     codegen::gen_trigger_ud(ctx);
 }
 pub fn instr_660F71_6_reg_jit(ctx: &mut JitContext, r: u32, imm8: u32) {
-    ctx.builder.const_i32(r as i32);
-    ctx.builder.const_i32(imm8 as i32);
-    ctx.builder.call_fn2("instr_660F71_6_reg");
+    sse_shift_imm(ctx, "instr_660F71_6_reg", r, imm8, 16, false, &|b| b.shl_i16x8());
 }
 
 pub fn instr_660F72_2_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _imm: u32) {
     codegen::gen_trigger_ud(ctx);
 }
 pub fn instr_660F72_2_reg_jit(ctx: &mut JitContext, r: u32, imm8: u32) {
-    ctx.builder.const_i32(r as i32);
-    ctx.builder.const_i32(imm8 as i32);
-    ctx.builder.call_fn2("instr_660F72_2_reg");
+    sse_shift_imm(ctx, "instr_660F72_2_reg", r, imm8, 32, false, &|b| b.shr_u_i32x4());
 }
 pub fn instr_660F72_4_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _imm: u32) {
     codegen::gen_trigger_ud(ctx);
 }
 pub fn instr_660F72_4_reg_jit(ctx: &mut JitContext, r: u32, imm8: u32) {
-    ctx.builder.const_i32(r as i32);
-    ctx.builder.const_i32(imm8 as i32);
-    ctx.builder.call_fn2("instr_660F72_4_reg");
+    sse_shift_imm(ctx, "instr_660F72_4_reg", r, imm8, 32, true, &|b| b.shr_s_i32x4());
 }
 pub fn instr_660F72_6_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _imm: u32) {
     codegen::gen_trigger_ud(ctx);
 }
 pub fn instr_660F72_6_reg_jit(ctx: &mut JitContext, r: u32, imm8: u32) {
-    ctx.builder.const_i32(r as i32);
-    ctx.builder.const_i32(imm8 as i32);
-    ctx.builder.call_fn2("instr_660F72_6_reg");
+    sse_shift_imm(ctx, "instr_660F72_6_reg", r, imm8, 32, false, &|b| b.shl_i32x4());
 }
 
 pub fn instr_660F73_2_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _imm: u32) {
     codegen::gen_trigger_ud(ctx);
 }
 pub fn instr_660F73_2_reg_jit(ctx: &mut JitContext, r: u32, imm8: u32) {
-    ctx.builder.const_i32(r as i32);
-    ctx.builder.const_i32(imm8 as i32);
-    ctx.builder.call_fn2("instr_660F73_2_reg");
+    sse_shift_imm(ctx, "instr_660F73_2_reg", r, imm8, 64, false, &|b| b.shr_u_i64x2());
 }
 pub fn instr_660F73_3_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _imm: u32) {
     codegen::gen_trigger_ud(ctx);
 }
 pub fn instr_660F73_3_reg_jit(ctx: &mut JitContext, r: u32, imm8: u32) {
-    ctx.builder.const_i32(r as i32);
-    ctx.builder.const_i32(imm8 as i32);
-    ctx.builder.call_fn2("instr_660F73_3_reg");
+    sse_shift_bytes_imm(ctx, "instr_660F73_3_reg", r, imm8, true);
 }
 pub fn instr_660F73_6_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _imm: u32) {
     codegen::gen_trigger_ud(ctx);
 }
 pub fn instr_660F73_6_reg_jit(ctx: &mut JitContext, r: u32, imm8: u32) {
-    ctx.builder.const_i32(r as i32);
-    ctx.builder.const_i32(imm8 as i32);
-    ctx.builder.call_fn2("instr_660F73_6_reg");
+    sse_shift_imm(ctx, "instr_660F73_6_reg", r, imm8, 64, false, &|b| b.shl_i64x2());
 }
 pub fn instr_660F73_7_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _imm: u32) {
     codegen::gen_trigger_ud(ctx);
 }
 pub fn instr_660F73_7_reg_jit(ctx: &mut JitContext, r: u32, imm8: u32) {
-    ctx.builder.const_i32(r as i32);
-    ctx.builder.const_i32(imm8 as i32);
-    ctx.builder.call_fn2("instr_660F73_7_reg");
+    sse_shift_bytes_imm(ctx, "instr_660F73_7_reg", r, imm8, false);
 }
 
 pub fn instr_0F74_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
@@ -6803,22 +7306,22 @@ pub fn instr_0F76_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
 }
 
 pub fn instr_660F74_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F74", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F74", modrm_byte, r, &|b| b.eq_i8x16());
 }
 pub fn instr_660F74_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F74", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F74", r1, r2, &|b| b.eq_i8x16());
 }
 pub fn instr_660F75_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F75", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F75", modrm_byte, r, &|b| b.eq_i16x8());
 }
 pub fn instr_660F75_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F75", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F75", r1, r2, &|b| b.eq_i16x8());
 }
 pub fn instr_660F76_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660F76", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660F76", modrm_byte, r, &|b| b.eq_i32x4());
 }
 pub fn instr_660F76_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660F76", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660F76", r1, r2, &|b| b.eq_i32x4());
 }
 
 pub fn instr_660F7C_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
@@ -6865,16 +7368,14 @@ pub fn instr_0F7E_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
 pub fn instr_660F7E_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     codegen::gen_modrm_resolve(ctx, modrm_byte);
     let address_local = ctx.builder.set_new_local();
-    ctx.builder
-        .load_fixed_i32(global_pointers::get_reg_xmm_offset(r));
+    codegen::gen_get_reg_xmm_dword(ctx, r, 0);
     let value_local = ctx.builder.set_new_local();
     codegen::gen_safe_write32(ctx, &address_local, &value_local);
     ctx.builder.free_local(address_local);
     ctx.builder.free_local(value_local);
 }
 pub fn instr_660F7E_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder
-        .load_fixed_i32(global_pointers::get_reg_xmm_offset(r2));
+    codegen::gen_get_reg_xmm_dword(ctx, r2, 0);
     codegen::gen_set_reg32(ctx, r1);
 }
 
@@ -6895,15 +7396,10 @@ pub fn instr_0F7F_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
 }
 
 pub fn instr_F30F7E_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r) as i32);
     codegen::gen_modrm_resolve_safe_read64(ctx, modrm_byte);
-    ctx.builder.store_aligned_i64(0);
-
-    ctx.builder
-        .const_i32(global_pointers::get_reg_xmm_offset(r) as i32 + 8);
+    codegen::gen_set_reg_xmm_low(ctx, r);
     ctx.builder.const_i64(0);
-    ctx.builder.store_aligned_i64(0);
+    codegen::gen_set_reg_xmm_high(ctx, r);
 }
 pub fn instr_F30F7E_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
     instr_660FD6_reg_jit(ctx, r2, r1)
@@ -6914,7 +7410,7 @@ pub fn instr_660F7F_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32)
 }
 pub fn instr_660F7F_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r2, r1) }
 pub fn instr_F30F7F_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    instr_0F29_mem_jit(ctx, modrm_byte, r);
+    sse_write128_mem_xmm(ctx, modrm_byte, r, false)
 }
 pub fn instr_F30F7F_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) { sse_mov_xmm_xmm(ctx, r2, r1) }
 
@@ -7368,96 +7864,91 @@ pub fn instr_660FD3_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
     sse_read128_xmm_xmm(ctx, "instr_660FD3", r1, r2);
 }
 pub fn instr_660FD4_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FD4", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FD4", modrm_byte, r, &|b| b.add_i64x2());
 }
 pub fn instr_660FD4_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FD4", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FD4", r1, r2, &|b| b.add_i64x2());
 }
 pub fn instr_660FD5_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FD5", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FD5", modrm_byte, r, &|b| b.mul_i16x8());
 }
 pub fn instr_660FD5_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FD5", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FD5", r1, r2, &|b| b.mul_i16x8());
 }
 
 pub fn instr_660FD6_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     codegen::gen_modrm_resolve(ctx, modrm_byte);
     let address_local = ctx.builder.set_new_local();
-    ctx.builder
-        .load_fixed_i64(global_pointers::get_reg_xmm_offset(r));
+    codegen::gen_get_reg_xmm_low(ctx, r);
     let value_local = ctx.builder.set_new_local_i64();
     codegen::gen_safe_write64(ctx, &address_local, &value_local);
     ctx.builder.free_local(address_local);
     ctx.builder.free_local_i64(value_local);
 }
 pub fn instr_660FD6_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    ctx.builder.const_i32(0);
-    ctx.builder
-        .load_fixed_i64(global_pointers::get_reg_xmm_offset(r2));
-    ctx.builder
-        .store_aligned_i64(global_pointers::get_reg_xmm_offset(r1));
-    ctx.builder.const_i32(0);
+    codegen::gen_get_reg_xmm_low(ctx, r2);
+    codegen::gen_set_reg_xmm_low(ctx, r1);
     ctx.builder.const_i64(0);
-    ctx.builder
-        .store_aligned_i64(global_pointers::get_reg_xmm_offset(r1) + 8);
+    codegen::gen_set_reg_xmm_high(ctx, r1);
 }
 
 pub fn instr_660FD7_mem_jit(ctx: &mut JitContext, _modrm_byte: ModrmByte, _r: u32) {
     codegen::gen_trigger_ud(ctx)
 }
 pub fn instr_660FD7_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
+    codegen::gen_move_reg_xmm_from_local_to_memory(ctx, r1);
     ctx.builder.const_i32(r1 as i32);
     ctx.builder.call_fn1_ret("instr_660FD7");
     codegen::gen_set_reg32(ctx, r2);
 }
 
 pub fn instr_660FD8_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FD8", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FD8", modrm_byte, r, &|b| b.sub_sat_u_i8x16());
 }
 pub fn instr_660FD8_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FD8", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FD8", r1, r2, &|b| b.sub_sat_u_i8x16());
 }
 pub fn instr_660FD9_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FD9", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FD9", modrm_byte, r, &|b| b.sub_sat_u_i16x8());
 }
 pub fn instr_660FD9_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FD9", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FD9", r1, r2, &|b| b.sub_sat_u_i16x8());
 }
 pub fn instr_660FDA_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FDA", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FDA", modrm_byte, r, &|b| b.min_u_i8x16());
 }
 pub fn instr_660FDA_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FDA", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FDA", r1, r2, &|b| b.min_u_i8x16());
 }
 pub fn instr_660FDB_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FDB", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FDB", modrm_byte, r, &|b| b.and_v128());
 }
 pub fn instr_660FDB_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FDB", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FDB", r1, r2, &|b| b.and_v128());
 }
 pub fn instr_660FDC_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FDC", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FDC", modrm_byte, r, &|b| b.add_sat_u_i8x16());
 }
 pub fn instr_660FDC_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FDC", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FDC", r1, r2, &|b| b.add_sat_u_i8x16());
 }
 pub fn instr_660FDD_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FDD", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FDD", modrm_byte, r, &|b| b.add_sat_u_i16x8());
 }
 pub fn instr_660FDD_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FDD", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FDD", r1, r2, &|b| b.add_sat_u_i16x8());
 }
 pub fn instr_660FDE_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FDE", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FDE", modrm_byte, r, &|b| b.max_u_i8x16());
 }
 pub fn instr_660FDE_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FDE", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FDE", r1, r2, &|b| b.max_u_i8x16());
 }
 pub fn instr_660FDF_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FDF", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FDF", modrm_byte, r, &gen_andnot_reversed);
 }
 pub fn instr_660FDF_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FDF", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FDF", r1, r2, &gen_andnot_reversed);
 }
 
 pub fn instr_0FE0_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
@@ -7547,10 +8038,10 @@ pub fn instr_0FEF_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
 }
 
 pub fn instr_660FE0_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FE0", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FE0", modrm_byte, r, &|b| b.avgr_u_i8x16());
 }
 pub fn instr_660FE0_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FE0", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FE0", r1, r2, &|b| b.avgr_u_i8x16());
 }
 pub fn instr_660FE1_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     sse_read128_xmm_mem(ctx, "instr_660FE1", modrm_byte, r);
@@ -7565,10 +8056,10 @@ pub fn instr_660FE2_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
     sse_read128_xmm_xmm(ctx, "instr_660FE2", r1, r2);
 }
 pub fn instr_660FE3_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FE3", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FE3", modrm_byte, r, &|b| b.avgr_u_i16x8());
 }
 pub fn instr_660FE3_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FE3", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FE3", r1, r2, &|b| b.avgr_u_i16x8());
 }
 pub fn instr_660FE4_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     sse_read128_xmm_mem(ctx, "instr_660FE4", modrm_byte, r);
@@ -7610,52 +8101,52 @@ pub fn instr_660FE7_reg_jit(ctx: &mut JitContext, _r1: u32, _r2: u32) {
 }
 
 pub fn instr_660FE8_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FE8", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FE8", modrm_byte, r, &|b| b.sub_sat_s_i8x16());
 }
 pub fn instr_660FE8_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FE8", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FE8", r1, r2, &|b| b.sub_sat_s_i8x16());
 }
 pub fn instr_660FE9_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FE9", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FE9", modrm_byte, r, &|b| b.sub_sat_s_i16x8());
 }
 pub fn instr_660FE9_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FE9", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FE9", r1, r2, &|b| b.sub_sat_s_i16x8());
 }
 pub fn instr_660FEA_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FEA", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FEA", modrm_byte, r, &|b| b.min_s_i16x8());
 }
 pub fn instr_660FEA_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FEA", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FEA", r1, r2, &|b| b.min_s_i16x8());
 }
 pub fn instr_660FEB_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FEB", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FEB", modrm_byte, r, &|b| b.or_v128());
 }
 pub fn instr_660FEB_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FEB", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FEB", r1, r2, &|b| b.or_v128());
 }
 pub fn instr_660FEC_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FEC", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FEC", modrm_byte, r, &|b| b.add_sat_s_i8x16());
 }
 pub fn instr_660FEC_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FEC", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FEC", r1, r2, &|b| b.add_sat_s_i8x16());
 }
 pub fn instr_660FED_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FED", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FED", modrm_byte, r, &|b| b.add_sat_s_i16x8());
 }
 pub fn instr_660FED_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FED", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FED", r1, r2, &|b| b.add_sat_s_i16x8());
 }
 pub fn instr_660FEE_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FEE", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FEE", modrm_byte, r, &|b| b.max_s_i16x8());
 }
 pub fn instr_660FEE_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FEE", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FEE", r1, r2, &|b| b.max_s_i16x8());
 }
 pub fn instr_660FEF_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FEF", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FEF", modrm_byte, r, &|b| b.xor_v128());
 }
 pub fn instr_660FEF_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FEF", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FEF", r1, r2, &|b| b.xor_v128());
 }
 
 pub fn instr_0FF1_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
@@ -7792,10 +8283,10 @@ pub fn instr_660FF4_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
     sse_read128_xmm_xmm(ctx, "instr_660FF4", r1, r2);
 }
 pub fn instr_660FF5_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FF5", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FF5", modrm_byte, r, &|b| b.dot_signed_i16x8_to_i32x4());
 }
 pub fn instr_660FF5_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FF5", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FF5", r1, r2, &|b| b.dot_signed_i16x8_to_i32x4());
 }
 pub fn instr_660FF6_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
     sse_read128_xmm_mem(ctx, "instr_660FF6", modrm_byte, r);
@@ -7834,44 +8325,44 @@ pub fn instr_660FF7_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
 }
 
 pub fn instr_660FF8_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FF8", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FF8", modrm_byte, r, &|b| b.sub_i8x16());
 }
 pub fn instr_660FF8_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FF8", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FF8", r1, r2, &|b| b.sub_i8x16());
 }
 pub fn instr_660FF9_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FF9", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FF9", modrm_byte, r, &|b| b.sub_i16x8());
 }
 pub fn instr_660FF9_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FF9", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FF9", r1, r2, &|b| b.sub_i16x8());
 }
 pub fn instr_660FFA_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FFA", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FFA", modrm_byte, r, &|b| b.sub_i32x4());
 }
 pub fn instr_660FFA_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FFA", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FFA", r1, r2, &|b| b.sub_i32x4());
 }
 pub fn instr_660FFB_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FFB", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FFB", modrm_byte, r, &|b| b.sub_i64x2());
 }
 pub fn instr_660FFB_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FFB", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FFB", r1, r2, &|b| b.sub_i64x2());
 }
 pub fn instr_660FFC_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FFC", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FFC", modrm_byte, r, &|b| b.add_i8x16());
 }
 pub fn instr_660FFC_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FFC", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FFC", r1, r2, &|b| b.add_i8x16());
 }
 pub fn instr_660FFD_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FFD", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FFD", modrm_byte, r, &|b| b.add_i16x8());
 }
 pub fn instr_660FFD_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FFD", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FFD", r1, r2, &|b| b.add_i16x8());
 }
 pub fn instr_660FFE_mem_jit(ctx: &mut JitContext, modrm_byte: ModrmByte, r: u32) {
-    sse_read128_xmm_mem(ctx, "instr_660FFE", modrm_byte, r);
+    sse_simd_xmm_mem(ctx, "instr_660FFE", modrm_byte, r, &|b| b.add_i32x4());
 }
 pub fn instr_660FFE_reg_jit(ctx: &mut JitContext, r1: u32, r2: u32) {
-    sse_read128_xmm_xmm(ctx, "instr_660FFE", r1, r2);
+    sse_simd_xmm_xmm(ctx, "instr_660FFE", r1, r2, &|b| b.add_i32x4());
 }
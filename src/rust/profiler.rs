@@ -1,9 +1,12 @@
 #[allow(non_camel_case_types)]
 pub enum stat {
     COMPILE,
+    COMPILE_BASELINE,
+    COMPILE_PROMOTE_MODULE,
     COMPILE_SKIPPED_NO_NEW_ENTRY_POINTS,
     COMPILE_WRONG_ADDRESS_SPACE,
     COMPILE_CUT_OFF_AT_END_OF_PAGE,
+    COMPILE_CONTINUED_INTO_NEXT_PAGE,
     COMPILE_WITH_LOOP_SAFETY,
     COMPILE_PAGE,
     COMPILE_BASIC_BLOCK,
@@ -13,6 +16,9 @@ pub enum stat {
     COMPILE_DISPATCHER,
     COMPILE_ENTRY_POINT,
     COMPILE_WASM_TOTAL_BYTES,
+    COMPILE_FAILED,
+    JIT_CACHE_HIT,
+    JIT_CACHE_MISS,
 
     RUN_INTERPRETED,
     RUN_INTERPRETED_NEW_PAGE,
@@ -25,6 +31,7 @@ pub enum stat {
     RUN_INTERPRETED_DIFFERENT_STATE_IS32,
     RUN_INTERPRETED_DIFFERENT_STATE_SS32,
     RUN_INTERPRETED_MISSED_COMPILED_ENTRY_RUN_INTERPRETED,
+    RUN_INTERPRETED_MAPPED_MEMORY,
     RUN_INTERPRETED_STEPS,
 
     RUN_FROM_CACHE,
@@ -33,6 +40,10 @@ pub enum stat {
     DIRECT_EXIT,
     INDIRECT_JUMP,
     INDIRECT_JUMP_NO_ENTRY,
+    INDIRECT_JUMP_INLINE_CACHE_HIT,
+    INDIRECT_JUMP_INLINE_CACHE_MISS,
+    RETURN_STACK_HIT,
+    RETURN_STACK_MISS,
     NORMAL_PAGE_CHANGE,
     NORMAL_FALLTHRU,
     NORMAL_FALLTHRU_WITH_TARGET_BLOCK,
@@ -81,6 +92,9 @@ pub enum stat {
     SAFE_READ_WRITE_SLOW_READ_ONLY,
     SAFE_READ_WRITE_SLOW_HAS_CODE,
 
+    REP_STRING_FAST,
+    REP_STRING_SLOW,
+
     PAGE_FAULT,
     TLB_MISS,
 
@@ -89,7 +103,8 @@ pub enum stat {
     DO_MANY_CYCLES,
     CYCLE_INTERNAL,
 
-    INVALIDATE_ALL_MODULES_NO_FREE_WASM_INDICES,
+    INVALIDATE_MODULES_NO_FREE_WASM_INDICES,
+    INVALIDATE_MODULE_EVICTED,
     INVALIDATE_MODULE_WRITTEN_WHILE_COMPILED,
     INVALIDATE_MODULE_UNUSED_AFTER_OVERWRITE,
     INVALIDATE_MODULE_DIRTY_PAGE,
@@ -97,6 +112,8 @@ pub enum stat {
     INVALIDATE_PAGE_HAD_CODE,
     INVALIDATE_PAGE_HAD_ENTRY_POINTS,
     DIRTY_PAGE_DID_NOT_HAVE_CODE,
+    DIRTY_PAGE_DID_NOT_WRITE_CODE,
+    DIRTY_PAGE_CODE_UNCHANGED,
 
     RUN_FROM_CACHE_EXIT_SAME_PAGE,
     RUN_FROM_CACHE_EXIT_NEAR_END_OF_PAGE,
@@ -106,6 +123,7 @@ pub enum stat {
     FULL_CLEAR_TLB,
     TLB_FULL,
     TLB_GLOBAL_FULL,
+    SMP_CPU_SWITCH,
 
     MODRM_SIMPLE_REG,
     MODRM_SIMPLE_REG_WITH_OFFSET,
@@ -118,6 +136,8 @@ pub enum stat {
     SEG_OFFSET_NOT_OPTIMISED_FS,
     SEG_OFFSET_NOT_OPTIMISED_GS,
     SEG_OFFSET_NOT_OPTIMISED_NOT_FLAT,
+
+    LOCKED_OPERATION,
 }
 
 #[allow(non_upper_case_globals)]
@@ -31,6 +31,46 @@ extern "C" {
     static mut softfloat_exceptionFlags: u8;
 }
 
+// Single and double precision operations on raw bit patterns, used by the sse instructions. Like
+// the F80 operations, they round according to the global rounding mode and raise the global
+// exception flags
+extern "C" {
+    pub fn f32_add(x: u32, y: u32) -> u32;
+    pub fn f32_sub(x: u32, y: u32) -> u32;
+    pub fn f32_mul(x: u32, y: u32) -> u32;
+    pub fn f32_div(x: u32, y: u32) -> u32;
+    pub fn f32_sqrt(x: u32) -> u32;
+    pub fn f32_eq(x: u32, y: u32) -> bool;
+    pub fn f32_le(x: u32, y: u32) -> bool;
+    pub fn f32_lt(x: u32, y: u32) -> bool;
+    pub fn f32_lt_quiet(x: u32, y: u32) -> bool;
+    pub fn f32_to_f64(x: u32) -> u64;
+    pub fn f32_to_i32(x: u32, rounding_mode: u8, raise_inexact: bool) -> i32;
+    pub fn i32_to_f32(x: i32) -> u32;
+
+    pub fn f64_add(x: u64, y: u64) -> u64;
+    pub fn f64_sub(x: u64, y: u64) -> u64;
+    pub fn f64_mul(x: u64, y: u64) -> u64;
+    pub fn f64_div(x: u64, y: u64) -> u64;
+    pub fn f64_sqrt(x: u64) -> u64;
+    pub fn f64_eq(x: u64, y: u64) -> bool;
+    pub fn f64_le(x: u64, y: u64) -> bool;
+    pub fn f64_lt(x: u64, y: u64) -> bool;
+    pub fn f64_lt_quiet(x: u64, y: u64) -> bool;
+    pub fn f64_to_f32(x: u64) -> u32;
+    pub fn f64_to_i32(x: u64, rounding_mode: u8, raise_inexact: bool) -> i32;
+}
+
+/// The rounding mode is shared by the fpu and the sse instructions, the latter swap in the mode
+/// from MXCSR for the duration of an instruction (0: nearest even, 1: truncate, 2: floor, 3: ceil)
+pub fn swap_rounding_mode(mode: u8) -> u8 {
+    unsafe {
+        let old = softfloat_roundingMode;
+        softfloat_roundingMode = mode;
+        old
+    }
+}
+
 pub enum RoundingMode {
     NearEven,
     Trunc,
@@ -112,11 +152,9 @@ impl F80 {
         unsafe { f64_to_extF80M(src, &mut x) };
         x
     }
-    fn of_f64x(src: f64) -> F80 { F80::of_f64(unsafe { std::mem::transmute(src) }) }
 
     pub fn to_f32(&self) -> i32 { unsafe { extF80M_to_f32(self) } }
     pub fn to_f64(&self) -> u64 { unsafe { extF80M_to_f64(self) } }
-    fn to_f64x(&self) -> f64 { unsafe { std::mem::transmute(extF80M_to_f64(self)) } }
 
     pub fn to_i32(&self) -> i32 { unsafe { extF80M_to_i32(self, softfloat_roundingMode, false) } }
     pub fn to_i64(&self) -> i64 { unsafe { extF80M_to_i64(self, softfloat_roundingMode, false) } }
@@ -124,14 +162,275 @@ impl F80 {
     pub fn truncate_to_i32(&self) -> i32 { unsafe { extF80M_to_i32(self, 1, false) } }
     pub fn truncate_to_i64(&self) -> i64 { unsafe { extF80M_to_i64(self, 1, false) } }
 
-    pub fn cos(self) -> F80 { F80::of_f64x(self.to_f64x().cos()) }
-    pub fn sin(self) -> F80 { F80::of_f64x(self.to_f64x().sin()) }
-    pub fn tan(self) -> F80 { F80::of_f64x(self.to_f64x().tan()) }
-    pub fn atan(self) -> F80 { F80::of_f64x(self.to_f64x().atan()) }
-    pub fn atan2(self, other: F80) -> F80 { F80::of_f64x(self.to_f64x().atan2(other.to_f64x())) }
+    // The transcendental functions below are evaluated with a 128-bit mantissa (see Ext) and
+    // rounded once to 80 bits using the current rounding mode.
+    // sin, cos and tan return arguments with |x| >= 2**63 unchanged, like fsin does
+
+    pub fn sin(self) -> F80 {
+        match self.trig_reduce() {
+            Err(result) => result,
+            Ok((q, r)) => match q {
+                0 => Ext::sin_kernel(r),
+                1 => Ext::cos_kernel(r),
+                2 => Ext::sin_kernel(r).neg(),
+                _ => Ext::cos_kernel(r).neg(),
+            }
+            .to_f80(),
+        }
+    }
+    pub fn cos(self) -> F80 {
+        match self.trig_reduce() {
+            Err(result) => result,
+            Ok((q, r)) => match q {
+                0 => Ext::cos_kernel(r),
+                1 => Ext::sin_kernel(r).neg(),
+                2 => Ext::cos_kernel(r).neg(),
+                _ => Ext::sin_kernel(r),
+            }
+            .to_f80(),
+        }
+    }
+    pub fn tan(self) -> F80 {
+        match self.trig_reduce() {
+            Err(result) => result,
+            Ok((q, r)) => {
+                let sin = Ext::sin_kernel(r);
+                let cos = Ext::cos_kernel(r);
+                if q & 1 == 0 { sin.div(cos) } else { cos.div(sin).neg() }.to_f80()
+            },
+        }
+    }
+    pub fn atan(self) -> F80 { self.atan2(F80::ONE) }
+    pub fn atan2(self, other: F80) -> F80 {
+        // self is y, other is x, special cases follow IEEE 754 (and fpatan)
+        let (y, x) = (self, other);
+        if y.is_nan_encoding() || x.is_nan_encoding() {
+            return F80::propagate_nan(y, x);
+        }
+        let y_zero = y.mantissa == 0;
+        let x_zero = x.mantissa == 0;
+        let y_inf = y.is_infinity();
+        let x_inf = x.is_infinity();
+        let result = if y_zero || (x_inf && !y_inf) {
+            if x.sign() { Ext::PI } else { Ext::ZERO }
+        }
+        else if x_zero || (y_inf && !x_inf) {
+            Ext::PI_2
+        }
+        else if y_inf && x_inf {
+            if x.sign() { Ext::PI_4.add(Ext::PI_2) } else { Ext::PI_4 }
+        }
+        else {
+            let a = Ext::of_f80(y).abs();
+            let b = Ext::of_f80(x).abs();
+            let r = if a.cmp_abs(&b) != std::cmp::Ordering::Greater {
+                Ext::atan_kernel(a.div(b))
+            }
+            else {
+                Ext::PI_2.sub(Ext::atan_kernel(b.div(a)))
+            };
+            if x.sign() { Ext::PI.sub(r) } else { r }
+        };
+        Ext { sign: y.sign(), ..result }.to_f80()
+    }
+
+    pub fn log2(self) -> F80 {
+        match self.log2_ext() {
+            Ok(log) => log.to_f80(),
+            Err(result) => result,
+        }
+    }
+    pub fn ln(self) -> F80 {
+        match self.log2_ext() {
+            Ok(log) => log.mul(Ext::LN_2).to_f80(),
+            Err(result) => result,
+        }
+    }
+
+    /// other * log2(self), rounded once (fyl2x)
+    pub fn mul_log2(self, other: F80) -> F80 {
+        let (x, y) = (self, other);
+        if y.is_nan_encoding() {
+            return F80::propagate_nan(x, y);
+        }
+        match x.log2_ext() {
+            Ok(log) => F80::mul_by_log(y, log),
+            // x is zero or +inf
+            Err(result) if result.is_infinity() => {
+                if y.mantissa == 0 {
+                    F80::invalid()
+                }
+                else if y.sign() {
+                    -result
+                }
+                else {
+                    result
+                }
+            },
+            Err(result) => result,
+        }
+    }
+    /// other * log2(self + 1), rounded once and without cancellation for small self (fyl2xp1)
+    pub fn mul_log2_1p(self, other: F80) -> F80 {
+        let (x, y) = (self, other);
+        if x.is_nan_encoding() || y.is_nan_encoding() {
+            return F80::propagate_nan(x, y);
+        }
+        if x.is_infinity() {
+            return x.mul_log2(y);
+        }
+        if x.sign() && x.exponent() >= -1 {
+            // x + 1 is exact, or negative and invalid
+            return Ext::of_f80(x).add(Ext::ONE).to_f80().mul_log2(y);
+        }
+        let x = Ext::of_f80(x);
+        let log = if !x.is_zero() && x.exponent >= -1 {
+            Ext::log2_kernel(x.add(Ext::ONE))
+        }
+        else {
+            // ln(1 + x) = 2 * atanh(x / (2 + x))
+            let s = x.div(Ext::ONE.scale(1).add(x));
+            Ext::atanh_kernel(s).scale(1).mul(Ext::LOG2_E)
+        };
+        F80::mul_by_log(y, log)
+    }
+
+    /// 2**self - 1, rounded once and without cancellation for small self (f2xm1)
+    pub fn two_pow_minus_one(self) -> F80 {
+        if self.is_nan_encoding() {
+            return F80::propagate_nan(self, self);
+        }
+        if self.is_infinity() {
+            return if self.sign() { -F80::ONE } else { F80::POS_INFINITY };
+        }
+        if self.exponent() >= 0 {
+            return Ext::two_pow_kernel(Ext::of_f80(self))
+                .sub(Ext::ONE)
+                .to_f80();
+        }
+        Ext::expm1_kernel(Ext::of_f80(self).mul(Ext::LN_2)).to_f80()
+    }
+
+    pub fn is_denormal(&self) -> bool { self.sign_exponent & 0x7FFF == 0 && self.mantissa != 0 }
+    fn is_infinity(&self) -> bool {
+        self.sign_exponent & 0x7FFF == 0x7FFF && self.mantissa << 1 == 0
+    }
+    /// Like is_nan, but doesn't compare through softfloat and so never raises invalid
+    fn is_nan_encoding(&self) -> bool {
+        self.sign_exponent & 0x7FFF == 0x7FFF && self.mantissa << 1 != 0
+    }
+    fn is_signaling_nan(&self) -> bool { self.is_nan_encoding() && self.mantissa & 1 << 62 == 0 }
+
+    fn invalid() -> F80 {
+        unsafe { softfloat_exceptionFlags |= SOFTFLOAT_FLAG_INVALID };
+        F80::INDEFINITE_NAN
+    }
+    fn propagate_nan(a: F80, b: F80) -> F80 {
+        let nan = if a.is_nan_encoding() { a } else { b };
+        if a.is_signaling_nan() || b.is_signaling_nan() {
+            unsafe { softfloat_exceptionFlags |= SOFTFLOAT_FLAG_INVALID };
+        }
+        F80 {
+            mantissa: nan.mantissa | 1 << 62,
+            sign_exponent: nan.sign_exponent,
+        }
+    }
+
+    /// Split into a quadrant and a remainder in [-pi/4, pi/4], such that
+    /// self = (4 * k + quadrant + remainder / (pi / 2)) * pi / 2 for some integer k
+    fn trig_reduce(self) -> Result<(u32, Ext), F80> {
+        if self.is_nan_encoding() {
+            return Err(F80::propagate_nan(self, self));
+        }
+        if self.is_infinity() {
+            return Err(F80::invalid());
+        }
+        if self.exponent() >= 63 {
+            return Err(self);
+        }
+        let x = Ext::of_f80(self);
+        if x.is_zero() || x.cmp_abs(&Ext::PI_4) != std::cmp::Ordering::Greater {
+            return Ok((0, x));
+        }
+
+        // Payne-Hanek: multiply the 64-bit mantissa by enough bits of 2/pi that the integer part
+        // and at least 128 bits of the fraction of self * 2/pi are exact
+        let mut product = [0u64; TWO_OVER_PI.len() + 1];
+        for (i, &word) in TWO_OVER_PI.iter().enumerate() {
+            let low = TWO_OVER_PI.len() - 1 - i;
+            let mut carry = self.mantissa as u128 * word as u128;
+            for limb in product[low..].iter_mut() {
+                let sum = *limb as u128 + (carry as u64) as u128;
+                *limb = sum as u64;
+                carry = (carry >> 64) + (sum >> 64);
+                if carry == 0 {
+                    break;
+                }
+            }
+        }
+        // number of fraction bits in product
+        let point = 63 + 64 * TWO_OVER_PI.len() as i32 - self.exponent() as i32;
 
-    pub fn log2(self) -> F80 { F80::of_f64x(self.to_f64x().log2()) }
-    pub fn ln(self) -> F80 { F80::of_f64x(self.to_f64x().ln()) }
+        let mut quadrant = bits_at(&product, point) as u32 & 3;
+        let mut negative = false;
+        if bits_at(&product, point - 1) & 1 == 1 {
+            // fraction >= 1/2, continue with fraction - 1
+            quadrant = (quadrant + 1) & 3;
+            negative = true;
+            let mut carry = true;
+            for limb in product.iter_mut() {
+                let (sum, overflow) = (!*limb).overflowing_add(carry as u64);
+                *limb = sum;
+                carry = overflow;
+            }
+        }
+        let mut top = point - 1;
+        while top >= 0 && bits_at(&product, top) & 1 == 0 {
+            top -= 1;
+        }
+        if top < 0 {
+            // only happens for the exact multiple zero
+            return Ok((quadrant, Ext::ZERO));
+        }
+        let fraction = Ext {
+            sign: negative,
+            exponent: top - point,
+            mantissa: bits_at(&product, top - 127),
+        };
+        let remainder = fraction.mul(Ext::PI_2);
+        if self.sign() {
+            Ok(((4 - quadrant) & 3, remainder.neg()))
+        }
+        else {
+            Ok((quadrant, remainder))
+        }
+    }
+
+    fn log2_ext(self) -> Result<Ext, F80> {
+        if self.is_nan_encoding() {
+            return Err(F80::propagate_nan(self, self));
+        }
+        if self.mantissa == 0 {
+            unsafe { softfloat_exceptionFlags |= SOFTFLOAT_FLAG_INFINITE };
+            return Err(F80::NEG_INFINITY);
+        }
+        if self.sign() {
+            return Err(F80::invalid());
+        }
+        if self.is_infinity() {
+            return Err(F80::POS_INFINITY);
+        }
+        Ok(Ext::log2_kernel(Ext::of_f80(self)))
+    }
+    fn mul_by_log(y: F80, log: Ext) -> F80 {
+        if y.is_infinity() {
+            if log.is_zero() {
+                return F80::invalid();
+            }
+            return if y.sign() != log.sign { F80::NEG_INFINITY } else { F80::POS_INFINITY };
+        }
+        Ext::of_f80(y).mul(log).to_f80()
+    }
 
     pub fn abs(self) -> F80 {
         F80 {
@@ -139,7 +438,15 @@ impl F80 {
             sign_exponent: self.sign_exponent & !0x8000,
         }
     }
-    pub fn two_pow(self) -> F80 { F80::of_f64x(2.0f64.powf(self.to_f64x())) }
+    pub fn two_pow(self) -> F80 {
+        if self.is_nan_encoding() {
+            return F80::propagate_nan(self, self);
+        }
+        if self.is_infinity() {
+            return if self.sign() { F80::ZERO } else { F80::POS_INFINITY };
+        }
+        Ext::two_pow_kernel(Ext::of_f80(self)).to_f80()
+    }
     pub fn round(self) -> F80 {
         let mut result = F80::ZERO;
         unsafe { extF80M_roundToInt(&self, softfloat_roundingMode, false, &mut result) };
@@ -189,7 +496,7 @@ impl F80 {
     pub fn get_exception_flags() -> u8 {
         let f = unsafe { softfloat_exceptionFlags };
         // translate softfloat's flags to x87 status flags
-        f >> 4 & 1 | f >> 1 & 4 | f << 3 & 16
+        f >> 4 & 1 | f >> 1 & 4 | f << 1 & 8 | f << 3 & 16 | f << 5 & 32
     }
     pub fn clear_exception_flags() { unsafe { softfloat_exceptionFlags = 0 } }
 
@@ -284,3 +591,764 @@ impl PartialOrd for F80 {
         }
     }
 }
+
+const SOFTFLOAT_FLAG_INEXACT: u8 = 1;
+const SOFTFLOAT_FLAG_UNDERFLOW: u8 = 2;
+const SOFTFLOAT_FLAG_OVERFLOW: u8 = 4;
+const SOFTFLOAT_FLAG_INFINITE: u8 = 8;
+const SOFTFLOAT_FLAG_INVALID: u8 = 16;
+
+/// 2/pi as a 512-bit binary fraction, most significant word first
+const TWO_OVER_PI: [u64; 8] = [
+    0xA2F9836E4E441529,
+    0xFC2757D1F534DDC0,
+    0xDB6295993C439041,
+    0xFE5163ABDEBBC561,
+    0xB7246E3A424DD2E0,
+    0x06492EEA09D1921C,
+    0xFE1DEB1CB129A73E,
+    0xE88235F52EBB4484,
+];
+
+/// The 128 bits starting at bit index start of a little endian multi-word integer
+fn bits_at(words: &[u64], start: i32) -> u128 {
+    let mut result = 0;
+    for i in (0..2).rev() {
+        let bit = start + 64 * i;
+        let word = if bit >= 0 {
+            let (index, shift) = ((bit / 64) as usize, bit % 64);
+            let low = words.get(index).map_or(0, |w| w >> shift);
+            let high = if shift == 0 {
+                0
+            }
+            else {
+                words.get(index + 1).map_or(0, |w| w << (64 - shift))
+            };
+            low | high
+        }
+        else if bit > -64 {
+            words[0] << -bit
+        }
+        else {
+            0
+        };
+        result = result << 64 | word as u128;
+    }
+    result
+}
+
+/// Unpacked float with a 128-bit mantissa, used as the intermediate format of the transcendental
+/// functions. The value is mantissa * 2**(exponent - 127), with bit 127 of the mantissa set
+/// unless the value is zero
+#[derive(Copy, Clone)]
+struct Ext {
+    sign: bool,
+    exponent: i32,
+    mantissa: u128,
+}
+
+impl Ext {
+    const ZERO: Ext = Ext {
+        sign: false,
+        exponent: 0,
+        mantissa: 0,
+    };
+    const ONE: Ext = Ext {
+        sign: false,
+        exponent: 0,
+        mantissa: 1 << 127,
+    };
+    const PI: Ext = Ext {
+        sign: false,
+        exponent: 1,
+        mantissa: 0xC90FDAA22168C234C4C6628B80DC1CD1,
+    };
+    const PI_2: Ext = Ext {
+        sign: false,
+        exponent: 0,
+        mantissa: 0xC90FDAA22168C234C4C6628B80DC1CD1,
+    };
+    const PI_4: Ext = Ext {
+        sign: false,
+        exponent: -1,
+        mantissa: 0xC90FDAA22168C234C4C6628B80DC1CD1,
+    };
+    const TAN_PI_8: Ext = Ext {
+        sign: false,
+        exponent: -2,
+        mantissa: 0xD413CCCFE779921165F626CDD52AFA7C,
+    };
+    const LN_2: Ext = Ext {
+        sign: false,
+        exponent: -1,
+        mantissa: 0xB17217F7D1CF79ABC9E3B39803F2F6AF,
+    };
+    const LOG2_E: Ext = Ext {
+        sign: false,
+        exponent: 0,
+        mantissa: 0xB8AA3B295C17F0BBBE87FED0691D3E89,
+    };
+    const SQRT_2_MANTISSA: u128 = 0xB504F333F9DE6484597D89B3754ABE9F;
+
+    /// self must be finite
+    fn of_f80(x: F80) -> Ext {
+        if x.mantissa == 0 {
+            return Ext {
+                sign: x.sign(),
+                ..Ext::ZERO
+            };
+        }
+        let biased = (x.sign_exponent & 0x7FFF) as i32;
+        let shift = x.mantissa.leading_zeros();
+        Ext {
+            sign: x.sign(),
+            // denormals (and pseudo-denormals) have an effective exponent of 1
+            exponent: biased.max(1) - 0x3FFF - shift as i32,
+            mantissa: ((x.mantissa << shift) as u128) << 64,
+        }
+    }
+    fn of_i32(x: i32) -> Ext {
+        if x == 0 {
+            return Ext::ZERO;
+        }
+        let abs = (x as i64).abs() as u128;
+        let shift = abs.leading_zeros();
+        Ext {
+            sign: x < 0,
+            exponent: 127 - shift as i32,
+            mantissa: abs << shift,
+        }
+    }
+
+    /// Round to 80 bits using the current rounding mode, raising inexact, underflow and overflow
+    fn to_f80(self) -> F80 {
+        let sign = if self.sign { 0x8000 } else { 0 };
+        if self.is_zero() {
+            return F80 {
+                mantissa: 0,
+                sign_exponent: sign,
+            };
+        }
+        let mut biased = self.exponent + 0x3FFF;
+        let tiny = biased <= 0;
+        // denormals lose additional bits
+        let shift = if tiny { (65 - biased).min(129) as u32 } else { 64 };
+        let (mut mantissa, rest, half) = if shift == 129 {
+            (0, 1, 2)
+        }
+        else if shift == 128 {
+            (0, self.mantissa, 1 << 127)
+        }
+        else {
+            (
+                (self.mantissa >> shift) as u64,
+                self.mantissa & ((1 << shift) - 1),
+                1 << (shift - 1),
+            )
+        };
+        let mode = unsafe { softfloat_roundingMode };
+        let round_up = match mode {
+            0 => rest > half || (rest == half && mantissa & 1 == 1),
+            1 => false,
+            2 => rest != 0 && self.sign,
+            _ => rest != 0 && !self.sign,
+        };
+        if round_up {
+            let (sum, overflow) = mantissa.overflowing_add(1);
+            mantissa = sum;
+            if overflow {
+                mantissa = 1 << 63;
+                biased += 1;
+            }
+        }
+        if tiny {
+            // rounding up may have produced the smallest normal number
+            biased = if mantissa >> 63 == 1 { 1 } else { 0 };
+        }
+
+        let mut flags = 0;
+        if rest != 0 {
+            flags |= SOFTFLOAT_FLAG_INEXACT;
+            if tiny {
+                flags |= SOFTFLOAT_FLAG_UNDERFLOW;
+            }
+        }
+        let result = if biased >= 0x7FFF {
+            flags |= SOFTFLOAT_FLAG_OVERFLOW | SOFTFLOAT_FLAG_INEXACT;
+            let to_infinity = match mode {
+                0 => true,
+                1 => false,
+                2 => self.sign,
+                _ => !self.sign,
+            };
+            if to_infinity {
+                F80 {
+                    mantissa: 1 << 63,
+                    sign_exponent: sign | 0x7FFF,
+                }
+            }
+            else {
+                F80 {
+                    mantissa: !0,
+                    sign_exponent: sign | 0x7FFE,
+                }
+            }
+        }
+        else {
+            F80 {
+                mantissa,
+                sign_exponent: sign | biased as u16,
+            }
+        };
+        unsafe { softfloat_exceptionFlags |= flags };
+        result
+    }
+
+    fn is_zero(&self) -> bool { self.mantissa == 0 }
+
+    fn neg(self) -> Ext {
+        Ext {
+            sign: !self.sign,
+            ..self
+        }
+    }
+    fn abs(self) -> Ext {
+        Ext {
+            sign: false,
+            ..self
+        }
+    }
+    /// Multiply by 2**n
+    fn scale(self, n: i32) -> Ext {
+        if self.is_zero() {
+            return self;
+        }
+        Ext {
+            exponent: self.exponent + n,
+            ..self
+        }
+    }
+
+    fn cmp_abs(&self, other: &Ext) -> std::cmp::Ordering {
+        match (self.is_zero(), other.is_zero()) {
+            (true, true) => std::cmp::Ordering::Equal,
+            (true, false) => std::cmp::Ordering::Less,
+            (false, true) => std::cmp::Ordering::Greater,
+            (false, false) => (self.exponent, self.mantissa).cmp(&(other.exponent, other.mantissa)),
+        }
+    }
+
+    fn add(self, other: Ext) -> Ext {
+        if other.is_zero() {
+            return self;
+        }
+        if self.is_zero() {
+            return other;
+        }
+        let (a, b) = if self.cmp_abs(&other) == std::cmp::Ordering::Less {
+            (other, self)
+        }
+        else {
+            (self, other)
+        };
+        let shift = (a.exponent - b.exponent) as u32;
+        let b_mantissa = if shift >= 128 { 0 } else { b.mantissa >> shift };
+        if a.sign == b.sign {
+            let (sum, overflow) = a.mantissa.overflowing_add(b_mantissa);
+            if overflow {
+                Ext {
+                    sign: a.sign,
+                    exponent: a.exponent + 1,
+                    mantissa: sum >> 1 | 1 << 127,
+                }
+            }
+            else {
+                Ext {
+                    mantissa: sum,
+                    ..a
+                }
+            }
+        }
+        else {
+            let difference = a.mantissa - b_mantissa;
+            if difference == 0 {
+                return Ext::ZERO;
+            }
+            let shift = difference.leading_zeros();
+            Ext {
+                sign: a.sign,
+                exponent: a.exponent - shift as i32,
+                mantissa: difference << shift,
+            }
+        }
+    }
+    fn sub(self, other: Ext) -> Ext { self.add(other.neg()) }
+
+    fn mul(self, other: Ext) -> Ext {
+        let sign = self.sign != other.sign;
+        if self.is_zero() || other.is_zero() {
+            return Ext { sign, ..Ext::ZERO };
+        }
+        const LOW: u128 = 0xFFFF_FFFF_FFFF_FFFF;
+        let (a1, a0) = (self.mantissa >> 64, self.mantissa & LOW);
+        let (b1, b0) = (other.mantissa >> 64, other.mantissa & LOW);
+        let (p00, p01, p10, p11) = (a0 * b0, a0 * b1, a1 * b0, a1 * b1);
+        let middle = (p00 >> 64) + (p01 & LOW) + (p10 & LOW);
+        let high = p11 + (p01 >> 64) + (p10 >> 64) + (middle >> 64);
+        let low = middle << 64 | p00 & LOW;
+        let exponent = self.exponent + other.exponent;
+        if high >> 127 == 1 {
+            Ext {
+                sign,
+                exponent: exponent + 1,
+                mantissa: high,
+            }
+        }
+        else {
+            Ext {
+                sign,
+                exponent,
+                mantissa: high << 1 | low >> 127,
+            }
+        }
+    }
+
+    /// other must not be zero
+    fn div(self, other: Ext) -> Ext {
+        let sign = self.sign != other.sign;
+        if self.is_zero() {
+            return Ext { sign, ..Ext::ZERO };
+        }
+        let mut exponent = self.exponent - other.exponent;
+        let mut remainder = self.mantissa;
+        let mut carry = false;
+        let mut quotient: u128 = 0;
+        let mut bits = 128;
+        if remainder < other.mantissa {
+            // the quotient is less than one, start one bit further down
+            carry = remainder >> 127 == 1;
+            remainder <<= 1;
+            exponent -= 1;
+        }
+        while bits > 0 {
+            quotient <<= 1;
+            if carry || remainder >= other.mantissa {
+                remainder = remainder.wrapping_sub(other.mantissa);
+                quotient |= 1;
+            }
+            carry = remainder >> 127 == 1;
+            remainder <<= 1;
+            bits -= 1;
+        }
+        Ext {
+            sign,
+            exponent,
+            mantissa: quotient,
+        }
+    }
+
+    /// Whether term is too small to affect sum
+    fn is_negligible(term: &Ext, sum: &Ext) -> bool {
+        term.is_zero() || (!sum.is_zero() && term.exponent < sum.exponent - 130)
+    }
+
+    /// sin(x) for |x| <= pi/4
+    fn sin_kernel(x: Ext) -> Ext {
+        let x2 = x.mul(x);
+        let mut term = x;
+        let mut sum = x;
+        let mut n = 1;
+        loop {
+            term = term.mul(x2).div(Ext::of_i32((n + 1) * (n + 2))).neg();
+            n += 2;
+            if Ext::is_negligible(&term, &sum) {
+                return sum;
+            }
+            sum = sum.add(term);
+        }
+    }
+    /// cos(x) for |x| <= pi/4
+    fn cos_kernel(x: Ext) -> Ext {
+        let x2 = x.mul(x);
+        let mut term = Ext::ONE;
+        let mut sum = Ext::ONE;
+        let mut n = 0;
+        loop {
+            term = term.mul(x2).div(Ext::of_i32((n + 1) * (n + 2))).neg();
+            n += 2;
+            if Ext::is_negligible(&term, &sum) {
+                return sum;
+            }
+            sum = sum.add(term);
+        }
+    }
+
+    /// x - x**3/3 + x**5/5 - ... (atan) or x + x**3/3 + x**5/5 + ... (atanh) for small |x|
+    fn odd_power_series(x: Ext, alternating: bool) -> Ext {
+        let x2 = if alternating { x.mul(x).neg() } else { x.mul(x) };
+        let mut power = x;
+        let mut sum = x;
+        let mut n = 1;
+        loop {
+            power = power.mul(x2);
+            n += 2;
+            let term = power.div(Ext::of_i32(n));
+            if Ext::is_negligible(&term, &sum) {
+                return sum;
+            }
+            sum = sum.add(term);
+        }
+    }
+    /// atan(x) for 0 <= x <= 1
+    fn atan_kernel(x: Ext) -> Ext {
+        if x.cmp_abs(&Ext::TAN_PI_8) == std::cmp::Ordering::Greater {
+            // atan(x) = pi/4 + atan((x - 1) / (x + 1))
+            let reduced = x.sub(Ext::ONE).div(x.add(Ext::ONE));
+            Ext::PI_4.add(Ext::odd_power_series(reduced, true))
+        }
+        else {
+            Ext::odd_power_series(x, true)
+        }
+    }
+    /// atanh(x) for |x| <= 1/3
+    fn atanh_kernel(x: Ext) -> Ext { Ext::odd_power_series(x, false) }
+
+    /// log2(x) for positive x
+    fn log2_kernel(x: Ext) -> Ext {
+        // x = m * 2**e with m in [sqrt(1/2), sqrt(2)]
+        let (m, e) = if x.mantissa > Ext::SQRT_2_MANTISSA {
+            (Ext { exponent: -1, ..x }, x.exponent + 1)
+        }
+        else {
+            (Ext { exponent: 0, ..x }, x.exponent)
+        };
+        // ln(m) = 2 * atanh((m - 1) / (m + 1))
+        let s = m.sub(Ext::ONE).div(m.add(Ext::ONE));
+        let ln_m = Ext::atanh_kernel(s).scale(1);
+        Ext::of_i32(e).add(ln_m.mul(Ext::LOG2_E))
+    }
+
+    /// e**x - 1 for |x| < 1
+    fn expm1_kernel(x: Ext) -> Ext {
+        let mut term = x;
+        let mut sum = x;
+        let mut n = 1;
+        loop {
+            n += 1;
+            term = term.mul(x).div(Ext::of_i32(n));
+            if Ext::is_negligible(&term, &sum) {
+                return sum;
+            }
+            sum = sum.add(term);
+        }
+    }
+    /// 2**x, x must be finite
+    fn two_pow_kernel(x: Ext) -> Ext {
+        if x.is_zero() {
+            return Ext::ONE;
+        }
+        if x.exponent >= 15 {
+            // far outside of the 80-bit exponent range, to_f80 overflows or underflows
+            return Ext {
+                sign: false,
+                exponent: if x.sign { -0x10000 } else { 0x10000 },
+                mantissa: 1 << 127,
+            };
+        }
+        // x = n + f with integer n and |f| < 1
+        let n = if x.exponent < 0 { 0 } else { (x.mantissa >> (127 - x.exponent)) as i32 };
+        let n = if x.sign { -n } else { n };
+        let f = x.sub(Ext::of_i32(n));
+        if f.is_zero() {
+            return Ext::ONE.scale(n);
+        }
+        Ext::ONE
+            .add(Ext::expm1_kernel(f.mul(Ext::LN_2)))
+            .scale(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::F80;
+
+    // The transcendental functions are implemented in rust and only use the global rounding mode
+    // and exception flags of softfloat, which isn't linked into the tests
+    #[no_mangle]
+    #[allow(non_upper_case_globals)]
+    static mut softfloat_roundingMode: u8 = 0;
+    #[no_mangle]
+    #[allow(non_upper_case_globals)]
+    static mut softfloat_exceptionFlags: u8 = 0;
+
+    const ONE: F80 = F80::ONE;
+
+    const fn f(mantissa: u64, sign_exponent: u16) -> F80 {
+        F80 {
+            mantissa,
+            sign_exponent,
+        }
+    }
+
+    fn run(op: &str, x: F80, y: F80) -> F80 {
+        match op {
+            "fsin" => x.sin(),
+            "fcos" => x.cos(),
+            "fptan" => x.tan(),
+            "fpatan" => y.atan2(x),
+            "fyl2x" => x.mul_log2(y),
+            "fyl2xp1" => x.mul_log2_1p(y),
+            "f2xm1" => x.two_pow_minus_one(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Position of x on the number line in units in the last place
+    fn ulps(x: F80) -> i128 {
+        let position = ((x.sign_exponent & 0x7FFF) as i128) << 63 | (x.mantissa & !(1 << 63)) as i128;
+        if x.sign() { -position } else { position }
+    }
+
+    // Captured with an x87 (cw=0x37F), as (instruction, st0, st1, result). The instructions aren't
+    // correctly rounded everywhere, so the results may differ by one ulp
+    const HARDWARE_VECTORS: [(&str, F80, F80, F80); 103] = [
+        ("fsin", f(0xC90FDAA22168C235, 0x3FFE), ONE, f(0xB504F333F9DE6485, 0x3FFE)),
+        ("fcos", f(0xC90FDAA22168C235, 0x3FFE), ONE, f(0xB504F333F9DE6484, 0x3FFE)),
+        ("fptan", f(0xC90FDAA22168C235, 0x3FFE), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fsin", f(0xC90FDAA22168C236, 0x3FFE), ONE, f(0xB504F333F9DE6485, 0x3FFE)),
+        ("fcos", f(0xC90FDAA22168C236, 0x3FFE), ONE, f(0xB504F333F9DE6483, 0x3FFE)),
+        ("fptan", f(0xC90FDAA22168C236, 0x3FFE), ONE, f(0x8000000000000001, 0x3FFF)),
+        ("fsin", f(0xC90FDAA22168C234, 0x3FFE), ONE, f(0xB504F333F9DE6484, 0x3FFE)),
+        ("fcos", f(0xC90FDAA22168C234, 0x3FFE), ONE, f(0xB504F333F9DE6485, 0x3FFE)),
+        ("fptan", f(0xC90FDAA22168C234, 0x3FFE), ONE, f(0xFFFFFFFFFFFFFFFE, 0x3FFE)),
+        ("fsin", f(0xC90FDAA22168C235, 0x3FFF), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fsin", f(0xC90FDAA22168C236, 0x3FFF), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fsin", f(0xC90FDAA22168C234, 0x3FFF), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fsin", f(0x96CBE3F9990E91A8, 0x4000), ONE, f(0xB504F333F9DE6483, 0x3FFE)),
+        ("fcos", f(0x96CBE3F9990E91A8, 0x4000), ONE, f(0xB504F333F9DE6486, 0xBFFE)),
+        ("fptan", f(0x96CBE3F9990E91A8, 0x4000), ONE, f(0xFFFFFFFFFFFFFFFC, 0xBFFE)),
+        ("fsin", f(0x96CBE3F9990E91A9, 0x4000), ONE, f(0xB504F333F9DE6480, 0x3FFE)),
+        ("fcos", f(0x96CBE3F9990E91A9, 0x4000), ONE, f(0xB504F333F9DE6488, 0xBFFE)),
+        ("fptan", f(0x96CBE3F9990E91A9, 0x4000), ONE, f(0xFFFFFFFFFFFFFFF5, 0xBFFE)),
+        ("fsin", f(0x96CBE3F9990E91A7, 0x4000), ONE, f(0xB504F333F9DE6486, 0x3FFE)),
+        ("fcos", f(0x96CBE3F9990E91A7, 0x4000), ONE, f(0xB504F333F9DE6483, 0xBFFE)),
+        ("fptan", f(0x96CBE3F9990E91A7, 0x4000), ONE, f(0x8000000000000002, 0xBFFF)),
+        ("fcos", f(0xC90FDAA22168C235, 0x4000), ONE, f(0x8000000000000000, 0xBFFF)),
+        ("fcos", f(0xC90FDAA22168C236, 0x4000), ONE, f(0x8000000000000000, 0xBFFF)),
+        ("fcos", f(0xC90FDAA22168C234, 0x4000), ONE, f(0x8000000000000000, 0xBFFF)),
+        ("fsin", f(0xFB53D14AA9C2F2C2, 0x4000), ONE, f(0xB504F333F9DE6485, 0xBFFE)),
+        ("fcos", f(0xFB53D14AA9C2F2C2, 0x4000), ONE, f(0xB504F333F9DE6484, 0xBFFE)),
+        ("fptan", f(0xFB53D14AA9C2F2C2, 0x4000), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fsin", f(0xFB53D14AA9C2F2C3, 0x4000), ONE, f(0xB504F333F9DE6487, 0xBFFE)),
+        ("fcos", f(0xFB53D14AA9C2F2C3, 0x4000), ONE, f(0xB504F333F9DE6481, 0xBFFE)),
+        ("fptan", f(0xFB53D14AA9C2F2C3, 0x4000), ONE, f(0x8000000000000004, 0x3FFF)),
+        ("fsin", f(0xFB53D14AA9C2F2C1, 0x4000), ONE, f(0xB504F333F9DE6482, 0xBFFE)),
+        ("fcos", f(0xFB53D14AA9C2F2C1, 0x4000), ONE, f(0xB504F333F9DE6487, 0xBFFE)),
+        ("fptan", f(0xFB53D14AA9C2F2C1, 0x4000), ONE, f(0xFFFFFFFFFFFFFFF8, 0x3FFE)),
+        ("fsin", f(0x96CBE3F9990E91A8, 0x4001), ONE, f(0x8000000000000000, 0xBFFF)),
+        ("fsin", f(0x96CBE3F9990E91A9, 0x4001), ONE, f(0x8000000000000000, 0xBFFF)),
+        ("fsin", f(0x96CBE3F9990E91A7, 0x4001), ONE, f(0x8000000000000000, 0xBFFF)),
+        ("fcos", f(0xC90FDAA22168C235, 0x4001), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fcos", f(0xC90FDAA22168C236, 0x4001), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fcos", f(0xC90FDAA22168C234, 0x4001), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fcos", f(0x9D1462CEAA19D7B9, 0x4005), ONE, f(0x8000000000000000, 0xBFFF)),
+        ("fcos", f(0x9D1462CEAA19D7BA, 0x4005), ONE, f(0x8000000000000000, 0xBFFF)),
+        ("fcos", f(0x9D1462CEAA19D7B8, 0x4005), ONE, f(0x8000000000000000, 0xBFFF)),
+        ("fcos", f(0xBFBF629D46A48BD6, 0x4012), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fcos", f(0xBFBF629D46A48BD7, 0x4012), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fcos", f(0xBFBF629D46A48BD5, 0x4012), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fsin", f(0x0000000000000001, 0x0000), ONE, f(0x0000000000000001, 0x0000)),
+        ("fcos", f(0x0000000000000001, 0x0000), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fptan", f(0x0000000000000001, 0x0000), ONE, f(0x0000000000000001, 0x0000)),
+        ("fsin", f(0x7FFFFFFFFFFFFFFF, 0x0000), ONE, f(0x7FFFFFFFFFFFFFFF, 0x0000)),
+        ("fcos", f(0x7FFFFFFFFFFFFFFF, 0x0000), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fptan", f(0x7FFFFFFFFFFFFFFF, 0x0000), ONE, f(0x7FFFFFFFFFFFFFFF, 0x0000)),
+        ("fsin", f(0x8000000000000000, 0x0001), ONE, f(0x8000000000000000, 0x0001)),
+        ("fcos", f(0x8000000000000000, 0x0001), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fptan", f(0x8000000000000000, 0x0001), ONE, f(0x8000000000000000, 0x0001)),
+        ("fsin", f(0x9C3D73864F3805C0, 0x0C17), ONE, f(0x9C3D73864F3805C0, 0x0C17)),
+        ("fcos", f(0x9C3D73864F3805C0, 0x0C17), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("fptan", f(0x9C3D73864F3805C0, 0x0C17), ONE, f(0x9C3D73864F3805C0, 0x0C17)),
+        ("fsin", f(0x8000000000000000, 0xBFFF), ONE, f(0xD76AA47848677021, 0xBFFE)),
+        ("fcos", f(0x8000000000000000, 0xBFFF), ONE, f(0x8A51407DA8345C92, 0x3FFE)),
+        ("fptan", f(0x8000000000000000, 0xBFFF), ONE, f(0xC75922E5F71D2DC5, 0xBFFF)),
+        ("fsin", f(0x8000000000000000, 0x3FFE), ONE, f(0xF57743A2582F7F44, 0x3FFD)),
+        ("fcos", f(0x8000000000000000, 0x3FFE), ONE, f(0xE0A94032DBEA7CEE, 0x3FFE)),
+        ("fptan", f(0x8000000000000000, 0x3FFE), ONE, f(0x8BDA7ADF9A3A5219, 0x3FFE)),
+        ("fpatan", f(0x8000000000000000, 0x3FFF), f(0x8000000000000000, 0x3FFF),
+            f(0xC90FDAA22168C235, 0x3FFE)),
+        ("fpatan", f(0x8000000000000000, 0xBFFF), f(0x8000000000000000, 0xBFFF),
+            f(0x96CBE3F9990E91A8, 0xC000)),
+        ("fpatan", f(0x8000000000000000, 0xBFFF), f(0x8000000000000000, 0x3FFF),
+            f(0x96CBE3F9990E91A8, 0x4000)),
+        ("fpatan", f(0x8000000000000000, 0xBFFF), f(0xDBE6FECEBDEDD5BF, 0x3FDD),
+            f(0xC90FDAA205EBE25B, 0x4000)),
+        ("fpatan", f(0x8000000000000000, 0x3FFF), f(0xC000000000000000, 0x4000),
+            f(0x9FE0BB5BD42AFFEC, 0x3FFF)),
+        ("fpatan", f(0xC000000000000000, 0x4000), f(0x8000000000000000, 0x3FFF),
+            f(0xA4BC7D1934F70924, 0x3FFD)),
+        ("fpatan", f(0x8000000000000000, 0x3FFF), f(0x0000200000000000, 0x0000),
+            f(0x0000200000000000, 0x0000)),
+        ("fpatan", f(0x0000200000000000, 0x0000), f(0x8000000000000000, 0x3FFF),
+            f(0xC90FDAA22168C235, 0x3FFF)),
+        ("fpatan", f(0x8000000000000000, 0x3FFF), f(0xD413CCCFE7799211, 0x3FFD),
+            f(0xC90FDAA22168C234, 0x3FFD)),
+        ("fpatan", f(0x8000000000000000, 0x3FFF), f(0xD413CCCFE7799215, 0x3FFD),
+            f(0xC90FDAA22168C238, 0x3FFD)),
+        ("fpatan", f(0x9C3D73864F3805C0, 0x0C17), f(0xD1BA8323FE558C61, 0x73E6),
+            f(0xC90FDAA22168C235, 0x3FFF)),
+        ("fyl2x", f(0x8000000000000001, 0x3FFF), f(0x8000000000000000, 0x3FFF),
+            f(0xB8AA3B295C17F0BB, 0x3FC0)),
+        ("fyl2x", f(0xFFFFFFFFFFFFFFFF, 0x3FFE), f(0x8000000000000000, 0x3FFF),
+            f(0xB8AA3B295C17F0BC, 0xBFBF)),
+        ("fyl2x", f(0x8000000000000000, 0x4000), f(0x8000000000000000, 0x3FFF),
+            f(0x8000000000000000, 0x3FFF)),
+        ("fyl2x", f(0xD1BA8323FE558C61, 0x73E6), f(0x8000000000000000, 0x3FFF),
+            f(0xCF9ED97A064CE5BC, 0x400C)),
+        ("fyl2x", f(0x0000000000000001, 0x0000), f(0x8000000000000000, 0x3FFF),
+            f(0x807A000000000000, 0xC00D)),
+        ("fyl2x", f(0xFFFFFFFFFFFFFFFF, 0x7E7F), f(0x8000000000000000, 0x3FFF),
+            f(0xFA04000000000000, 0x400C)),
+        ("fyl2x", f(0xB504F333F9DE6484, 0x3FFE), f(0x8000000000000000, 0x3FFF),
+            f(0x8000000000000001, 0xBFFE)),
+        ("fyl2x", f(0xB504F333F9DE6484, 0x3FFF), f(0x8000000000000000, 0x3FFF),
+            f(0xFFFFFFFFFFFFFFFE, 0x3FFD)),
+        ("fyl2x", f(0xA000000000000000, 0x4002), f(0x8000000000000000, 0x3FFF),
+            f(0xD49A784BCD1B8AFE, 0x4000)),
+        ("fyl2x", f(0x8000000000000000, 0x4002), f(0xE000000000000000, 0xC000),
+            f(0xA800000000000000, 0xC002)),
+        ("fyl2xp1", f(0x95F619980C4336F7, 0x3FFD), f(0x8000000000000000, 0x3FFF),
+            f(0xBDBFB1693CC7E3E4, 0x3FFD)),
+        ("fyl2xp1", f(0x95F619980C4336F7, 0xBFFD), f(0x8000000000000000, 0x3FFF),
+            f(0xFFFFFFFFFFFFFFFF, 0xBFFD)),
+        ("fyl2xp1", f(0x95F619980C4336F6, 0x3FFD), f(0x8000000000000000, 0x3FFF),
+            f(0xBDBFB1693CC7E3E3, 0x3FFD)),
+        ("fyl2xp1", f(0xBCE5086492111AEB, 0x3FBC), f(0x8000000000000000, 0x3FFF),
+            f(0x884239C72956C9B3, 0x3FBD)),
+        ("fyl2xp1", f(0x0000000000000001, 0x0000), f(0x8000000000000000, 0x3FFF),
+            f(0x0000000000000001, 0x0000)),
+        ("fyl2xp1", f(0x0000000000000001, 0x8000), f(0x8000000000000000, 0x3FFF),
+            f(0x0000000000000001, 0x8000)),
+        ("fyl2xp1", f(0xA7C5AC471B478423, 0xBFEE), f(0x8000000000000000, 0x3FFF),
+            f(0xF20B970A9C6B4F3D, 0xBFEE)),
+        ("fyl2xp1", f(0x8000000000000000, 0x3FFE), f(0x8000000000000000, 0x3FFF),
+            f(0x95C01A39FBD687A0, 0x3FFE)),
+        ("fyl2xp1", f(0xFFBE76C8B4395810, 0x3FFE), f(0x8000000000000000, 0x3FFF),
+            f(0xFFD0B6C530FD044A, 0x3FFE)),
+        ("f2xm1", f(0x8000000000000000, 0x3FFF), ONE, f(0x8000000000000000, 0x3FFF)),
+        ("f2xm1", f(0x8000000000000000, 0xBFFF), ONE, f(0x8000000000000000, 0xBFFE)),
+        ("f2xm1", f(0xFFFFFFFFFFFFFFFF, 0x3FFE), ONE, f(0xFFFFFFFFFFFFFFFF, 0x3FFE)),
+        ("f2xm1", f(0xFFFFFFFFFFFFFFFF, 0xBFFE), ONE, f(0xFFFFFFFFFFFFFFFF, 0xBFFD)),
+        ("f2xm1", f(0x8000000000000000, 0x3FFE), ONE, f(0xD413CCCFE7799211, 0x3FFD)),
+        ("f2xm1", f(0x8000000000000000, 0xBFFE), ONE, f(0x95F619980C4336F7, 0xBFFD)),
+        ("f2xm1", f(0xBCE5086492111AEB, 0x3FBC), ONE, f(0x82EE8A7AB5531436, 0x3FBC)),
+        ("f2xm1", f(0x0000000000000001, 0x0000), ONE, f(0x0000000000000001, 0x0000)),
+        ("f2xm1", f(0x8000000000000000, 0x8001), ONE, f(0x58B90BFBE8E7BCD6, 0x8000)),
+        ("f2xm1", f(0xA7C5AC471B478423, 0x3FEE), ONE, f(0xE8951885AF479379, 0x3FED)),
+    ];
+
+    // Close to multiples of pi/2 and for huge arguments, the x87 reduces with a 66-bit pi and
+    // returns mostly garbage. These results are correctly rounded, computed with 600-bit mpmath
+    const REDUCTION_VECTORS: [(&str, F80, F80, F80); 51] = [
+        ("fcos", f(0xC90FDAA22168C235, 0x3FFF), ONE, f(0xECE675D1FC8F8CBB, 0xBFBD)),
+        ("fptan", f(0xC90FDAA22168C235, 0x3FFF), ONE, f(0x8A51E04DAABDA35F, 0xC040)),
+        ("fcos", f(0xC90FDAA22168C236, 0x3FFF), ONE, f(0x9D9CCEBA3F91F197, 0xBFC0)),
+        ("fptan", f(0xC90FDAA22168C236, 0x3FFF), ONE, f(0xCFE6FA111D2FEB4F, 0xC03D)),
+        ("fcos", f(0xC90FDAA22168C234, 0x3FFF), ONE, f(0xC4C6628B80DC1CD1, 0x3FBF)),
+        ("fptan", f(0xC90FDAA22168C234, 0x3FFF), ONE, f(0xA686780675D73F75, 0x403E)),
+        ("fsin", f(0xC90FDAA22168C235, 0x4000), ONE, f(0xECE675D1FC8F8CBB, 0xBFBE)),
+        ("fptan", f(0xC90FDAA22168C235, 0x4000), ONE, f(0xECE675D1FC8F8CBB, 0x3FBE)),
+        ("fsin", f(0xC90FDAA22168C236, 0x4000), ONE, f(0x9D9CCEBA3F91F197, 0xBFC1)),
+        ("fptan", f(0xC90FDAA22168C236, 0x4000), ONE, f(0x9D9CCEBA3F91F197, 0x3FC1)),
+        ("fsin", f(0xC90FDAA22168C234, 0x4000), ONE, f(0xC4C6628B80DC1CD1, 0x3FC0)),
+        ("fptan", f(0xC90FDAA22168C234, 0x4000), ONE, f(0xC4C6628B80DC1CD1, 0xBFC0)),
+        ("fcos", f(0x96CBE3F9990E91A8, 0x4001), ONE, f(0xD8D66C2EBEB5D4C6, 0x3FC0)),
+        ("fptan", f(0x96CBE3F9990E91A8, 0x4001), ONE, f(0x971E224F6C0521C6, 0xC03D)),
+        ("fcos", f(0x96CBE3F9990E91A9, 0x4001), ONE, f(0xB6359B0BAFAD7532, 0x3FC2)),
+        ("fptan", f(0x96CBE3F9990E91A9, 0x4001), ONE, f(0xB3D648BF475CC439, 0xC03B)),
+        ("fcos", f(0x96CBE3F9990E91A7, 0x4001), ONE, f(0x9394C9E8A0A5159D, 0xBFC1)),
+        ("fptan", f(0x96CBE3F9990E91A7, 0x4001), ONE, f(0xDE08A0089D1EFF46, 0x403C)),
+        ("fsin", f(0xC90FDAA22168C235, 0x4001), ONE, f(0xECE675D1FC8F8CBB, 0x3FBF)),
+        ("fptan", f(0xC90FDAA22168C235, 0x4001), ONE, f(0xECE675D1FC8F8CBB, 0x3FBF)),
+        ("fsin", f(0xC90FDAA22168C236, 0x4001), ONE, f(0x9D9CCEBA3F91F197, 0x3FC2)),
+        ("fptan", f(0xC90FDAA22168C236, 0x4001), ONE, f(0x9D9CCEBA3F91F197, 0x3FC2)),
+        ("fsin", f(0xC90FDAA22168C234, 0x4001), ONE, f(0xC4C6628B80DC1CD1, 0xBFC1)),
+        ("fptan", f(0xC90FDAA22168C234, 0x4001), ONE, f(0xC4C6628B80DC1CD1, 0xBFC1)),
+        ("fsin", f(0x9D1462CEAA19D7B9, 0x4005), ONE, f(0xE6EBF3F3F2AFDA0E, 0x3FC3)),
+        ("fptan", f(0x9D1462CEAA19D7B9, 0x4005), ONE, f(0xE6EBF3F3F2AFDA0E, 0xBFC3)),
+        ("fsin", f(0x9D1462CEAA19D7BA, 0x4005), ONE, f(0xC64503030354097D, 0xBFC5)),
+        ("fptan", f(0x9D1462CEAA19D7BA, 0x4005), ONE, f(0xC64503030354097D, 0x3FC5)),
+        ("fsin", f(0x9D1462CEAA19D7B8, 0x4005), ONE, f(0x9CDD7E7E7E55FB42, 0x3FC6)),
+        ("fptan", f(0x9D1462CEAA19D7B8, 0x4005), ONE, f(0x9CDD7E7E7E55FB42, 0xBFC6)),
+        ("fsin", f(0xBFBF629D46A48BD6, 0x4012), ONE, f(0xCA0E7C5A5B202B29, 0x3FD1)),
+        ("fptan", f(0xBFBF629D46A48BD6, 0x4012), ONE, f(0xCA0E7C5A5B202B29, 0x3FD1)),
+        ("fsin", f(0xBFBF629D46A48BD7, 0x4012), ONE, f(0xB2839F1696C80ACA, 0x3FD3)),
+        ("fptan", f(0xBFBF629D46A48BD7, 0x4012), ONE, f(0xB2839F1696C80ACA, 0x3FD3)),
+        ("fsin", f(0xBFBF629D46A48BD5, 0x4012), ONE, f(0x9AF8C1D2D26FEA6B, 0xBFD2)),
+        ("fptan", f(0xBFBF629D46A48BD5, 0x4012), ONE, f(0x9AF8C1D2D26FEA6B, 0xBFD2)),
+        ("fsin", f(0xDE0B6B3A76400000, 0x403A), ONE, f(0xFE333CC682E96D39, 0xBFFE)),
+        ("fcos", f(0xDE0B6B3A76400000, 0x403A), ONE, f(0xF26D039603ACD38C, 0x3FFB)),
+        ("fptan", f(0xDE0B6B3A76400000, 0x403A), ONE, f(0x86377FDEB0569285, 0xC002)),
+        ("fsin", f(0x8000000000000000, 0x403D), ONE, f(0xB3F2B9AAF80A3946, 0xBFFE)),
+        ("fcos", f(0x8000000000000000, 0x403D), ONE, f(0xB6158FC106383E05, 0xBFFE)),
+        ("fptan", f(0x8000000000000000, 0x403D), ONE, f(0xFCFF2DF3327D3A09, 0x3FFE)),
+        ("fsin", f(0xFFFFFFFFFFFFFFFF, 0x403D), ONE, f(0xDF327E112ABEEF8F, 0x3FFE)),
+        ("fcos", f(0xFFFFFFFFFFFFFFFF, 0x403D), ONE, f(0xFAC035EC484929C2, 0x3FFD)),
+        ("fptan", f(0xFFFFFFFFFFFFFFFF, 0x403D), ONE, f(0xE3DE9ED3992F3138, 0x3FFF)),
+        ("fsin", f(0x8E1BC9BF04000000, 0x4033), ONE, f(0xE6D694F9DE0CB493, 0xBFFE)),
+        ("fcos", f(0x8E1BC9BF04000000, 0x4033), ONE, f(0xDD5B5E80A6BFB56D, 0xBFFD)),
+        ("fptan", f(0x8E1BC9BF04000000, 0x4033), ONE, f(0x857B8A3113D546FA, 0x4000)),
+        ("fsin", f(0xB1A2BC2EC5000000, 0xC037), ONE, f(0xEDD6E3D08CFE3D54, 0x3FFD)),
+        ("fcos", f(0xB1A2BC2EC5000000, 0xC037), ONE, f(0xE2B3E293C57E58BD, 0xBFFE)),
+        ("fptan", f(0xB1A2BC2EC5000000, 0xC037), ONE, f(0x8649B9356FCC5CEB, 0xBFFE)),
+    ];
+
+    #[test]
+    fn hardware_vectors() {
+        for &(op, x, y, expected) in HARDWARE_VECTORS.iter() {
+            let result = run(op, x, y);
+            assert!(
+                (ulps(result) - ulps(expected)).abs() <= 1,
+                "{} {:04X}:{:016X} {:04X}:{:016X}: {:04X}:{:016X}, expected {:04X}:{:016X}",
+                op,
+                x.sign_exponent,
+                x.mantissa,
+                y.sign_exponent,
+                y.mantissa,
+                result.sign_exponent,
+                result.mantissa,
+                expected.sign_exponent,
+                expected.mantissa
+            );
+        }
+    }
+
+    #[test]
+    fn reduction_vectors() {
+        for &(op, x, y, expected) in REDUCTION_VECTORS.iter() {
+            let result = run(op, x, y);
+            assert!(
+                ulps(result) == ulps(expected),
+                "{} {:04X}:{:016X}: {:04X}:{:016X}, expected {:04X}:{:016X}",
+                op,
+                x.sign_exponent,
+                x.mantissa,
+                result.sign_exponent,
+                result.mantissa,
+                expected.sign_exponent,
+                expected.mantissa
+            );
+        }
+    }
+
+    #[test]
+    fn trig_out_of_range() {
+        // |x| >= 2**63 is returned unchanged, fpu_trig_operand_out_of_range sets C2
+        for &x in [f(0x8000000000000000, 0x403E), f(0xFFFFFFFFFFFFFFFF, 0xC03E)].iter() {
+            for op in ["fsin", "fcos", "fptan"].iter() {
+                let result = run(op, x, ONE);
+                assert!(result.mantissa == x.mantissa && result.sign_exponent == x.sign_exponent);
+            }
+        }
+    }
+}
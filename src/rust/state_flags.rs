@@ -7,21 +7,28 @@ impl CachedStateFlags {
     const MASK_SS32: u8 = 1 << 1;
     const MASK_CPL3: u8 = 1 << 2;
     const MASK_FLAT_SEGS: u8 = 1 << 3;
+    // the x87 control word selects double precision and rounding to nearest and masks all
+    // exceptions, so that x87 arithmetic can be done on f64 values
+    const MASK_FPU_F64: u8 = 1 << 4;
+
+    const MASK_ALL: u8 = Self::MASK_IS_32
+        | Self::MASK_SS32
+        | Self::MASK_CPL3
+        | Self::MASK_FLAT_SEGS
+        | Self::MASK_FPU_F64;
 
     pub const EMPTY: CachedStateFlags = CachedStateFlags(0);
 
     pub fn of_u32(f: u32) -> CachedStateFlags {
-        dbg_assert!(
-            f as u8
-                & !(Self::MASK_IS_32 | Self::MASK_SS32 | Self::MASK_CPL3 | Self::MASK_FLAT_SEGS)
-                == 0
-        );
+        dbg_assert!(Self::is_valid(f));
         CachedStateFlags(f as u8)
     }
+    pub fn is_valid(f: u32) -> bool { f & !(Self::MASK_ALL as u32) == 0 }
     pub fn to_u32(&self) -> u32 { self.0 as u32 }
 
     pub fn cpl3(&self) -> bool { self.0 & CachedStateFlags::MASK_CPL3 != 0 }
     pub fn has_flat_segmentation(&self) -> bool { self.0 & CachedStateFlags::MASK_FLAT_SEGS != 0 }
     pub fn is_32(&self) -> bool { self.0 & CachedStateFlags::MASK_IS_32 != 0 }
     pub fn ssize_32(&self) -> bool { self.0 & CachedStateFlags::MASK_SS32 != 0 }
+    pub fn fpu_f64(&self) -> bool { self.0 & CachedStateFlags::MASK_FPU_F64 != 0 }
 }
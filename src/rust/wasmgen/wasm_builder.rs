@@ -63,6 +63,10 @@ pub struct WasmBuilder {
 
     initial_static_size: usize, // size of module after initialization, rest is drained on reset
 
+    // offsets of constants that can be rewritten after the module is finished, relative to the
+    // instruction body until finish is called and to the output afterwards
+    relocations: Vec<u32>,
+
     // label for referencing block/if/loop constructs directly via branch instructions
     next_label: Label,
     label_stack: Vec<Label>,
@@ -70,6 +74,8 @@ pub struct WasmBuilder {
 
     free_locals_i32: Vec<WasmLocal>,
     free_locals_i64: Vec<WasmLocalI64>,
+    free_locals_f64: Vec<WasmLocalF64>,
+    free_locals_v128: Vec<WasmLocalV128>,
     local_count: u8,
     pub arg_local_initial_state: WasmLocal,
 }
@@ -88,6 +94,16 @@ impl WasmLocalI64 {
     pub fn idx(&self) -> u8 { self.0 }
 }
 
+pub struct WasmLocalF64(u8);
+impl WasmLocalF64 {
+    pub fn idx(&self) -> u8 { self.0 }
+}
+
+pub struct WasmLocalV128(u8);
+impl WasmLocalV128 {
+    pub fn idx(&self) -> u8 { self.0 }
+}
+
 #[derive(Copy, Clone, Eq, Hash, PartialEq)]
 pub struct Label(u32);
 impl Label {
@@ -110,12 +126,16 @@ impl WasmBuilder {
 
             initial_static_size: 0,
 
+            relocations: Vec::new(),
+
             label_to_depth: HashMap::new(),
             label_stack: Vec::new(),
             next_label: Label::ZERO,
 
             free_locals_i32: Vec::with_capacity(8),
             free_locals_i64: Vec::with_capacity(8),
+            free_locals_f64: Vec::with_capacity(8),
+            free_locals_v128: Vec::with_capacity(8),
             local_count: 0,
             arg_local_initial_state: WasmLocal(0),
         };
@@ -144,8 +164,11 @@ impl WasmBuilder {
         self.set_import_table_size(2);
         self.set_import_count(0);
         self.instruction_body.clear();
+        self.relocations.clear();
         self.free_locals_i32.clear();
         self.free_locals_i64.clear();
+        self.free_locals_f64.clear();
+        self.free_locals_v128.clear();
         self.local_count = 0;
 
         dbg_assert!(self.label_to_depth.is_empty());
@@ -180,18 +203,30 @@ impl WasmBuilder {
         self.output.push(0);
 
         dbg_assert!(
-            self.local_count as usize == self.free_locals_i32.len() + self.free_locals_i64.len(),
+            self.local_count as usize
+                == self.free_locals_i32.len()
+                    + self.free_locals_i64.len()
+                    + self.free_locals_f64.len()
+                    + self.free_locals_v128.len(),
             "All locals should have been freed"
         );
 
         let free_locals_i32 = &self.free_locals_i32;
         let free_locals_i64 = &self.free_locals_i64;
+        let free_locals_f64 = &self.free_locals_f64;
+        let free_locals_v128 = &self.free_locals_v128;
 
         let locals = (0..self.local_count).map(|i| {
             let local_index = WASM_MODULE_ARGUMENT_COUNT + i;
             if free_locals_i64.iter().any(|v| v.idx() == local_index) {
                 op::TYPE_I64
             }
+            else if free_locals_f64.iter().any(|v| v.idx() == local_index) {
+                op::TYPE_F64
+            }
+            else if free_locals_v128.iter().any(|v| v.idx() == local_index) {
+                op::TYPE_V128
+            }
             else {
                 dbg_assert!(free_locals_i32.iter().any(|v| v.idx() == local_index));
                 op::TYPE_I32
@@ -216,6 +251,10 @@ impl WasmBuilder {
             self.output.push(local_type);
         }
 
+        let idx_instruction_body = self.output.len() as u32;
+        for offset in self.relocations.iter_mut() {
+            *offset += idx_instruction_body;
+        }
         self.output.append(&mut self.instruction_body);
 
         self.output.push(op::OP_END);
@@ -530,6 +569,8 @@ impl WasmBuilder {
 
     pub fn get_output_ptr(&self) -> *const u8 { self.output.as_ptr() }
     pub fn get_output_len(&self) -> u32 { self.output.len() as u32 }
+    pub fn get_output(&self) -> &[u8] { &self.output }
+    pub fn get_relocations(&self) -> &[u32] { &self.relocations }
 
     fn open_block(&mut self) -> Label {
         let label = self.next_label;
@@ -623,19 +664,114 @@ impl WasmBuilder {
         self.instruction_body.push(local.idx());
         local
     }
+    pub fn set_local_i64(&mut self, local: &WasmLocalI64) {
+        self.instruction_body.push(op::OP_SETLOCAL);
+        self.instruction_body.push(local.idx());
+    }
     pub fn get_local_i64(&mut self, local: &WasmLocalI64) {
         self.instruction_body.push(op::OP_GETLOCAL);
         self.instruction_body.push(local.idx());
     }
 
+    #[must_use = "local allocated but not used"]
+    fn alloc_local_f64(&mut self) -> WasmLocalF64 {
+        match self.free_locals_f64.pop() {
+            Some(local) => local,
+            None => {
+                let new_idx = self.local_count + WASM_MODULE_ARGUMENT_COUNT;
+                self.local_count += 1;
+                WasmLocalF64(new_idx)
+            },
+        }
+    }
+    pub fn free_local_f64(&mut self, local: WasmLocalF64) {
+        dbg_assert!(
+            (WASM_MODULE_ARGUMENT_COUNT..self.local_count + WASM_MODULE_ARGUMENT_COUNT)
+                .contains(&local.0)
+        );
+        self.free_locals_f64.push(local)
+    }
+    #[must_use = "local allocated but not used"]
+    pub fn set_new_local_f64(&mut self) -> WasmLocalF64 {
+        let local = self.alloc_local_f64();
+        self.instruction_body.push(op::OP_SETLOCAL);
+        self.instruction_body.push(local.idx());
+        local
+    }
+    #[must_use = "local allocated but not used"]
+    pub fn tee_new_local_f64(&mut self) -> WasmLocalF64 {
+        let local = self.alloc_local_f64();
+        self.instruction_body.push(op::OP_TEELOCAL);
+        self.instruction_body.push(local.idx());
+        local
+    }
+    pub fn set_local_f64(&mut self, local: &WasmLocalF64) {
+        self.instruction_body.push(op::OP_SETLOCAL);
+        self.instruction_body.push(local.idx());
+    }
+    pub fn get_local_f64(&mut self, local: &WasmLocalF64) {
+        self.instruction_body.push(op::OP_GETLOCAL);
+        self.instruction_body.push(local.idx());
+    }
+
+    #[must_use = "local allocated but not used"]
+    fn alloc_local_v128(&mut self) -> WasmLocalV128 {
+        match self.free_locals_v128.pop() {
+            Some(local) => local,
+            None => {
+                let new_idx = self.local_count + WASM_MODULE_ARGUMENT_COUNT;
+                self.local_count += 1;
+                WasmLocalV128(new_idx)
+            },
+        }
+    }
+    pub fn free_local_v128(&mut self, local: WasmLocalV128) {
+        dbg_assert!(
+            (WASM_MODULE_ARGUMENT_COUNT..self.local_count + WASM_MODULE_ARGUMENT_COUNT)
+                .contains(&local.0)
+        );
+        self.free_locals_v128.push(local)
+    }
+    #[must_use = "local allocated but not used"]
+    pub fn set_new_local_v128(&mut self) -> WasmLocalV128 {
+        let local = self.alloc_local_v128();
+        self.instruction_body.push(op::OP_SETLOCAL);
+        self.instruction_body.push(local.idx());
+        local
+    }
+    pub fn get_local_v128(&mut self, local: &WasmLocalV128) {
+        self.instruction_body.push(op::OP_GETLOCAL);
+        self.instruction_body.push(local.idx());
+    }
+
     pub fn const_i32(&mut self, v: i32) {
         self.instruction_body.push(op::OP_I32CONST);
         write_leb_i32(&mut self.instruction_body, v);
     }
+    /// An i32 constant that is encoded using two bytes, so that it can be rewritten in the finished
+    /// module at the offsets returned by get_relocations
+    pub fn const_i32_relocatable(&mut self, v: u16) {
+        dbg_assert!(v < (1 << 13)); // the highest bit is the sign bit
+        self.instruction_body.push(op::OP_I32CONST);
+        self.relocations.push(self.instruction_body.len() as u32);
+        self.instruction_body.push(0);
+        self.instruction_body.push(0);
+        let idx = self.instruction_body.len() - 2;
+        write_fixed_leb16_at_idx(&mut self.instruction_body, idx, v);
+    }
     pub fn const_i64(&mut self, v: i64) {
         self.instruction_body.push(op::OP_I64CONST);
         write_leb_i64(&mut self.instruction_body, v);
     }
+    pub fn const_f64(&mut self, v: f64) {
+        self.instruction_body.push(op::OP_F64CONST);
+        self.instruction_body.extend_from_slice(&v.to_bits().to_le_bytes());
+    }
+
+    pub fn const_v128(&mut self, v: u128) {
+        self.simd_op(op::OP_V128CONST);
+        self.instruction_body.extend_from_slice(&v.to_le_bytes());
+    }
 
     pub fn load_fixed_u8(&mut self, addr: u32) {
         self.const_i32(addr as i32);
@@ -689,24 +825,12 @@ impl WasmBuilder {
         write_leb_u32(&mut self.instruction_body, byte_offset);
     }
 
-    pub fn load_aligned_f64(&mut self, byte_offset: u32) {
-        self.instruction_body.push(op::OP_F64LOAD);
-        self.instruction_body.push(op::MEM_ALIGN64);
-        write_leb_u32(&mut self.instruction_body, byte_offset);
-    }
-
     pub fn load_aligned_i64(&mut self, byte_offset: u32) {
         self.instruction_body.push(op::OP_I64LOAD);
         self.instruction_body.push(op::MEM_ALIGN64);
         write_leb_u32(&mut self.instruction_body, byte_offset);
     }
 
-    pub fn load_aligned_f32(&mut self, byte_offset: u32) {
-        self.instruction_body.push(op::OP_F32LOAD);
-        self.instruction_body.push(op::MEM_ALIGN32);
-        write_leb_u32(&mut self.instruction_body, byte_offset);
-    }
-
     pub fn load_aligned_i32(&mut self, byte_offset: u32) {
         self.instruction_body.push(op::OP_I32LOAD);
         self.instruction_body.push(op::MEM_ALIGN32);
@@ -725,12 +849,6 @@ impl WasmBuilder {
         write_leb_u32(&mut self.instruction_body, byte_offset);
     }
 
-    pub fn store_aligned_u16(&mut self, byte_offset: u32) {
-        self.instruction_body.push(op::OP_I32STORE16);
-        self.instruction_body.push(op::MEM_ALIGN16);
-        write_leb_u32(&mut self.instruction_body, byte_offset);
-    }
-
     pub fn store_aligned_i32(&mut self, byte_offset: u32) {
         self.instruction_body.push(op::OP_I32STORE);
         self.instruction_body.push(op::MEM_ALIGN32);
@@ -743,6 +861,17 @@ impl WasmBuilder {
         write_leb_u32(&mut self.instruction_body, byte_offset);
     }
 
+    pub fn load_aligned_v128(&mut self, byte_offset: u32) {
+        self.simd_op(op::OP_V128LOAD);
+        self.instruction_body.push(op::MEM_ALIGN128);
+        write_leb_u32(&mut self.instruction_body, byte_offset);
+    }
+    pub fn store_aligned_v128(&mut self, byte_offset: u32) {
+        self.simd_op(op::OP_V128STORE);
+        self.instruction_body.push(op::MEM_ALIGN128);
+        write_leb_u32(&mut self.instruction_body, byte_offset);
+    }
+
     pub fn store_unaligned_u16(&mut self, byte_offset: u32) {
         self.instruction_body.push(op::OP_I32STORE16);
         self.instruction_body.push(op::MEM_NO_ALIGN);
@@ -774,6 +903,7 @@ impl WasmBuilder {
     pub fn sub_i32(&mut self) { self.instruction_body.push(op::OP_I32SUB); }
     pub fn and_i32(&mut self) { self.instruction_body.push(op::OP_I32AND); }
     pub fn or_i32(&mut self) { self.instruction_body.push(op::OP_I32OR); }
+    pub fn and_i64(&mut self) { self.instruction_body.push(op::OP_I64AND); }
     pub fn or_i64(&mut self) { self.instruction_body.push(op::OP_I64OR); }
     pub fn xor_i32(&mut self) { self.instruction_body.push(op::OP_I32XOR); }
     pub fn mul_i32(&mut self) { self.instruction_body.push(op::OP_I32MUL); }
@@ -781,6 +911,14 @@ impl WasmBuilder {
     pub fn div_i64(&mut self) { self.instruction_body.push(op::OP_I64DIVU); }
     pub fn rem_i64(&mut self) { self.instruction_body.push(op::OP_I64REMU); }
 
+    pub fn add_f64(&mut self) { self.instruction_body.push(op::OP_F64ADD); }
+    pub fn sub_f64(&mut self) { self.instruction_body.push(op::OP_F64SUB); }
+    pub fn mul_f64(&mut self) { self.instruction_body.push(op::OP_F64MUL); }
+    pub fn div_f64(&mut self) { self.instruction_body.push(op::OP_F64DIV); }
+    pub fn sqrt_f64(&mut self) { self.instruction_body.push(op::OP_F64SQRT); }
+    pub fn neg_f64(&mut self) { self.instruction_body.push(op::OP_F64NEG); }
+    pub fn abs_f64(&mut self) { self.instruction_body.push(op::OP_F64ABS); }
+
     pub fn rotl_i32(&mut self) { self.instruction_body.push(op::OP_I32ROTL); }
 
     pub fn shl_i32(&mut self) { self.instruction_body.push(op::OP_I32SHL); }
@@ -806,20 +944,102 @@ impl WasmBuilder {
 
     pub fn gtu_i64(&mut self) { self.instruction_body.push(op::OP_I64GTU); }
 
+    /// memory.copy(dest, source, length), with memmove semantics
+    pub fn memory_copy(&mut self) {
+        self.instruction_body.push(op::OP_MISC_PREFIX);
+        write_leb_u32(&mut self.instruction_body, op::OP_MEMORYCOPY as u32);
+        self.instruction_body.push(0); // destination memory
+        self.instruction_body.push(0); // source memory
+    }
+    /// memory.fill(dest, byte, length)
+    pub fn memory_fill(&mut self) {
+        self.instruction_body.push(op::OP_MISC_PREFIX);
+        write_leb_u32(&mut self.instruction_body, op::OP_MEMORYFILL as u32);
+        self.instruction_body.push(0); // memory
+    }
+
+    // simd, the host must support the wasm simd proposal, see jit::jit_use_simd
+    fn simd_op(&mut self, op: u8) {
+        self.instruction_body.push(op::OP_SIMD_PREFIX);
+        write_leb_u32(&mut self.instruction_body, op as u32);
+    }
+    pub fn splat_i64x2(&mut self) { self.simd_op(op::OP_I64X2SPLAT); }
+    pub fn extract_lane_i64x2(&mut self, lane: u8) {
+        dbg_assert!(lane < 2);
+        self.simd_op(op::OP_I64X2EXTRACTLANE);
+        self.instruction_body.push(lane);
+    }
+    pub fn replace_lane_i64x2(&mut self, lane: u8) {
+        dbg_assert!(lane < 2);
+        self.simd_op(op::OP_I64X2REPLACELANE);
+        self.instruction_body.push(lane);
+    }
+    /// Select bytes from the two operands, 0-15 from the first and 16-31 from the second
+    pub fn shuffle_i8x16(&mut self, lanes: &[u8; 16]) {
+        dbg_assert!(lanes.iter().all(|&l| l < 32));
+        self.simd_op(op::OP_I8X16SHUFFLE);
+        self.instruction_body.extend_from_slice(lanes);
+    }
+    pub fn narrow_signed_i16x8_to_i8x16(&mut self) { self.simd_op(op::OP_I8X16NARROWI16X8S); }
+    pub fn narrow_unsigned_i16x8_to_i8x16(&mut self) { self.simd_op(op::OP_I8X16NARROWI16X8U); }
+    pub fn narrow_signed_i32x4_to_i16x8(&mut self) { self.simd_op(op::OP_I16X8NARROWI32X4S); }
+    pub fn dot_signed_i16x8_to_i32x4(&mut self) { self.simd_op(op::OP_I32X4DOTI16X8S); }
+    pub fn and_v128(&mut self) { self.simd_op(op::OP_V128AND); }
+    pub fn andnot_v128(&mut self) { self.simd_op(op::OP_V128ANDNOT); }
+    pub fn or_v128(&mut self) { self.simd_op(op::OP_V128OR); }
+    pub fn xor_v128(&mut self) { self.simd_op(op::OP_V128XOR); }
+    pub fn add_i8x16(&mut self) { self.simd_op(op::OP_I8X16ADD); }
+    pub fn add_sat_s_i8x16(&mut self) { self.simd_op(op::OP_I8X16ADDSATS); }
+    pub fn add_sat_u_i8x16(&mut self) { self.simd_op(op::OP_I8X16ADDSATU); }
+    pub fn sub_i8x16(&mut self) { self.simd_op(op::OP_I8X16SUB); }
+    pub fn sub_sat_s_i8x16(&mut self) { self.simd_op(op::OP_I8X16SUBSATS); }
+    pub fn sub_sat_u_i8x16(&mut self) { self.simd_op(op::OP_I8X16SUBSATU); }
+    pub fn min_u_i8x16(&mut self) { self.simd_op(op::OP_I8X16MINU); }
+    pub fn max_u_i8x16(&mut self) { self.simd_op(op::OP_I8X16MAXU); }
+    pub fn avgr_u_i8x16(&mut self) { self.simd_op(op::OP_I8X16AVGRU); }
+    pub fn eq_i8x16(&mut self) { self.simd_op(op::OP_I8X16EQ); }
+    pub fn gt_i8x16(&mut self) { self.simd_op(op::OP_I8X16GTS); }
+    pub fn add_i16x8(&mut self) { self.simd_op(op::OP_I16X8ADD); }
+    pub fn add_sat_s_i16x8(&mut self) { self.simd_op(op::OP_I16X8ADDSATS); }
+    pub fn add_sat_u_i16x8(&mut self) { self.simd_op(op::OP_I16X8ADDSATU); }
+    pub fn sub_i16x8(&mut self) { self.simd_op(op::OP_I16X8SUB); }
+    pub fn sub_sat_s_i16x8(&mut self) { self.simd_op(op::OP_I16X8SUBSATS); }
+    pub fn sub_sat_u_i16x8(&mut self) { self.simd_op(op::OP_I16X8SUBSATU); }
+    pub fn mul_i16x8(&mut self) { self.simd_op(op::OP_I16X8MUL); }
+    pub fn min_s_i16x8(&mut self) { self.simd_op(op::OP_I16X8MINS); }
+    pub fn max_s_i16x8(&mut self) { self.simd_op(op::OP_I16X8MAXS); }
+    pub fn avgr_u_i16x8(&mut self) { self.simd_op(op::OP_I16X8AVGRU); }
+    pub fn eq_i16x8(&mut self) { self.simd_op(op::OP_I16X8EQ); }
+    pub fn gt_i16x8(&mut self) { self.simd_op(op::OP_I16X8GTS); }
+    pub fn shl_i16x8(&mut self) { self.simd_op(op::OP_I16X8SHL); }
+    pub fn shr_s_i16x8(&mut self) { self.simd_op(op::OP_I16X8SHRS); }
+    pub fn shr_u_i16x8(&mut self) { self.simd_op(op::OP_I16X8SHRU); }
+    pub fn add_i32x4(&mut self) { self.simd_op(op::OP_I32X4ADD); }
+    pub fn sub_i32x4(&mut self) { self.simd_op(op::OP_I32X4SUB); }
+    pub fn eq_i32x4(&mut self) { self.simd_op(op::OP_I32X4EQ); }
+    pub fn gt_i32x4(&mut self) { self.simd_op(op::OP_I32X4GTS); }
+    pub fn shl_i32x4(&mut self) { self.simd_op(op::OP_I32X4SHL); }
+    pub fn shr_s_i32x4(&mut self) { self.simd_op(op::OP_I32X4SHRS); }
+    pub fn shr_u_i32x4(&mut self) { self.simd_op(op::OP_I32X4SHRU); }
+    pub fn add_i64x2(&mut self) { self.simd_op(op::OP_I64X2ADD); }
+    pub fn sub_i64x2(&mut self) { self.simd_op(op::OP_I64X2SUB); }
+    pub fn shl_i64x2(&mut self) { self.simd_op(op::OP_I64X2SHL); }
+    pub fn shr_u_i64x2(&mut self) { self.simd_op(op::OP_I64X2SHRU); }
+
     pub fn reinterpret_i32_as_f32(&mut self) {
         self.instruction_body.push(op::OP_F32REINTERPRETI32);
     }
-    //pub fn reinterpret_f32_as_i32(&mut self) {
-    //    self.instruction_body.push(op::OP_I32REINTERPRETF32);
-    //}
+    pub fn reinterpret_f32_as_i32(&mut self) {
+        self.instruction_body.push(op::OP_I32REINTERPRETF32);
+    }
     pub fn reinterpret_i64_as_f64(&mut self) {
         self.instruction_body.push(op::OP_F64REINTERPRETI64);
     }
-    //pub fn reinterpret_f64_as_i64(&mut self) {
-    //    self.instruction_body.push(op::OP_I64REINTERPRETF64);
-    //}
-    //pub fn promote_f32_to_f64(&mut self) { self.instruction_body.push(op::OP_F64PROMOTEF32); }
-    //pub fn demote_f64_to_f32(&mut self) { self.instruction_body.push(op::OP_F32DEMOTEF64); }
+    pub fn reinterpret_f64_as_i64(&mut self) {
+        self.instruction_body.push(op::OP_I64REINTERPRETF64);
+    }
+    pub fn promote_f32_to_f64(&mut self) { self.instruction_body.push(op::OP_F64PROMOTEF32); }
+    pub fn demote_f64_to_f32(&mut self) { self.instruction_body.push(op::OP_F32DEMOTEF64); }
     //pub fn convert_i32_to_f64(&mut self) { self.instruction_body.push(op::OP_F64CONVERTSI32); }
     //pub fn convert_i64_to_f64(&mut self) { self.instruction_body.push(op::OP_F64CONVERTSI64); }
     pub fn extend_unsigned_i32_to_i64(&mut self) {
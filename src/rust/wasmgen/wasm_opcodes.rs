@@ -26,6 +26,7 @@ c!(TYPE_I32, 0x7f);
 c!(TYPE_I64, 0x7e);
 c!(TYPE_F32, 0x7d);
 c!(TYPE_F64, 0x7c);
+c!(TYPE_V128, 0x7b);
 c!(TYPE_ANYFUNC, 0x70);
 c!(TYPE_FUNC, 0x60);
 c!(TYPE_VOID_BLOCK, 0x40);
@@ -215,7 +216,130 @@ c!(OP_I64REINTERPRETF64, 0xbd);
 c!(OP_F32REINTERPRETI32, 0xbe);
 c!(OP_F64REINTERPRETI64, 0xbf);
 
+// https://github.com/WebAssembly/bulk-memory-operations/blob/master/proposals/bulk-memory-operations/Overview.md
+// Prefixed by OP_MISC_PREFIX, followed by the sub-opcode as a leb128-encoded u32
+c!(OP_MISC_PREFIX, 0xfc);
+c!(OP_MEMORYCOPY, 0x0a);
+c!(OP_MEMORYFILL, 0x0b);
+
+// https://github.com/WebAssembly/simd/blob/main/proposals/simd/BinarySIMD.md
+// Prefixed by OP_SIMD_PREFIX, followed by the sub-opcode as a leb128-encoded u32
+c!(OP_SIMD_PREFIX, 0xfd);
+c!(OP_V128LOAD, 0x00);
+c!(OP_V128STORE, 0x0b);
+c!(OP_V128CONST, 0x0c);
+c!(OP_I8X16SHUFFLE, 0x0d);
+c!(OP_I8X16SWIZZLE, 0x0e);
+c!(OP_I8X16SPLAT, 0x0f);
+c!(OP_I16X8SPLAT, 0x10);
+c!(OP_I32X4SPLAT, 0x11);
+c!(OP_I64X2SPLAT, 0x12);
+c!(OP_F32X4SPLAT, 0x13);
+c!(OP_F64X2SPLAT, 0x14);
+c!(OP_I32X4EXTRACTLANE, 0x1b);
+c!(OP_I32X4REPLACELANE, 0x1c);
+c!(OP_I64X2EXTRACTLANE, 0x1d);
+c!(OP_I64X2REPLACELANE, 0x1e);
+c!(OP_F32X4EXTRACTLANE, 0x1f);
+c!(OP_F32X4REPLACELANE, 0x20);
+c!(OP_F64X2EXTRACTLANE, 0x21);
+c!(OP_F64X2REPLACELANE, 0x22);
+c!(OP_I8X16EQ, 0x23);
+c!(OP_I8X16GTS, 0x27);
+c!(OP_I16X8EQ, 0x2d);
+c!(OP_I16X8GTS, 0x31);
+c!(OP_I32X4EQ, 0x37);
+c!(OP_I32X4GTS, 0x3b);
+c!(OP_F32X4EQ, 0x41);
+c!(OP_F32X4NE, 0x42);
+c!(OP_F32X4LT, 0x43);
+c!(OP_F32X4LE, 0x45);
+c!(OP_F64X2EQ, 0x47);
+c!(OP_F64X2NE, 0x48);
+c!(OP_F64X2LT, 0x49);
+c!(OP_F64X2LE, 0x4b);
+c!(OP_V128NOT, 0x4d);
+c!(OP_V128AND, 0x4e);
+c!(OP_V128ANDNOT, 0x4f);
+c!(OP_V128OR, 0x50);
+c!(OP_V128XOR, 0x51);
+c!(OP_V128BITSELECT, 0x52);
+c!(OP_V128ANYTRUE, 0x53);
+c!(OP_V128LOAD32ZERO, 0x5c);
+c!(OP_V128LOAD64ZERO, 0x5d);
+c!(OP_I8X16BITMASK, 0x64);
+c!(OP_I8X16NARROWI16X8S, 0x65);
+c!(OP_I8X16NARROWI16X8U, 0x66);
+c!(OP_I8X16ADD, 0x6e);
+c!(OP_I8X16ADDSATS, 0x6f);
+c!(OP_I8X16ADDSATU, 0x70);
+c!(OP_I8X16SUB, 0x71);
+c!(OP_I8X16SUBSATS, 0x72);
+c!(OP_I8X16SUBSATU, 0x73);
+c!(OP_I8X16MINS, 0x76);
+c!(OP_I8X16MINU, 0x77);
+c!(OP_I8X16MAXS, 0x78);
+c!(OP_I8X16MAXU, 0x79);
+c!(OP_I8X16AVGRU, 0x7b);
+c!(OP_I16X8NARROWI32X4S, 0x85);
+c!(OP_I16X8NARROWI32X4U, 0x86);
+c!(OP_I16X8SHL, 0x8b);
+c!(OP_I16X8SHRS, 0x8c);
+c!(OP_I16X8SHRU, 0x8d);
+c!(OP_I16X8ADD, 0x8e);
+c!(OP_I16X8ADDSATS, 0x8f);
+c!(OP_I16X8ADDSATU, 0x90);
+c!(OP_I16X8SUB, 0x91);
+c!(OP_I16X8SUBSATS, 0x92);
+c!(OP_I16X8SUBSATU, 0x93);
+c!(OP_I16X8MUL, 0x95);
+c!(OP_I16X8MINS, 0x96);
+c!(OP_I16X8MINU, 0x97);
+c!(OP_I16X8MAXS, 0x98);
+c!(OP_I16X8MAXU, 0x99);
+c!(OP_I16X8AVGRU, 0x9b);
+c!(OP_I32X4SHL, 0xab);
+c!(OP_I32X4SHRS, 0xac);
+c!(OP_I32X4SHRU, 0xad);
+c!(OP_I32X4ADD, 0xae);
+c!(OP_I32X4SUB, 0xb1);
+c!(OP_I32X4MUL, 0xb5);
+c!(OP_I32X4MINS, 0xb6);
+c!(OP_I32X4MINU, 0xb7);
+c!(OP_I32X4MAXS, 0xb8);
+c!(OP_I32X4MAXU, 0xb9);
+c!(OP_I32X4DOTI16X8S, 0xba);
+c!(OP_I64X2SHL, 0xcb);
+c!(OP_I64X2SHRS, 0xcc);
+c!(OP_I64X2SHRU, 0xcd);
+c!(OP_I64X2ADD, 0xce);
+c!(OP_I64X2SUB, 0xd1);
+c!(OP_I64X2MUL, 0xd5);
+c!(OP_F32X4ABS, 0xe0);
+c!(OP_F32X4NEG, 0xe1);
+c!(OP_F32X4SQRT, 0xe3);
+c!(OP_F32X4ADD, 0xe4);
+c!(OP_F32X4SUB, 0xe5);
+c!(OP_F32X4MUL, 0xe6);
+c!(OP_F32X4DIV, 0xe7);
+c!(OP_F32X4MIN, 0xe8);
+c!(OP_F32X4MAX, 0xe9);
+c!(OP_F32X4PMIN, 0xea);
+c!(OP_F32X4PMAX, 0xeb);
+c!(OP_F64X2ABS, 0xec);
+c!(OP_F64X2NEG, 0xed);
+c!(OP_F64X2SQRT, 0xef);
+c!(OP_F64X2ADD, 0xf0);
+c!(OP_F64X2SUB, 0xf1);
+c!(OP_F64X2MUL, 0xf2);
+c!(OP_F64X2DIV, 0xf3);
+c!(OP_F64X2MIN, 0xf4);
+c!(OP_F64X2MAX, 0xf5);
+c!(OP_F64X2PMIN, 0xf6);
+c!(OP_F64X2PMAX, 0xf7);
+
 c!(MEM_NO_ALIGN, 0);
 c!(MEM_ALIGN16, 1);
 c!(MEM_ALIGN32, 2);
 c!(MEM_ALIGN64, 3);
+c!(MEM_ALIGN128, 4);
@@ -60,6 +60,38 @@ const VIRTQ_DESC_F_INDIRECT = 4;
 const VIRTQ_AVAIL_F_NO_INTERRUPT = 1;
 const VIRTQ_USED_F_NO_NOTIFY = 1;
 
+// Queues and buffers may be at physical addresses above 4 GiB. These are kept as numbers and
+// written by the driver in two 32-bit halves.
+
+/**
+ * @param {number} addr
+ * @return {number}
+ */
+function get_high_dword(addr)
+{
+    return Math.floor(addr / 0x100000000);
+}
+
+/**
+ * @param {number} addr
+ * @param {number} low
+ * @return {number}
+ */
+function set_low_dword(addr, low)
+{
+    return get_high_dword(addr) * 0x100000000 + (low >>> 0);
+}
+
+/**
+ * @param {number} addr
+ * @param {number} high
+ * @return {number}
+ */
+function set_high_dword(addr, high)
+{
+    return (high >>> 0) * 0x100000000 + (addr >>> 0);
+}
+
 // Closure Compiler Types.
 
 /**
@@ -541,55 +573,61 @@ VirtIO.prototype.create_common_capability = function(options)
             {
                 bytes: 4,
                 name: "queue_desc (low dword)",
-                read: () => this.queue_selected ? this.queue_selected.desc_addr : 0,
+                read: () => this.queue_selected ? this.queue_selected.desc_addr >>> 0 : 0,
                 write: data =>
                 {
-                    if(this.queue_selected) this.queue_selected.desc_addr = data;
+                    const queue = this.queue_selected;
+                    if(queue) queue.desc_addr = set_low_dword(queue.desc_addr, data);
                 },
             },
             {
                 bytes: 4,
                 name: "queue_desc (high dword)",
-                read: () => 0,
+                read: () => this.queue_selected ? get_high_dword(this.queue_selected.desc_addr) : 0,
                 write: data =>
                 {
-                    dbg_log("Warning: High dword of 64 bit queue_desc ignored", LOG_VIRTIO);
+                    const queue = this.queue_selected;
+                    if(queue) queue.desc_addr = set_high_dword(queue.desc_addr, data);
                 },
             },
             {
                 bytes: 4,
                 name: "queue_avail (low dword)",
-                read: () => this.queue_selected ? this.queue_selected.avail_addr : 0,
+                read: () => this.queue_selected ? this.queue_selected.avail_addr >>> 0 : 0,
                 write: data =>
                 {
-                    if(this.queue_selected) this.queue_selected.avail_addr = data;
+                    const queue = this.queue_selected;
+                    if(queue) queue.avail_addr = set_low_dword(queue.avail_addr, data);
                 },
             },
             {
                 bytes: 4,
                 name: "queue_avail (high dword)",
-                read: () => 0,
+                read: () => this.queue_selected ? get_high_dword(this.queue_selected.avail_addr) : 0,
                 write: data =>
                 {
-                    dbg_log("Warning: High dword of 64 bit queue_avail ignored", LOG_VIRTIO);
+                    const queue = this.queue_selected;
+                    if(queue) queue.avail_addr = set_high_dword(queue.avail_addr, data);
                 },
             },
             {
                 bytes: 4,
                 name: "queue_used (low dword)",
-                read: () => this.queue_selected ? this.queue_selected.used_addr : 0,
+                read: () => this.queue_selected ? this.queue_selected.used_addr >>> 0 : 0,
                 write: data =>
                 {
-                    if(this.queue_selected) this.queue_selected.used_addr = data;
+                    const queue = this.queue_selected;
+                    if(queue) queue.used_addr = set_low_dword(queue.used_addr, data);
                 },
             },
             {
                 bytes: 4,
                 name: "queue_used (high dword)",
-                read: () => 0,
+                read: () => this.queue_selected ? get_high_dword(this.queue_selected.used_addr) : 0,
                 write: data =>
                 {
-                    dbg_log("Warning: High dword of 64 bit queue_used ignored", LOG_VIRTIO);
+                    const queue = this.queue_selected;
+                    if(queue) queue.used_addr = set_high_dword(queue.used_addr, data);
                 },
             },
         ],
@@ -1244,12 +1282,12 @@ VirtQueue.prototype.notify_me_after = function(num_skipped_requests)
  */
 VirtQueue.prototype.get_descriptor = function(table_address, i)
 {
+    const desc_address = this.cpu.map_physical_address(table_address + i * VIRTQ_DESC_ENTRYSIZE);
     return {
-        addr_low: this.cpu.read32s(table_address + i * VIRTQ_DESC_ENTRYSIZE),
-        addr_high: this.cpu.read32s(table_address + i * VIRTQ_DESC_ENTRYSIZE + 4),
-        len: this.cpu.read32s(table_address + i * VIRTQ_DESC_ENTRYSIZE + 8),
-        flags: this.cpu.read16(table_address + i * VIRTQ_DESC_ENTRYSIZE + 12),
-        next: this.cpu.read16(table_address + i * VIRTQ_DESC_ENTRYSIZE + 14),
+        addr: set_high_dword(this.cpu.read32s(desc_address), this.cpu.read32s(desc_address + 4)),
+        len: this.cpu.read32s(desc_address + 8),
+        flags: this.cpu.read16(desc_address + 12),
+        next: this.cpu.read16(desc_address + 14),
     };
 };
 
@@ -1257,55 +1295,59 @@ VirtQueue.prototype.get_descriptor = function(table_address, i)
 
 VirtQueue.prototype.avail_get_flags = function()
 {
-    return this.cpu.read16(this.avail_addr);
+    return this.cpu.read16(this.cpu.map_physical_address(this.avail_addr));
 };
 
 VirtQueue.prototype.avail_get_idx = function()
 {
-    return this.cpu.read16(this.avail_addr + 2);
+    return this.cpu.read16(this.cpu.map_physical_address(this.avail_addr + 2));
 };
 
 VirtQueue.prototype.avail_get_entry = function(i)
 {
-    return this.cpu.read16(this.avail_addr + 4 + VIRTQ_AVAIL_ENTRYSIZE * i);
+    const addr = this.cpu.map_physical_address(this.avail_addr + 4 + VIRTQ_AVAIL_ENTRYSIZE * i);
+    return this.cpu.read16(addr);
 };
 
 VirtQueue.prototype.avail_get_used_event = function()
 {
-    return this.cpu.read16(this.avail_addr + 4 + VIRTQ_AVAIL_ENTRYSIZE * this.size);
+    const addr = this.cpu.map_physical_address(this.avail_addr + 4 + VIRTQ_AVAIL_ENTRYSIZE * this.size);
+    return this.cpu.read16(addr);
 };
 
 // Used ring fields
 
 VirtQueue.prototype.used_get_flags = function()
 {
-    return this.cpu.read16(this.used_addr);
+    return this.cpu.read16(this.cpu.map_physical_address(this.used_addr));
 };
 
 VirtQueue.prototype.used_set_flags = function(value)
 {
-    this.cpu.write16(this.used_addr, value);
+    this.cpu.write16(this.cpu.map_physical_address(this.used_addr), value);
 };
 
 VirtQueue.prototype.used_get_idx = function()
 {
-    return this.cpu.read16(this.used_addr + 2);
+    return this.cpu.read16(this.cpu.map_physical_address(this.used_addr + 2));
 };
 
 VirtQueue.prototype.used_set_idx = function(value)
 {
-    this.cpu.write16(this.used_addr + 2, value);
+    this.cpu.write16(this.cpu.map_physical_address(this.used_addr + 2), value);
 };
 
 VirtQueue.prototype.used_set_entry = function(i, desc_idx, length_written)
 {
-    this.cpu.write32(this.used_addr + 4 + VIRTQ_USED_ENTRYSIZE * i, desc_idx);
-    this.cpu.write32(this.used_addr + 8 + VIRTQ_USED_ENTRYSIZE * i, length_written);
+    const addr = this.cpu.map_physical_address(this.used_addr + 4 + VIRTQ_USED_ENTRYSIZE * i);
+    this.cpu.write32(addr, desc_idx);
+    this.cpu.write32(addr + 4, length_written);
 };
 
 VirtQueue.prototype.used_set_avail_event = function(value)
 {
-    this.cpu.write16(this.used_addr + 4 + VIRTQ_USED_ENTRYSIZE * this.size, value);
+    const addr = this.cpu.map_physical_address(this.used_addr + 4 + VIRTQ_USED_ENTRYSIZE * this.size);
+    this.cpu.write16(addr, value);
 };
 
 /**
@@ -1351,7 +1393,7 @@ function VirtQueueBufferChain(virtqueue, head_idx)
     {
         const desc = virtqueue.get_descriptor(table_address, desc_idx);
 
-        dbg_log("descriptor: idx=" + desc_idx + " addr=" + h(desc.addr_high, 8) + ":" + h(desc.addr_low, 8) +
+        dbg_log("descriptor: idx=" + desc_idx + " addr=" + h(desc.addr, 9) +
             " len=" + h(desc.len, 8) + " flags=" + h(desc.flags, 4) + " next=" + h(desc.next, 4), LOG_VIRTIO);
 
         if(has_indirect_feature && (desc.flags & VIRTQ_DESC_F_INDIRECT))
@@ -1362,7 +1404,7 @@ function VirtQueueBufferChain(virtqueue, head_idx)
             }
 
             // Carry on using indirect table, starting at first entry.
-            table_address = desc.addr_low;
+            table_address = desc.addr;
             desc_idx = 0;
             chain_length = 0;
             chain_max = desc.len / VIRTQ_DESC_ENTRYSIZE;
@@ -1426,7 +1468,7 @@ VirtQueueBufferChain.prototype.get_next_blob = function(dest_buffer)
         }
 
         const buf = this.read_buffers[this.read_buffer_idx];
-        const read_address = buf.addr_low + this.read_buffer_offset;
+        const read_address = this.cpu.map_physical_address(buf.addr + this.read_buffer_offset);
         let read_length = buf.len - this.read_buffer_offset;
 
         if(read_length > remaining)
@@ -1468,7 +1510,7 @@ VirtQueueBufferChain.prototype.set_next_blob = function(src_buffer)
         }
 
         const buf = this.write_buffers[this.write_buffer_idx];
-        const write_address = buf.addr_low + this.write_buffer_offset;
+        const write_address = this.cpu.map_physical_address(buf.addr + this.write_buffer_offset);
         let write_length = buf.len - this.write_buffer_offset;
 
         if(write_length > remaining)
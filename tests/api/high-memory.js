@@ -0,0 +1,81 @@
+#!/usr/bin/env node
+"use strict";
+
+// This test checks that linux boots with more than 3 GiB of memory (the part above 3 GiB is
+// remapped above 4 GiB) and that virtio dma works when buffers may be anywhere in memory
+
+const TEST_RELEASE_BUILD = +process.env.TEST_RELEASE_BUILD;
+
+const assert = require("assert").strict;
+var V86 = require(`../../build/${TEST_RELEASE_BUILD ? "libv86" : "libv86-debug"}.js`).V86;
+
+process.on("unhandledRejection", exn => { throw exn; });
+
+const config = {
+    bios: { url: __dirname + "/../../bios/seabios.bin" },
+    vga_bios: { url: __dirname + "/../../bios/vgabios.bin" },
+    cdrom: { url: __dirname + "/../../images/linux4.iso", async: true },
+    network_relay_url: "<UNUSED>",
+    autostart: true,
+    // the largest size below the limit of 3.5 GiB minus 128 KiB
+    memory_size: 3583 * 1024 * 1024,
+    filesystem: {},
+    log_level: 0,
+    disable_jit: +process.env.DISABLE_JIT,
+    screen_dummy: true,
+};
+
+const FILE_SIZE = 16 * 1024 * 1024;
+
+const emulator = new V86(config);
+
+const timeout = setTimeout(() => {
+    throw new Error("Timeout");
+}, 180 * 1000);
+
+async function sleep(ms) { return new Promise(resolve => setTimeout(resolve, ms)); }
+
+let serial_text = "";
+let sent_command = false;
+
+emulator.add_listener("serial0-output-byte", async function(byte)
+{
+    serial_text += String.fromCharCode(byte);
+
+    if(!sent_command && serial_text.includes("Files send via emulator appear in /mnt/"))
+    {
+        sent_command = true;
+        console.log("Booted, writing files");
+        emulator.keyboard_send_text(
+            "grep MemTotal /proc/meminfo > /mnt/meminfo; " +
+            `dd if=/dev/zero bs=1024 count=${FILE_SIZE / 1024} | tr '\\000' v > /mnt/data; ` +
+            "echo > /mnt/done\n");
+
+        let done = false;
+        while(!done)
+        {
+            await sleep(1000);
+            try
+            {
+                await emulator.read_file("done");
+                done = true;
+            }
+            catch(e)
+            {
+            }
+        }
+
+        const meminfo = Buffer.from(await emulator.read_file("meminfo")).toString();
+        console.log(meminfo.trim());
+        const mem_total = +meminfo.match(/MemTotal:\s*(\d+) kB/)[1];
+        assert(mem_total > 2.5 * 1024 * 1024, "Expected more than 2.5 GiB of memory");
+
+        const data = await emulator.read_file("data");
+        assert.equal(data.length, FILE_SIZE);
+        assert(data.every(byte => byte === "v".charCodeAt(0)), "Corrupted data in /mnt/data");
+
+        console.log("Ok");
+        clearTimeout(timeout);
+        emulator.stop();
+    }
+});
@@ -0,0 +1,192 @@
+#!/usr/bin/env node
+"use strict";
+
+// This test checks that code exported with export_jit_cache is installed into a new instance
+// without being compiled again and behaves the same, and that it isn't installed when the memory
+// it has been compiled from has been modified
+
+const TEST_RELEASE_BUILD = +process.env.TEST_RELEASE_BUILD;
+
+const fs = require("fs");
+const assert = require("assert").strict;
+var V86 = require(`../../build/${TEST_RELEASE_BUILD ? "libv86" : "libv86-debug"}.js`).V86;
+
+process.on("unhandledRejection", exn => { throw exn; });
+
+if(+process.env.DISABLE_JIT)
+{
+    console.log("Skipped: Requires the jit");
+    process.exit(0);
+}
+
+// Indices of the profiler stats, in the order of the enum in profiler.rs
+const stat_names = fs.readFileSync(__dirname + "/../../src/rust/profiler.rs").toString()
+    .match(/pub enum stat \{([^}]*)\}/)[1].split(",").map(s => s.trim()).filter(s => s);
+const JIT_CACHE_HIT = stat_names.indexOf("JIT_CACHE_HIT");
+const JIT_CACHE_MISS = stat_names.indexOf("JIT_CACHE_MISS");
+assert(JIT_CACHE_HIT !== -1 && JIT_CACHE_MISS !== -1);
+
+const CODE_ADDRESS = 0x2000;
+const RESULT_ADDRESS = 0x500;
+const DONE_ADDRESS = 0x502;
+const WAITING_ADDRESS = 0x503;
+const CONTINUE_ADDRESS = 0x504;
+
+// Hot loop, copied to its own page so that nothing else on the page changes between runs:
+// for(dx = 0; dx < 0x100; dx++) for(cx = 0; cx < 0x10000; cx++) ax = rol(ax + cx, 1) ^ key
+function payload(key)
+{
+    return [
+        0x31, 0xC0,                         // xor ax, ax
+        0x31, 0xD2,                         // xor dx, dx
+        0x31, 0xC9,                         // outer: xor cx, cx
+        0x01, 0xC8,                         // inner: add ax, cx
+        0xD1, 0xC0,                         // rol ax, 1
+        0x35, key & 0xFF, key >> 8,         // xor ax, key
+        0x41,                               // inc cx
+        0x75, 0xF6,                         // jnz inner
+        0x42,                               // inc dx
+        0x81, 0xFA, 0x00, 0x01,             // cmp dx, 0x100
+        0x75, 0xED,                         // jne outer
+        0xA3, 0x00, 0x05,                   // mov [RESULT_ADDRESS], ax
+        0xC6, 0x06, 0x02, 0x05, 0x01,       // mov byte [DONE_ADDRESS], 1
+        0xF4,                               // halt: hlt
+        0xEB, 0xFD,                         // jmp halt
+    ];
+}
+
+function expected_result(key)
+{
+    let ax = 0;
+    for(let dx = 0; dx < 0x100; dx++)
+    {
+        for(let cx = 0; cx < 0x10000; cx++)
+        {
+            ax = ax + cx & 0xFFFF;
+            ax = (ax << 1 | ax >> 15) & 0xFFFF ^ key;
+        }
+    }
+    return ax;
+}
+
+// Boot sector: Copy the payload and wait until the stats have been read before running it. Waits
+// using hlt, so that no module is being compiled when the payload runs for the first time (cached
+// modules are only installed when nothing is being compiled)
+function floppy_image(key)
+{
+    const code = payload(key);
+    const boot = [
+        0xFA,                               // cli
+        0x31, 0xC0,                         // xor ax, ax
+        0x8E, 0xD8,                         // mov ds, ax
+        0x8E, 0xC0,                         // mov es, ax
+        0x8E, 0xD0,                         // mov ss, ax
+        0xBC, 0x00, 0x7C,                   // mov sp, 0x7C00
+        0xA3, 0x02, 0x05,                   // mov [DONE_ADDRESS], ax
+        0xA2, 0x04, 0x05,                   // mov [CONTINUE_ADDRESS], al
+        0xFC,                               // cld
+        0xBF, 0x00, 0x20,                   // mov di, CODE_ADDRESS
+        0xB9, 0x00, 0x08,                   // mov cx, 0x800
+        0xF3, 0xAB,                         // rep stosw
+        0xBE, 0x3A, 0x7C,                   // mov si, payload
+        0xBF, 0x00, 0x20,                   // mov di, CODE_ADDRESS
+        0xB9, code.length, 0x00,            // mov cx, payload length
+        0xF3, 0xA4,                         // rep movsb
+        0xC6, 0x06, 0x03, 0x05, 0x01,       // mov byte [WAITING_ADDRESS], 1
+        0xFB,                               // sti
+        0xF4,                               // wait: hlt
+        0x80, 0x3E, 0x04, 0x05, 0x00,       // cmp byte [CONTINUE_ADDRESS], 0
+        0x74, 0xF8,                         // je wait
+        0xFA,                               // cli
+        0xEA, 0x00, 0x20, 0x00, 0x00,       // jmp 0:CODE_ADDRESS
+    ];
+    assert.equal(boot.length, 0x3A);
+
+    const image = new Uint8Array(1440 * 1024);
+    image.set(boot, 0);
+    image.set(code, boot.length);
+    image[510] = 0x55;
+    image[511] = 0xAA;
+    return image.buffer;
+}
+
+async function sleep(ms) { return new Promise(resolve => setTimeout(resolve, ms)); }
+
+async function wait_for_byte(emulator, address)
+{
+    while(emulator.read_memory(address, 1)[0] === 0)
+    {
+        await sleep(10);
+    }
+}
+
+// Returns the result of the hot loop and the cache hits and misses while running it
+async function run(key, options)
+{
+    const emulator = new V86(Object.assign({
+        bios: { url: __dirname + "/../../bios/seabios.bin" },
+        vga_bios: { url: __dirname + "/../../bios/vgabios.bin" },
+        fda: { buffer: floppy_image(key) },
+        autostart: true,
+        memory_size: 32 * 1024 * 1024,
+        log_level: 0,
+        screen_dummy: true,
+    }, options));
+    await new Promise(resolve => emulator.add_listener("emulator-loaded", resolve));
+
+    const stat = n => emulator.v86.cpu.wm.exports["profiler_stat_get"](n);
+
+    await wait_for_byte(emulator, WAITING_ADDRESS);
+    const hits = stat(JIT_CACHE_HIT);
+    const misses = stat(JIT_CACHE_MISS);
+    emulator.write_memory([1], CONTINUE_ADDRESS);
+
+    await wait_for_byte(emulator, DONE_ADDRESS);
+    const result = emulator.read_memory(RESULT_ADDRESS, 2);
+
+    const run = {
+        result: result[0] | result[1] << 8,
+        hits: stat(JIT_CACHE_HIT) - hits,
+        misses: stat(JIT_CACHE_MISS) - misses,
+        jit_cache: options.record_jit_cache ? emulator.export_jit_cache() : undefined,
+        profiler: emulator.v86.cpu.wm.exports["profiler_is_enabled"](),
+    };
+    await emulator.destroy();
+    return run;
+}
+
+(async function()
+{
+    const timeout = setTimeout(() => {
+        throw new Error("Timeout");
+    }, 120 * 1000);
+
+    const KEY = 0x1234;
+    const MODIFIED_KEY = 0x4321;
+
+    const first = await run(KEY, { record_jit_cache: true });
+    assert.equal(first.result, expected_result(KEY));
+    assert(first.jit_cache.byteLength > 0);
+    console.log("Exported %d bytes of jit cache", first.jit_cache.byteLength);
+
+    const cached = await run(KEY, { jit_cache: { buffer: first.jit_cache } });
+    assert.equal(cached.result, first.result);
+
+    // Only the key differs, all other bytes of the code page are the same
+    const modified = await run(MODIFIED_KEY, { jit_cache: { buffer: first.jit_cache } });
+    assert.equal(modified.result, expected_result(MODIFIED_KEY));
+
+    if(first.profiler)
+    {
+        assert.equal(cached.hits, 1, "Expected the cached module to be installed");
+        assert.equal(modified.hits, 0, "Expected the module of a modified page not to be installed");
+        assert.equal(modified.misses, 1);
+    }
+    else
+    {
+        console.log("Not checking JIT_CACHE_HIT, requires a build with the profiler feature");
+    }
+
+    clearTimeout(timeout);
+    console.log("Ok");
+})();
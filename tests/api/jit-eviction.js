@@ -0,0 +1,114 @@
+#!/usr/bin/env node
+"use strict";
+
+// This test fills the wasm table with modules until cold modules are evicted, with the jit state
+// invariants checked after every change. Modules compiled since the previous eviction must survive
+// the next one
+
+const TEST_RELEASE_BUILD = +process.env.TEST_RELEASE_BUILD;
+
+const fs = require("fs");
+const assert = require("assert").strict;
+var V86 = require(`../../build/${TEST_RELEASE_BUILD ? "libv86" : "libv86-debug"}.js`).V86;
+
+process.on("unhandledRejection", exn => { throw exn; });
+
+if(+process.env.DISABLE_JIT)
+{
+    console.log("Skipped: Requires the jit");
+    process.exit(0);
+}
+
+// Indices of the profiler stats, in the order of the enum in profiler.rs
+const stat_names = fs.readFileSync(__dirname + "/../../src/rust/profiler.rs").toString()
+    .match(/pub enum stat \{([^}]*)\}/)[1].split(",").map(s => s.trim()).filter(s => s);
+const INVALIDATE_MODULE_EVICTED = stat_names.indexOf("INVALIDATE_MODULE_EVICTED");
+assert(INVALIDATE_MODULE_EVICTED !== -1);
+
+// See const.js and jit.rs, index 0 is never used
+const WASM_TABLE_SIZE = 900;
+const EVICTION_BATCH_SIZE = Math.floor(WASM_TABLE_SIZE / 8);
+
+const CODE_ADDRESS = 0x100000;
+const PAGE_COUNT = WASM_TABLE_SIZE + EVICTION_BATCH_SIZE;
+
+const emulator = new V86({
+    autostart: false,
+    memory_size: 8 * 1024 * 1024,
+    log_level: 0,
+});
+
+emulator.add_listener("emulator-loaded", async function()
+{
+    const cpu = emulator.v86.cpu;
+
+    // check_jit_state_invariants
+    cpu.set_jit_config(7, 1);
+
+    // inc eax; hlt in every page
+    for(let i = 0; i < PAGE_COUNT; i++)
+    {
+        cpu.mem8.set([0x40, 0xF4], CODE_ADDRESS + i * 0x1000);
+    }
+    cpu.is_32[0] = true;
+    cpu.stack_size_32[0] = true;
+    cpu.update_state_flags();
+
+    // record the wasm table index of each compiled module and the freed indices
+    const module_of_page = new Map();
+    let freed = [];
+
+    const codegen_finalize = cpu.codegen_finalize;
+    cpu.codegen_finalize = function(wasm_table_index, start, state_flags, ptr, len)
+    {
+        module_of_page.set(start, wasm_table_index);
+        codegen_finalize.call(this, wasm_table_index, start, state_flags, ptr, len);
+    };
+
+    const jit_clear_func = cpu.jit_clear_func;
+    cpu.jit_clear_func = function(wasm_table_index)
+    {
+        freed.push(wasm_table_index);
+        jit_clear_func.call(this, wasm_table_index);
+    };
+
+    const stat = n => cpu.wm.exports["profiler_stat_get"](n);
+    const evicted_before = stat(INVALIDATE_MODULE_EVICTED);
+
+    async function compile(i)
+    {
+        const finalized = new Promise(resolve => { cpu.test_hook_did_finalize_wasm = resolve; });
+        cpu.jit_force_generate(CODE_ADDRESS + i * 0x1000);
+        await finalized;
+    }
+
+    // Fill the table, compiling the next page evicts cold modules. The modules compiled after that
+    // haven't run yet and must be kept when the table is full again
+    const fresh = new Set();
+    for(let i = 0; i < PAGE_COUNT; i++)
+    {
+        await compile(i);
+        if(freed.length === EVICTION_BATCH_SIZE)
+        {
+            fresh.add(module_of_page.get(CODE_ADDRESS + i * 0x1000));
+        }
+    }
+    assert.equal(fresh.size, EVICTION_BATCH_SIZE);
+    assert.equal(freed.length, 2 * EVICTION_BATCH_SIZE);
+    assert(freed.slice(EVICTION_BATCH_SIZE).every(index => !fresh.has(index)),
+        "Expected modules compiled since the last eviction not to be evicted");
+    assert.equal(cpu.wm.exports["jit_get_wasm_table_index_free_list_count"](),
+        EVICTION_BATCH_SIZE - 1);
+
+    if(cpu.wm.exports["profiler_is_enabled"]())
+    {
+        assert.equal(stat(INVALIDATE_MODULE_EVICTED) - evicted_before, 2 * EVICTION_BATCH_SIZE);
+    }
+    else
+    {
+        console.log("Not checking INVALIDATE_MODULE_EVICTED, requires a build with the profiler feature");
+    }
+
+    await emulator.destroy();
+    console.log("Ok");
+});
@@ -81,12 +81,23 @@ function run_test({ name, executable_file, expect_file, actual_file, actual_wasm
         autostart: false,
         memory_size: 2 * 1024 * 1024,
         log_level: LOG_LEVEL,
+        // the wabt version used for disassembly doesn't support simd
+        disable_jit_simd: true,
     });
 
     const executable = fs.readFileSync(executable_file);
     const asm = fs.readFileSync(asm_file);
 
     const is_32 = asm.includes("BITS 32\n");
+    // compile for x87 code running in double precision with all exceptions masked
+    const fpu_f64 = asm.includes("; fpu control word: 0x27F\n");
+    // run the code in the interpreter first, so that entry points in other pages are known
+    const interpret_first = asm.includes("; interpret before compiling\n");
+    // compile with the baseline tier, then run the code again until the module is promoted to the
+    // optimising tier, the promoted module is checked
+    const promote = asm.includes("; promote to the optimising tier\n");
+    // compile with the baseline tier instead of the optimising tier
+    const baseline = promote || asm.includes("; compile with the baseline tier\n");
 
     emulator.add_listener("emulator-loaded", function()
         {
@@ -96,7 +107,9 @@ function run_test({ name, executable_file, expect_file, actual_file, actual_wasm
                 throw new Error("Hook for code generation not called");
             }, 1000);
 
-            cpu.test_hook_did_generate_wasm = function(wasm)
+            const START_ADDRESS = 0x1000;
+
+            const check_wasm = function(wasm)
             {
                 const wast = normalise_wast(disassemble_wasm(wasm));
 
@@ -164,20 +177,63 @@ Hint: Use tests/expect/run.js --accept-all to accept all changes (use git diff t
                     assert(!result.stderr);
                 }
 
-                onfinished();
+                if(!promote)
+                {
+                    onfinished();
+                }
             };
 
+            if(promote)
+            {
+                let free_indices;
+
+                cpu.test_hook_did_generate_wasm = function()
+                {
+                    cpu.test_hook_did_finalize_wasm = function()
+                    {
+                        cpu.test_hook_did_finalize_wasm = function()
+                        {
+                            // the module from the baseline tier has been freed
+                            assert.equal(
+                                cpu.wm.exports["jit_get_wasm_table_index_free_list_count"](),
+                                free_indices);
+                            onfinished();
+                        };
+                        cpu.test_hook_did_generate_wasm = check_wasm;
+
+                        free_indices = cpu.wm.exports["jit_get_wasm_table_index_free_list_count"]();
+                        cpu.instruction_pointer[0] = START_ADDRESS;
+                        cpu.in_hlt[0] = 0;
+                        cpu.main_loop();
+                    };
+                };
+            }
+            else
+            {
+                cpu.test_hook_did_generate_wasm = check_wasm;
+            }
+
             if(is_32)
             {
                 cpu.is_32[0] = true;
                 cpu.stack_size_32[0] = true;
             }
 
-            const START_ADDRESS = 0x1000;
+            if(fpu_f64)
+            {
+                cpu.fpu_control_word[0] = 0x27F;
+            }
 
             cpu.mem8.set(executable, START_ADDRESS);
             cpu.update_state_flags();
-            cpu.jit_force_generate(START_ADDRESS);
+
+            if(interpret_first)
+            {
+                cpu.instruction_pointer[0] = START_ADDRESS;
+                cpu.main_loop();
+            }
+
+            cpu.jit_force_generate(START_ADDRESS, baseline);
         });
 }
 
@@ -0,0 +1,12 @@
+BITS 32
+; compile with the baseline tier
+
+    mov ecx, 10
+loop:
+    dec ecx
+    jnz loop
+    cmp eax, 1
+    je skip
+    inc ebx
+skip:
+    hlt
@@ -20,14 +20,14 @@
   (type $t18 (func (param i32 i64 i32)))
   (type $t19 (func (param i32 i64 i32) (result i32)))
   (type $t20 (func (param i32 i64 i64 i32) (result i32)))
+  (import "e" "instr_F4" (func $e.instr_F4 (type $t0)))
   (import "e" "safe_write32_slow_jit" (func $e.safe_write32_slow_jit (type $t16)))
   (import "e" "safe_read32s_slow_jit" (func $e.safe_read32s_slow_jit (type $t7)))
   (import "e" "jit_find_cache_entry_in_page" (func $e.jit_find_cache_entry_in_page (type $t16)))
-  (import "e" "instr_F4" (func $e.instr_F4 (type $t0)))
   (import "e" "trigger_fault_end_jit" (func $e.trigger_fault_end_jit (type $t0)))
   (import "e" "m" (memory {normalised output}))
   (func $f (export "f") (type $t1) (param $p0 i32)
-    (local $l0 i32) (local $l1 i32) (local $l2 i32) (local $l3 i32) (local $l4 i32) (local $l5 i32) (local $l6 i32) (local $l7 i32) (local $l8 i32) (local $l9 i32) (local $l10 i32) (local $l11 i32) (local $l12 i32)
+    (local $l0 i32) (local $l1 i32) (local $l2 i32) (local $l3 i32) (local $l4 i32) (local $l5 i32) (local $l6 i32) (local $l7 i32) (local $l8 i32) (local $l9 i32) (local $l10 i32) (local $l11 i32) (local $l12 i32) (local $l13 i32) (local $l14 i32) (local $l15 i32) (local $l16 i32) (local $l17 i32) (local $l18 i32) (local $l19 i32) (local $l20 i32)
     (set_local $l0
       (i32.load
         (i32.const 64)))
@@ -54,6 +54,22 @@
         (i32.const 92)))
     (set_local $l8
       (i32.const 0))
+    (set_local $l9
+      (i32.const -1))
+    (set_local $l10
+      (i32.const -1))
+    (set_local $l11
+      (i32.const -1))
+    (set_local $l12
+      (i32.const -1))
+    (set_local $l13
+      (i32.const -1))
+    (set_local $l14
+      (i32.const -1))
+    (set_local $l15
+      (i32.const -1))
+    (set_local $l16
+      (i32.const -1))
     (block $B0
       (block $B1
         (loop $L2
@@ -72,234 +88,282 @@
                 (i32.add
                   (get_local $l8)
                   (i32.const 1)))
-              (set_local $l9
-                (i32.sub
-                  (i32.or
-                    (i32.and
-                      (i32.load
-                        (i32.const 556))
-                      (i32.const -4096))
-                    (i32.const 5))
-                  (i32.load
-                    (i32.const 740))))
-              (set_local $l11
-                (i32.add
-                  (tee_local $l10
-                    (i32.sub
-                      (get_local $l4)
-                      (i32.const 4)))
-                  (i32.load
-                    (i32.const 744))))
-              (block $B6
-                (br_if $B6
-                  (i32.and
-                    (i32.eq
-                      (i32.and
-                        (tee_local $l12
-                          (i32.load offset={normalised output}
-                            (i32.shl
-                              (i32.shr_u
-                                (get_local $l11)
-                                (i32.const 12))
-                              (i32.const 2))))
-                        (i32.const 4075))
-                      (i32.const 1))
-                    (i32.le_s
-                      (i32.and
-                        (get_local $l11)
-                        (i32.const 4095))
-                      (i32.const 4092))))
-                (br_if $B1
-                  (i32.and
-                    (tee_local $l12
-                      (call $e.safe_write32_slow_jit
-                        (get_local $l11)
-                        (get_local $l9)
-                        (i32.const 0)))
-                    (i32.const 1))))
-              (i32.store align=1
-                (i32.xor
+              (i32.store
+                (i32.const 560)
+                (i32.or
                   (i32.and
-                    (get_local $l12)
+                    (i32.load
+                      (i32.const 556))
                     (i32.const -4096))
-                  (get_local $l11))
-                (get_local $l9))
-              (set_local $l4
-                (get_local $l10))
-              (set_local $l8
-                (i32.add
-                  (get_local $l8)
-                  (i32.const 2)))
+                  (i32.const 5)))
               (i32.store
-                (i32.const 120)
+                (i32.const 556)
                 (i32.or
                   (i32.and
                     (i32.load
-                      (i32.const 120))
-                    (i32.const -2))
-                  (if $I7 (result i32)
-                    (i32.and
-                      (tee_local $l9
-                        (i32.load
-                          (i32.const 100)))
-                      (i32.const 1))
-                    (then
-                      (set_local $l9
-                        (i32.shr_s
-                          (get_local $l9)
-                          (i32.const 31)))
-                      (i32.lt_u
-                        (i32.xor
-                          (i32.load
-                            (i32.const 112))
-                          (get_local $l9))
-                        (i32.xor
-                          (i32.load
-                            (i32.const 104))
-                          (get_local $l9))))
-                    (else
-                      (i32.and
-                        (i32.load
-                          (i32.const 120))
-                        (i32.const 1))))))
+                      (i32.const 556))
+                    (i32.const -4096))
+                  (i32.const 6)))
               (i32.store
-                (i32.const 104)
+                (i32.const 64)
                 (get_local $l0))
-              (set_local $l0
-                (i32.add
-                  (get_local $l0)
-                  (i32.const 1)))
               (i32.store
-                (i32.const 112)
-                (get_local $l0))
-              (i64.store
-                (i32.const 96)
-                (i64.const 9706626088991))
-              (i32.const 0)
-              (set_local $l9
-                (i32.add
-                  (get_local $l4)
-                  (i32.load
-                    (i32.const 744))))
-              (block $B8
-                (br_if $B8
-                  (i32.and
-                    (i32.eq
-                      (i32.and
-                        (tee_local $l10
-                          (i32.load offset={normalised output}
-                            (i32.shl
-                              (i32.shr_u
-                                (get_local $l9)
-                                (i32.const 12))
-                              (i32.const 2))))
-                        (i32.const 4041))
-                      (i32.const 1))
-                    (i32.le_s
-                      (i32.and
-                        (get_local $l9)
-                        (i32.const 4095))
-                      (i32.const 4092))))
-                (br_if $B1
-                  (i32.and
-                    (tee_local $l10
-                      (call $e.safe_read32s_slow_jit
-                        (get_local $l9)
-                        (i32.const 7)))
-                    (i32.const 1))))
-              (i32.load align=1
-                (i32.xor
-                  (i32.and
-                    (get_local $l10)
-                    (i32.const -4096))
-                  (get_local $l9)))
+                (i32.const 68)
+                (get_local $l1))
+              (i32.store
+                (i32.const 72)
+                (get_local $l2))
+              (i32.store
+                (i32.const 76)
+                (get_local $l3))
+              (i32.store
+                (i32.const 80)
+                (get_local $l4))
+              (i32.store
+                (i32.const 84)
+                (get_local $l5))
+              (i32.store
+                (i32.const 88)
+                (get_local $l6))
+              (i32.store
+                (i32.const 92)
+                (get_local $l7))
+              (call $e.instr_F4)
+              (set_local $l0
+                (i32.load
+                  (i32.const 64)))
+              (set_local $l1
+                (i32.load
+                  (i32.const 68)))
+              (set_local $l2
+                (i32.load
+                  (i32.const 72)))
+              (set_local $l3
+                (i32.load
+                  (i32.const 76)))
               (set_local $l4
-                (i32.add
-                  (get_local $l4)
-                  (i32.const 4)))
-              (i32.load
-                (i32.const 740))
-              (i32.add)
-              (i32.store offset=556)
-              (br_if $L2
-                (i32.ge_s
-                  (tee_local $p0
-                    (call $e.jit_find_cache_entry_in_page
-                      (i32.load
-                        (i32.const 556))
-                      (i32.const 899)
-                      (i32.const 3)))
-                  (i32.const 0)))
+                (i32.load
+                  (i32.const 80)))
+              (set_local $l5
+                (i32.load
+                  (i32.const 84)))
+              (set_local $l6
+                (i32.load
+                  (i32.const 88)))
+              (set_local $l7
+                (i32.load
+                  (i32.const 92)))
               (br $B0))
-            (set_local $l8
-              (i32.add
-                (get_local $l8)
-                (i32.const 1)))
-            (i32.store
-              (i32.const 560)
+            (set_local $l15
+              (get_local $l13))
+            (set_local $l16
+              (get_local $l14))
+            (set_local $l13
+              (get_local $l11))
+            (set_local $l14
+              (get_local $l12))
+            (set_local $l11
+              (get_local $l9))
+            (set_local $l12
+              (get_local $l10))
+            (set_local $l9
               (i32.or
                 (i32.and
                   (i32.load
                     (i32.const 556))
                   (i32.const -4096))
                 (i32.const 5)))
+            (set_local $l10
+              (i32.const 1))
+            (set_local $l8
+              (i32.add
+                (get_local $l8)
+                (i32.const 1)))
+            (set_local $l17
+              (i32.sub
+                (i32.or
+                  (i32.and
+                    (i32.load
+                      (i32.const 556))
+                    (i32.const -4096))
+                  (i32.const 5))
+                (i32.load
+                  (i32.const 740))))
+            (set_local $l19
+              (i32.add
+                (tee_local $l18
+                  (i32.sub
+                    (get_local $l4)
+                    (i32.const 4)))
+                (i32.load
+                  (i32.const 744))))
+            (block $B6
+              (br_if $B6
+                (i32.and
+                  (i32.eq
+                    (i32.and
+                      (tee_local $l20
+                        (i32.load offset={normalised output}
+                          (i32.shl
+                            (i32.shr_u
+                              (get_local $l19)
+                              (i32.const 12))
+                            (i32.const 2))))
+                      (i32.const 4075))
+                    (i32.const 1))
+                  (i32.le_s
+                    (i32.and
+                      (get_local $l19)
+                      (i32.const 4095))
+                    (i32.const 4092))))
+              (br_if $B1
+                (i32.and
+                  (tee_local $l20
+                    (call $e.safe_write32_slow_jit
+                      (get_local $l19)
+                      (get_local $l17)
+                      (i32.const 0)))
+                  (i32.const 1))))
+            (i32.store align=1
+              (i32.xor
+                (i32.and
+                  (get_local $l20)
+                  (i32.const -4096))
+                (get_local $l19))
+              (get_local $l17))
+            (set_local $l4
+              (get_local $l18))
+            (set_local $l8
+              (i32.add
+                (get_local $l8)
+                (i32.const 2)))
             (i32.store
-              (i32.const 556)
+              (i32.const 120)
               (i32.or
                 (i32.and
                   (i32.load
-                    (i32.const 556))
-                  (i32.const -4096))
-                (i32.const 6)))
+                    (i32.const 120))
+                  (i32.const -2))
+                (if $I7 (result i32)
+                  (i32.and
+                    (tee_local $l17
+                      (i32.load
+                        (i32.const 100)))
+                    (i32.const 1))
+                  (then
+                    (set_local $l17
+                      (i32.shr_s
+                        (get_local $l17)
+                        (i32.const 31)))
+                    (i32.lt_u
+                      (i32.xor
+                        (i32.load
+                          (i32.const 112))
+                        (get_local $l17))
+                      (i32.xor
+                        (i32.load
+                          (i32.const 104))
+                        (get_local $l17))))
+                  (else
+                    (i32.and
+                      (i32.load
+                        (i32.const 120))
+                      (i32.const 1))))))
             (i32.store
-              (i32.const 64)
+              (i32.const 104)
               (get_local $l0))
-            (i32.store
-              (i32.const 68)
-              (get_local $l1))
-            (i32.store
-              (i32.const 72)
-              (get_local $l2))
-            (i32.store
-              (i32.const 76)
-              (get_local $l3))
-            (i32.store
-              (i32.const 80)
-              (get_local $l4))
-            (i32.store
-              (i32.const 84)
-              (get_local $l5))
-            (i32.store
-              (i32.const 88)
-              (get_local $l6))
-            (i32.store
-              (i32.const 92)
-              (get_local $l7))
-            (call $e.instr_F4)
             (set_local $l0
-              (i32.load
-                (i32.const 64)))
-            (set_local $l1
-              (i32.load
-                (i32.const 68)))
-            (set_local $l2
-              (i32.load
-                (i32.const 72)))
-            (set_local $l3
-              (i32.load
-                (i32.const 76)))
+              (i32.add
+                (get_local $l0)
+                (i32.const 1)))
+            (i32.store
+              (i32.const 112)
+              (get_local $l0))
+            (i64.store
+              (i32.const 96)
+              (i64.const 9706626088991))
+            (i32.const 0)
+            (set_local $l17
+              (i32.add
+                (get_local $l4)
+                (i32.load
+                  (i32.const 744))))
+            (block $B8
+              (br_if $B8
+                (i32.and
+                  (i32.eq
+                    (i32.and
+                      (tee_local $l18
+                        (i32.load offset={normalised output}
+                          (i32.shl
+                            (i32.shr_u
+                              (get_local $l17)
+                              (i32.const 12))
+                            (i32.const 2))))
+                      (i32.const 4041))
+                    (i32.const 1))
+                  (i32.le_s
+                    (i32.and
+                      (get_local $l17)
+                      (i32.const 4095))
+                    (i32.const 4092))))
+              (br_if $B1
+                (i32.and
+                  (tee_local $l18
+                    (call $e.safe_read32s_slow_jit
+                      (get_local $l17)
+                      (i32.const 7)))
+                  (i32.const 1))))
+            (i32.load align=1
+              (i32.xor
+                (i32.and
+                  (get_local $l18)
+                  (i32.const -4096))
+                (get_local $l17)))
             (set_local $l4
-              (i32.load
-                (i32.const 80)))
-            (set_local $l5
-              (i32.load
-                (i32.const 84)))
-            (set_local $l6
-              (i32.load
-                (i32.const 88)))
-            (set_local $l7
-              (i32.load
-                (i32.const 92)))
+              (i32.add
+                (get_local $l4)
+                (i32.const 4)))
+            (i32.load
+              (i32.const 740))
+            (i32.add)
+            (i32.store offset=556)
+            (i32.and
+              (i32.eq
+                (i32.load
+                  (i32.const 556))
+                (get_local $l9))
+              (i32.ge_s
+                (get_local $l10)
+                (i32.const 0)))
+            (set_local $p0
+              (get_local $l10))
+            (set_local $l9
+              (get_local $l11))
+            (set_local $l10
+              (get_local $l12))
+            (set_local $l11
+              (get_local $l13))
+            (set_local $l12
+              (get_local $l14))
+            (set_local $l13
+              (get_local $l15))
+            (set_local $l14
+              (get_local $l16))
+            (set_local $l15
+              (i32.const -1))
+            (set_local $l16
+              (i32.const -1))
+            (br_if $L2)
+            (br_if $L2
+              (i32.ge_s
+                (tee_local $p0
+                  (call $e.jit_find_cache_entry_in_page
+                    (i32.load
+                      (i32.const 556))
+                    (i32.const 899)
+                    (i32.const 3)))
+                (i32.const 0)))
             (br $B0))
           (unreachable)))
       (i32.store
@@ -0,0 +1,11 @@
+BITS 32
+    cmp eax, ebx
+    je equal
+    jb below
+    inc ecx
+
+below:
+    inc edx
+
+equal:
+    hlt
@@ -0,0 +1,8 @@
+BITS 32
+    add eax, ebx
+    sub ecx, edx
+    jz end
+    inc ecx
+
+end:
+    hlt
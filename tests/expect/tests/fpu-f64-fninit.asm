@@ -0,0 +1,13 @@
+BITS 32
+; fpu control word: 0x27F
+    fld qword [esi]
+    fld1
+    faddp
+    fstp qword [edi]
+    ; resets the control word to extended precision, so the module is left
+    fninit
+    fld qword [esi]
+    fld1
+    faddp
+    fstp qword [edi]
+    hlt
@@ -0,0 +1,11 @@
+BITS 32
+; fpu control word: 0x27F
+    ; x87 registers are kept in f64 locals and written back before leaving the module
+    fld qword [esi]
+    fld1
+    faddp
+    fsqrt
+    fstp qword [edi]
+    ; leaves the module if the control word doesn't select double precision anymore
+    fldcw [ebx]
+    hlt
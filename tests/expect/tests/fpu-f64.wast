@@ -0,0 +1,591 @@
+(module
+  (type $t0 (func))
+  (type $t1 (func (param i32)))
+  (type $t2 (func (param i32 i32)))
+  (type $t3 (func (param i32 i32 i32)))
+  (type $t4 (func (result i32)))
+  (type $t5 (func (result i64)))
+  (type $t6 (func (param i32) (result i32)))
+  (type $t7 (func (param i32 i32) (result i32)))
+  (type $t8 (func (param i32) (result i64)))
+  (type $t9 (func (param f32) (result i32)))
+  (type $t10 (func (param f64) (result i32)))
+  (type $t11 (func (param i32 i64)))
+  (type $t12 (func (param i64 i32)))
+  (type $t13 (func (param i64 i32) (result i32)))
+  (type $t14 (func (param i64 i32) (result i64)))
+  (type $t15 (func (param f32 i32)))
+  (type $t16 (func (param i32 i32 i32) (result i32)))
+  (type $t17 (func (param i64 i32 i32)))
+  (type $t18 (func (param i32 i64 i32)))
+  (type $t19 (func (param i32 i64 i32) (result i32)))
+  (type $t20 (func (param i32 i64 i64 i32) (result i32)))
+  (import "e" "task_switch_test_jit" (func $e.task_switch_test_jit (type $t1)))
+  (import "e" "fpu_exception_test_jit" (func $e.fpu_exception_test_jit (type $t6)))
+  (import "e" "trigger_gp_jit" (func $e.trigger_gp_jit (type $t2)))
+  (import "e" "safe_read64s_slow_jit" (func $e.safe_read64s_slow_jit (type $t7)))
+  (import "e" "fpu_push_f64_jit" (func $e.fpu_push_f64_jit (type $t4)))
+  (import "e" "fpu_set_sti_f64_jit" (func $e.fpu_set_sti_f64_jit (type $t11)))
+  (import "e" "fpu_pop" (func $e.fpu_pop (type $t0)))
+  (import "e" "safe_write64_slow_jit" (func $e.safe_write64_slow_jit (type $t19)))
+  (import "e" "safe_read16_slow_jit" (func $e.safe_read16_slow_jit (type $t7)))
+  (import "e" "set_control_word" (func $e.set_control_word (type $t1)))
+  (import "e" "instr_F4" (func $e.instr_F4 (type $t0)))
+  (import "e" "trigger_fault_end_jit" (func $e.trigger_fault_end_jit (type $t0)))
+  (import "e" "m" (memory {normalised output}))
+  (func $f (export "f") (type $t1) (param $p0 i32)
+    (local $l0 i32) (local $l1 i32) (local $l2 i32) (local $l3 i32) (local $l4 i32) (local $l5 i32) (local $l6 i32) (local $l7 i32) (local $l8 i32) (local $l9 i32) (local $l10 i32) (local $l11 f64) (local $l12 f64) (local $l13 f64) (local $l14 i64)
+    (set_local $l0
+      (i32.load
+        (i32.const 64)))
+    (set_local $l1
+      (i32.load
+        (i32.const 68)))
+    (set_local $l2
+      (i32.load
+        (i32.const 72)))
+    (set_local $l3
+      (i32.load
+        (i32.const 76)))
+    (set_local $l4
+      (i32.load
+        (i32.const 80)))
+    (set_local $l5
+      (i32.load
+        (i32.const 84)))
+    (set_local $l6
+      (i32.load
+        (i32.const 88)))
+    (set_local $l7
+      (i32.load
+        (i32.const 92)))
+    (set_local $l8
+      (i32.const 0))
+    (block $B0
+      (block $B1
+        (loop $L2
+          (br_if $B0
+            (i32.ge_u
+              (get_local $l8)
+              (i32.const 100003)))
+          (block $B3
+            (block $B4
+            )
+            (set_local $l8
+              (i32.add
+                (get_local $l8)
+                (i32.const 7)))
+            (if $I5
+              (i32.and
+                (i32.load8_u
+                  (i32.const 580))
+                (i32.const 12))
+              (then
+                (call $e.task_switch_test_jit
+                  (i32.const 4096))
+                (br $B1)))
+            (if $I6
+              (i32.and
+                (i32.and
+                  (i32.load16_u
+                    (i32.const 1040))
+                  (i32.xor
+                    (i32.load16_u
+                      (i32.const 1036))
+                    (i32.const -1)))
+                (i32.const 63))
+              (then
+                (if $I7
+                  (call $e.fpu_exception_test_jit
+                    (i32.const 4096))
+                  (then
+                    (br $B1)))))
+            (get_local $l6)
+            (if $I8
+              (i32.load8_u
+                (i32.const 727))
+              (then
+                (call $e.trigger_gp_jit
+                  (i32.const 0)
+                  (i32.const 4096))
+                (br $B1)))
+            (i32.load
+              (i32.const 748))
+            (i32.add)
+            (set_local $l9)
+            (block $B9
+              (br_if $B9
+                (i32.and
+                  (i32.eq
+                    (i32.and
+                      (tee_local $l10
+                        (i32.load offset={normalised output}
+                          (i32.shl
+                            (i32.shr_u
+                              (get_local $l9)
+                              (i32.const 12))
+                            (i32.const 2))))
+                      (i32.const 4041))
+                    (i32.const 1))
+                  (i32.le_s
+                    (i32.and
+                      (get_local $l9)
+                      (i32.const 4095))
+                    (i32.const 4088))))
+              (br_if $B1
+                (i32.and
+                  (tee_local $l10
+                    (call $e.safe_read64s_slow_jit
+                      (get_local $l9)
+                      (i32.const 0)))
+                  (i32.const 1))))
+            (set_local $l11
+              (f64.reinterpret/i64
+                (i64.load align=1
+                  (i32.xor
+                    (i32.and
+                      (get_local $l10)
+                      (i32.const -4096))
+                    (get_local $l9)))))
+            (set_local $l11
+              (select
+                (get_local $l11)
+                (f64.const -nan)
+                (call $e.fpu_push_f64_jit)))
+            (if $I10
+              (i32.and
+                (i32.load8_u
+                  (i32.const 580))
+                (i32.const 12))
+              (then
+                (call $e.fpu_set_sti_f64_jit
+                  (i32.const 0)
+                  (i64.reinterpret/f64
+                    (get_local $l11)))
+                (call $e.task_switch_test_jit
+                  (i32.const 4098))
+                (br $B1)))
+            (if $I11
+              (i32.and
+                (i32.and
+                  (i32.load16_u
+                    (i32.const 1040))
+                  (i32.xor
+                    (i32.load16_u
+                      (i32.const 1036))
+                    (i32.const -1)))
+                (i32.const 63))
+              (then
+                (if $I12
+                  (call $e.fpu_exception_test_jit
+                    (i32.const 4098))
+                  (then
+                    (call $e.fpu_set_sti_f64_jit
+                      (i32.const 0)
+                      (i64.reinterpret/f64
+                        (get_local $l11)))
+                    (br $B1)))))
+            (set_local $l12
+              (f64.const 0x1p+0 (;=1;)))
+            (set_local $l12
+              (select
+                (get_local $l12)
+                (f64.const -nan)
+                (call $e.fpu_push_f64_jit)))
+            (if $I13
+              (i32.and
+                (i32.load8_u
+                  (i32.const 580))
+                (i32.const 12))
+              (then
+                (call $e.fpu_set_sti_f64_jit
+                  (i32.const 0)
+                  (i64.reinterpret/f64
+                    (get_local $l12)))
+                (call $e.fpu_set_sti_f64_jit
+                  (i32.const 1)
+                  (i64.reinterpret/f64
+                    (get_local $l11)))
+                (call $e.task_switch_test_jit
+                  (i32.const 4100))
+                (br $B1)))
+            (if $I14
+              (i32.and
+                (i32.and
+                  (i32.load16_u
+                    (i32.const 1040))
+                  (i32.xor
+                    (i32.load16_u
+                      (i32.const 1036))
+                    (i32.const -1)))
+                (i32.const 63))
+              (then
+                (if $I15
+                  (call $e.fpu_exception_test_jit
+                    (i32.const 4100))
+                  (then
+                    (call $e.fpu_set_sti_f64_jit
+                      (i32.const 0)
+                      (i64.reinterpret/f64
+                        (get_local $l12)))
+                    (call $e.fpu_set_sti_f64_jit
+                      (i32.const 1)
+                      (i64.reinterpret/f64
+                        (get_local $l11)))
+                    (br $B1)))))
+            (set_local $l13
+              (get_local $l11))
+            (set_local $l11
+              (f64.add
+                (get_local $l12)
+                (get_local $l13)))
+            (call $e.fpu_set_sti_f64_jit
+              (i32.const 0)
+              (i64.reinterpret/f64
+                (get_local $l12)))
+            (call $e.fpu_pop)
+            (if $I16
+              (i32.and
+                (i32.load8_u
+                  (i32.const 580))
+                (i32.const 12))
+              (then
+                (call $e.fpu_set_sti_f64_jit
+                  (i32.const 0)
+                  (i64.reinterpret/f64
+                    (get_local $l11)))
+                (call $e.task_switch_test_jit
+                  (i32.const 4102))
+                (br $B1)))
+            (if $I17
+              (i32.and
+                (i32.and
+                  (i32.load16_u
+                    (i32.const 1040))
+                  (i32.xor
+                    (i32.load16_u
+                      (i32.const 1036))
+                    (i32.const -1)))
+                (i32.const 63))
+              (then
+                (if $I18
+                  (call $e.fpu_exception_test_jit
+                    (i32.const 4102))
+                  (then
+                    (call $e.fpu_set_sti_f64_jit
+                      (i32.const 0)
+                      (i64.reinterpret/f64
+                        (get_local $l11)))
+                    (br $B1)))))
+            (set_local $l11
+              (f64.sqrt
+                (get_local $l11)))
+            (if $I19
+              (i32.and
+                (i32.load8_u
+                  (i32.const 580))
+                (i32.const 12))
+              (then
+                (call $e.fpu_set_sti_f64_jit
+                  (i32.const 0)
+                  (i64.reinterpret/f64
+                    (get_local $l11)))
+                (call $e.task_switch_test_jit
+                  (i32.const 4104))
+                (br $B1)))
+            (if $I20
+              (i32.and
+                (i32.and
+                  (i32.load16_u
+                    (i32.const 1040))
+                  (i32.xor
+                    (i32.load16_u
+                      (i32.const 1036))
+                    (i32.const -1)))
+                (i32.const 63))
+              (then
+                (if $I21
+                  (call $e.fpu_exception_test_jit
+                    (i32.const 4104))
+                  (then
+                    (call $e.fpu_set_sti_f64_jit
+                      (i32.const 0)
+                      (i64.reinterpret/f64
+                        (get_local $l11)))
+                    (br $B1)))))
+            (get_local $l7)
+            (if $I22
+              (i32.load8_u
+                (i32.const 727))
+              (then
+                (call $e.trigger_gp_jit
+                  (i32.const 0)
+                  (i32.const 4104))
+                (br $B1)))
+            (i32.load
+              (i32.const 748))
+            (i32.add)
+            (set_local $l9)
+            (set_local $l14
+              (i64.reinterpret/f64
+                (get_local $l11)))
+            (block $B23
+              (br_if $B23
+                (i32.and
+                  (i32.eq
+                    (i32.and
+                      (tee_local $l10
+                        (i32.load offset={normalised output}
+                          (i32.shl
+                            (i32.shr_u
+                              (get_local $l9)
+                              (i32.const 12))
+                            (i32.const 2))))
+                      (i32.const 4075))
+                    (i32.const 1))
+                  (i32.le_s
+                    (i32.and
+                      (get_local $l9)
+                      (i32.const 4095))
+                    (i32.const 4088))))
+              (if $I24
+                (i32.and
+                  (tee_local $l10
+                    (call $e.safe_write64_slow_jit
+                      (get_local $l9)
+                      (get_local $l14)
+                      (i32.const 8)))
+                  (i32.const 1))
+                (then
+                  (call $e.fpu_set_sti_f64_jit
+                    (i32.const 0)
+                    (i64.reinterpret/f64
+                      (get_local $l11)))
+                  (br $B1))))
+            (i64.store align=1
+              (i32.xor
+                (i32.and
+                  (get_local $l10)
+                  (i32.const -4096))
+                (get_local $l9))
+              (get_local $l14))
+            (call $e.fpu_set_sti_f64_jit
+              (i32.const 0)
+              (i64.reinterpret/f64
+                (get_local $l11)))
+            (call $e.fpu_pop)
+            (if $I25
+              (i32.and
+                (i32.load8_u
+                  (i32.const 580))
+                (i32.const 12))
+              (then
+                (call $e.task_switch_test_jit
+                  (i32.const 4106))
+                (br $B1)))
+            (if $I26
+              (i32.and
+                (i32.and
+                  (i32.load16_u
+                    (i32.const 1040))
+                  (i32.xor
+                    (i32.load16_u
+                      (i32.const 1036))
+                    (i32.const -1)))
+                (i32.const 63))
+              (then
+                (if $I27
+                  (call $e.fpu_exception_test_jit
+                    (i32.const 4106))
+                  (then
+                    (br $B1)))))
+            (get_local $l3)
+            (if $I28
+              (i32.load8_u
+                (i32.const 727))
+              (then
+                (call $e.trigger_gp_jit
+                  (i32.const 0)
+                  (i32.const 4106))
+                (br $B1)))
+            (i32.load
+              (i32.const 748))
+            (i32.add)
+            (set_local $l9)
+            (block $B29
+              (br_if $B29
+                (i32.and
+                  (i32.eq
+                    (i32.and
+                      (tee_local $l10
+                        (i32.load offset={normalised output}
+                          (i32.shl
+                            (i32.shr_u
+                              (get_local $l9)
+                              (i32.const 12))
+                            (i32.const 2))))
+                      (i32.const 4041))
+                    (i32.const 1))
+                  (i32.le_s
+                    (i32.and
+                      (get_local $l9)
+                      (i32.const 4095))
+                    (i32.const 4094))))
+              (br_if $B1
+                (i32.and
+                  (tee_local $l10
+                    (call $e.safe_read16_slow_jit
+                      (get_local $l9)
+                      (i32.const 10)))
+                  (i32.const 1))))
+            (call $e.set_control_word
+              (i32.load16_u align=1
+                (i32.xor
+                  (i32.and
+                    (get_local $l10)
+                    (i32.const -4096))
+                  (get_local $l9))))
+            (if $I30
+              (i32.ne
+                (i32.load8_u
+                  (i32.const 108))
+                (i32.const 19))
+              (then
+                (i32.store
+                  (i32.const 556)
+                  (i32.or
+                    (i32.and
+                      (i32.load
+                        (i32.const 556))
+                      (i32.const -4096))
+                    (i32.const 12)))
+                (br $B0)))
+            (i32.store
+              (i32.const 560)
+              (i32.or
+                (i32.and
+                  (i32.load
+                    (i32.const 556))
+                  (i32.const -4096))
+                (i32.const 12)))
+            (i32.store
+              (i32.const 556)
+              (i32.or
+                (i32.and
+                  (i32.load
+                    (i32.const 556))
+                  (i32.const -4096))
+                (i32.const 13)))
+            (i32.store
+              (i32.const 64)
+              (get_local $l0))
+            (i32.store
+              (i32.const 68)
+              (get_local $l1))
+            (i32.store
+              (i32.const 72)
+              (get_local $l2))
+            (i32.store
+              (i32.const 76)
+              (get_local $l3))
+            (i32.store
+              (i32.const 80)
+              (get_local $l4))
+            (i32.store
+              (i32.const 84)
+              (get_local $l5))
+            (i32.store
+              (i32.const 88)
+              (get_local $l6))
+            (i32.store
+              (i32.const 92)
+              (get_local $l7))
+            (call $e.instr_F4)
+            (set_local $l0
+              (i32.load
+                (i32.const 64)))
+            (set_local $l1
+              (i32.load
+                (i32.const 68)))
+            (set_local $l2
+              (i32.load
+                (i32.const 72)))
+            (set_local $l3
+              (i32.load
+                (i32.const 76)))
+            (set_local $l4
+              (i32.load
+                (i32.const 80)))
+            (set_local $l5
+              (i32.load
+                (i32.const 84)))
+            (set_local $l6
+              (i32.load
+                (i32.const 88)))
+            (set_local $l7
+              (i32.load
+                (i32.const 92)))
+            (br $B0))
+          (unreachable)))
+      (i32.store
+        (i32.const 64)
+        (get_local $l0))
+      (i32.store
+        (i32.const 68)
+        (get_local $l1))
+      (i32.store
+        (i32.const 72)
+        (get_local $l2))
+      (i32.store
+        (i32.const 76)
+        (get_local $l3))
+      (i32.store
+        (i32.const 80)
+        (get_local $l4))
+      (i32.store
+        (i32.const 84)
+        (get_local $l5))
+      (i32.store
+        (i32.const 88)
+        (get_local $l6))
+      (i32.store
+        (i32.const 92)
+        (get_local $l7))
+      (call $e.trigger_fault_end_jit)
+      (i32.store
+        (i32.const 664)
+        (i32.add
+          (i32.load
+            (i32.const 664))
+          (get_local $l8)))
+      (return))
+    (i32.store
+      (i32.const 64)
+      (get_local $l0))
+    (i32.store
+      (i32.const 68)
+      (get_local $l1))
+    (i32.store
+      (i32.const 72)
+      (get_local $l2))
+    (i32.store
+      (i32.const 76)
+      (get_local $l3))
+    (i32.store
+      (i32.const 80)
+      (get_local $l4))
+    (i32.store
+      (i32.const 84)
+      (get_local $l5))
+    (i32.store
+      (i32.const 88)
+      (get_local $l6))
+    (i32.store
+      (i32.const 92)
+      (get_local $l7))
+    (i32.store
+      (i32.const 664)
+      (i32.add
+        (i32.load
+          (i32.const 664))
+        (get_local $l8)))))
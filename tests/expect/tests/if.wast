@@ -92,31 +92,9 @@
                     (i32.load
                       (i32.const 120))
                     (i32.const -2))
-                  (if $I6 (result i32)
-                    (i32.and
-                      (tee_local $l9
-                        (i32.load
-                          (i32.const 100)))
-                      (i32.const 1))
-                    (then
-                      (set_local $l9
-                        (i32.shr_s
-                          (get_local $l9)
-                          (i32.const 31)))
-                      (i32.lt_u
-                        (i32.xor
-                          (i32.load
-                            (i32.const 112))
-                          (get_local $l9))
-                        (i32.xor
-                          (i32.load
-                            (i32.const 104))
-                          (get_local $l9))))
-                    (else
-                      (i32.and
-                        (i32.load
-                          (i32.const 120))
-                        (i32.const 1))))))
+                  (i32.lt_u
+                    (get_local $l0)
+                    (i32.const 5))))
               (i32.store
                 (i32.const 104)
                 (get_local $l1))
@@ -141,7 +119,7 @@
                   (i32.load
                     (i32.const 120))
                   (i32.const -2))
-                (if $I7 (result i32)
+                (if $I6 (result i32)
                   (i32.and
                     (tee_local $l9
                       (i32.load
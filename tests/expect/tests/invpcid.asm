@@ -0,0 +1,5 @@
+BITS 32
+    ; the descriptor address is resolved with the segment and address size prefixes
+    invpcid eax, [fs:ebx+8]
+    invpcid ecx, [bx+si]
+    hlt
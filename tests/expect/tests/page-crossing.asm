@@ -0,0 +1,24 @@
+BITS 32
+; interpret before compiling
+
+    mov eax, 1
+    jmp end_of_page
+
+    times 0xfd6 db 0
+
+    ; at 0x1fe0, the last instruction straddles the page boundary, the block continues in the next
+    ; page
+end_of_page:
+    add eax, 2
+    add eax, 3
+    add eax, 4
+    add eax, 5
+    add eax, 6
+    add eax, 7
+    mov ecx, 5
+    add eax, 4
+    nop
+    nop
+    mov edx, 0x12345678
+    inc edx
+    hlt
@@ -0,0 +1,17 @@
+BITS 32
+; interpret before compiling
+; promote to the optimising tier
+
+    ; 1500 iterations in the interpreter, so that the return address becomes an entry point of the
+    ; module from the baseline tier, then 3000 iterations, each entering the module
+    mov esp, 0x10000
+    add ebx, 1500
+    mov ecx, ebx
+next:
+    call function
+    dec ecx
+    jnz next
+    hlt
+
+function:
+    ret
@@ -0,0 +1,6 @@
+BITS 32
+    ; xmm registers are kept in locals, no loads or stores between instructions
+    movdqa xmm0, xmm1
+    movdqa xmm2, xmm0
+    movd eax, xmm2
+    hlt
@@ -21,6 +21,7 @@
   (type $t19 (func (param i32 i64 i32) (result i32)))
   (type $t20 (func (param i32 i64 i64 i32) (result i32)))
   (import "e" "task_switch_test_jit" (func $e.task_switch_test_jit (type $t1)))
+  (import "e" "fpu_exception_test_jit" (func $e.fpu_exception_test_jit (type $t6)))
   (import "e" "fpu_get_sti_jit" (func $e.fpu_get_sti_jit (type $t2)))
   (import "e" "fpu_fadd" (func $e.fpu_fadd (type $t18)))
   (import "e" "fpu_pop" (func $e.fpu_pop (type $t0)))
@@ -78,6 +79,22 @@
                 (call $e.task_switch_test_jit
                   (i32.const 4096))
                 (br $B1)))
+            (if $I6
+              (i32.and
+                (i32.and
+                  (i32.load16_u
+                    (i32.const 1040))
+                  (i32.xor
+                    (i32.load16_u
+                      (i32.const 1036))
+                    (i32.const -1)))
+                (i32.const 63))
+              (then
+                (if $I7
+                  (call $e.fpu_exception_test_jit
+                    (i32.const 4096))
+                  (then
+                    (br $B1)))))
             (i32.const 1)
             (call $e.fpu_get_sti_jit
               (i32.const 1136)
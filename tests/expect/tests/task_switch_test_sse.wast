@@ -26,7 +26,7 @@
   (import "e" "trigger_fault_end_jit" (func $e.trigger_fault_end_jit (type $t0)))
   (import "e" "m" (memory {normalised output}))
   (func $f (export "f") (type $t1) (param $p0 i32)
-    (local $l0 i32) (local $l1 i32) (local $l2 i32) (local $l3 i32) (local $l4 i32) (local $l5 i32) (local $l6 i32) (local $l7 i32) (local $l8 i32)
+    (local $l0 i32) (local $l1 i32) (local $l2 i32) (local $l3 i32) (local $l4 i32) (local $l5 i32) (local $l6 i32) (local $l7 i32) (local $l8 i64) (local $l9 i64) (local $l10 i64) (local $l11 i64) (local $l12 i32)
     (set_local $l0
       (i32.load
         (i32.const 64)))
@@ -52,20 +52,32 @@
       (i32.load
         (i32.const 92)))
     (set_local $l8
+      (i64.load
+        (i32.const 832)))
+    (set_local $l9
+      (i64.load
+        (i32.const 840)))
+    (set_local $l10
+      (i64.load
+        (i32.const 848)))
+    (set_local $l11
+      (i64.load
+        (i32.const 856)))
+    (set_local $l12
       (i32.const 0))
     (block $B0
       (block $B1
         (loop $L2
           (br_if $B0
             (i32.ge_u
-              (get_local $l8)
+              (get_local $l12)
               (i32.const 100003)))
           (block $B3
             (block $B4
             )
-            (set_local $l8
+            (set_local $l12
               (i32.add
-                (get_local $l8)
+                (get_local $l12)
                 (i32.const 2)))
             (if $I5
               (i32.and
@@ -78,15 +90,25 @@
                 (br $B1)))
             (i64.store
               (i32.const 1136)
-              (i64.load
-                (i32.const 848)))
+              (get_local $l10))
             (i64.store
               (i32.const 1144)
+              (get_local $l11))
+            (i32.const 1136)
+            (i32.const 0)
+            (i64.store
+              (i32.const 832)
+              (get_local $l8))
+            (i64.store
+              (i32.const 840)
+              (get_local $l9))
+            (call $e.instr_660F54)
+            (set_local $l8
               (i64.load
-                (i32.const 856)))
-            (call $e.instr_660F54
-              (i32.const 1136)
-              (i32.const 0))
+                (i32.const 832)))
+            (set_local $l9
+              (i64.load
+                (i32.const 840)))
             (i32.store
               (i32.const 560)
               (i32.or
@@ -127,6 +149,18 @@
             (i32.store
               (i32.const 92)
               (get_local $l7))
+            (i64.store
+              (i32.const 832)
+              (get_local $l8))
+            (i64.store
+              (i32.const 840)
+              (get_local $l9))
+            (i64.store
+              (i32.const 848)
+              (get_local $l10))
+            (i64.store
+              (i32.const 856)
+              (get_local $l11))
             (call $e.instr_F4)
             (set_local $l0
               (i32.load
@@ -152,6 +186,18 @@
             (set_local $l7
               (i32.load
                 (i32.const 92)))
+            (set_local $l8
+              (i64.load
+                (i32.const 832)))
+            (set_local $l9
+              (i64.load
+                (i32.const 840)))
+            (set_local $l10
+              (i64.load
+                (i32.const 848)))
+            (set_local $l11
+              (i64.load
+                (i32.const 856)))
             (br $B0))
           (unreachable)))
       (i32.store
@@ -178,13 +224,25 @@
       (i32.store
         (i32.const 92)
         (get_local $l7))
+      (i64.store
+        (i32.const 832)
+        (get_local $l8))
+      (i64.store
+        (i32.const 840)
+        (get_local $l9))
+      (i64.store
+        (i32.const 848)
+        (get_local $l10))
+      (i64.store
+        (i32.const 856)
+        (get_local $l11))
       (call $e.trigger_fault_end_jit)
       (i32.store
         (i32.const 664)
         (i32.add
           (i32.load
             (i32.const 664))
-          (get_local $l8)))
+          (get_local $l12)))
       (return))
     (i32.store
       (i32.const 64)
@@ -210,9 +268,21 @@
     (i32.store
       (i32.const 92)
       (get_local $l7))
+    (i64.store
+      (i32.const 832)
+      (get_local $l8))
+    (i64.store
+      (i32.const 840)
+      (get_local $l9))
+    (i64.store
+      (i32.const 848)
+      (get_local $l10))
+    (i64.store
+      (i32.const 856)
+      (get_local $l11))
     (i32.store
       (i32.const 664)
       (i32.add
         (i32.load
           (i32.const 664))
-        (get_local $l8)))))
+        (get_local $l12)))))
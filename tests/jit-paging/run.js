@@ -66,7 +66,7 @@ emulator.add_listener("serial0-output-byte", async function(byte)
         emulator.stop();
 
         let result = Buffer.from(data).toString();
-        if(result !== "test_shared passed\ntest_consecutive_written passed\n")
+        if(result !== "test_shared passed\ntest_consecutive_written passed\ntest_remapped_next_page passed\n")
         {
             console.error("[!] Error. Result was:\n" + result);
             process.exit(1);
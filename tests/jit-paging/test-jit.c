@@ -192,6 +192,110 @@ void test_consecutive_written()
     printf("test_consecutive_written passed\n");
 }
 
+void test_remapped_next_page()
+{
+    static char filename[] = "/tmp/RemapXXXXXX";
+    int fd = mkstemp(filename);
+    if(fd == -1)
+    {
+        fatal("mkstemp");
+    }
+    if(ftruncate(fd, 3 * PAGE_SIZE) == -1)
+    {
+        fatal("ftruncate");
+    }
+
+    // The code is written through write_addr and runs from exec_addr, whose second page maps
+    // either the second or the third page of the file
+    uint8_t *const write_addr = mmap(0, 3 * PAGE_SIZE, PROT_READ | PROT_WRITE, MAP_SHARED, fd, 0);
+    uint8_t *const exec_addr = mmap(0, 2 * PAGE_SIZE,
+            PROT_READ | PROT_WRITE | PROT_EXEC, MAP_SHARED, fd, 0);
+
+    if(write_addr == MAP_FAILED || exec_addr == MAP_FAILED)
+    {
+        fatal("mmap");
+    }
+
+    uint8_t* ptr = write_addr + PAGE_SIZE - 16;
+
+    // xor eax, eax
+    *ptr++ = 0x31;
+    *ptr++ = 0xc0;
+
+    for(int i = 0; i < 11; i++)
+    {
+        // inc eax
+        *ptr++ = 0x40;
+    }
+
+    // mov ecx, 0x100, straddling the page boundary
+    *ptr++ = 0xb9;
+    *ptr++ = 0x00;
+    *ptr++ = 0x01;
+    *ptr++ = 0x00;
+    *ptr++ = 0x00;
+
+    // add eax, ecx
+    *ptr++ = 0x01;
+    *ptr++ = 0xc8;
+
+    for(int i = 0; i < 4; i++)
+    {
+        // inc eax
+        *ptr++ = 0x40;
+    }
+
+    // ret
+    *ptr++ = 0xC3;
+
+    // The third page of the file: The last two bytes of the immediate are 0x01 and 0x00 and two
+    // of the incs are nops
+    memcpy(write_addr + 2 * PAGE_SIZE, write_addr + PAGE_SIZE, 16);
+    write_addr[2 * PAGE_SIZE + 0] = 0x01;
+    write_addr[2 * PAGE_SIZE + 4] = 0x90;
+    write_addr[2 * PAGE_SIZE + 5] = 0x90;
+
+    int (*fun_pointer)() = (void*)(exec_addr + PAGE_SIZE - 16);
+
+    // Compiled code may continue into the next page (if it happens to be physically contiguous),
+    // or compile the straddling mov
+    for(int i = 0; i < 15000; i++)
+    {
+        if(fun_pointer() != 11 + 0x100 + 4)
+        {
+            fatal("test_remapped_next_page");
+        }
+    }
+
+    // map the third page of the file after the first one, without writing to either of them
+    if(mmap(exec_addr + PAGE_SIZE, PAGE_SIZE, PROT_READ | PROT_WRITE | PROT_EXEC,
+                MAP_SHARED | MAP_FIXED, fd, 2 * PAGE_SIZE) == MAP_FAILED)
+    {
+        fatal("mmap");
+    }
+
+    for(int i = 0; i < 15000; i++)
+    {
+        if(fun_pointer() != 11 + 0x10100 + 2)
+        {
+            fatal("test_remapped_next_page after remap");
+        }
+    }
+
+    // the immediate of the straddling mov changes in the second physical page
+    write_addr[2 * PAGE_SIZE + 1] = 0x02;
+
+    if(fun_pointer() != 11 + 0x2010100 + 2)
+    {
+        fatal("test_remapped_next_page after overwrite");
+    }
+
+    printf("test_remapped_next_page passed\n");
+
+    munmap(write_addr, 3 * PAGE_SIZE);
+    munmap(exec_addr, 2 * PAGE_SIZE);
+}
+
 int main()
 {
     test_shared();
@@ -201,5 +305,7 @@ int main()
 
     test_consecutive_written();
 
+    test_remapped_next_page();
+
     return 0;
 }
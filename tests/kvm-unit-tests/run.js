@@ -32,8 +32,13 @@ var emulator = new V86({
     vga_bios: { buffer: vga_bios },
     multiboot: new Loader(process.argv[2]),
     autostart: true,
-    memory_size: 64 * 1024 * 1024,
+    // for x86/highmem.flat: MEMORY_SIZE=3583 (in MiB)
+    memory_size: (+process.env.MEMORY_SIZE || 64) * 1024 * 1024,
     disable_jit: +process.env.DISABLE_JIT,
+    // the startup code of tests using the library reads the apic id
+    acpi: true,
+    // for x86/smptest.flat: CPU_COUNT=2
+    cpu_count: +process.env.CPU_COUNT || 1,
     log_level: 0,
 });
 
@@ -0,0 +1,155 @@
+/* Access ram above 4 GiB through PSE-36 and PAE mappings. Requires more than
+ * 3 GiB of memory, the part above 3 GiB is remapped to 4 GiB (run with
+ * MEMORY_SIZE=3583). */
+
+#include "fwcfg.h"
+#include "asm/page.h"
+#include "processor.h"
+
+#ifdef __x86_64__
+#error This test is 32-bit only.
+#endif
+
+#define PSE_PAGE_SIZE (1UL << 22)
+#define HUGE_PAGE_SIZE (1UL << 21)
+
+#define REMAP_START 0xC0000000ULL
+#define HIGH_MEMORY 0x100000000ULL
+
+/* Virtual addresses of the test mappings, everything below 1 GiB is identity
+ * mapped */
+#define HIGH_START ((volatile uint32_t *)0x80000000)
+#define HIGH_END ((volatile uint32_t *)0x80400000)
+#define UNBACKED ((volatile uint32_t *)0x80800000)
+#define HOLE ((volatile uint32_t *)0x80C00000)
+
+uint32_t page_dir[1024] __attribute__((aligned(0x1000)));
+
+uint64_t pdpt[4] __attribute__((aligned(0x20)));
+uint64_t pae_page_dirs[4 * 512] __attribute__((aligned(0x1000)));
+uint64_t pae_page_tables[2 * 512] __attribute__((aligned(0x1000)));
+
+static uint64_t high_end;
+
+static bool has_cpuid_feature(int bit)
+{
+    return cpuid(1).d & (1 << bit);
+}
+
+/* 4 MiB page, bits 32-35 of the address go into bits 13-16 of the entry */
+static uint32_t pse36_entry(uint64_t phys)
+{
+    return (uint32_t)phys | (uint32_t)(phys >> 32) << 13 |
+        PT_PRESENT_MASK | PT_WRITABLE_MASK | PT_PAGE_SIZE_MASK;
+}
+
+static void disable_paging(void)
+{
+    write_cr0(read_cr0() & ~X86_CR0_PG);
+}
+
+static void setup_pse36(void)
+{
+    for (unsigned int i = 0; i < 256; i++)
+        page_dir[i] = pse36_entry((uint64_t)i * PSE_PAGE_SIZE);
+
+    page_dir[(uint32_t)HIGH_START >> 22] = pse36_entry(HIGH_MEMORY);
+    page_dir[(uint32_t)HIGH_END >> 22] = pse36_entry(high_end - PSE_PAGE_SIZE);
+    page_dir[(uint32_t)UNBACKED >> 22] = pse36_entry(high_end + PSE_PAGE_SIZE);
+    page_dir[(uint32_t)HOLE >> 22] = pse36_entry(REMAP_START);
+
+    disable_paging();
+    write_cr4((read_cr4() & ~X86_CR4_PAE) | X86_CR4_PSE);
+    write_cr3((uint32_t)page_dir);
+    write_cr0(read_cr0() | X86_CR0_PG);
+}
+
+static void setup_pae(void)
+{
+    uint64_t *pd_low = &pae_page_dirs[0 * 512];
+    uint64_t *pd_high = &pae_page_dirs[2 * 512];
+
+    pdpt[0] = (uint32_t)pd_low | PT_PRESENT_MASK;
+    for (unsigned int i = 0; i < 512; i++)
+        pd_low[i] = (uint64_t)i * HUGE_PAGE_SIZE | PT_PRESENT_MASK |
+            PT_WRITABLE_MASK | PT_PAGE_SIZE_MASK;
+
+    /* HIGH_START with 2 MiB pages, HIGH_END with 4 KiB pages */
+    pdpt[2] = (uint32_t)pd_high | PT_PRESENT_MASK;
+    pd_high[0] = HIGH_MEMORY | PT_PRESENT_MASK | PT_WRITABLE_MASK | PT_PAGE_SIZE_MASK;
+    pd_high[1] = (HIGH_MEMORY + HUGE_PAGE_SIZE) | PT_PRESENT_MASK | PT_WRITABLE_MASK |
+        PT_PAGE_SIZE_MASK;
+    pd_high[2] = (uint32_t)&pae_page_tables[0] | PT_PRESENT_MASK | PT_WRITABLE_MASK;
+    pd_high[3] = (uint32_t)&pae_page_tables[512] | PT_PRESENT_MASK | PT_WRITABLE_MASK;
+    for (unsigned int i = 0; i < 2 * 512; i++)
+        pae_page_tables[i] = (high_end - PSE_PAGE_SIZE + (uint64_t)i * PAGE_SIZE) |
+            PT_PRESENT_MASK | PT_WRITABLE_MASK;
+
+    disable_paging();
+    write_cr4(read_cr4() | X86_CR4_PAE);
+    write_cr3((uint32_t)pdpt);
+    write_cr0(read_cr0() | X86_CR0_PG);
+}
+
+static uint32_t pattern(unsigned int i)
+{
+    return 0x9E3779B9 * (i + 1);
+}
+
+#define WORDS (PSE_PAGE_SIZE / 4)
+#define STRIDE 1021
+
+int main(void)
+{
+    uint64_t mem_size = fwcfg_get_u64(FW_CFG_RAM_SIZE);
+    bool ok;
+
+    if (!has_cpuid_feature(3) || !has_cpuid_feature(6) || !has_cpuid_feature(17)) {
+        report_abort("PSE, PAE or PSE-36 not supported");
+    }
+    if (mem_size < REMAP_START + 2 * PSE_PAGE_SIZE) {
+        report_abort("requires more than 3 GiB of memory, got %u MiB",
+                     (uint32_t)(mem_size >> 20));
+    }
+    printf("ram above 4 GiB: %u MiB\n", (uint32_t)((mem_size - REMAP_START) >> 20));
+    /* the last complete 4 MiB page of ram ends here, the next one is unbacked */
+    high_end = (HIGH_MEMORY + (mem_size - REMAP_START)) & ~(uint64_t)(PSE_PAGE_SIZE - 1);
+
+    setup_pse36();
+
+    for (unsigned int i = 0; i < WORDS; i += STRIDE) {
+        HIGH_START[i] = pattern(i);
+        HIGH_END[i] = ~pattern(i);
+    }
+    ok = true;
+    for (unsigned int i = 0; i < WORDS; i += STRIDE)
+        ok = ok && HIGH_START[i] == pattern(i) && HIGH_END[i] == ~pattern(i);
+    report("pse-36: read back writes to the first and last 4 MiB above 4 GiB", ok);
+
+    ok = true;
+    for (unsigned int i = 0; i < WORDS; i += STRIDE)
+        ok = ok && HOLE[i] == 0xFFFFFFFF;
+    report("pse-36: the remapped ram isn't visible below 4 GiB", ok);
+
+    UNBACKED[0] = 0x12345678;
+    report("pse-36: nothing is backed after the end of ram", UNBACKED[0] == 0xFFFFFFFF);
+
+    setup_pae();
+
+    ok = true;
+    for (unsigned int i = 0; i < WORDS; i += STRIDE)
+        ok = ok && HIGH_START[i] == pattern(i) && HIGH_END[i] == ~pattern(i);
+    report("pae: 2 MiB and 4 KiB pages map the same memory as pse-36", ok);
+
+    for (unsigned int i = 0; i < WORDS; i += STRIDE)
+        HIGH_END[i] = pattern(i) ^ 0x55555555;
+
+    setup_pse36();
+
+    ok = true;
+    for (unsigned int i = 0; i < WORDS; i += STRIDE)
+        ok = ok && HIGH_END[i] == (pattern(i) ^ 0x55555555);
+    report("pse-36: writes through pae are visible", ok);
+
+    return report_summary();
+}
@@ -832,6 +832,122 @@ static void test_int(void)
 	report("int 1", 0, 1);
 }
 
+static void test_icebp(void)
+{
+	*(u32 *)(0x1 * 4) = 0x1000; /* Store a pointer to address 0x1000 in IDT entry 1 */
+	*(u64 *)(0x1000) = 0xcff0210f4166ull; /* inc ecx; mov eax, dr6; iret */
+
+	/* #db is a trap: The handler returns to the inc, and dr6 is left unchanged */
+	MK_INSN(icebp,
+		"mov %ebx, %dr6\n\t"
+		".byte 0xf1\n\t"
+		"inc %edx\n\t");
+
+	init_inregs(&(struct regs){ .ebx = 0xffff0ff0 });
+	exec_in_big_real_mode(&insn_icebp);
+	report("icebp", R_AX | R_CX | R_DX,
+	       outregs.ecx == 1 && outregs.edx == 1 && outregs.eax == 0xffff0ff0);
+}
+
+static void test_fpu_exceptions(void)
+{
+	u32 int10 = *(u32 *)(0x10 * 4);
+	u32 cr0;
+
+	/* #MF handler: inc ecx; fnclex; iret */
+	*(u32 *)(0x10 * 4) = 0x1000;
+	*(u64 *)(0x1000) = 0xcfe2db4166ull;
+	/* irq 13 handler: inc edx; push ax; mov al, 0; out 0xf0, al; fnclex; mov al, 0x20;
+	   out 0xa0, al; out 0x20, al; pop ax; iret */
+	*(u32 *)(0x75 * 4) = 0x1010;
+	*(u64 *)(0x1010) = 0xdbf0e600b0504266ull;
+	*(u64 *)(0x1018) = 0x5820e6a0e620b0e2ull;
+	*(u8 *)(0x1020) = 0xcf;
+	/* control word with the zero divide exception unmasked */
+	*(u16 *)(0x2010) = 0x37b;
+
+	MK_INSN(fpu_read_cr0, "mov %cr0, %eax\n\t");
+	MK_INSN(fpu_write_cr0, "mov %ebx, %cr0\n\t");
+	/* 1 / 0, reported by the fwait. hlt returns after irq 13 or the next timer interrupt */
+	MK_INSN(fpu_div_zero, "fninit\n\t"
+			      "fldcw 0x2010\n\t"
+			      "fldz\n\t"
+			      "fld1\n\t"
+			      "fdiv %st(1), %st\n\t"
+			      "fwait\n\t"
+			      "sti\n\t"
+			      "hlt\n\t"
+			      "cli\n\t");
+
+	init_inregs(NULL);
+	exec_in_big_real_mode(&insn_fpu_read_cr0);
+	cr0 = outregs.eax;
+
+	init_inregs(&(struct regs){ .ebx = cr0 | 0x20 });
+	exec_in_big_real_mode(&insn_fpu_write_cr0);
+	init_inregs(NULL);
+	exec_in_big_real_mode(&insn_fpu_div_zero);
+	report("fpu exception with cr0.ne", R_CX, outregs.ecx == 1 && outregs.edx == 0);
+
+	/* test_sti_inhibit leaves the cascade in service on the master pic, which blocks irq 13 */
+	outb(0x20, 0x20);
+	init_inregs(&(struct regs){ .ebx = cr0 & ~0x20 });
+	exec_in_big_real_mode(&insn_fpu_write_cr0);
+	init_inregs(NULL);
+	exec_in_big_real_mode(&insn_fpu_div_zero);
+	report("fpu exception without cr0.ne", R_DX, outregs.ecx == 0 && outregs.edx == 1);
+
+	init_inregs(&(struct regs){ .ebx = cr0 });
+	exec_in_big_real_mode(&insn_fpu_write_cr0);
+	*(u32 *)(0x10 * 4) = int10;
+}
+
+static void test_sse_exceptions(void)
+{
+	/* #XM and #UD handlers: count the fault in ecx or edx and skip the 4-byte divss */
+	*(u32 *)(19 * 4) = 0x1000;
+	*(u64 *)(0x1000) = 0xcf5604c6835e4166ull; /* inc ecx; pop si; add si, 4; push si; iret */
+	*(u32 *)(6 * 4) = 0x1010;
+	*(u64 *)(0x1010) = 0xcf5604c6835e4266ull; /* inc edx; pop si; add si, 4; push si; iret */
+
+	MK_INSN(sse_read_cr4, "mov %cr4, %eax\n\t");
+	MK_INSN(sse_write_cr4, "mov %ebx, %cr4\n\t");
+	/* divide by zero with cr4 from ebx and mxcsr from edi */
+	MK_INSN(sse_div_zero,
+		"mov %ebx, %cr4\n\t"
+		"push %edi\n\t"
+		"ldmxcsr (%esp)\n\t"
+		"pop %edi\n\t"
+		"mov $0x3f800000, %eax\n\t"
+		"movd %eax, %xmm0\n\t"
+		"xorps %xmm1, %xmm1\n\t"
+		"divss %xmm1, %xmm0\n\t");
+	u32 cr4;
+
+	init_inregs(NULL);
+	exec_in_big_real_mode(&insn_sse_read_cr4);
+	cr4 = outregs.eax;
+
+	init_inregs(&(struct regs){ .ebx = cr4 | 0x600, .edi = 0x1f80 });
+	exec_in_big_real_mode(&insn_sse_div_zero);
+	report("sse masked exception", R_AX, outregs.ecx == 0 && outregs.edx == 0);
+
+	init_inregs(&(struct regs){ .ebx = cr4 | 0x600, .edi = 0x1d80 });
+	exec_in_big_real_mode(&insn_sse_div_zero);
+	report("sse unmasked exception with osxmmexcpt", R_AX | R_CX | R_SI,
+	       outregs.ecx == 1 && outregs.edx == 0);
+
+	init_inregs(&(struct regs){ .ebx = cr4 | 0x200, .edi = 0x1d80 });
+	exec_in_big_real_mode(&insn_sse_div_zero);
+	report("sse unmasked exception without osxmmexcpt", R_AX | R_DX | R_SI,
+	       outregs.ecx == 0 && outregs.edx == 1);
+
+	init_inregs(&(struct regs){ .ebx = cr4 | 0x200, .edi = 0x1f80 });
+	exec_in_big_real_mode(&insn_sse_div_zero);
+	init_inregs(&(struct regs){ .ebx = cr4 });
+	exec_in_big_real_mode(&insn_sse_write_cr4);
+}
+
 static void test_sti_inhibit(void)
 {
 	init_inregs(NULL);
@@ -852,6 +968,37 @@ static void test_sti_inhibit(void)
 	report("sti inhibit", ~0, 1);
 }
 
+static void test_monitor_mwait(void)
+{
+	u32 irq0_handler = *(u32 *)(0x8 * 4);
+
+	/* Count timer interrupts in ecx: inc ecx; push ax; mov al, 0x20; out 0x20, al; pop ax; iret */
+	*(u32 *)(0x8 * 4) = 0x1020;
+	*(u64 *)(0x1020) = 0x5820e620b0504166ull;
+	*(u8 *)(0x1028) = 0xcf;
+
+	MK_INSN(mwait_irq, "monitor\n\t"
+			   "sti\n\t"
+			   "mwait\n\t"
+			   "cli\n\t");
+	MK_INSN(mwait_write, "monitor\n\t"
+			     "movb $1, (%bx)\n\t"
+			     "sti\n\t"
+			     "mwait\n\t"
+			     "cli\n\t");
+
+	init_inregs(&(struct regs){ .eax = 0x2000 });
+	exec_in_big_real_mode(&insn_mwait_irq);
+	report("mwait wake-up on interrupt", R_CX, outregs.ecx == 1);
+
+	/* A write to the monitored line disarms the monitor, so mwait doesn't wait for the timer */
+	init_inregs(&(struct regs){ .eax = 0x2000, .ebx = 0x2000 });
+	exec_in_big_real_mode(&insn_mwait_write);
+	report("mwait after a write to the monitored line", 0, *(u8 *)0x2000 == 1);
+
+	*(u32 *)(0x8 * 4) = irq0_handler;
+}
+
 static void test_imul(void)
 {
 	MK_INSN(imul8_1, "mov $2, %al\n\t"
@@ -1402,6 +1549,66 @@ static void test_lds_lss(void)
 		outregs.ebx == desc.sel);
 }
 
+static struct {
+	u64 pcid;
+	u64 address;
+} invpcid_desc = { 0, 0x1000 };
+
+static void test_invpcid(void)
+{
+	int type;
+
+	/* #GP handler: count the fault in ecx and skip the 6-byte invpcid */
+	*(u32 *)(13 * 4) = 0x1000;
+	*(u64 *)(0x1000) = 0xcf5606c6835e4166ull; /* inc ecx; pop si; add si, 6; push si; iret */
+
+	MK_INSN(invpcid, "invpcid (%ebx), %eax\n\t");
+
+	/* individual address, single context, all contexts including and excluding globals */
+	for (type = 0; type < 4; type++) {
+		init_inregs(&(struct regs){ .eax = type, .ebx = (unsigned long)&invpcid_desc });
+		exec_in_big_real_mode(&insn_invpcid);
+		report("invpcid", 0, outregs.ecx == 0);
+	}
+
+	init_inregs(&(struct regs){ .eax = 4, .ebx = (unsigned long)&invpcid_desc });
+	exec_in_big_real_mode(&insn_invpcid);
+	report("invpcid invalid type", R_CX | R_SI, outregs.ecx == 1);
+
+	/* without cr4.pcide, only pcid 0 can be invalidated by the single address and context types */
+	invpcid_desc.pcid = 1;
+	init_inregs(&(struct regs){ .eax = 1, .ebx = (unsigned long)&invpcid_desc });
+	exec_in_big_real_mode(&insn_invpcid);
+	report("invpcid pcid without pcide", R_CX | R_SI, outregs.ecx == 1);
+
+	init_inregs(&(struct regs){ .eax = 3, .ebx = (unsigned long)&invpcid_desc });
+	exec_in_big_real_mode(&insn_invpcid);
+	report("invpcid all contexts ignores pcid", 0, outregs.ecx == 0);
+	invpcid_desc.pcid = 0;
+}
+
+static u32 a20_value;
+
+static void test_a20(void)
+{
+	/* with a20 disabled, the write above 1 MiB wraps around to a20_value */
+	MK_INSN(a20, "inb $0x92, %al\n\t"
+		     "andb $~2, %al\n\t"
+		     "outb %al, $0x92\n\t"
+		     "movl $0x12345678, (%ebx)\n\t"
+		     "orb $2, %al\n\t"
+		     "outb %al, $0x92\n\t"
+		     "movl $0, (%ebx)\n\t"
+		     "movl (%ecx), %edx\n\t");
+
+	init_inregs(&(struct regs){
+		.ebx = (unsigned long)&a20_value | 0x100000,
+		.ecx = (unsigned long)&a20_value,
+	});
+	exec_in_big_real_mode(&insn_a20);
+	report("a20", R_AX | R_DX, outregs.edx == 0x12345678);
+}
+
 static void test_jcxz(void)
 {
 	MK_INSN(jcxz1, "jcxz 1f\n\t"
@@ -1802,7 +2009,9 @@ void realmode_start(void)
 	test_xchg();
 	test_iret();
 	test_int();
+	test_icebp();
 	test_sti_inhibit();
+	test_monitor_mwait();
 	test_imul();
 	test_mul();
 	test_div();
@@ -1812,6 +2021,10 @@ void realmode_start(void)
 	test_cwd_cdq();
 	test_das();
 	test_lds_lss();
+	test_invpcid();
+	test_fpu_exceptions();
+	test_sse_exceptions();
+	test_a20();
 	test_jcxz();
 	test_cpuid();
 	test_ss_base_for_esp_ebp();
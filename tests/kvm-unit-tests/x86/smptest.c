@@ -1,5 +1,6 @@
 #include "libcflat.h"
 #include "smp.h"
+#include "processor.h"
 
 unsigned nipis;
 
@@ -14,6 +15,38 @@ static void ipi_test(void *data)
         nipis++;
 }
 
+static volatile int mwait_flag;
+static volatile int mwait_wakeups;
+
+static void mwait_test(void *data)
+{
+    /* mwait returns right away with interrupts disabled, and no interrupts are sent to this cpu */
+    asm volatile("sti");
+    while (!mwait_flag) {
+	asm volatile("monitor" : : "a"(&mwait_flag), "c"(0), "d"(0));
+	if (!mwait_flag) {
+	    asm volatile("mwait" : : "a"(0), "c"(0));
+	    mwait_wakeups++;
+	}
+    }
+    asm volatile("cli");
+}
+
+static void test_mwait_wakeup(void)
+{
+    long i;
+
+    on_cpu_async(1, mwait_test, 0);
+    /* give the other cpu time to start waiting */
+    for (i = 0; i < 1000000; ++i)
+	pause();
+    mwait_flag = 1;
+    for (i = 0; i < 10000000 && cpus_active() > 1; ++i)
+	pause();
+
+    report("mwait wake-up on write by another cpu", cpus_active() == 1 && mwait_wakeups == 1);
+}
+
 int main()
 {
     int ncpus;
@@ -27,5 +60,9 @@ int main()
 	on_cpu(i, ipi_test, (void *)(long)i);
 
     report("IPI to each CPU", nipis == ncpus);
+
+    if (ncpus > 1)
+	test_mwait_wakeup();
+
     return report_summary();
 }
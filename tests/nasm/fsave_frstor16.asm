@@ -0,0 +1,13 @@
+global _start
+
+%include "header.inc"
+
+    sub esp, 128
+    fldz
+    fld1
+    o16 fnsave [esp]
+    o16 frstor [esp]
+    mov dword [esp + 6], 0 ; fpu ip and cs (currently not emulated)
+    mov dword [esp + 10], 0 ; fpu dp and ds (currently not emulated)
+
+%include "footer.inc"
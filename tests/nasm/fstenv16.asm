@@ -0,0 +1,14 @@
+global _start
+
+section .data
+	align 16
+
+%include "header.inc"
+
+    o16 fnstenv [esp]
+
+    ; fpu ip, cs, dp, ds (currently not emulated)
+    mov dword [esp + 6], 0
+    mov dword [esp + 10], 0
+
+%include "footer.inc"
@@ -0,0 +1,33 @@
+global _start
+
+section .data
+	align 16
+large:
+	dq 1.0e18
+medium:
+	dq 100000.0
+ratio:
+	dq 0.75
+out_of_range:
+	dq 0x43E0000000000000 ; 2**63
+
+%include "header.inc"
+
+    ; arguments close to 2**63 need the full 2/pi for argument reduction
+    fld qword [large]
+    fsin
+    fld qword [large]
+    fcos
+
+    fld qword [medium]
+    fptan
+
+    fld qword [ratio]
+    fld1
+    fpatan
+
+    ; out of range: st0 is left unchanged and C2 is set
+    fld qword [out_of_range]
+    fsincos
+
+%include "footer.inc"
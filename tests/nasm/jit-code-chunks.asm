@@ -0,0 +1,47 @@
+global _start
+
+section .data
+align 4096
+	; a function at the start of its own page, the data below it is in a different chunk
+patched_fn:
+	add		eax, 1
+	ret
+	times 0x100 db 0
+data:
+	dd		0
+
+%include "header.inc"
+
+	; run the function often enough to be compiled
+	xor		eax, eax
+	mov		ecx, 0x10000
+warm:
+	call		patched_fn
+	dec		ecx
+	jnz		warm
+
+	; writes to a chunk without code and writes that don't change the code keep the compiled code
+	mov		ecx, 0x10000
+hot:
+	call		patched_fn
+	mov		[data], ecx
+	mov		dl, [patched_fn+2]
+	mov		[patched_fn+2], dl
+	mov		edx, [patched_fn]
+	mov		[patched_fn], edx
+	dec		ecx
+	jnz		hot
+	mov		ebx, eax
+
+	; a write that changes the code
+	mov		byte [patched_fn+2], 3
+	mov		ecx, 0x10000
+hot2:
+	call		patched_fn
+	dec		ecx
+	jnz		hot2
+
+	mov		ecx, [data]
+	mov		edx, [patched_fn]
+
+%include "footer.inc"
@@ -0,0 +1,19 @@
+global _start
+
+section .data
+	align 16
+value:
+	dd	5
+
+%include "header.inc"
+
+	mov			eax, 3
+	lock add	[value], eax
+	lock xadd	[value], eax
+	mov			ebx, [value]
+
+	; lock on a register destination
+	db			0xF0
+	add			eax, ebx
+
+%include "footer.inc"
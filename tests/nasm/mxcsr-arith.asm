@@ -0,0 +1,65 @@
+global _start
+
+section .data
+	align 16
+one:
+	dd	1.0
+third:
+	dd	3.0
+tiny:
+	dd	0x00400000 ; denormal
+small:
+	dd	0x00800000 ; smallest normal
+half:
+	dd	0.5
+one_and_half:
+	dq	1.5
+mxcsr:
+	dd	0
+
+%macro  loadMxcsr 1
+	mov			dword [mxcsr], %1
+	ldmxcsr		[mxcsr]
+%endmacro
+
+%include "header.inc"
+
+	; rounding of inexact results, all exceptions masked
+	loadMxcsr 0x1F80 ; Round to nearest
+	movss		xmm0, [one]
+	divss		xmm0, [third]
+	loadMxcsr 0x3F80 ; Round down
+	movss		xmm1, [one]
+	divss		xmm1, [third]
+	loadMxcsr 0x5F80 ; Round up
+	movss		xmm2, [one]
+	divss		xmm2, [third]
+	loadMxcsr 0x7F80 ; Round toward zero
+	movsd		xmm3, [one_and_half]
+	cvtsd2si	eax, xmm3
+	cvttsd2si	ebx, xmm3
+	stmxcsr		[mxcsr]
+	mov			ecx, [mxcsr]
+
+	; denormals are zero
+	loadMxcsr 0x1FC0
+	movss		xmm4, [tiny]
+	addss		xmm4, [small]
+
+	; flush to zero
+	loadMxcsr 0x9F80
+	movss		xmm5, [small]
+	mulss		xmm5, [half]
+	stmxcsr		[mxcsr]
+	mov			edx, [mxcsr]
+
+	; denormal operand flag
+	loadMxcsr 0x1F80
+	movss		xmm6, [tiny]
+	addss		xmm6, [one]
+	stmxcsr		[mxcsr]
+	mov			esi, [mxcsr]
+
+	loadMxcsr 0x1F80
+
+%include "footer.inc"
@@ -0,0 +1,33 @@
+global _start
+
+section .data
+	align 16
+dq1:
+	dq	0x70ad80ad7fffffff
+	dq	0xff00ff00f0f0f0f0
+dq2:
+	dq	0x71ae01ff0f00ffbe
+	dq	0x0ff00ff0cccccccc
+
+%include "header.inc"
+
+	; the destination is the inverted operand
+	movdqa		xmm0, [dq1]
+	movdqa		xmm1, [dq2]
+	movdqa		xmm2, [dq1]
+	movdqa		xmm3, [dq2]
+	movdqa		xmm4, [dq1]
+	movdqa		xmm5, [dq2]
+	movdqa		xmm6, [dq1]
+	movdqa		xmm7, [dq2]
+
+	pandn		xmm0, xmm1
+	pandn		xmm1, xmm2
+	pandn		xmm2, [dq2]
+	andnps		xmm3, xmm4
+	andnps		xmm4, [dq2]
+	andnpd		xmm5, xmm6
+	andnpd		xmm6, [dq1]
+	pandn		xmm7, xmm7
+
+%include "footer.inc"
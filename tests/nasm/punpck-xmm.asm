@@ -0,0 +1,39 @@
+global _start
+
+section .data
+	align 16
+dq1:
+	dq	0x0706050403020100
+	dq	0x0f0e0d0c0b0a0908
+dq2:
+	dq	0x1716151413121110
+	dq	0x1f1e1d1c1b1a1918
+
+%include "header.inc"
+
+	movdqa		xmm0, [dq1]
+	movdqa		xmm1, [dq1]
+	movdqa		xmm2, [dq1]
+	movdqa		xmm3, [dq1]
+	movdqa		xmm4, [dq1]
+	movdqa		xmm5, [dq1]
+	movdqa		xmm6, [dq1]
+	movdqa		xmm7, [dq2]
+
+	punpcklbw	xmm0, xmm7
+	punpckhbw	xmm1, xmm7
+	punpcklwd	xmm2, xmm7
+	punpckhwd	xmm3, xmm7
+	punpckldq	xmm4, [dq2]
+	punpckhdq	xmm5, [dq2]
+	punpcklqdq	xmm6, xmm7
+	punpckhqdq	xmm7, [dq1]
+
+	movdqa		[esp], xmm0
+	movdqa		xmm0, [dq1]
+	unpckhps	xmm0, [dq2]
+	movdqa		[esp+16], xmm1
+	movdqa		xmm1, [dq1]
+	unpckhpd	xmm1, [dq2]
+
+%include "footer.inc"
@@ -0,0 +1,37 @@
+global _start
+
+%include "header.inc"
+
+	; fill the first 0x100 bytes of the bss with a pattern
+	mov		edi, 0x100000
+	mov		ecx, 0x100
+	xor		eax, eax
+fill:
+	stosb
+	add		al, 7
+	loop		fill
+
+	; a destination above the source repeats the bytes that were copied first
+	mov		esi, 0x100000
+	mov		edi, 0x100003
+	mov		ecx, 0x40
+	rep movsb
+
+	; a destination below the source
+	mov		esi, 0x100085
+	mov		edi, 0x100080
+	mov		ecx, 0x10
+	rep movsd
+
+	; overlapping by part of an element
+	mov		esi, 0x1000c0
+	mov		edi, 0x1000c2
+	mov		ecx, 0x10
+	rep movsd
+
+	mov		esi, 0x1000e1
+	mov		edi, 0x1000e0
+	mov		ecx, 0x8
+	rep movsw
+
+%include "footer.inc"
@@ -0,0 +1,41 @@
+global _start
+
+%include "header.inc"
+
+	mov		edi, 0x100f00
+	mov		ecx, 0x200
+	xor		eax, eax
+fill:
+	stosb
+	add		al, 11
+	loop		fill
+
+	; the destination crosses into the next page
+	mov		esi, 0x100f10
+	mov		edi, 0x100ff0
+	mov		ecx, 0x40
+	rep movsb
+
+	; the source crosses into the next page
+	mov		esi, 0x100ffa
+	mov		edi, 0x101200
+	mov		ecx, 0x4
+	rep movsd
+
+	; more than a page
+	mov		esi, 0x100000
+	mov		edi, 0x100800
+	mov		ecx, 0x1100
+	rep movsb
+
+	mov		edi, 0x100ffe
+	mov		eax, 0xabcdabcd
+	mov		ecx, 0x5
+	rep stosw
+
+	mov		edi, 0x100ff8
+	mov		eax, 0x77777777
+	mov		ecx, 0x4
+	rep stosd
+
+%include "footer.inc"
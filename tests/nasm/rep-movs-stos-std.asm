@@ -0,0 +1,43 @@
+global _start
+
+%include "header.inc"
+
+	mov		edi, 0x100000
+	mov		ecx, 0x100
+	xor		eax, eax
+fill:
+	stosb
+	add		al, 13
+	loop		fill
+
+	; with the direction flag set, strings are processed from the end
+	std
+
+	mov		esi, 0x10007f
+	mov		edi, 0x1000ff
+	mov		ecx, 0x40
+	rep movsb
+
+	mov		esi, 0x10003c
+	mov		edi, 0x10003e
+	mov		ecx, 0x8
+	rep movsd
+
+	mov		esi, 0x100010
+	mov		edi, 0x10000c
+	mov		ecx, 0x5
+	rep movsw
+
+	mov		edi, 0x10017c
+	mov		eax, 0x11223344
+	mov		ecx, 0x8
+	rep stosd
+
+	mov		edi, 0x1001fe
+	mov		al, 0x5a
+	mov		ecx, 0x21
+	rep stosb
+
+	cld
+
+%include "footer.inc"
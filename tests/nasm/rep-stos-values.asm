@@ -0,0 +1,42 @@
+global _start
+
+%include "header.inc"
+
+	; values whose bytes differ
+	mov		edi, 0x100000
+	mov		eax, 0x12345678
+	mov		ecx, 0x9
+	rep stosd
+
+	mov		edi, 0x100040
+	mov		eax, 0xabab1234
+	mov		ecx, 0x9
+	rep stosw
+
+	mov		edi, 0x100080
+	mov		eax, 0x00cd00cd
+	mov		ecx, 0x9
+	rep stosd
+
+	; only the low word is stored, so the high word of eax doesn't matter
+	mov		edi, 0x1000c0
+	mov		eax, 0x1234abab
+	mov		ecx, 0x9
+	rep stosw
+
+	mov		edi, 0x100100
+	mov		eax, 0xabababab
+	mov		ecx, 0x9
+	rep stosd
+
+	mov		edi, 0x100140
+	mov		eax, 0x12345678
+	mov		ecx, 0x9
+	rep stosb
+
+	; a count of zero stores nothing
+	mov		edi, 0x100180
+	mov		ecx, 0
+	rep stosd
+
+%include "footer.inc"
@@ -315,6 +315,7 @@ else {
         autostart: false,
         memory_size: 2 * 1024 * 1024,
         disable_jit: +process.env.DISABLE_JIT,
+        disable_jit_simd: +process.env.DISABLE_JIT_SIMD,
         log_level: 0,
     });
 
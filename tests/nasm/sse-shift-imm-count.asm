@@ -0,0 +1,50 @@
+global _start
+
+section .data
+	align 16
+dq1:
+	dq	0x8000ceadad00ff81
+	dq	0x42ff88ff11aabbcc
+
+%include "header.inc"
+
+	; counts at and above the lane width clear the lanes (or fill them with the sign bit)
+	movdqa		xmm0, [dq1]
+	movdqa		xmm1, [dq1]
+	movdqa		xmm2, [dq1]
+	movdqa		xmm3, [dq1]
+	movdqa		xmm4, [dq1]
+	movdqa		xmm5, [dq1]
+	movdqa		xmm6, [dq1]
+	movdqa		xmm7, [dq1]
+
+	psrlw		xmm0, 15
+	psrlw		xmm1, 16
+	psraw		xmm2, 15
+	psraw		xmm3, 16
+	psraw		xmm4, 0xff
+	psllw		xmm5, 15
+	psllw		xmm6, 16
+	psllw		xmm7, 17
+
+	movdqa		[esp], xmm0
+	movdqa		xmm0, [dq1]
+	psrld		xmm0, 32
+	movdqa		[esp+16], xmm1
+	movdqa		xmm1, [dq1]
+	psrad		xmm1, 31
+
+	movdqu		xmm2, [dq1]
+	psrad		xmm2, 32
+	movdqu		xmm3, [dq1]
+	pslld		xmm3, 31
+	movdqu		xmm4, [dq1]
+	pslld		xmm4, 33
+	movdqu		xmm5, [dq1]
+	psrlq		xmm5, 63
+	movdqu		xmm6, [dq1]
+	psrlq		xmm6, 64
+	movdqu		xmm7, [dq1]
+	psllq		xmm7, 0x80
+
+%include "footer.inc"
@@ -0,0 +1,28 @@
+global _start
+
+section .data
+	align 16
+	times 16 db 0
+data:
+	dd	1, 2, 3, 4, 5
+
+%include "header.inc"
+
+	; unaligned loads and stores are fine for movups and movdqu
+	movups		xmm0, [data + 4]
+	movdqu		xmm1, [data + 4]
+	movups		[data + 4], xmm0
+
+	; scalar and 64-bit operands don't need to be aligned
+	addss		xmm0, [data + 4]
+	movq		xmm2, [data + 4]
+
+	; the aligned forms are fine on aligned addresses
+	movaps		xmm3, [data]
+	paddd		xmm3, [data]
+	movdqa		[data], xmm3
+
+	; #gp
+	movaps		xmm4, [data + 4]
+
+%include "footer.inc"
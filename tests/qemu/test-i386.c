@@ -1887,6 +1887,10 @@ void test_vm86(void)
                 }
             }
             break;
+        case VM86_TRAP:
+            /* icebp delivers #db after the instruction, so just continue */
+            printf("vm86 trap %d\n", VM86_ARG(ret));
+            break;
         case VM86_SIGNAL:
             /* a signal came, we just ignore that */
             break;
@@ -2066,6 +2070,11 @@ void test_exceptions(void)
         asm volatile ("int3");
     }
 
+    printf("ICEBP exception:\n");
+    if (setjmp(jmp_env) == 0) {
+        asm volatile (".byte 0xf1");
+    }
+
     printf("CLI exception:\n");
     if (setjmp(jmp_env) == 0) {
         asm volatile ("cli");